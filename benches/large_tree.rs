@@ -0,0 +1,69 @@
+//! Generates the summary of a synthetic book with about 9 000 chapter files, so that changes to the
+//! traversal can be compared. Run it with `cargo bench`.
+
+use std::path::{Path, PathBuf};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use mdbook_generate_summary::{Config, GenerateSummary};
+
+/// The subdirectories of every directory, down to `DEPTH`.
+const WIDTH: usize = 10;
+const DEPTH: usize = 3;
+/// The chapter files in every directory with subdirectories, directories at `DEPTH` get ten times
+/// as many.
+const FILES: usize = 9;
+
+fn create_tree(dir: &Path, depth: usize) -> std::io::Result<usize> {
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join("README.md"), "# Chapter\n\nSome text.\n")?;
+    let files = if depth == DEPTH { FILES * 10 } else { FILES };
+    for file in 0..files {
+        std::fs::write(
+            dir.join(format!("chapter-{:03}.md", file)),
+            format!("# Chapter {}\n\nSome text.\n", file),
+        )?;
+    }
+    let mut count = files + 1;
+    if depth < DEPTH {
+        for subdir in 0..WIDTH {
+            count += create_tree(&dir.join(format!("part-{:02}", subdir)), depth + 1)?;
+        }
+    }
+    Ok(count)
+}
+
+/// The src directory of the synthetic book, removed when it is dropped.
+struct Tree(PathBuf);
+
+impl Drop for Tree {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn large_tree(criterion: &mut Criterion) {
+    let tree =
+        Tree(std::env::temp_dir().join(format!("generate-summary-bench-{}", std::process::id())));
+    let files = create_tree(&tree.0, 1).expect("Failed to create the book");
+
+    let generator = GenerateSummary::new();
+    let mut group = criterion.benchmark_group(format!("{} files", files));
+    for (name, from_files) in [("file names", false), ("titles from files", true)] {
+        let config = Config::builder()
+            .get_chapter_name_from_file(from_files)
+            .build()
+            .unwrap();
+        group.bench_function(name, |bencher| {
+            bencher.iter(|| generator.generate_summary(&tree.0, &config).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    // Every iteration visits the whole tree, fewer samples keep `cargo bench` short
+    config = Criterion::default().sample_size(10);
+    targets = large_tree
+}
+criterion_main!(benches);
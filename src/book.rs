@@ -0,0 +1,78 @@
+// The types of a summary. With the `preprocessor` feature they are the ones of mdBook, without it
+// they are defined here with the same fields, so that generating a summary does not need mdBook.
+
+#[cfg(feature = "preprocessor")]
+pub use mdbook::book::{Link, SectionNumber, Summary, SummaryItem};
+
+#[cfg(not(feature = "preprocessor"))]
+pub use self::types::{Link, SectionNumber, Summary, SummaryItem};
+
+#[cfg(not(feature = "preprocessor"))]
+mod types {
+    use std::{
+        fmt::{self, Display, Formatter},
+        ops::{Deref, DerefMut},
+        path::PathBuf,
+    };
+
+    use serde::{Deserialize, Serialize};
+
+    /// The structure of a book, like `mdbook::book::Summary`.
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Summary {
+        pub title: Option<String>,
+        pub prefix_chapters: Vec<SummaryItem>,
+        pub numbered_chapters: Vec<SummaryItem>,
+        pub suffix_chapters: Vec<SummaryItem>,
+    }
+
+    /// A chapter of the summary, like `mdbook::book::Link`.
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Link {
+        pub name: String,
+        /// The location of the chapter file relative to the src directory, [`Option::None`] for
+        /// drafts.
+        pub location: Option<PathBuf>,
+        pub number: Option<SectionNumber>,
+        pub nested_items: Vec<SummaryItem>,
+    }
+
+    /// An item of the summary, like `mdbook::book::SummaryItem`.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub enum SummaryItem {
+        Link(Link),
+        Separator,
+        PartTitle(String),
+    }
+
+    /// A section number like "1.2.", like `mdbook::book::SectionNumber`.
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct SectionNumber(pub Vec<u32>);
+
+    impl Display for SectionNumber {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            if self.0.is_empty() {
+                write!(f, "0")
+            } else {
+                for number in &self.0 {
+                    write!(f, "{}.", number)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    impl Deref for SectionNumber {
+        type Target = Vec<u32>;
+
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl DerefMut for SectionNumber {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.0
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use log::debug;
+
+use crate::{
+    book::{Link, SummaryItem},
+    renumber,
+};
+
+/// Merge the chapter of a directory without a chapter file of its own into its only nested
+/// chapter, see `collapse_single_child_dirs`. The nested chapters are collapsed already, so a
+/// whole chain of such directories becomes one chapter. It is named by the nested chapter, or by
+/// both names joined with `joiner`, and keeps the section number of the directory.
+/// A directory with a chapter file ends the chain, as its content would be lost otherwise.
+pub(crate) fn collapse_single_child(link: &mut Link, joiner: Option<&str>) {
+    if link.location.is_some() {
+        return;
+    }
+    if !matches!(link.nested_items.as_slice(), [SummaryItem::Link(_)]) {
+        return;
+    }
+    let Some(SummaryItem::Link(child)) = link.nested_items.pop() else {
+        unreachable!("The only nested item is a link");
+    };
+    debug!(
+        "Collapsing the single chapter {} into its directory {}",
+        child.name, link.name
+    );
+    link.name = match joiner {
+        Some(joiner) => format!("{}{}{}", link.name, joiner, child.name),
+        None => child.name,
+    };
+    link.location = child.location;
+    link.nested_items = child.nested_items;
+    renumber(&mut link.nested_items, link.number.as_ref());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        config::Config,
+        summary_fs::MemoryFs,
+        test_util::{chapters, generate},
+    };
+
+    /// The chapters of a guide with the chain `advanced/internals/` of directories without a
+    /// chapter file, unless `landing_page` adds one to `advanced/`. The directories are collapsed
+    /// with `joiner`.
+    fn collapsed_chapters(landing_page: bool, joiner: Option<&str>) -> Vec<String> {
+        let mut fs = MemoryFs::new()
+            .with_file("/src/guide/README.md", "")
+            .with_file("/src/guide/setup.md", "")
+            .with_file("/src/guide/advanced/internals/overview.md", "");
+        if landing_page {
+            fs = fs.with_file("/src/guide/advanced/README.md", "");
+        }
+        let mut builder = Config::builder()
+            .ignore_missing_chapter_files(true)
+            .collapse_single_child_dirs(true);
+        if let Some(joiner) = joiner {
+            builder = builder.collapse_joiner(joiner);
+        }
+        let (summary, _) = generate(fs, Path::new("/src"), &builder.build().unwrap());
+        chapters(&summary.unwrap().numbered_chapters)
+    }
+
+    #[test]
+    fn collapses_chains_of_directories_with_a_single_chapter() {
+        assert_eq!(
+            collapsed_chapters(false, None),
+            [
+                "1. guide (guide/README.md)",
+                "1.1. overview (guide/advanced/internals/overview.md)",
+                "1.2. setup (guide/setup.md)",
+            ]
+        );
+    }
+
+    #[test]
+    fn ends_the_chain_at_a_chapter_file() {
+        assert_eq!(
+            collapsed_chapters(true, None),
+            [
+                "1. guide (guide/README.md)",
+                "1.1. advanced (guide/advanced/README.md)",
+                "1.1.1. overview (guide/advanced/internals/overview.md)",
+                "1.2. setup (guide/setup.md)",
+            ]
+        );
+    }
+
+    #[test]
+    fn joins_the_names_of_the_chain_with_the_collapse_joiner() {
+        assert_eq!(
+            collapsed_chapters(false, Some(" / ")),
+            [
+                "1. guide (guide/README.md)",
+                "1.1. advanced / internals / overview (guide/advanced/internals/overview.md)",
+                "1.2. setup (guide/setup.md)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,3156 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{anyhow, Error};
+use log::Level;
+use serde::{de::Error as _, Deserialize, Deserializer};
+use toml::{map::Map, value::Value};
+
+use crate::warnings::{Warning, WarningKind};
+
+/// Possible configuration options when running the preprocessor, read from the
+/// `[preprocessor.generate-summary]` table of book.toml. The options are described in the README.
+///
+/// It can be deserialized from the table, or created with [`Config::builder`]. Unknown keys of the
+/// table are not rejected (unless `strict_config` is set), but reported as a warning once the
+/// summary is generated.
+///
+/// # Example
+/// ```
+/// use mdbook_generate_summary::{Config, OnError};
+///
+/// let config: Config = toml::from_str(
+///     r#"
+///     chapter_file_name = "index"
+///     leaf_directories = ["blog"]
+///     "#,
+/// )?;
+///
+/// let config = Config::builder()
+///     .chapter_file_name("index")
+///     .leaf_directories(["blog"])
+///     .on_error(OnError::Skip)
+///     .build()?;
+///
+/// let invalid: Result<Config, _> = toml::from_str("max_depth = 0");
+/// assert!(invalid.is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Use the first line of the file and parse '# <chapter_name>' if set. Defaults to false.
+    pub(crate) get_chapter_name_from_file: bool,
+    /// Where the name of a chapter is taken from, the first one which has a name is used. Defaults
+    /// to the sidecar file, then the heading.
+    pub(crate) name_sources: Vec<NameSource>,
+    /// Read the title, weight, draft status and tags of the files and directories from their
+    /// sidecar files, `foo.md.toml` next to `foo.md` and `dir.toml` inside of a directory. Defaults
+    /// to false.
+    pub(crate) sidecar_files: bool,
+    /// Remove the front matter at the start of every chapter from the content passed on to the
+    /// renderer, the files are not changed. Defaults to false.
+    pub(crate) strip_frontmatter: bool,
+    /// The file to use for chapters with children. Defaults to 'README'.
+    /// Do not include the file extension as it will be '.md' anyways.
+    /// `{dir}` is replaced by the name of each directory, see [`Config::chapter_file_name_of`].
+    pub(crate) chapter_file_name: String,
+    /// Creates empty file with name chapter_file_name if it is missing in a directory. Defaults to
+    /// false, or to mdBook's `build.create-missing` if the configuration is read from book.toml.
+    /// When false the preprocessor fails if the file is <chapter_file_name>.md is missing in a
+    /// directory.
+    /// When "once" files are only created if mdBook is not watching the src directory, see
+    /// [`CreateMissingChapterFiles::Once`].
+    pub(crate) create_missing_chapter_files: CreateMissingChapterFiles,
+    /// If a create_missing_chapter_files is false, but the file is missing the implementations
+    /// fails by default.
+    /// Set this to true to instead use ignore the missing file.
+    pub(crate) ignore_missing_chapter_files: bool,
+    /// Directories (relative to the src directory) whose chapter file is included, but whose
+    /// children are not. Defaults to an empty list.
+    /// A directory can also be marked by setting `summary_leaf: true` in the front matter of its
+    /// chapter file.
+    pub(crate) leaf_directories: Vec<PathBuf>,
+    /// Directories (relative to the src directory) which are added, without section numbers, after
+    /// the numbered chapters. Defaults to an empty list.
+    /// A directory can also be marked by setting `summary_unnumbered: true` in the front matter of
+    /// its chapter file.
+    pub(crate) unnumbered_directories: Vec<PathBuf>,
+    /// Add directories which do not contain any markdown files as draft chapters, instead of
+    /// handling their missing chapter file. Defaults to false.
+    pub(crate) draft_empty_directories: bool,
+    /// Generate the chapters of directories containing a book.toml, which are books of their own.
+    /// Defaults to false, which leaves them out.
+    pub(crate) descend_into_nested_books: bool,
+    /// Follow symbolic links to markdown files and directories, keeping only the first entry
+    /// resolving to the same file or directory. Defaults to false, which leaves them out.
+    pub(crate) follow_symlinks: bool,
+    /// Only add the files which git tracks, and the directories containing tracked markdown files.
+    /// Defaults to false. Ignored with a warning outside of a git work tree.
+    pub(crate) git_tracked_only: bool,
+    /// Merge a chain of directories which each contain a single chapter, and have no chapter file
+    /// of their own, into one chapter. Defaults to false.
+    pub(crate) collapse_single_child_dirs: bool,
+    /// Joins the names of the chapters of a collapsed chain. Defaults to none, which uses the name
+    /// of the deepest chapter.
+    pub(crate) collapse_joiner: Option<String>,
+    /// Chapters which are not written yet, by the directory (relative to the src directory, empty
+    /// for the src directory itself) they are added to as drafts. Defaults to none.
+    pub(crate) planned: BTreeMap<PathBuf, Vec<PlannedChapter>>,
+    /// The file declaring the structure of the book, relative to the src directory. The markdown
+    /// files and directories it does not list are appended with a warning. Defaults to none, which
+    /// uses the directory layout.
+    pub(crate) outline: Option<PathBuf>,
+    /// Markdown files outside of the src directory which are added as chapters, relative to the
+    /// book root. Defaults to none.
+    pub(crate) external_chapters: Vec<ExternalChapter>,
+    /// Directories outside of the src directory whose chapters are added as if they were inside of
+    /// it. Defaults to none.
+    pub(crate) mounts: Vec<Mount>,
+    /// Commands converting files of other formats to markdown, whose files are added like markdown
+    /// files. Defaults to none.
+    pub(crate) converters: Vec<Converter>,
+    /// The directory the output of the `converters` is kept in, so that unchanged files are not
+    /// converted again. Defaults to none, which converts the files on every run.
+    pub(crate) converter_cache: Option<PathBuf>,
+    /// The front matter key the top level chapters are grouped by, `group_by = "frontmatter:<key>"`.
+    /// Defaults to none, which keeps the directory layout.
+    pub(crate) group_by: Option<String>,
+    /// The order of the groups, the groups which are not listed follow alphabetically. Defaults to
+    /// an empty list.
+    pub(crate) group_order: Vec<String>,
+    /// The group of the chapters without the `group_by` key. Defaults to "Other".
+    pub(crate) default_group: String,
+    /// How the groups are added to the summary. Defaults to [`GroupStyle::Part`].
+    pub(crate) group_style: GroupStyle,
+    /// Add a chapter listing all chapters of the book, e.g. for printed output. Defaults to false.
+    pub(crate) generate_toc_chapter: bool,
+    /// The title of the table of contents chapter. Defaults to "Table of contents".
+    pub(crate) toc_title: String,
+    /// Where the table of contents chapter is added, the first prefix chapter with
+    /// [`ChapterPosition::Prefix`]. Defaults to [`ChapterPosition::Prefix`].
+    pub(crate) toc_position: ChapterPosition,
+    /// Leave out the chapters which preprocessors running before this one added without a file,
+    /// instead of keeping them. Defaults to false.
+    pub(crate) drop_foreign_chapters: bool,
+    /// The title of a part at the end of the numbered chapters, which the chapters added by other
+    /// preprocessors are moved to. Defaults to none, which keeps them at the end of their parent
+    /// chapter.
+    pub(crate) foreign_chapters_part: Option<String>,
+    /// Start the content of nested chapters with links to their parent chapters, without changing
+    /// the files. Defaults to false.
+    pub(crate) inject_breadcrumbs: bool,
+    /// What is put between the breadcrumbs with `inject_breadcrumbs`. Defaults to " › ".
+    pub(crate) breadcrumb_separator: String,
+    /// Fail instead of warning about unknown keys in the preprocessor table. Defaults to false.
+    pub(crate) strict_config: bool,
+    /// What to do with entries which can not be added. Defaults to [`OnError::Fail`].
+    pub(crate) on_error: OnError,
+    /// `best_effort = true` from earlier versions, which sets `on_error = "skip"`. Defaults to
+    /// false.
+    pub(crate) best_effort: bool,
+    /// Still fail after generating the summary if any entry was skipped or added as a draft due to
+    /// `on_error`. Defaults to false.
+    pub(crate) fail_at_end: bool,
+    /// How to handle a file `foo.md` next to a directory `foo/`. Defaults to
+    /// [`FileDirectoryCollision::Warn`].
+    pub(crate) file_directory_collision: FileDirectoryCollision,
+    /// How deep directories may be nested in the src directory. Defaults to 1024.
+    pub(crate) max_depth: usize,
+    /// How many markdown files and directories may be found in the src directory, the generation
+    /// is aborted with an error once there are more. Defaults to 20000.
+    pub(crate) max_chapters: usize,
+    /// The section number of the first numbered chapter, the following top level chapters count
+    /// on from it. Defaults to 1.
+    pub(crate) first_section_number: usize,
+    /// Prefix the names of numbered chapters with their section number. Defaults to false.
+    pub(crate) number_in_names: bool,
+    /// Prefix the names of draft chapters as well with `number_in_names`. Defaults to false.
+    pub(crate) number_in_draft_names: bool,
+    /// What is put between the section number and the name with `number_in_names`. Defaults to
+    /// " — ".
+    pub(crate) number_separator: String,
+    /// Warn about relative links in the chapters to markdown files which are not part of the
+    /// book. Defaults to false.
+    pub(crate) check_links: bool,
+    /// Fail instead of warning if an existing SUMMARY.md differs from the generated summary.
+    /// Defaults to false.
+    pub(crate) fail_if_summary_differs: bool,
+    /// Pass the book on unchanged if the src directory has a hand-written SUMMARY.md listing
+    /// chapters. Defaults to false.
+    pub(crate) prefer_manual: bool,
+    /// How many bytes at the start of a chapter file are read to find its front matter and title.
+    /// Defaults to 4096.
+    pub(crate) max_title_bytes: usize,
+    /// How the chapters of a directory are ordered. Defaults to [`SortOrder::FileName`].
+    pub(crate) sort: SortOrder,
+    /// Keep the number at the start of headings in the chapter names with
+    /// [`SortOrder::HeadingNumber`]. Defaults to false.
+    pub(crate) keep_heading_numbers: bool,
+    /// The encoding the start of chapter files which are not valid UTF-8 is decoded with to find
+    /// their title. Defaults to none, invalid bytes are ignored then.
+    pub(crate) fallback_encoding: Option<Encoding>,
+    /// Chapter files whose content (without the front matter and the first heading) is shorter
+    /// than this many bytes are stubs, see `stub_behavior`. Defaults to none.
+    pub(crate) stub_threshold_bytes: Option<usize>,
+    /// Chapter files whose content (without the front matter and the first heading) has fewer
+    /// words are stubs, see `stub_behavior`. Defaults to none.
+    pub(crate) stub_threshold_words: Option<usize>,
+    /// What to do with stubs. Defaults to [`StubBehavior::Draft`].
+    pub(crate) stub_behavior: StubBehavior,
+    /// Fail after generating the summary if any warning was emitted. Defaults to false.
+    pub(crate) fail_on_warnings: bool,
+    /// Categories of warnings which are not emitted, see [`WarningKind::name`]. Defaults to an
+    /// empty list.
+    pub(crate) allow: Vec<WarningKind>,
+    /// How to check the generated summary before mdBook loads it. Defaults to
+    /// [`Validation::Error`].
+    pub(crate) validate: Validation,
+    /// Write the generated summary to SUMMARY.md in the src directory, if it changed. Defaults to
+    /// false.
+    pub(crate) write_summary_file: bool,
+    /// File to write the src-relative paths of all created chapter files to, relative to the book
+    /// root. A JSON array if the file ends in '.json', one path per line otherwise. Not written by
+    /// default.
+    pub(crate) created_files_manifest: Option<PathBuf>,
+    /// Add the chapter files created by `create_missing_chapter_files` to a block of the
+    /// `.gitignore` in the book root, and remove the ones replaced by real files from it. Fails
+    /// outside of a git repository. Defaults to false.
+    pub(crate) gitignore_created_files: bool,
+    /// File to write the generated summary to as JSON, relative to the book root. Not written by
+    /// default.
+    pub(crate) summary_json: Option<PathBuf>,
+    /// File to write the warnings and notable decisions of every run to as JSON, relative to the
+    /// book root. Not written by default.
+    pub(crate) diagnostics_json: Option<PathBuf>,
+    /// The only renderers to run for, if set. mdBook reads this key itself, so the preprocessor is
+    /// not even started for other renderers. Not set by default.
+    pub(crate) renderers: Option<Vec<String>>,
+    /// Renderers not to run for, the book is passed on unchanged for them. Defaults to an empty
+    /// list.
+    pub(crate) skip_renderers: Vec<String>,
+    /// Print the structural changes of the generated summary compared to an existing SUMMARY.md.
+    /// Defaults to false.
+    pub(crate) diff_against_existing: bool,
+    /// Log the informational messages (e.g. about created files) at debug instead of info level,
+    /// e.g. for `mdbook serve`. Defaults to false.
+    pub(crate) quiet: bool,
+    /// Log the statistics of the generation on one line per counter instead of on a single line.
+    /// Defaults to false.
+    pub(crate) verbose_stats: bool,
+    /// How many threads read the directories and chapter files before the chapters are generated,
+    /// 0 for one per CPU and 1 to read everything while generating the chapters. Defaults to 0.
+    pub(crate) threads: usize,
+    /// File to cache the titles and front matter of the chapter files in between runs, relative to
+    /// the book root. Not used by default.
+    pub(crate) title_cache: Option<PathBuf>,
+    /// Also cache the entries of every directory in the title cache, so that directories which did
+    /// not change since are not read again. Defaults to false.
+    pub(crate) incremental: bool,
+    /// Ignore the title cache and read everything again. Defaults to false.
+    pub(crate) full_scan: bool,
+    /// The title of the summary. Defaults to none, which mdBook shows as "Summary".
+    pub(crate) title: Option<String>,
+    /// The directories next to each other which contain the translations of the book, e.g.
+    /// `["en", "de"]` for `src/en` and `src/de`. Defaults to an empty list.
+    pub(crate) language_dirs: Vec<String>,
+    /// The language of the book, which selects the overrides of the `languages` table. Detected from
+    /// `book.src` if it is one of `language_dirs`, not set by default.
+    pub(crate) language: Option<String>,
+    /// Only print the generated summary and pass the book through unchanged, without creating or
+    /// writing any files. Defaults to false.
+    pub(crate) dry_run: bool,
+    /// Guarantee that nothing is written under the book root: the options which write files are
+    /// rejected and every write fails, see [`WriteGuard`](crate::write_guard::WriteGuard). mdBook's
+    /// `build.create-missing` is not used then. Defaults to false.
+    pub(crate) assert_no_writes: bool,
+    /// Warnings found while parsing the configuration. They are emitted once the run starts, so
+    /// that `allow` applies to them as well.
+    pub(crate) warnings: Vec<Warning>,
+    /// Directories inside of the src directory which are never traversed, as mdBook writes its
+    /// output to them (`build.build-dir`) or reads its theme from them. They are not configured,
+    /// but set by the preprocessor from book.toml.
+    pub(crate) excluded_directories: Vec<PathBuf>,
+}
+
+/// When to create missing chapter files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreateMissingChapterFiles {
+    Never,
+    Always,
+    /// Only create them if mdBook is not watching the src directory (e.g. `mdbook build`, but not
+    /// `mdbook serve`), as every created file would trigger another build. While watching, missing
+    /// chapter files are ignored instead.
+    Once,
+}
+
+/// A directory outside of the src directory (e.g. shared by several books) whose chapters are
+/// added as if it was the directory `at` inside of it, see [`ConfigBuilder::mount`]. Like for
+/// [`ExternalChapter`], the files are not copied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    /// The mounted directory. The preprocessor resolves it against the book root.
+    pub source: PathBuf,
+    /// The path of the directory within the book, relative to the src directory.
+    pub at: PathBuf,
+    /// The name of the chapter of the directory. Defaults to the name it would have inside of the
+    /// src directory.
+    pub title: Option<String>,
+}
+
+impl Mount {
+    /// Mount the directory `source` at the path `at` within the book.
+    pub fn new(source: impl Into<PathBuf>, at: impl Into<PathBuf>) -> Self {
+        Self {
+            source: source.into(),
+            at: at.into(),
+            title: None,
+        }
+    }
+
+    /// Name the chapter of the directory `title`.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+}
+
+/// A command converting the files with one of the `extensions` (e.g. AsciiDoc) to markdown, see
+/// [`ConfigBuilder::converter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Converter {
+    /// The extensions of the converted files, without the leading dot.
+    pub extensions: Vec<String>,
+    /// The program and its arguments, separated by whitespace. `{input}` is replaced by the path
+    /// of the converted file. The command has to print the markdown to stdout.
+    pub command: String,
+}
+
+impl Converter {
+    /// Convert the files with the `extensions` with `command`.
+    pub fn new<S: Into<String>>(
+        extensions: impl IntoIterator<Item = S>,
+        command: impl Into<String>,
+    ) -> Self {
+        Self {
+            extensions: extensions.into_iter().map(Into::into).collect(),
+            command: command.into(),
+        }
+    }
+}
+
+/// A markdown file outside of the src directory (e.g. the CHANGELOG.md of the repository) which
+/// is added as a chapter, see [`ConfigBuilder::external_chapter`]. Its content is added to the book
+/// by the preprocessor, the file is not copied into the src directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalChapter {
+    /// The markdown file. The preprocessor resolves it against the book root.
+    pub path: PathBuf,
+    /// The name of the chapter. Defaults to the `title` of its front matter or its heading, and
+    /// then to its file name.
+    pub title: Option<String>,
+    pub position: ChapterPosition,
+}
+
+impl ExternalChapter {
+    /// The file at `path` as a suffix chapter named after its title.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            title: None,
+            position: ChapterPosition::Suffix,
+        }
+    }
+
+    /// Name the chapter `title`.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Add the chapter at `position` of the summary.
+    pub fn position(mut self, position: ChapterPosition) -> Self {
+        self.position = position;
+        self
+    }
+}
+
+/// Where a chapter is added to the summary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChapterPosition {
+    /// Before the numbered chapters.
+    Prefix,
+    /// After the numbered chapters, numbered like them.
+    Numbered,
+    /// After all other chapters.
+    Suffix,
+}
+
+/// A chapter which is not written yet, added as a draft to the summary, see
+/// [`ConfigBuilder::planned`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedChapter {
+    pub title: String,
+    /// The position of the chapter among the chapters of its directory, counting from 1. The
+    /// chapter is added after the existing ones if it is [`Option::None`] or larger than their
+    /// number.
+    pub position: Option<usize>,
+}
+
+impl PlannedChapter {
+    /// A planned chapter added after the existing chapters of its directory.
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            position: None,
+        }
+    }
+
+    /// Add the chapter at `position` (counting from 1) among the chapters of its directory.
+    pub fn at(mut self, position: usize) -> Self {
+        self.position = Some(position);
+        self
+    }
+}
+
+/// A place the name of a chapter is taken from, see [`ConfigBuilder::name_sources`]. If none of
+/// them has a name, the file or directory name is used.
+///
+/// # Example
+/// ```
+/// use std::path::Path;
+///
+/// use mdbook_generate_summary::{Config, GenerateSummary, MemoryFs, NameSource, SummaryItem};
+///
+/// let fs = MemoryFs::new()
+///     .with_file("/src/intro.md", "# Introduction\n")
+///     .with_file("/src/intro.md.toml", "title = \"Welcome\"\nweight = 1\n")
+///     .with_file("/src/setup.md", "---\ntitle: Installation\n---\n# Setup\n")
+///     .with_file("/src/todo.md", "# To do\n")
+///     .with_file("/src/todo.md.toml", "draft = true\n");
+/// let config = Config::builder()
+///     .get_chapter_name_from_file(true)
+///     .sidecar_files(true)
+///     .name_sources([NameSource::Sidecar, NameSource::FrontMatter, NameSource::Heading])
+///     .build()?;
+/// let summary = GenerateSummary::new()
+///     .with_fs(Box::new(fs))
+///     .generate_summary(Path::new("/src"), &config)?;
+/// let chapters: Vec<_> = summary
+///     .numbered_chapters
+///     .iter()
+///     .filter_map(|item| match item {
+///         SummaryItem::Link(link) => Some((link.name.as_str(), link.location.is_some())),
+///         _ => None,
+///     })
+///     .collect();
+/// // intro.md is sorted last by its weight
+/// assert_eq!(
+///     chapters,
+///     [("Installation", true), ("To do", false), ("Welcome", true)]
+/// );
+///
+/// // Malformed sidecar files are ignored with a warning
+/// let fs = MemoryFs::new()
+///     .with_file("/src/intro.md", "")
+///     .with_file("/src/intro.md.toml", "weight = \"first\"\n");
+/// let config = Config::builder()
+///     .sidecar_files(true)
+///     .fail_on_warnings(true)
+///     .build()?;
+/// let error = GenerateSummary::new()
+///     .with_fs(Box::new(fs))
+///     .generate_summary(Path::new("/src"), &config)
+///     .unwrap_err();
+/// assert!(format!("{:#}", error).contains("Ignoring the sidecar file /src/intro.md.toml"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameSource {
+    /// The `title` of the sidecar file, with `sidecar_files`.
+    Sidecar,
+    /// The `title` key of the front matter of the chapter file, with `get_chapter_name_from_file`.
+    FrontMatter,
+    /// The first line of the chapter file if it looks like '# <title>', with
+    /// `get_chapter_name_from_file`.
+    Heading,
+}
+
+impl NameSource {
+    /// The name of the source in `name_sources`.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            NameSource::Sidecar => "sidecar",
+            NameSource::FrontMatter => "front_matter",
+            NameSource::Heading => "heading",
+        }
+    }
+}
+
+/// How the groups of `group_by` are added to the summary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupStyle {
+    /// A part title followed by the chapters of the group.
+    Part,
+    /// A draft chapter with the chapters of the group nested in it.
+    Chapter,
+}
+
+/// What to do with chapter files below `stub_threshold_bytes` or `stub_threshold_words`.
+/// The chapter file of a directory only affects the chapter of the directory, its nested chapters
+/// are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StubBehavior {
+    /// Add them as chapters like the other files.
+    Chapter,
+    /// Add them as draft chapters, which mdBook shows without a link.
+    Draft,
+    /// Leave them out. Directories become draft chapters instead.
+    Skip,
+}
+
+/// How the chapters of a directory are ordered, after their sidecar weight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// By file name.
+    FileName,
+    /// By the number at the start of their heading like "3." or "3.2", compared component-wise.
+    /// Chapters without one follow by file name. Requires `get_chapter_name_from_file`.
+    HeadingNumber,
+}
+
+/// An encoding of chapter files other than UTF-8, see `fallback_encoding`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// ISO 8859-1, every byte is the character of the same code point.
+    Latin1,
+    /// Windows-1252, Latin-1 with printable characters like '€' and '„' instead of most control
+    /// characters from 0x80 to 0x9F.
+    Windows1252,
+}
+
+/// How to handle an entry which can not be added, e.g. because it can not be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnError {
+    /// Collect the errors of all entries and fail with them.
+    Fail,
+    /// Leave the entry out of the summary and keep going.
+    Skip,
+    /// Add the entry as a draft chapter and keep going.
+    Draft,
+}
+
+/// How to check the generated summary before mdBook loads it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validation {
+    /// Do not check the summary.
+    Off,
+    /// Fail, naming every invalid chapter.
+    Error,
+    /// Fix the invalid chapters with a warning.
+    Fix,
+}
+
+/// The ways to handle a file `foo.md` next to a directory `foo/`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileDirectoryCollision {
+    /// Keep both and warn about it.
+    Warn,
+    /// Keep both without warning.
+    KeepBoth,
+    /// Only keep the directory. The file is used as its chapter file if it does not have one.
+    PreferDirectory,
+    /// Only keep the file.
+    PreferFile,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::try_from(&Map::new()).expect("The default configuration is valid")
+    }
+}
+
+impl<'de> Deserialize<'de> for Config {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let table = Map::deserialize(deserializer)?;
+        Config::try_from(&table).map_err(|error| D::Error::custom(format!("{:#}", error)))
+    }
+}
+
+impl TryFrom<&Map<String, Value>> for Config {
+    type Error = Error;
+
+    fn try_from(value: &Map<String, Value>) -> Result<Self, Self::Error> {
+        Self::from_table(value, &HashMap::new())
+    }
+}
+
+impl Config {
+    /// Read the configuration from the preprocessor table, with the keys of `environment` (the
+    /// values of environment variables by the key they set) overriding the ones of the table.
+    fn from_table(
+        value: &Map<String, Value>,
+        environment: &HashMap<String, String>,
+    ) -> Result<Self, Error> {
+        let merged = with_overrides(value, environment)?;
+        let table = Table::new(&merged, environment);
+        let mut warnings = vec![];
+        let mut config = Self {
+            get_chapter_name_from_file: table.get_bool("get_chapter_name_from_file")?,
+            name_sources: match table.get("name_sources") {
+                None => vec![NameSource::Sidecar, NameSource::Heading],
+                Some(_) => table
+                    .get_strings("name_sources")?
+                    .iter()
+                    .map(|source| match source.as_str() {
+                        "sidecar" => Ok(NameSource::Sidecar),
+                        "front_matter" => Ok(NameSource::FrontMatter),
+                        "heading" => Ok(NameSource::Heading),
+                        _ => Err(invalid_value(
+                            "name_sources",
+                            "an array out of \"sidecar\", \"front_matter\" and \"heading\"",
+                            source,
+                        )),
+                    })
+                    .collect::<Result<_, _>>()?,
+            },
+            sidecar_files: table.get_bool("sidecar_files")?,
+            strip_frontmatter: table.get_bool("strip_frontmatter")?,
+            chapter_file_name: validate_chapter_file_name(
+                table.get_string("chapter_file_name")?,
+                &mut warnings,
+            )?,
+            create_missing_chapter_files: match table.get("create_missing_chapter_files") {
+                None | Some(Value::Boolean(false)) => CreateMissingChapterFiles::Never,
+                Some(Value::Boolean(true)) => CreateMissingChapterFiles::Always,
+                Some(Value::String(value)) if value == "once" => CreateMissingChapterFiles::Once,
+                Some(value) => {
+                    return Err(invalid_type(
+                        "create_missing_chapter_files",
+                        "a boolean or \"once\"",
+                        value,
+                    ))
+                }
+            },
+            ignore_missing_chapter_files: table.get_bool("ignore_missing_chapter_files")?,
+            leaf_directories: table.get_paths("leaf_directories")?,
+            unnumbered_directories: table.get_paths("unnumbered_directories")?,
+            draft_empty_directories: table.get_bool("draft_empty_directories")?,
+            descend_into_nested_books: table.get_bool("descend_into_nested_books")?,
+            follow_symlinks: table.get_bool("follow_symlinks")?,
+            git_tracked_only: table.get_bool("git_tracked_only")?,
+            collapse_single_child_dirs: table.get_bool("collapse_single_child_dirs")?,
+            collapse_joiner: table.get_string("collapse_joiner")?,
+            planned: match table.get("planned") {
+                None => BTreeMap::new(),
+                Some(Value::Table(planned)) => parse_planned(planned)?,
+                Some(value) => return Err(invalid_type("planned", "a table", value)),
+            },
+            outline: table.get_string("outline")?.map(PathBuf::from),
+            mounts: match table.get("mount") {
+                None => vec![],
+                Some(Value::Array(mounts)) => parse_mounts(mounts)?,
+                Some(value) => return Err(invalid_type("mount", "an array of tables", value)),
+            },
+            converters: match table.get("converter") {
+                None => vec![],
+                Some(Value::Array(converters)) => parse_converters(converters)?,
+                Some(value) => return Err(invalid_type("converter", "an array of tables", value)),
+            },
+            converter_cache: table.get_string("converter_cache")?.map(PathBuf::from),
+            external_chapters: match table.get("external_chapters") {
+                None => vec![],
+                Some(Value::Array(chapters)) => parse_external_chapters(chapters)?,
+                Some(value) => {
+                    return Err(invalid_type(
+                        "external_chapters",
+                        "an array of tables",
+                        value,
+                    ))
+                }
+            },
+            group_by: match table.get_string("group_by")? {
+                None => None,
+                Some(value) => match value.strip_prefix("frontmatter:") {
+                    Some(key) if !key.trim().is_empty() => Some(key.trim().to_owned()),
+                    _ => return Err(invalid_value("group_by", "\"frontmatter:<key>\"", &value)),
+                },
+            },
+            group_order: table.get_strings("group_order")?,
+            default_group: table
+                .get_string("default_group")?
+                .unwrap_or_else(|| "Other".to_owned()),
+            group_style: match table.get_string("group_style")? {
+                None => GroupStyle::Part,
+                Some(value) => match value.as_str() {
+                    "part" => GroupStyle::Part,
+                    "chapter" => GroupStyle::Chapter,
+                    _ => {
+                        return Err(invalid_value(
+                            "group_style",
+                            "one of \"part\" or \"chapter\"",
+                            &value,
+                        ))
+                    }
+                },
+            },
+            generate_toc_chapter: table.get_bool("generate_toc_chapter")?,
+            toc_title: table
+                .get_string("toc_title")?
+                .unwrap_or_else(|| "Table of contents".to_owned()),
+            toc_position: match table.get_string("toc_position")? {
+                None => ChapterPosition::Prefix,
+                Some(position) => parse_chapter_position("toc_position", &position)?,
+            },
+            drop_foreign_chapters: table.get_bool("drop_foreign_chapters")?,
+            foreign_chapters_part: table.get_string("foreign_chapters_part")?,
+            inject_breadcrumbs: table.get_bool("inject_breadcrumbs")?,
+            breadcrumb_separator: table
+                .get_string("breadcrumb_separator")?
+                .unwrap_or_else(|| " › ".to_owned()),
+            strict_config: table.get_bool("strict_config")?,
+            best_effort: table.get_bool("best_effort")?,
+            on_error: match table.get_string("on_error")? {
+                None if table.get_bool("best_effort")? => OnError::Skip,
+                None => OnError::Fail,
+                Some(value) => match value.as_str() {
+                    "fail" => OnError::Fail,
+                    "skip" => OnError::Skip,
+                    "draft" => OnError::Draft,
+                    _ => {
+                        return Err(invalid_value(
+                            "on_error",
+                            "one of \"fail\", \"skip\" or \"draft\"",
+                            &value,
+                        ))
+                    }
+                },
+            },
+            fail_at_end: table.get_bool("fail_at_end")?,
+            max_depth: table.get_positive_integer("max_depth")?.unwrap_or(1024),
+            max_chapters: table.get_positive_integer("max_chapters")?.unwrap_or(20000),
+            first_section_number: table
+                .get_positive_integer("first_section_number")?
+                .unwrap_or(1),
+            number_in_names: table.get_bool("number_in_names")?,
+            number_in_draft_names: table.get_bool("number_in_draft_names")?,
+            number_separator: table
+                .get_string("number_separator")?
+                .unwrap_or_else(|| " — ".to_owned()),
+            check_links: table.get_bool("check_links")?,
+            fail_if_summary_differs: table.get_bool("fail_if_summary_differs")?,
+            prefer_manual: table.get_bool("prefer_manual")?,
+            max_title_bytes: table
+                .get_positive_integer("max_title_bytes")?
+                .unwrap_or(4096),
+            sort: match table.get_string("sort")? {
+                None => SortOrder::FileName,
+                Some(value) => match value.as_str() {
+                    "file-name" => SortOrder::FileName,
+                    "heading-number" => SortOrder::HeadingNumber,
+                    _ => {
+                        return Err(invalid_value(
+                            "sort",
+                            "one of \"file-name\" or \"heading-number\"",
+                            &value,
+                        ))
+                    }
+                },
+            },
+            keep_heading_numbers: table.get_bool("keep_heading_numbers")?,
+            fallback_encoding: match table.get_string("fallback_encoding")? {
+                None => None,
+                Some(value) => match value.as_str() {
+                    "latin1" => Some(Encoding::Latin1),
+                    "windows-1252" => Some(Encoding::Windows1252),
+                    _ => {
+                        return Err(invalid_value(
+                            "fallback_encoding",
+                            "one of \"latin1\" or \"windows-1252\"",
+                            &value,
+                        ))
+                    }
+                },
+            },
+            stub_threshold_bytes: table.get_positive_integer("stub_threshold_bytes")?,
+            stub_threshold_words: table.get_positive_integer("stub_threshold_words")?,
+            stub_behavior: match table.get_string("stub_behavior")? {
+                None => StubBehavior::Draft,
+                Some(value) => match value.as_str() {
+                    "chapter" => StubBehavior::Chapter,
+                    "draft" => StubBehavior::Draft,
+                    "skip" => StubBehavior::Skip,
+                    _ => {
+                        return Err(invalid_value(
+                            "stub_behavior",
+                            "one of \"chapter\", \"draft\" or \"skip\"",
+                            &value,
+                        ))
+                    }
+                },
+            },
+            fail_on_warnings: table.get_bool("fail_on_warnings")?,
+            allow: table
+                .get_strings("allow")?
+                .into_iter()
+                .map(|name| {
+                    WarningKind::from_name(&name).ok_or_else(|| {
+                        let names: Vec<_> = WarningKind::ALL
+                            .into_iter()
+                            .map(|kind| format!("\"{}\"", kind.name()))
+                            .collect();
+                        invalid_value(
+                            "allow",
+                            &format!("warning categories out of {}", names.join(", ")),
+                            &name,
+                        )
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+            validate: match table.get_string("validate")? {
+                None => Validation::Error,
+                Some(value) => match value.as_str() {
+                    "off" => Validation::Off,
+                    "error" => Validation::Error,
+                    "fix" => Validation::Fix,
+                    _ => {
+                        return Err(invalid_value(
+                            "validate",
+                            "one of \"off\", \"error\" or \"fix\"",
+                            &value,
+                        ))
+                    }
+                },
+            },
+            write_summary_file: table.get_bool("write_summary_file")?,
+            created_files_manifest: table
+                .get_string("created_files_manifest")?
+                .map(PathBuf::from),
+            gitignore_created_files: table.get_bool("gitignore_created_files")?,
+            summary_json: table.get_string("summary_json")?.map(PathBuf::from),
+            diagnostics_json: table.get_string("diagnostics_json")?.map(PathBuf::from),
+            renderers: match table.get("renderers") {
+                None => None,
+                Some(_) => Some(table.get_strings("renderers")?),
+            },
+            skip_renderers: table.get_strings("skip_renderers")?,
+            diff_against_existing: table.get_bool("diff_against_existing")?,
+            quiet: table.get_bool("quiet")?,
+            verbose_stats: table.get_bool("verbose_stats")?,
+            threads: match table.get("threads") {
+                None => 0,
+                Some(value) => {
+                    let integer = value
+                        .as_integer()
+                        .ok_or_else(|| invalid_type("threads", "an integer", value))?;
+                    usize::try_from(integer).map_err(|_| {
+                        invalid_value("threads", "0 or a positive integer", &integer.to_string())
+                    })?
+                }
+            },
+            title_cache: table.get_string("title_cache")?.map(PathBuf::from),
+            incremental: table.get_bool("incremental")?,
+            full_scan: table.get_bool("full_scan")?,
+            title: table.get_string("title")?,
+            language_dirs: table.get_strings("language_dirs")?,
+            language: table.get_string("language")?,
+            dry_run: table.get_bool("dry_run")?,
+            assert_no_writes: table.get_bool("assert_no_writes")?,
+            warnings: vec![],
+            excluded_directories: vec![],
+            file_directory_collision: match table.get_string("file_directory_collision")? {
+                None => FileDirectoryCollision::Warn,
+                Some(value) => match value.as_str() {
+                    "warn" => FileDirectoryCollision::Warn,
+                    "keep_both" => FileDirectoryCollision::KeepBoth,
+                    "prefer_directory" => FileDirectoryCollision::PreferDirectory,
+                    "prefer_file" => FileDirectoryCollision::PreferFile,
+                    _ => return Err(invalid_value(
+                        "file_directory_collision",
+                        "one of \"warn\", \"keep_both\", \"prefer_directory\" or \"prefer_file\"",
+                        &value,
+                    )),
+                },
+            },
+        };
+        config.validate()?;
+
+        if let Some(Value::Table(languages)) = table.get("languages") {
+            for language in languages.keys() {
+                if !config.language_dirs.contains(language) {
+                    warnings.push(Warning {
+                        kind: WarningKind::UnknownConfigKeys,
+                        message: format!(
+                            "`preprocessor.generate-summary.languages.{}` is not used, as `{}` is \
+                             not one of `language_dirs`",
+                            language, language
+                        ),
+                    });
+                }
+            }
+        }
+
+        let unknown_keys = table.unknown_keys();
+        if !unknown_keys.is_empty() {
+            if config.strict_config {
+                return Err(anyhow!(
+                    "Unknown configuration keys:\n{}",
+                    unknown_keys.join("\n")
+                ));
+            }
+            for unknown_key in unknown_keys {
+                warnings.push(Warning {
+                    kind: WarningKind::UnknownConfigKeys,
+                    message: unknown_key,
+                });
+            }
+        }
+
+        config.warnings = warnings;
+        Ok(config)
+    }
+
+    /// Read the configuration for `renderer` from the preprocessor table. The keys of a nested
+    /// table named like the renderer (e.g. `[preprocessor.generate-summary.html]`) override the
+    /// ones of the preprocessor table. Nested tables of other renderers are ignored, unless they
+    /// are not one of the `configured_renderers` of the book, which is reported as a warning.
+    /// Unless `language` is set, it is the last directory of `src_dir` if that is one of
+    /// `language_dirs`. Unless `create_missing_chapter_files` is set, it is mdBook's
+    /// `create_missing` (`build.create-missing`), see `inherit_create_missing`.
+    /// The `MDBOOK_GENERATE_SUMMARY__<KEY>` environment variables override all of them.
+    #[cfg(feature = "preprocessor")]
+    pub(crate) fn for_renderer(
+        table: &Map<String, Value>,
+        renderer: Option<&str>,
+        configured_renderers: &[&str],
+        src_dir: &std::path::Path,
+        create_missing: bool,
+    ) -> Result<Self, Error> {
+        Self::for_renderer_with_environment(
+            table,
+            renderer,
+            configured_renderers,
+            src_dir,
+            create_missing,
+            &environment_overrides(),
+        )
+    }
+
+    /// Like [`Config::for_renderer`], with the keys of `environment` instead of the ones of the
+    /// environment variables, see [`Config::from_table`].
+    #[cfg(feature = "preprocessor")]
+    fn for_renderer_with_environment(
+        table: &Map<String, Value>,
+        renderer: Option<&str>,
+        configured_renderers: &[&str],
+        src_dir: &std::path::Path,
+        create_missing: bool,
+        environment: &HashMap<String, String>,
+    ) -> Result<Self, Error> {
+        let mut merged = Map::new();
+        let mut unknown_renderers = vec![];
+        for (key, value) in table {
+            if !value.is_table() || TABLE_KEYS.contains(&key.as_str()) {
+                merged.insert(key.clone(), value.clone());
+            } else if !configured_renderers.contains(&key.as_str()) {
+                unknown_renderers.push(key.as_str());
+            }
+        }
+        if let Some(Value::Table(overrides)) = renderer.and_then(|renderer| table.get(renderer)) {
+            for (key, value) in overrides {
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+        if !merged.contains_key("language") {
+            let src_name = src_dir.file_name().and_then(|name| name.to_str());
+            let is_language_dir =
+                |value: &Value| value.as_str().is_some() && value.as_str() == src_name;
+            if let Some(Value::Array(language_dirs)) = merged.get("language_dirs") {
+                if let Some(language) = language_dirs.iter().find(|value| is_language_dir(value)) {
+                    merged.insert("language".to_owned(), language.clone());
+                }
+            }
+        }
+
+        inherit_create_missing(&mut merged, environment, create_missing)?;
+        let mut config = match Self::from_table(&merged, environment) {
+            Err(error) if !environment.is_empty() => {
+                let mut variables: Vec<_> =
+                    environment.keys().map(|key| env_variable(key)).collect();
+                variables.sort();
+                return Err(error.context(format!(
+                    "Invalid configuration, including the environment variables {}",
+                    variables.join(", ")
+                )));
+            }
+            config => config?,
+        };
+        for renderer in unknown_renderers {
+            config.warnings.push(Warning {
+                kind: WarningKind::UnknownConfigKeys,
+                message: format!(
+                    "`preprocessor.generate-summary.{}` is not used, as the book has no `{}` \
+                     renderer",
+                    renderer, renderer
+                ),
+            });
+        }
+        Ok(config)
+    }
+
+    /// Create a configuration in code, starting with the defaults.
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder {
+            config: Config::default(),
+            chapter_file_name: None,
+        }
+    }
+
+    /// Returns false if the preprocessor should not run for the renderer, according to `renderers`
+    /// and `skip_renderers`.
+    #[cfg(feature = "preprocessor")]
+    pub(crate) fn runs_for_renderer(&self, renderer: &str) -> bool {
+        match &self.renderers {
+            Some(renderers) => renderers.iter().any(|name| name == renderer),
+            None => !self.skip_renderers.iter().any(|name| name == renderer),
+        }
+    }
+
+    /// The level of informational messages, see `quiet`.
+    pub(crate) fn info_level(&self) -> Level {
+        if self.quiet {
+            Level::Debug
+        } else {
+            Level::Info
+        }
+    }
+
+    /// The `chapter_file_name` of the directory at `dir`, with `{dir}` replaced by the name of the
+    /// directory. For the src directory it is the name of the src directory, e.g. `src.md`.
+    pub(crate) fn chapter_file_name_of(&self, dir: &Path) -> Cow<'_, str> {
+        match dir.file_name() {
+            Some(name) if self.chapter_file_name.contains(DIR_PLACEHOLDER) => Cow::Owned(
+                self.chapter_file_name
+                    .replace(DIR_PLACEHOLDER, &name.to_string_lossy()),
+            ),
+            _ => Cow::Borrowed(&self.chapter_file_name),
+        }
+    }
+
+    /// The converter of the file at `path`, if it is not a markdown file but converted to one.
+    pub(crate) fn converter(&self, path: &Path) -> Option<&Converter> {
+        let extension = path.extension()?;
+        self.converters.iter().find(|converter| {
+            converter
+                .extensions
+                .iter()
+                .any(|converted| extension == converted.as_str())
+        })
+    }
+
+    /// The number of threads to use, see `threads`.
+    pub(crate) fn thread_count(&self) -> usize {
+        match self.threads {
+            0 => std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+            threads => threads,
+        }
+    }
+
+    /// Reject combinations of options which contradict each other.
+    /// Every conflict is listed together with the alternatives which are supported instead.
+    fn validate(&self) -> Result<(), Error> {
+        if let Some(language) = &self.language {
+            if !self.language_dirs.is_empty() && !self.language_dirs.contains(language) {
+                return Err(invalid_value(
+                    "language",
+                    "one of `language_dirs`",
+                    language,
+                ));
+            }
+        }
+        for converter in &self.converters {
+            if let Some(extension) = converter
+                .extensions
+                .iter()
+                .find(|extension| extension.is_empty() || *extension == "md")
+            {
+                return Err(invalid_value(
+                    "converter.extensions",
+                    "extensions other than \"md\"",
+                    extension,
+                ));
+            }
+            if !converter.command.contains("{input}") {
+                return Err(invalid_value(
+                    "converter.command",
+                    "a command containing `{input}`",
+                    &converter.command,
+                ));
+            }
+        }
+        if self.assert_no_writes {
+            let writing: Vec<_> = [
+                (
+                    self.create_missing_chapter_files != CreateMissingChapterFiles::Never,
+                    "create_missing_chapter_files",
+                ),
+                (self.write_summary_file, "write_summary_file"),
+                (
+                    self.created_files_manifest.is_some(),
+                    "created_files_manifest",
+                ),
+                (self.gitignore_created_files, "gitignore_created_files"),
+                (self.summary_json.is_some(), "summary_json"),
+                (self.diagnostics_json.is_some(), "diagnostics_json"),
+                (self.title_cache.is_some(), "title_cache"),
+                (self.converter_cache.is_some(), "converter_cache"),
+            ]
+            .into_iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, key)| format!("`{}`", key))
+            .collect();
+            if !writing.is_empty() {
+                return Err(anyhow!(
+                    "`preprocessor.generate-summary.assert_no_writes` forbids writing files, but \
+                     {} would write to the book. Disable them or `assert_no_writes`",
+                    writing.join(", ")
+                ));
+            }
+        }
+
+        // Every pair of options which contradict each other, or where one needs the other
+        let conflicts = [
+            (
+                self.create_missing_chapter_files != CreateMissingChapterFiles::Never
+                    && self.ignore_missing_chapter_files,
+                "create_missing_chapter_files",
+                "can not be used together with",
+                "ignore_missing_chapter_files",
+                "Missing chapter files can either be created or ignored. Enable only one of them \
+                 or use `draft_empty_directories` to add directories without any content as \
+                 drafts",
+            ),
+            (
+                // `on_error` is only something else if it is set
+                self.best_effort && self.on_error != OnError::Skip,
+                "best_effort",
+                "can not be used together with",
+                "on_error",
+                "Replace `best_effort = true` with `on_error = \"skip\"`",
+            ),
+            (
+                self.incremental && self.title_cache.is_none(),
+                "incremental",
+                "needs",
+                "title_cache",
+                "It is the file the directories are cached in. Set it or disable `incremental`",
+            ),
+        ];
+
+        let errors: Vec<_> = conflicts
+            .into_iter()
+            .filter(|(conflicting, ..)| *conflicting)
+            .map(|(_, key, relation, other_key, hint)| {
+                format!("`{}` {} `{}`. {}", key, relation, other_key, hint)
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Conflicting configuration in `preprocessor.generate-summary`:\n{}",
+                errors.join("\n")
+            ))
+        }
+    }
+}
+
+/// Builds a [`Config`], see [`Config::builder`]. Every option which is not set keeps its default.
+/// The options are checked like in book.toml once [`ConfigBuilder::build`] is called.
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder {
+    config: Config,
+    /// The chapter file name as given, it is checked by [`ConfigBuilder::build`].
+    chapter_file_name: Option<String>,
+}
+
+/// Define a setter of the builder for every option.
+macro_rules! setters {
+    ($($(#[$doc:meta])* $field:ident: $type:ty;)*) => {
+        $(
+            $(#[$doc])*
+            pub fn $field(mut self, $field: $type) -> Self {
+                self.config.$field = $field;
+                self
+            }
+        )*
+    };
+}
+
+impl ConfigBuilder {
+    setters! {
+        /// See `get_chapter_name_from_file`.
+        get_chapter_name_from_file: bool;
+        /// See `sidecar_files`.
+        sidecar_files: bool;
+        /// See `create_missing_chapter_files`.
+        create_missing_chapter_files: CreateMissingChapterFiles;
+        /// See `ignore_missing_chapter_files`.
+        ignore_missing_chapter_files: bool;
+        /// See `draft_empty_directories`.
+        draft_empty_directories: bool;
+        /// See `collapse_single_child_dirs`.
+        collapse_single_child_dirs: bool;
+        /// See `on_error`.
+        on_error: OnError;
+        /// See `fail_at_end`.
+        fail_at_end: bool;
+        /// See `file_directory_collision`.
+        file_directory_collision: FileDirectoryCollision;
+        /// See `max_depth`, it must be positive.
+        max_depth: usize;
+        /// See `fail_if_summary_differs`.
+        fail_if_summary_differs: bool;
+        /// See `max_title_bytes`, it must be positive.
+        max_title_bytes: usize;
+        /// See `stub_behavior`.
+        stub_behavior: StubBehavior;
+        /// See `keep_heading_numbers`.
+        keep_heading_numbers: bool;
+        /// See `number_in_draft_names`.
+        number_in_draft_names: bool;
+        /// See `fail_on_warnings`.
+        fail_on_warnings: bool;
+        /// See `validate`.
+        validate: Validation;
+        /// See `write_summary_file`.
+        write_summary_file: bool;
+        /// See `diff_against_existing`.
+        diff_against_existing: bool;
+        /// See `quiet`.
+        quiet: bool;
+        /// See `verbose_stats`.
+        verbose_stats: bool;
+        /// See `threads`.
+        threads: usize;
+        /// See `incremental`, it needs `title_cache`.
+        incremental: bool;
+        /// See `full_scan`.
+        full_scan: bool;
+        /// See `dry_run`.
+        dry_run: bool;
+        /// See `group_style`.
+        group_style: GroupStyle;
+        /// See `generate_toc_chapter`.
+        generate_toc_chapter: bool;
+        /// See `toc_position`.
+        toc_position: ChapterPosition;
+        /// See `drop_foreign_chapters`.
+        drop_foreign_chapters: bool;
+        /// See `git_tracked_only`.
+        git_tracked_only: bool;
+    }
+
+    /// See `strip_frontmatter`. Only the chapters passed on to the renderer are changed when
+    /// running as preprocessor, not the files.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(feature = "preprocessor")]
+    /// # {
+    /// use mdbook_generate_summary::TestBook;
+    ///
+    /// let output = TestBook::new()
+    ///     .file("intro.md", "---\ntitle: Introduction\n---\n# Introduction\n")
+    ///     .config(toml::toml! { strip_frontmatter = true })
+    ///     .run()?;
+    /// assert_eq!(output.content("intro.md"), Some("# Introduction\n"));
+    /// # }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn strip_frontmatter(mut self, strip_frontmatter: bool) -> Self {
+        self.config.strip_frontmatter = strip_frontmatter;
+        self
+    }
+
+    /// See `inject_breadcrumbs`. They are added to the chapters passed on to the renderer when
+    /// running as preprocessor, not to the files.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(feature = "preprocessor")]
+    /// # {
+    /// use mdbook_generate_summary::TestBook;
+    ///
+    /// let output = TestBook::new()
+    ///     .file("guide/README.md", "# Guide\n")
+    ///     .file("guide/setup.md", "# Setup\n")
+    ///     .config(toml::toml! { inject_breadcrumbs = true })
+    ///     .run()?;
+    /// assert_eq!(
+    ///     output.content("guide/setup.md"),
+    ///     Some("[guide](../guide/README.md) › setup\n\n# Setup\n")
+    /// );
+    /// # }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn inject_breadcrumbs(mut self, inject_breadcrumbs: bool) -> Self {
+        self.config.inject_breadcrumbs = inject_breadcrumbs;
+        self
+    }
+
+    /// See `prefer_manual`. A SUMMARY.md without chapters, like the `# Summary` placeholder, is
+    /// not written by hand.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(feature = "preprocessor")]
+    /// # {
+    /// use mdbook_generate_summary::TestBook;
+    ///
+    /// let output = TestBook::new()
+    ///     .file("SUMMARY.md", "# Summary\n\n- [Usage](usage.md)\n")
+    ///     .file("intro.md", "# Introduction\n")
+    ///     .file("usage.md", "# Usage\n")
+    ///     .config(toml::toml! { prefer_manual = true })
+    ///     .run()?;
+    /// assert_eq!(output.chapter_names(), ["1. Usage"]);
+    /// # }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn prefer_manual(mut self, prefer_manual: bool) -> Self {
+        self.config.prefer_manual = prefer_manual;
+        self
+    }
+
+    /// See `check_links`. The links are checked when running as preprocessor, and fail the build
+    /// with `fail_on_warnings`.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(feature = "preprocessor")]
+    /// # {
+    /// use mdbook_generate_summary::TestBook;
+    ///
+    /// let output = TestBook::new()
+    ///     .file("intro.md", "# Introduction\n\nSee the [usage](usage.md).\n")
+    ///     .config(toml::toml! { check_links = true })
+    ///     .run()?;
+    /// assert_eq!(
+    ///     output.warnings()[0].message,
+    ///     "intro.md:3 links to usage.md, which does not exist"
+    /// );
+    /// # }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn check_links(mut self, check_links: bool) -> Self {
+        self.config.check_links = check_links;
+        self
+    }
+
+    /// See `gitignore_created_files`. The files are listed in a block of the `.gitignore` in the
+    /// book root until a real file replaces them, the book has to be inside of a git work tree.
+    pub fn gitignore_created_files(mut self, gitignore_created_files: bool) -> Self {
+        self.config.gitignore_created_files = gitignore_created_files;
+        self
+    }
+
+    /// See `chapter_file_name`. Names are compared after Unicode normalization, so that it matches
+    /// whether the name of the file is composed (NFC) or decomposed (NFD, as stored by macOS).
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{Config, GenerateSummary, MemoryFs, SummaryItem};
+    ///
+    /// // "übersicht" composed and decomposed
+    /// let (composed, decomposed) = ("\u{fc}bersicht", "u\u{308}bersicht");
+    /// for (configured, on_disk) in [(composed, decomposed), (decomposed, composed)] {
+    ///     let fs = MemoryFs::new()
+    ///         .with_file(format!("/src/guide/{}.md", on_disk), "# Guide\n")
+    ///         .with_file("/src/guide/setup.md", "# Setup\n");
+    ///     let config = Config::builder().chapter_file_name(configured).build()?;
+    ///     let summary = GenerateSummary::new()
+    ///         .with_fs(Box::new(fs))
+    ///         .generate_summary(Path::new("/src"), &config)?;
+    ///
+    ///     let SummaryItem::Link(guide) = &summary.numbered_chapters[0] else {
+    ///         unreachable!()
+    ///     };
+    ///     // The link has the name of the file on disk, which is not a chapter of its own
+    ///     let location = format!("guide/{}.md", on_disk);
+    ///     assert_eq!(guide.location.as_deref(), Some(Path::new(&location)));
+    ///     assert_eq!(guide.nested_items.len(), 1);
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// `{dir}` is replaced by the name of each directory, so that the chapter file of
+    /// `networking/` can be `networking/networking.md`:
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{summary_to_markdown, Config, GenerateSummary, MemoryFs};
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/networking/networking.md", "")
+    ///     .with_file("/src/networking/dns.md", "");
+    /// let config = Config::builder().chapter_file_name("{dir}").build()?;
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/src"), &config)?;
+    /// assert_eq!(
+    ///     summary_to_markdown(&summary),
+    ///     "# Summary\n\n\
+    ///      - [networking](networking/networking.md)\n    \
+    ///          - [dns](networking/dns.md)\n"
+    /// );
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn chapter_file_name(mut self, chapter_file_name: impl Into<String>) -> Self {
+        self.chapter_file_name = Some(chapter_file_name.into());
+        self
+    }
+
+    /// See `name_sources` and [`NameSource`].
+    pub fn name_sources(mut self, name_sources: impl IntoIterator<Item = NameSource>) -> Self {
+        self.config.name_sources = name_sources.into_iter().collect();
+        self
+    }
+
+    /// See `leaf_directories`.
+    pub fn leaf_directories<P: Into<PathBuf>>(
+        mut self,
+        leaf_directories: impl IntoIterator<Item = P>,
+    ) -> Self {
+        self.config.leaf_directories = leaf_directories.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// See `unnumbered_directories`.
+    pub fn unnumbered_directories<P: Into<PathBuf>>(
+        mut self,
+        unnumbered_directories: impl IntoIterator<Item = P>,
+    ) -> Self {
+        self.config.unnumbered_directories =
+            unnumbered_directories.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Add the `chapters` to the `planned` chapters of the `directory`, relative to the src
+    /// directory. Use an empty path for the src directory itself.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{
+    ///     Config, GenerateSummary, MemoryFs, PlannedChapter, SummaryItem,
+    /// };
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/guide/README.md", "")
+    ///     .with_file("/src/guide/setup.md", "");
+    /// let config = Config::builder()
+    ///     .planned("guide", [PlannedChapter::new("Basics").at(1)])
+    ///     .build()?;
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/src"), &config)?;
+    ///
+    /// let SummaryItem::Link(guide) = &summary.numbered_chapters[0] else {
+    ///     unreachable!()
+    /// };
+    /// let SummaryItem::Link(basics) = &guide.nested_items[0] else {
+    ///     unreachable!()
+    /// };
+    /// // A draft until its file is written
+    /// assert_eq!(basics.name, "Basics");
+    /// assert_eq!(basics.location, None);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn planned<P: Into<PathBuf>>(
+        mut self,
+        directory: P,
+        chapters: impl IntoIterator<Item = PlannedChapter>,
+    ) -> Self {
+        self.config
+            .planned
+            .entry(directory.into())
+            .or_default()
+            .extend(chapters);
+        self
+    }
+
+    /// Add the `chapter` to the `external_chapters`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{
+    ///     summary_to_markdown, Config, ExternalChapter, GenerateSummary, MemoryFs,
+    /// };
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/book/src/intro.md", "")
+    ///     .with_file("/book/CHANGELOG.md", "");
+    /// let config = Config::builder()
+    ///     .external_chapter(ExternalChapter::new("/book/CHANGELOG.md").title("Changes"))
+    ///     .build()?;
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/book/src"), &config)?;
+    /// assert_eq!(
+    ///     summary_to_markdown(&summary),
+    ///     "# Summary\n\n- [intro](intro.md)\n\n[Changes](CHANGELOG.md)\n"
+    /// );
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn external_chapter(mut self, chapter: ExternalChapter) -> Self {
+        self.config.external_chapters.push(chapter);
+        self
+    }
+
+    /// Add the `mount` to the mounted directories.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{
+    ///     summary_to_markdown, Config, GenerateSummary, MemoryFs, Mount,
+    /// };
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/book/src/intro.md", "")
+    ///     .with_file("/shared/concepts/README.md", "");
+    /// let config = Config::builder()
+    ///     .mount(Mount::new("/shared/concepts", "concepts"))
+    ///     .build()?;
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/book/src"), &config)?;
+    /// assert_eq!(
+    ///     summary_to_markdown(&summary),
+    ///     "# Summary\n\n- [concepts](concepts/README.md)\n- [intro](intro.md)\n"
+    /// );
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn mount(mut self, mount: Mount) -> Self {
+        self.config.mounts.push(mount);
+        self
+    }
+
+    /// See `descend_into_nested_books`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{summary_to_markdown, Config, GenerateSummary, MemoryFs};
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/intro.md", "")
+    ///     .with_file("/src/embedded/book.toml", "[book]\n")
+    ///     .with_file("/src/embedded/src/inner.md", "");
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/src"), &Config::default())?;
+    /// // The nested book is left out
+    /// assert_eq!(summary_to_markdown(&summary), "# Summary\n\n- [intro](intro.md)\n");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn descend_into_nested_books(mut self, descend_into_nested_books: bool) -> Self {
+        self.config.descend_into_nested_books = descend_into_nested_books;
+        self
+    }
+
+    /// See `follow_symlinks`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{summary_to_markdown, Config, GenerateSummary, MemoryFs};
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/shared/intro.md", "")
+    ///     .with_symlink("/src/intro.md", "/shared/intro.md");
+    /// let config = Config::builder().follow_symlinks(true).build()?;
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/src"), &config)?;
+    /// assert_eq!(summary_to_markdown(&summary), "# Summary\n\n- [intro](intro.md)\n");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.config.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Add the `converter`, see `converter`. Its files are added as chapters like markdown files,
+    /// at the location with the extension `.md`.
+    ///
+    /// # Example
+    /// ```
+    /// use mdbook_generate_summary::{generate_summary, Config, Converter, SummaryItem};
+    ///
+    /// let src_dir = std::env::temp_dir().join(format!(
+    ///     "generate-summary-converter-doctest-{}",
+    ///     std::process::id()
+    /// ));
+    /// std::fs::create_dir_all(&src_dir)?;
+    /// std::fs::write(src_dir.join("intro.md"), "# Introduction\n")?;
+    /// std::fs::write(src_dir.join("usage.adoc"), "= Usage\n\nRun it.\n")?;
+    ///
+    /// // Replaces the AsciiDoc title by a markdown heading
+    /// let converter = Converter::new(["adoc"], "sed -e s/^=/#/ {input}");
+    /// let config = Config::builder()
+    ///     .get_chapter_name_from_file(true)
+    ///     .converter(converter)
+    ///     .build()?;
+    /// let summary = generate_summary(&src_dir, &config)?;
+    /// let links: Vec<_> = summary
+    ///     .numbered_chapters
+    ///     .iter()
+    ///     .filter_map(|item| match item {
+    ///         SummaryItem::Link(link) => Some((link.name.as_str(), link.location.clone().unwrap())),
+    ///         _ => None,
+    ///     })
+    ///     .collect();
+    /// assert_eq!(
+    ///     links,
+    ///     [("Introduction", "intro.md".into()), ("Usage", "usage.md".into())]
+    /// );
+    ///
+    /// // The command must know which file to convert
+    /// let converter = Converter::new(["adoc"], "asciidoctor -b markdown -o -");
+    /// assert!(Config::builder().converter(converter).build().is_err());
+    /// # std::fs::remove_dir_all(&src_dir)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn converter(mut self, converter: Converter) -> Self {
+        self.config.converters.push(converter);
+        self
+    }
+
+    /// See `converter_cache`.
+    pub fn converter_cache(mut self, converter_cache: impl Into<PathBuf>) -> Self {
+        self.config.converter_cache = Some(converter_cache.into());
+        self
+    }
+
+    /// See `assert_no_writes`. The options which write files are rejected with it.
+    ///
+    /// # Example
+    /// ```
+    /// use mdbook_generate_summary::{Config, CreateMissingChapterFiles};
+    ///
+    /// let writing = [
+    ///     Config::builder().create_missing_chapter_files(CreateMissingChapterFiles::Always),
+    ///     Config::builder().create_missing_chapter_files(CreateMissingChapterFiles::Once),
+    ///     Config::builder().write_summary_file(true),
+    ///     Config::builder().created_files_manifest("created.txt"),
+    ///     Config::builder().gitignore_created_files(true),
+    ///     Config::builder().summary_json("summary.json"),
+    ///     Config::builder().diagnostics_json("diagnostics.json"),
+    ///     Config::builder().title_cache(".generate-summary-cache.json"),
+    ///     Config::builder().converter_cache(".generate-summary-converted"),
+    /// ];
+    /// for builder in writing {
+    ///     assert!(builder.clone().build().is_ok());
+    ///     let error = builder.assert_no_writes(true).build().unwrap_err();
+    ///     assert!(error.to_string().contains("assert_no_writes` forbids writing files"));
+    /// }
+    /// assert!(Config::builder().assert_no_writes(true).dry_run(true).build().is_ok());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn assert_no_writes(mut self, assert_no_writes: bool) -> Self {
+        self.config.assert_no_writes = assert_no_writes;
+        self
+    }
+
+    /// See `stub_threshold_bytes`, it must be positive.
+    pub fn stub_threshold_bytes(mut self, stub_threshold_bytes: usize) -> Self {
+        self.config.stub_threshold_bytes = Some(stub_threshold_bytes);
+        self
+    }
+
+    /// See `stub_threshold_words`, it must be positive.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{summary_to_markdown, Config, GenerateSummary, MemoryFs};
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/intro.md", "# Introduction\nWhat this book is about.\n")
+    ///     .with_file("/src/usage.md", "# Usage\nTBD\n");
+    /// let config = Config::builder().stub_threshold_words(2).build()?;
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/src"), &config)?;
+    /// assert_eq!(
+    ///     summary_to_markdown(&summary),
+    ///     "# Summary\n\n- [intro](intro.md)\n- [usage]()\n"
+    /// );
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn stub_threshold_words(mut self, stub_threshold_words: usize) -> Self {
+        self.config.stub_threshold_words = Some(stub_threshold_words);
+        self
+    }
+
+    /// See `collapse_joiner`, which joins the names of the chains collapsed by
+    /// `collapse_single_child_dirs`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{summary_to_markdown, Config, GenerateSummary, MemoryFs};
+    ///
+    /// let fs = MemoryFs::new().with_file("/src/guide/internals/overview.md", "");
+    /// let config = Config::builder()
+    ///     .ignore_missing_chapter_files(true)
+    ///     .collapse_single_child_dirs(true)
+    ///     .collapse_joiner(" / ")
+    ///     .build()?;
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/src"), &config)?;
+    /// assert_eq!(
+    ///     summary_to_markdown(&summary),
+    ///     "# Summary\n\n- [guide / internals / overview](guide/internals/overview.md)\n"
+    /// );
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn collapse_joiner(mut self, collapse_joiner: impl Into<String>) -> Self {
+        self.config.collapse_joiner = Some(collapse_joiner.into());
+        self
+    }
+
+    /// See `sort`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{
+    ///     summary_to_markdown, Config, GenerateSummary, MemoryFs, SortOrder,
+    /// };
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/daemon.md", "# 3. Configuring the daemon\n")
+    ///     .with_file("/src/install.md", "# 2 Installing\n");
+    /// let config = Config::builder()
+    ///     .get_chapter_name_from_file(true)
+    ///     .sort(SortOrder::HeadingNumber)
+    ///     .build()?;
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/src"), &config)?;
+    /// assert_eq!(
+    ///     summary_to_markdown(&summary),
+    ///     "# Summary\n\n- [Installing](install.md)\n- [Configuring the daemon](daemon.md)\n"
+    /// );
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn sort(mut self, sort: SortOrder) -> Self {
+        self.config.sort = sort;
+        self
+    }
+
+    /// See `fallback_encoding`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{
+    ///     summary_to_markdown, Config, Encoding, GenerateSummary, MemoryFs,
+    /// };
+    ///
+    /// // "Über uns" in Windows-1252
+    /// let fs = MemoryFs::new().with_file("/src/about.md", b"# \xdcber uns\n".to_vec());
+    /// let config = Config::builder()
+    ///     .get_chapter_name_from_file(true)
+    ///     .fallback_encoding(Encoding::Windows1252)
+    ///     .build()?;
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/src"), &config)?;
+    /// assert_eq!(
+    ///     summary_to_markdown(&summary),
+    ///     "# Summary\n\n- [Über uns](about.md)\n"
+    /// );
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn fallback_encoding(mut self, fallback_encoding: Encoding) -> Self {
+        self.config.fallback_encoding = Some(fallback_encoding);
+        self
+    }
+
+    /// See `max_chapters`, it must be positive.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{Config, GenerateSummary, MemoryFs};
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/install.md", "# Installing\n")
+    ///     .with_file("/src/usage/README.md", "# Usage\n")
+    ///     .with_file("/src/usage/cli.md", "# CLI\n")
+    ///     .with_file("/src/usage/gui.md", "# GUI\n");
+    /// let generate = GenerateSummary::new().with_fs(Box::new(fs));
+    /// let config = Config::builder().max_chapters(5).build()?;
+    /// assert!(generate.generate_summary(Path::new("/src"), &config).is_ok());
+    ///
+    /// // The directory and its chapter file count as well
+    /// let config = Config::builder().max_chapters(4).build()?;
+    /// let error = generate
+    ///     .generate_summary(Path::new("/src"), &config)
+    ///     .unwrap_err();
+    /// assert!(format!("{:#}", error).contains("while scanning /src/usage"));
+    ///
+    /// assert!(Config::builder().max_chapters(0).build().is_err());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn max_chapters(mut self, max_chapters: usize) -> Self {
+        self.config.max_chapters = max_chapters;
+        self
+    }
+
+    /// See `first_section_number`, it must be positive.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{Config, GenerateSummary, MemoryFs, SectionNumber, SummaryItem};
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/install.md", "# Installing\n")
+    ///     .with_file("/src/usage/README.md", "# Usage\n")
+    ///     .with_file("/src/usage/cli.md", "# CLI\n")
+    ///     .with_file("/src/usage/gui.md", "# GUI\n");
+    /// let config = Config::builder().first_section_number(7).build()?;
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/src"), &config)?;
+    ///
+    /// // Only the top level is offset, nested chapters are numbered within their parent
+    /// fn collect(items: &[SummaryItem], numbers: &mut Vec<SectionNumber>) {
+    ///     for item in items {
+    ///         if let SummaryItem::Link(link) = item {
+    ///             numbers.extend(link.number.clone());
+    ///             collect(&link.nested_items, numbers);
+    ///         }
+    ///     }
+    /// }
+    /// let mut numbers = vec![];
+    /// collect(&summary.numbered_chapters, &mut numbers);
+    /// assert_eq!(
+    ///     numbers,
+    ///     [
+    ///         SectionNumber(vec![7]),
+    ///         SectionNumber(vec![8]),
+    ///         SectionNumber(vec![8, 1]),
+    ///         SectionNumber(vec![8, 2]),
+    ///     ]
+    /// );
+    ///
+    /// assert!(Config::builder().first_section_number(0).build().is_err());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn first_section_number(mut self, first_section_number: usize) -> Self {
+        self.config.first_section_number = first_section_number;
+        self
+    }
+
+    /// See `number_in_names`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{Config, GenerateSummary, MemoryFs, PlannedChapter, SummaryItem};
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/install.md", "# Installing\n")
+    ///     .with_file("/src/usage/README.md", "# Usage\n")
+    ///     .with_file("/src/usage/cli.md", "# 3.1 CLI\n");
+    /// let config = Config::builder()
+    ///     .get_chapter_name_from_file(true)
+    ///     .keep_heading_numbers(true)
+    ///     .first_section_number(2)
+    ///     .planned("usage", [PlannedChapter::new("GUI")])
+    ///     .number_in_names(true)
+    ///     .build()?;
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/src"), &config)?;
+    ///
+    /// fn collect(items: &[SummaryItem], names: &mut Vec<String>) {
+    ///     for item in items {
+    ///         if let SummaryItem::Link(link) = item {
+    ///             names.push(link.name.clone());
+    ///             collect(&link.nested_items, names);
+    ///         }
+    ///     }
+    /// }
+    /// let mut names = vec![];
+    /// collect(&summary.numbered_chapters, &mut names);
+    /// // The heading of cli.md already starts with its number, the draft is not numbered
+    /// assert_eq!(names, ["2 — Installing", "3 — Usage", "3.1 CLI", "GUI"]);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn number_in_names(mut self, number_in_names: bool) -> Self {
+        self.config.number_in_names = number_in_names;
+        self
+    }
+
+    /// See `number_separator`.
+    pub fn number_separator(mut self, number_separator: impl Into<String>) -> Self {
+        self.config.number_separator = number_separator.into();
+        self
+    }
+
+    /// See `outline`, the path is relative to the src directory.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{summary_to_markdown, Config, GenerateSummary, MemoryFs};
+    ///
+    /// let outline = r#"
+    /// [[chapters]]
+    /// file = "intro.md"
+    /// title = "Introduction"
+    ///
+    /// [[chapters]]
+    /// part = "Guide"
+    ///
+    /// [[chapters]]
+    /// dir = "guide"
+    /// "#;
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/outline.toml", outline)
+    ///     .with_file("/src/intro.md", "")
+    ///     .with_file("/src/guide/README.md", "")
+    ///     .with_file("/src/guide/setup.md", "");
+    /// let config = Config::builder().outline("outline.toml").build()?;
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/src"), &config)?;
+    /// assert_eq!(
+    ///     summary_to_markdown(&summary),
+    ///     "# Summary\n\n\
+    ///      - [Introduction](intro.md)\n\n# Guide\n\n\
+    ///      - [guide](guide/README.md)\n    \
+    ///          - [setup](guide/setup.md)\n"
+    /// );
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn outline(mut self, outline: impl Into<PathBuf>) -> Self {
+        self.config.outline = Some(outline.into());
+        self
+    }
+
+    /// Group the top level chapters by the front matter `key`, like
+    /// `group_by = "frontmatter:<key>"`.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{Config, GenerateSummary, MemoryFs, SummaryItem};
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/install.md", "---\nsection: Tutorials\n---\n")
+    ///     .with_file("/src/api/README.md", "---\nsection: Reference\n---\n")
+    ///     .with_file("/src/api/types.md", "")
+    ///     .with_file("/src/faq.md", "")
+    ///     .with_file("/src/basics.md", "---\nsection: Tutorials\n---\n");
+    /// let config = Config::builder()
+    ///     .group_by_front_matter("section")
+    ///     .group_order(["Tutorials"])
+    ///     .build()?;
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/src"), &config)?;
+    ///
+    /// let items: Vec<_> = summary
+    ///     .numbered_chapters
+    ///     .iter()
+    ///     .map(|item| match item {
+    ///         SummaryItem::Link(link) => format!("{} {}", link.number.as_ref().unwrap(), link.name),
+    ///         SummaryItem::PartTitle(title) => format!("# {}", title),
+    ///         SummaryItem::Separator => "---".to_owned(),
+    ///     })
+    ///     .collect();
+    /// assert_eq!(
+    ///     items,
+    ///     [
+    ///         "# Tutorials",
+    ///         "1. basics",
+    ///         "2. install",
+    ///         "# Reference",
+    ///         "3. api",
+    ///         "# Other",
+    ///         "4. faq",
+    ///     ]
+    /// );
+    /// // Subdirectories keep their nesting within their group
+    /// let SummaryItem::Link(api) = &summary.numbered_chapters[4] else {
+    ///     unreachable!()
+    /// };
+    /// assert_eq!(api.nested_items.len(), 1);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn group_by_front_matter(mut self, key: impl Into<String>) -> Self {
+        self.config.group_by = Some(key.into());
+        self
+    }
+
+    /// See `group_order`.
+    pub fn group_order<S: Into<String>>(
+        mut self,
+        group_order: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.config.group_order = group_order.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// See `toc_title`.
+    pub fn toc_title(mut self, toc_title: impl Into<String>) -> Self {
+        self.config.toc_title = toc_title.into();
+        self
+    }
+
+    /// See `foreign_chapters_part`. Without it, the chapters which preprocessors running before
+    /// this one added without a file are kept at the end of their parent chapter, or of the book.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(feature = "preprocessor")]
+    /// # {
+    /// use mdbook::{
+    ///     book::{Book, Chapter},
+    ///     preprocess::{Preprocessor, PreprocessorContext},
+    /// };
+    /// use mdbook_generate_summary::TestBook;
+    ///
+    /// struct AddApiPage;
+    ///
+    /// impl Preprocessor for AddApiPage {
+    ///     fn name(&self) -> &str {
+    ///         "add-api-page"
+    ///     }
+    ///
+    ///     fn run(&self, _: &PreprocessorContext, mut book: Book) -> mdbook::errors::Result<Book> {
+    ///         let mut api = Chapter::new("API", "# API\n".to_owned(), "api.md", vec![]);
+    ///         api.number = Some(Default::default());
+    ///         api.source_path = None;
+    ///         book.push_item(api);
+    ///         Ok(book)
+    ///     }
+    /// }
+    ///
+    /// let output = TestBook::new()
+    ///     .file("intro.md", "# Introduction\n")
+    ///     .config(toml::toml! { foreign_chapters_part = "Reference" })
+    ///     .preprocessor(AddApiPage)
+    ///     .run()?;
+    /// assert_eq!(output.chapter_names(), ["1. intro", "Reference", "2. API"]);
+    /// # }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn foreign_chapters_part(mut self, foreign_chapters_part: impl Into<String>) -> Self {
+        self.config.foreign_chapters_part = Some(foreign_chapters_part.into());
+        self
+    }
+
+    /// See `breadcrumb_separator`.
+    pub fn breadcrumb_separator(mut self, breadcrumb_separator: impl Into<String>) -> Self {
+        self.config.breadcrumb_separator = breadcrumb_separator.into();
+        self
+    }
+
+    /// See `default_group`.
+    pub fn default_group(mut self, default_group: impl Into<String>) -> Self {
+        self.config.default_group = default_group.into();
+        self
+    }
+
+    /// See `allow`.
+    pub fn allow(mut self, allow: impl IntoIterator<Item = WarningKind>) -> Self {
+        self.config.allow = allow.into_iter().collect();
+        self
+    }
+
+    /// See `created_files_manifest`.
+    pub fn created_files_manifest(mut self, created_files_manifest: impl Into<PathBuf>) -> Self {
+        self.config.created_files_manifest = Some(created_files_manifest.into());
+        self
+    }
+
+    /// See `summary_json`.
+    pub fn summary_json(mut self, summary_json: impl Into<PathBuf>) -> Self {
+        self.config.summary_json = Some(summary_json.into());
+        self
+    }
+
+    /// See `title_cache`.
+    pub fn title_cache(mut self, title_cache: impl Into<PathBuf>) -> Self {
+        self.config.title_cache = Some(title_cache.into());
+        self
+    }
+
+    /// See `renderers`.
+    pub fn renderers<S: Into<String>>(mut self, renderers: impl IntoIterator<Item = S>) -> Self {
+        self.config.renderers = Some(renderers.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// See `skip_renderers`.
+    pub fn skip_renderers<S: Into<String>>(
+        mut self,
+        skip_renderers: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.config.skip_renderers = skip_renderers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// See `diagnostics_json`.
+    pub fn diagnostics_json(mut self, diagnostics_json: impl Into<PathBuf>) -> Self {
+        self.config.diagnostics_json = Some(diagnostics_json.into());
+        self
+    }
+
+    /// See `title`.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.config.title = Some(title.into());
+        self
+    }
+
+    /// See `language_dirs`.
+    pub fn language_dirs<S: Into<String>>(
+        mut self,
+        language_dirs: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.config.language_dirs = language_dirs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// See `language`. The `languages` table only exists in book.toml, set the options of the
+    /// language directly instead.
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.config.language = Some(language.into());
+        self
+    }
+
+    /// Check the options and create the configuration.
+    ///
+    /// # Errors
+    /// If an option has an invalid value or options conflict, with the same errors as for
+    /// book.toml.
+    pub fn build(self) -> Result<Config, Error> {
+        let mut config = self.config;
+        if let Some(chapter_file_name) = self.chapter_file_name {
+            config.chapter_file_name =
+                validate_chapter_file_name(Some(chapter_file_name), &mut config.warnings)?;
+        }
+        for (key, value) in [
+            ("max_depth", Some(config.max_depth)),
+            ("max_chapters", Some(config.max_chapters)),
+            ("max_title_bytes", Some(config.max_title_bytes)),
+            ("first_section_number", Some(config.first_section_number)),
+            ("stub_threshold_bytes", config.stub_threshold_bytes),
+            ("stub_threshold_words", config.stub_threshold_words),
+        ] {
+            if value == Some(0) {
+                return Err(invalid_value(key, "a positive integer", "0"));
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// The configuration file read next to book.toml if `config_file` is not set.
+#[cfg(feature = "preprocessor")]
+pub(crate) const DEFAULT_CONFIG_FILE: &str = "generate-summary.toml";
+
+/// The preprocessor table merged with the configuration file of the book in `root`, whose keys
+/// override the ones of the table. The file is `config_file` relative to `root` if it is set, which
+/// must exist, or `generate-summary.toml` if that exists.
+#[cfg(feature = "preprocessor")]
+pub(crate) fn with_config_file(
+    table: &Map<String, Value>,
+    root: &std::path::Path,
+) -> Result<Map<String, Value>, Error> {
+    use anyhow::Context;
+
+    let mut table = table.clone();
+    let path = match table.remove("config_file") {
+        None => {
+            let path = root.join(DEFAULT_CONFIG_FILE);
+            if !path.is_file() {
+                return Ok(table);
+            }
+            path
+        }
+        Some(Value::String(config_file)) => root.join(config_file),
+        Some(value) => return Err(invalid_type("config_file", "a string", &value)),
+    };
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read the configuration file {}", path.display()))?;
+    let file: Map<String, Value> = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse the configuration file {}", path.display()))?;
+    log::debug!("Using the configuration file {}", path.display());
+    merge_tables(&mut table, file);
+    Ok(table)
+}
+
+/// Set `create_missing_chapter_files` of the merged preprocessor table to mdBook's
+/// `create_missing`, unless it is set in the table, its language table or the environment. It is
+/// not set either if `ignore_missing_chapter_files` or `assert_no_writes` is enabled, which contradict
+/// it.
+#[cfg(feature = "preprocessor")]
+fn inherit_create_missing(
+    merged: &mut Map<String, Value>,
+    environment: &HashMap<String, String>,
+    create_missing: bool,
+) -> Result<(), Error> {
+    let effective = with_overrides(merged, environment)?;
+    let enabled = |key: &str| effective.get(key) == Some(&Value::Boolean(true));
+    if effective.contains_key("create_missing_chapter_files") {
+        log::debug!(
+            "Using `create_missing_chapter_files` of the preprocessor configuration instead of \
+             `build.create-missing`"
+        );
+    } else if enabled("ignore_missing_chapter_files") {
+        log::debug!("Not using `build.create-missing`, `ignore_missing_chapter_files` is set");
+    } else if enabled("assert_no_writes") {
+        log::debug!("Not using `build.create-missing`, `assert_no_writes` is set");
+    } else {
+        log::debug!(
+            "Using `build.create-missing = {}` as `create_missing_chapter_files`, which is not \
+             set",
+            create_missing
+        );
+        merged.insert(
+            "create_missing_chapter_files".to_owned(),
+            Value::Boolean(create_missing),
+        );
+    }
+    Ok(())
+}
+
+/// Merge `overrides` into `table` key by key, nested tables are merged as well.
+#[cfg(feature = "preprocessor")]
+fn merge_tables(table: &mut Map<String, Value>, overrides: Map<String, Value>) {
+    for (key, value) in overrides {
+        match (table.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(value)) => merge_tables(existing, value),
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
+
+/// The preprocessor table with the keys of `languages.<language>` overriding its other keys, and
+/// the keys of `environment` (see [`Config::from_table`]) overriding all of them. The `language`
+/// may be set by the environment as well.
+fn with_overrides(
+    table: &Map<String, Value>,
+    environment: &HashMap<String, String>,
+) -> Result<Map<String, Value>, Error> {
+    let with_environment = |mut table: Map<String, Value>| {
+        for (key, value) in environment {
+            table.insert(key.clone(), environment_value(value));
+        }
+        table
+    };
+    let merged = with_environment(table.clone());
+    Ok(match with_language_overrides(&merged)? {
+        Some(overridden) => with_environment(overridden),
+        None => merged,
+    })
+}
+
+/// The preprocessor table with the keys of `languages.<language>` overriding its other keys, or
+/// [`Option::None`] if there is no table for the `language`.
+fn with_language_overrides(
+    table: &Map<String, Value>,
+) -> Result<Option<Map<String, Value>>, Error> {
+    let Some(language) = table.get("language") else {
+        return Ok(None);
+    };
+    let language = language
+        .as_str()
+        .ok_or_else(|| invalid_type("language", "a string", language))?;
+    let languages = match table.get("languages") {
+        None => return Ok(None),
+        Some(Value::Table(languages)) => languages,
+        Some(value) => return Err(invalid_type("languages", "a table", value)),
+    };
+    match languages.get(language) {
+        None => Ok(None),
+        Some(Value::Table(overrides)) => {
+            let mut merged = table.clone();
+            for (key, value) in overrides {
+                merged.insert(key.clone(), value.clone());
+            }
+            Ok(Some(merged))
+        }
+        Some(value) => Err(invalid_type(
+            &format!("languages.{}", language),
+            "a table",
+            value,
+        )),
+    }
+}
+
+/// Keys of the preprocessor table whose value is a table, but not the overrides of a renderer.
+#[cfg(feature = "preprocessor")]
+const TABLE_KEYS: [&str; 2] = ["languages", "planned"];
+
+/// Read the `planned` table, mapping directories to arrays of planned chapters. A chapter is its
+/// title, or a table with its `title` and `position`. The src directory is "" or ".".
+fn parse_planned(
+    planned: &Map<String, Value>,
+) -> Result<BTreeMap<PathBuf, Vec<PlannedChapter>>, Error> {
+    let expected = "an array of titles or tables with `title` and `position`";
+    let mut result = BTreeMap::new();
+    for (directory, chapters) in planned {
+        let key = format!("planned.{}", directory);
+        let chapters = chapters
+            .as_array()
+            .ok_or_else(|| invalid_type(&key, expected, chapters))?;
+        let chapters = chapters
+            .iter()
+            .map(|chapter| match chapter {
+                Value::String(title) => Ok(PlannedChapter::new(title.clone())),
+                Value::Table(table) => {
+                    let title = table
+                        .get("title")
+                        .and_then(Value::as_str)
+                        .ok_or_else(|| invalid_type(&key, expected, chapter))?;
+                    let position = match table.get("position") {
+                        None => None,
+                        Some(position) => Some(
+                            position
+                                .as_integer()
+                                .and_then(|position| usize::try_from(position).ok())
+                                .filter(|position| *position > 0)
+                                .ok_or_else(|| {
+                                    invalid_value(
+                                        &format!("{}.position", key),
+                                        "a positive integer",
+                                        &position.to_string(),
+                                    )
+                                })?,
+                        ),
+                    };
+                    Ok(PlannedChapter {
+                        title: title.to_owned(),
+                        position,
+                    })
+                }
+                _ => Err(invalid_type(&key, expected, chapter)),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let directory = match directory.as_str() {
+            "." => PathBuf::new(),
+            directory => PathBuf::from(directory.trim_end_matches('/')),
+        };
+        result
+            .entry(directory)
+            .or_insert_with(Vec::new)
+            .extend(chapters);
+    }
+    Ok(result)
+}
+
+/// Parse the `mount` tables with a `source`, an `at` path inside of the src directory and
+/// optionally a `title`.
+fn parse_mounts(mounts: &[Value]) -> Result<Vec<Mount>, Error> {
+    let expected = "an array of tables with a `source`, `at` and `title`";
+    mounts
+        .iter()
+        .map(|mount| {
+            let table = mount
+                .as_table()
+                .ok_or_else(|| invalid_type("mount", expected, mount))?;
+            let path = |key| {
+                table
+                    .get(key)
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| invalid_type("mount", expected, mount))
+            };
+            let at = path("at")?;
+            if at.is_empty()
+                || !Path::new(at)
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)))
+            {
+                return Err(invalid_value(
+                    "mount.at",
+                    "a path relative to the src directory without '..'",
+                    at,
+                ));
+            }
+            let mut result = Mount::new(path("source")?, at);
+            match table.get("title") {
+                None => {}
+                Some(Value::String(title)) => result.title = Some(title.clone()),
+                Some(title) => return Err(invalid_type("mount.title", "a string", title)),
+            }
+            Ok(result)
+        })
+        .collect()
+}
+
+/// Parse the `converter` tables with the `extensions` of the converted files and the `command`
+/// converting them.
+fn parse_converters(converters: &[Value]) -> Result<Vec<Converter>, Error> {
+    let expected = "an array of tables with `extensions` and a `command`";
+    converters
+        .iter()
+        .map(|converter| {
+            let table = converter
+                .as_table()
+                .ok_or_else(|| invalid_type("converter", expected, converter))?;
+            let extensions = table
+                .get("extensions")
+                .and_then(Value::as_array)
+                .ok_or_else(|| invalid_type("converter", expected, converter))?
+                .iter()
+                .map(|extension| {
+                    let extension = extension.as_str().ok_or_else(|| {
+                        invalid_type("converter.extensions", "an array of strings", extension)
+                    })?;
+                    Ok(extension.strip_prefix('.').unwrap_or(extension).to_owned())
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            let command = table
+                .get("command")
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid_type("converter", expected, converter))?;
+            Ok(Converter::new(extensions, command))
+        })
+        .collect()
+}
+
+/// Parse the `external_chapters`, tables with a `path` and optionally a `title` and a `position`.
+fn parse_external_chapters(chapters: &[Value]) -> Result<Vec<ExternalChapter>, Error> {
+    let key = "external_chapters";
+    let expected = "an array of tables with a `path`, `title` and `position`";
+    chapters
+        .iter()
+        .map(|chapter| {
+            let table = chapter
+                .as_table()
+                .ok_or_else(|| invalid_type(key, expected, chapter))?;
+            let path = table
+                .get("path")
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid_type(key, expected, chapter))?;
+            let mut external = ExternalChapter::new(path);
+            match table.get("title") {
+                None => {}
+                Some(Value::String(title)) => external.title = Some(title.clone()),
+                Some(title) => {
+                    return Err(invalid_type("external_chapters.title", "a string", title))
+                }
+            }
+            external.position = match table.get("position") {
+                None => ChapterPosition::Suffix,
+                Some(Value::String(position)) => {
+                    parse_chapter_position("external_chapters.position", position)?
+                }
+                Some(position) => {
+                    return Err(invalid_type(
+                        "external_chapters.position",
+                        "a string",
+                        position,
+                    ))
+                }
+            };
+            Ok(external)
+        })
+        .collect()
+}
+
+/// Parse the `position` of a chapter, see [`ChapterPosition`].
+fn parse_chapter_position(key: &str, position: &str) -> Result<ChapterPosition, Error> {
+    match position {
+        "prefix" => Ok(ChapterPosition::Prefix),
+        "numbered" => Ok(ChapterPosition::Numbered),
+        "suffix" => Ok(ChapterPosition::Suffix),
+        _ => Err(invalid_value(
+            key,
+            "one of \"prefix\", \"numbered\" or \"suffix\"",
+            position,
+        )),
+    }
+}
+
+/// The placeholder of `chapter_file_name` for the name of the directory.
+const DIR_PLACEHOLDER: &str = "{dir}";
+
+/// Check the configured chapter file name, defaulting to 'README'.
+/// A trailing '.md' is removed with a warning, empty names, names containing a path separator and
+/// placeholders other than `{dir}` are rejected.
+fn validate_chapter_file_name(
+    chapter_file_name: Option<String>,
+    warnings: &mut Vec<Warning>,
+) -> Result<String, Error> {
+    let Some(value) = chapter_file_name else {
+        return Ok("README".to_owned());
+    };
+
+    let chapter_file_name = value.strip_suffix(".md").unwrap_or(&value);
+    if chapter_file_name.is_empty() {
+        Err(invalid_value(
+            "chapter_file_name",
+            "a non-empty file name without extension like \"README\"",
+            &value,
+        ))
+    } else if chapter_file_name.contains(['/', '\\']) {
+        Err(invalid_value(
+            "chapter_file_name",
+            "a file name without directories like \"README\"",
+            &value,
+        ))
+    } else if chapter_file_name
+        .replace(DIR_PLACEHOLDER, "")
+        .contains(['{', '}'])
+    {
+        Err(invalid_value(
+            "chapter_file_name",
+            "a file name without placeholders other than `{dir}`",
+            &value,
+        ))
+    } else {
+        if chapter_file_name != value {
+            warnings.push(Warning {
+                kind: WarningKind::ChapterFileExtension,
+                message: format!(
+                    "`chapter_file_name` should not include the file extension, using \"{}\" \
+                     instead of \"{}\"",
+                    chapter_file_name, value
+                ),
+            });
+        }
+        Ok(chapter_file_name.to_owned())
+    }
+}
+
+/// The prefix of the environment variables overriding keys of the preprocessor table, e.g.
+/// `MDBOOK_GENERATE_SUMMARY__FAIL_ON_WARNINGS` for `fail_on_warnings`.
+const ENV_PREFIX: &str = "MDBOOK_GENERATE_SUMMARY__";
+
+/// The values of the environment variables starting with [`ENV_PREFIX`], by the key they set.
+#[cfg(feature = "preprocessor")]
+fn environment_overrides() -> HashMap<String, String> {
+    std::env::vars_os()
+        .filter_map(|(variable, value)| {
+            let key = variable.to_str()?.strip_prefix(ENV_PREFIX)?.to_lowercase();
+            Some((key, value.into_string().ok()?))
+        })
+        .collect()
+}
+
+/// The environment variable which sets the key.
+fn env_variable(key: &str) -> String {
+    format!("{}{}", ENV_PREFIX, key.to_uppercase())
+}
+
+/// The value of an environment variable as it would be written in book.toml: booleans and integers
+/// are converted, everything else is a string. Lists are split by [`Table::get_strings`].
+fn environment_value(value: &str) -> Value {
+    match value {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        _ => value
+            .parse()
+            .map_or_else(|_| Value::String(value.to_owned()), Value::Integer),
+    }
+}
+
+/// Keys of the preprocessor table which are used by mdBook itself.
+const MDBOOK_KEYS: [&str; 4] = ["command", "renderer", "before", "after"];
+
+/// The preprocessor table. Keeps track of the keys that were looked up, so that every key that
+/// is not looked up while constructing [`Config`] is reported as unknown.
+struct Table<'a> {
+    table: &'a Map<String, Value>,
+    /// The values of the keys set by environment variables, as they were given.
+    environment: &'a HashMap<String, String>,
+    known_keys: RefCell<Vec<&'static str>>,
+}
+
+impl<'a> Table<'a> {
+    fn new(table: &'a Map<String, Value>, environment: &'a HashMap<String, String>) -> Self {
+        Self {
+            table,
+            environment,
+            known_keys: RefCell::new(MDBOOK_KEYS.to_vec()),
+        }
+    }
+
+    /// Get the value of the key and remember it as known.
+    fn get(&self, key: &'static str) -> Option<&'a Value> {
+        self.known_keys.borrow_mut().push(key);
+        self.table.get(key)
+    }
+
+    /// Get the boolean value of the key, defaulting to false if the key is missing.
+    fn get_bool(&self, key: &'static str) -> Result<bool, Error> {
+        match self.get(key) {
+            Some(value) => value
+                .as_bool()
+                .ok_or_else(|| invalid_type(key, "a boolean", value)),
+            None => Ok(false),
+        }
+    }
+
+    /// Get the string value of the key, if it is set.
+    fn get_string(&self, key: &'static str) -> Result<Option<String>, Error> {
+        if let Some(value) = self.environment.get(key) {
+            self.known_keys.borrow_mut().push(key);
+            return Ok(Some(value.clone()));
+        }
+        self.get(key)
+            .map(|value| {
+                value
+                    .as_str()
+                    .map(str::to_owned)
+                    .ok_or_else(|| invalid_type(key, "a string", value))
+            })
+            .transpose()
+    }
+
+    /// Get the positive integer value of the key, if it is set.
+    fn get_positive_integer(&self, key: &'static str) -> Result<Option<usize>, Error> {
+        self.get(key)
+            .map(|value| {
+                let integer = value
+                    .as_integer()
+                    .ok_or_else(|| invalid_type(key, "a positive integer", value))?;
+                usize::try_from(integer)
+                    .ok()
+                    .filter(|integer| *integer > 0)
+                    .ok_or_else(|| invalid_value(key, "a positive integer", &integer.to_string()))
+            })
+            .transpose()
+    }
+
+    /// Get the array of paths of the key, defaulting to an empty list if the key is missing.
+    fn get_paths(&self, key: &'static str) -> Result<Vec<PathBuf>, Error> {
+        Ok(self
+            .get_strings(key)?
+            .into_iter()
+            .map(PathBuf::from)
+            .collect())
+    }
+
+    /// Get the array of strings of the key, defaulting to an empty list if the key is missing.
+    /// An environment variable sets it to a comma-separated list.
+    fn get_strings(&self, key: &'static str) -> Result<Vec<String>, Error> {
+        if let Some(value) = self.environment.get(key) {
+            self.known_keys.borrow_mut().push(key);
+            return Ok(value
+                .split(',')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_owned)
+                .collect());
+        }
+        let Some(value) = self.get(key) else {
+            return Ok(vec![]);
+        };
+
+        let array = value
+            .as_array()
+            .ok_or_else(|| invalid_type(key, "an array of strings", value))?;
+        array
+            .iter()
+            .map(|string| {
+                string
+                    .as_str()
+                    .map(str::to_owned)
+                    .ok_or_else(|| invalid_type(key, "an array of strings", value))
+            })
+            .collect()
+    }
+
+    /// Describe every key of the table which was never looked up, suggesting the most similar
+    /// known key if there is one.
+    fn unknown_keys(&self) -> Vec<String> {
+        let known_keys = self.known_keys.borrow();
+        self.table
+            .keys()
+            .filter(|key| !known_keys.contains(&key.as_str()))
+            .map(|key| {
+                let suggestion = known_keys
+                    .iter()
+                    .map(|known_key| (strsim::levenshtein(key, known_key), known_key))
+                    .filter(|(distance, _)| *distance <= 3)
+                    .min();
+                match (self.environment.contains_key(key), suggestion) {
+                    (true, Some((_, known_key))) => format!(
+                        "Unknown environment variable {}, did you mean {}?",
+                        env_variable(key),
+                        env_variable(known_key)
+                    ),
+                    (true, None) => format!("Unknown environment variable {}", env_variable(key)),
+                    (false, Some((_, known_key))) => format!(
+                        "Unknown key `{}` in `preprocessor.generate-summary`, did you mean `{}`?",
+                        key, known_key
+                    ),
+                    (false, None) => {
+                        format!("Unknown key `{}` in `preprocessor.generate-summary`", key)
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// Create the error for a key with a value of the correct type, which is still not allowed.
+fn invalid_value(key: &str, expected: &str, found: &str) -> Error {
+    anyhow!(
+        "Invalid value for `preprocessor.generate-summary.{}`: expected {}, found `{}`",
+        key,
+        expected,
+        found
+    )
+}
+
+/// Create the error for a key with a value of the wrong type.
+fn invalid_type(key: &str, expected: &str, found: &Value) -> Error {
+    anyhow!(
+        "Invalid value for `preprocessor.generate-summary.{}`: expected {}, found {} `{}`",
+        key,
+        expected,
+        found.type_str(),
+        found
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The keys by the type of their value, besides those accepting several types.
+    const BOOLEAN_KEYS: [&str; 31] = [
+        "assert_no_writes",
+        "best_effort",
+        "check_links",
+        "collapse_single_child_dirs",
+        "descend_into_nested_books",
+        "diff_against_existing",
+        "draft_empty_directories",
+        "drop_foreign_chapters",
+        "dry_run",
+        "fail_at_end",
+        "fail_if_summary_differs",
+        "fail_on_warnings",
+        "follow_symlinks",
+        "full_scan",
+        "generate_toc_chapter",
+        "get_chapter_name_from_file",
+        "git_tracked_only",
+        "gitignore_created_files",
+        "ignore_missing_chapter_files",
+        "incremental",
+        "inject_breadcrumbs",
+        "keep_heading_numbers",
+        "number_in_draft_names",
+        "number_in_names",
+        "prefer_manual",
+        "quiet",
+        "sidecar_files",
+        "strict_config",
+        "strip_frontmatter",
+        "verbose_stats",
+        "write_summary_file",
+    ];
+    const STRING_KEYS: [&str; 19] = [
+        "chapter_file_name",
+        "collapse_joiner",
+        "converter_cache",
+        "diagnostics_json",
+        "fallback_encoding",
+        "file_directory_collision",
+        "foreign_chapters_part",
+        "group_by",
+        "group_style",
+        "language",
+        "on_error",
+        "outline",
+        "sort",
+        "stub_behavior",
+        "summary_json",
+        "title",
+        "title_cache",
+        "toc_position",
+        "validate",
+    ];
+    const ARRAY_KEYS: [&str; 6] = [
+        "group_order",
+        "language_dirs",
+        "leaf_directories",
+        "renderers",
+        "skip_renderers",
+        "unnumbered_directories",
+    ];
+    const INTEGER_KEYS: [&str; 4] = [
+        "max_chapters",
+        "max_depth",
+        "stub_threshold_bytes",
+        "stub_threshold_words",
+    ];
+
+    /// The error of the configuration in the preprocessor table `toml`.
+    fn error(toml: &str) -> String {
+        let table: Map<String, Value> = toml::from_str(toml).unwrap();
+        format!("{:#}", Config::try_from(&table).unwrap_err())
+    }
+
+    #[test]
+    fn rejects_creating_and_ignoring_missing_chapter_files() {
+        for create in ["true", "\"once\""] {
+            let error = error(&format!(
+                "create_missing_chapter_files = {}\nignore_missing_chapter_files = true",
+                create
+            ));
+            assert!(error.contains(
+                "`create_missing_chapter_files` can not be used together with \
+                 `ignore_missing_chapter_files`"
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_best_effort_with_another_on_error() {
+        for on_error in ["fail", "draft"] {
+            let error = error(&format!("best_effort = true\non_error = \"{}\"", on_error));
+            assert!(error.contains("`best_effort` can not be used together with `on_error`"));
+        }
+        let table = toml::from_str("best_effort = true").unwrap();
+        assert_eq!(Config::try_from(&table).unwrap().on_error, OnError::Skip);
+    }
+
+    #[test]
+    fn rejects_incremental_without_title_cache() {
+        let error = error("incremental = true");
+        assert!(error.contains("`incremental` needs `title_cache`"));
+    }
+
+    #[test]
+    fn lists_all_conflicts_at_once() {
+        let error = error(
+            "create_missing_chapter_files = true\nignore_missing_chapter_files = true\n\
+             incremental = true",
+        );
+        assert!(error.contains("`create_missing_chapter_files`"));
+        assert!(error.contains("`incremental` needs `title_cache`"));
+    }
+
+    #[test]
+    fn rejects_values_of_the_wrong_type() {
+        let keys = [
+            (
+                "a boolean",
+                "\"yes\"",
+                "string `\"yes\"`",
+                BOOLEAN_KEYS.as_slice(),
+            ),
+            ("a string", "3", "integer `3`", STRING_KEYS.as_slice()),
+            (
+                "an array of strings",
+                "\"a\"",
+                "string `\"a\"`",
+                ARRAY_KEYS.as_slice(),
+            ),
+            (
+                "a positive integer",
+                "true",
+                "boolean `true`",
+                INTEGER_KEYS.as_slice(),
+            ),
+        ];
+        for (expected, value, found, keys) in keys {
+            for key in keys {
+                let error = error(&format!("{} = {}", key, value));
+                let message = format!(
+                    "Invalid value for `preprocessor.generate-summary.{}`: expected {}, found {}",
+                    key, expected, found
+                );
+                assert!(error.contains(&message), "{}", error);
+            }
+        }
+        let error = error("create_missing_chapter_files = 1");
+        assert!(error.contains("expected a boolean or \"once\", found integer `1`"));
+    }
+
+    #[test]
+    fn uses_the_defaults_for_missing_keys() {
+        let table = toml::from_str("get_chapter_name_from_file = true").unwrap();
+        let config = Config::try_from(&table).unwrap();
+        assert!(config.get_chapter_name_from_file);
+        assert_eq!(config.chapter_file_name, "README");
+        assert_eq!(
+            config.create_missing_chapter_files,
+            CreateMissingChapterFiles::Never
+        );
+        assert!(!config.ignore_missing_chapter_files);
+        assert_eq!(
+            config.name_sources,
+            [NameSource::Sidecar, NameSource::Heading]
+        );
+        assert_eq!(config.on_error, OnError::Fail);
+        assert_eq!(config.sort, SortOrder::FileName);
+        assert_eq!(config.max_depth, 1024);
+        assert_eq!(config.max_chapters, 20000);
+        assert_eq!(config.max_title_bytes, 4096);
+        assert_eq!(config.first_section_number, 1);
+        assert_eq!(config.number_separator, " — ");
+        assert!(config.leaf_directories.is_empty());
+        assert!(config.warnings.is_empty());
+    }
+
+    #[test]
+    fn warns_about_unknown_keys_with_a_suggestion() {
+        let table = toml::from_str("chapter-file-name = \"index\"\nunrelated = 1").unwrap();
+        let config = Config::try_from(&table).unwrap();
+        let messages: Vec<_> = config
+            .warnings
+            .iter()
+            .map(|warning| (warning.kind, warning.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (
+                    WarningKind::UnknownConfigKeys,
+                    "Unknown key `chapter-file-name` in `preprocessor.generate-summary`, did you \
+                     mean `chapter_file_name`?"
+                ),
+                (
+                    WarningKind::UnknownConfigKeys,
+                    "Unknown key `unrelated` in `preprocessor.generate-summary`"
+                ),
+            ]
+        );
+        // The default is used
+        assert_eq!(config.chapter_file_name, "README");
+
+        let error = error("strict_config = true\nchapter-file-name = \"index\"");
+        assert!(error.contains(
+            "Unknown configuration keys:\nUnknown key `chapter-file-name` in \
+             `preprocessor.generate-summary`, did you mean `chapter_file_name`?"
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_chapter_file_names() {
+        for (value, expected) in [
+            (
+                "",
+                "a non-empty file name without extension like \"README\"",
+            ),
+            (
+                ".md",
+                "a non-empty file name without extension like \"README\"",
+            ),
+            (
+                "docs/README",
+                "a file name without directories like \"README\"",
+            ),
+            (
+                "docs\\README",
+                "a file name without directories like \"README\"",
+            ),
+            (
+                "{name}",
+                "a file name without placeholders other than `{dir}`",
+            ),
+        ] {
+            let message = format!(
+                "Invalid value for `preprocessor.generate-summary.chapter_file_name`: expected {}, \
+                 found `{}`",
+                expected, value
+            );
+            let error = format!(
+                "{:#}",
+                Config::builder()
+                    .chapter_file_name(value)
+                    .build()
+                    .unwrap_err()
+            );
+            assert_eq!(error, message);
+        }
+    }
+
+    #[test]
+    fn strips_the_extension_of_the_chapter_file_name() {
+        let table = toml::from_str("chapter_file_name = \"index.md\"").unwrap();
+        let config = Config::try_from(&table).unwrap();
+        assert_eq!(config.chapter_file_name, "index");
+        let [warning] = config.warnings.as_slice() else {
+            panic!("Expected a single warning, got {:?}", config.warnings);
+        };
+        assert_eq!(warning.kind, WarningKind::ChapterFileExtension);
+        assert_eq!(
+            warning.message,
+            "`chapter_file_name` should not include the file extension, using \"index\" instead \
+             of \"index.md\""
+        );
+    }
+
+    /// The environment variables setting the keys to the values, by the key they set.
+    fn environment<const N: usize>(variables: [(&str, &str); N]) -> HashMap<String, String> {
+        variables
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_the_keys_set_by_environment_variables() {
+        let table = toml::from_str(
+            "fail_on_warnings = false\nmax_depth = 3\nchapter_file_name = \"index\"\n\
+             leaf_directories = [\"api\"]",
+        )
+        .unwrap();
+        let environment = environment([
+            ("fail_on_warnings", "true"),
+            ("max_depth", "5"),
+            ("chapter_file_name", "overview"),
+            ("leaf_directories", "reference/api, examples,,"),
+        ]);
+        let config = Config::from_table(&table, &environment).unwrap();
+        assert!(config.fail_on_warnings);
+        assert_eq!(config.max_depth, 5);
+        assert_eq!(config.chapter_file_name, "overview");
+        assert_eq!(
+            config.leaf_directories,
+            [PathBuf::from("reference/api"), PathBuf::from("examples")]
+        );
+        assert!(config.warnings.is_empty());
+    }
+
+    #[test]
+    fn warns_about_unknown_environment_variables_with_a_suggestion() {
+        let environment = environment([("fail_on_warning", "true"), ("unrelated", "1")]);
+        let config = Config::from_table(&Map::new(), &environment).unwrap();
+        let messages: Vec<_> = config
+            .warnings
+            .iter()
+            .map(|warning| (warning.kind, warning.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (
+                    WarningKind::UnknownConfigKeys,
+                    "Unknown environment variable MDBOOK_GENERATE_SUMMARY__FAIL_ON_WARNING, did \
+                     you mean MDBOOK_GENERATE_SUMMARY__FAIL_ON_WARNINGS?"
+                ),
+                (
+                    WarningKind::UnknownConfigKeys,
+                    "Unknown environment variable MDBOOK_GENERATE_SUMMARY__UNRELATED"
+                ),
+            ]
+        );
+        assert!(!config.fail_on_warnings);
+    }
+
+    /// The configuration for the html renderer of a book with the `configured_renderers`.
+    #[cfg(feature = "preprocessor")]
+    fn for_html(toml: &str, environment: &HashMap<String, String>) -> Result<Config, Error> {
+        let table = toml::from_str(toml).unwrap();
+        Config::for_renderer_with_environment(
+            &table,
+            Some("html"),
+            &["html"],
+            std::path::Path::new("src"),
+            false,
+            environment,
+        )
+    }
+
+    #[cfg(feature = "preprocessor")]
+    #[test]
+    fn names_the_environment_variables_of_an_invalid_configuration() {
+        let environment = environment([("max_depth", "deep"), ("quiet", "true")]);
+        let error = format!("{:#}", for_html("", &environment).unwrap_err());
+        assert_eq!(
+            error,
+            "Invalid configuration, including the environment variables \
+             MDBOOK_GENERATE_SUMMARY__MAX_DEPTH, MDBOOK_GENERATE_SUMMARY__QUIET: Invalid value \
+             for `preprocessor.generate-summary.max_depth`: expected a positive integer, found \
+             string `\"deep\"`"
+        );
+    }
+
+    #[cfg(feature = "preprocessor")]
+    #[test]
+    fn overrides_the_renderer_table_by_the_environment() {
+        let base = "max_depth = 3\n";
+        let renderer = "max_depth = 3\n[html]\nmax_depth = 4\n";
+        let with_environment = environment([("max_depth", "5")]);
+        for (toml, environment, expected) in [
+            (base, HashMap::new(), 3),
+            (renderer, HashMap::new(), 4),
+            (base, with_environment.clone(), 5),
+            (renderer, with_environment, 5),
+        ] {
+            let config = for_html(toml, &environment).unwrap();
+            assert_eq!(config.max_depth, expected, "{} {:?}", toml, environment);
+        }
+    }
+
+    #[cfg(feature = "preprocessor")]
+    #[test]
+    fn applies_the_language_table_of_a_language_set_by_the_environment() {
+        let toml = "title = \"Book\"\n\
+                    [languages.de]\n\
+                    title = \"Buch\"\n\
+                    ignore_missing_chapter_files = true";
+        let config = for_html(toml, &HashMap::new()).unwrap();
+        assert_eq!(config.title.as_deref(), Some("Book"));
+
+        let config = for_html(toml, &environment([("language", "de")])).unwrap();
+        assert_eq!(config.language.as_deref(), Some("de"));
+        assert_eq!(config.title.as_deref(), Some("Buch"));
+        // So `build.create-missing` is not used, which would contradict it
+        assert!(config.ignore_missing_chapter_files);
+        assert_eq!(
+            config.create_missing_chapter_files,
+            CreateMissingChapterFiles::Never
+        );
+
+        // The environment overrides the language table as well
+        let environment = environment([("language", "de"), ("title", "Handbuch")]);
+        let config = for_html(toml, &environment).unwrap();
+        assert_eq!(config.title.as_deref(), Some("Handbuch"));
+    }
+}
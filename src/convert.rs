@@ -0,0 +1,248 @@
+use std::{
+    cell::RefCell,
+    collections::{hash_map::DefaultHasher, HashMap},
+    ffi::OsString,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    process::Command,
+    rc::Rc,
+};
+
+use anyhow::{anyhow, Context, Error};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Config, Converter},
+    summary_fs::{StdFs, SummaryFs},
+    title_cache::Stamp,
+    write_guard::WriteGuard,
+};
+
+/// The markdown of the files converted by the `converter`s in one run. Every file is converted at
+/// most once per run, and not at all if the `converter_cache` has the output of the same command
+/// for the file as it is.
+pub(crate) struct Conversions {
+    cache_dir: Option<PathBuf>,
+    /// False with `dry_run`, the cache is only read then.
+    write_cache: bool,
+    write_guard: WriteGuard,
+    /// The markdown by the path of the converted file.
+    converted: RefCell<HashMap<PathBuf, Rc<str>>>,
+    /// The converted files by their location within the book.
+    sources: RefCell<HashMap<PathBuf, PathBuf>>,
+}
+
+/// A file of the `converter_cache`.
+#[derive(Serialize, Deserialize)]
+struct CachedConversion {
+    source: PathBuf,
+    stamp: Stamp,
+    command: String,
+    markdown: String,
+}
+
+impl Conversions {
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
+            cache_dir: config.converter_cache.clone(),
+            write_cache: !config.dry_run,
+            write_guard: WriteGuard::new(config),
+            converted: RefCell::new(HashMap::new()),
+            sources: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Remember that the chapter at `location` is the file at `path` converted to markdown.
+    pub(crate) fn add_location(&self, location: PathBuf, path: &Path) {
+        self.sources
+            .borrow_mut()
+            .insert(location, path.to_path_buf());
+    }
+
+    /// The converted files by their location within the book.
+    pub(crate) fn sources(&self) -> HashMap<PathBuf, PathBuf> {
+        self.sources.borrow().clone()
+    }
+
+    /// The markdown of the chapter at `location`, if it is a converted file.
+    #[cfg(feature = "preprocessor")]
+    pub(crate) fn markdown_at(
+        &self,
+        location: &Path,
+        config: &Config,
+    ) -> Option<Result<Rc<str>, Error>> {
+        let path = self.sources.borrow().get(location)?.clone();
+        let converter = config.converter(&path)?;
+        Some(self.markdown(&path, converter))
+    }
+
+    /// The markdown of the file at `path`, converted with `converter`.
+    pub(crate) fn markdown(&self, path: &Path, converter: &Converter) -> Result<Rc<str>, Error> {
+        if let Some(markdown) = self.converted.borrow().get(path) {
+            return Ok(Rc::clone(markdown));
+        }
+        let stamp = StdFs
+            .metadata(path)
+            .map(|metadata| Stamp::of(&metadata))
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let markdown = match self.cached(path, &stamp, converter) {
+            Some(markdown) => {
+                debug!("Using the cached conversion of {}", path.display());
+                markdown
+            }
+            None => {
+                let markdown = convert(path, converter)?;
+                self.cache(path, stamp, converter, &markdown);
+                markdown
+            }
+        };
+        let markdown: Rc<str> = markdown.into();
+        self.converted
+            .borrow_mut()
+            .insert(path.to_path_buf(), Rc::clone(&markdown));
+        Ok(markdown)
+    }
+
+    /// The file of the `converter_cache` for the file at `path`.
+    fn cache_file(&self, path: &Path) -> Option<PathBuf> {
+        let mut hasher = DefaultHasher::new();
+        path.hash(&mut hasher);
+        Some(
+            self.cache_dir
+                .as_ref()?
+                .join(format!("{:016x}.json", hasher.finish())),
+        )
+    }
+
+    /// The cached markdown of the file at `path`, if it was converted by the same command when it
+    /// had the same `stamp`.
+    fn cached(&self, path: &Path, stamp: &Stamp, converter: &Converter) -> Option<String> {
+        let content = std::fs::read(self.cache_file(path)?).ok()?;
+        let cached: CachedConversion = serde_json::from_slice(&content).ok()?;
+        (cached.source == path && cached.stamp == *stamp && cached.command == converter.command)
+            .then_some(cached.markdown)
+    }
+
+    fn cache(&self, path: &Path, stamp: Stamp, converter: &Converter, markdown: &str) {
+        let Some(cache_file) = self.cache_file(path).filter(|_| self.write_cache) else {
+            return;
+        };
+        if let Err(error) = self.write_guard.check(&cache_file) {
+            warn!("Failed to write the converter cache: {:#}", error);
+            return;
+        }
+        let cached = CachedConversion {
+            source: path.to_path_buf(),
+            stamp,
+            command: converter.command.clone(),
+            markdown: markdown.to_owned(),
+        };
+        let result = std::fs::create_dir_all(cache_file.parent().unwrap())
+            .and_then(|()| Ok(serde_json::to_vec(&cached)?))
+            .and_then(|content| std::fs::write(&cache_file, content));
+        if let Err(error) = result {
+            warn!(
+                "Failed to write the converter cache {}: {}",
+                cache_file.display(),
+                error
+            );
+        }
+    }
+}
+
+/// Run the command of the `converter` for the file at `path` and return what it printed.
+fn convert(path: &Path, converter: &Converter) -> Result<String, Error> {
+    debug!("Converting {} with `{}`", path.display(), converter.command);
+    let mut words = converter.command.split_whitespace().map(|word| {
+        if word == "{input}" {
+            path.as_os_str().to_owned()
+        } else {
+            OsString::from(word.replace("{input}", &path.to_string_lossy()))
+        }
+    });
+    let program = words
+        .next()
+        .ok_or_else(|| anyhow!("The command to convert {} is empty", path.display()))?;
+    let output = Command::new(&program)
+        .args(words)
+        .output()
+        .with_context(|| {
+            format!(
+                "Failed to run `{}` to convert {}",
+                program.to_string_lossy(),
+                path.display()
+            )
+        })?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to convert {} with `{}` ({}): {}",
+            path.display(),
+            converter.command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|_| {
+        anyhow!(
+            "The markdown converted from {} by `{}` is not valid UTF-8",
+            path.display(),
+            converter.command
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn caches_the_output_until_the_file_changes() -> Result<(), Error> {
+        let dir = TempDir::new("converter-cache");
+        let path = dir.write("src/usage.adoc", "= Usage\n\nRun it.\n");
+        // Replaces the AsciiDoc title by a markdown heading
+        let converter = Converter::new(["adoc"], "sed -e s/^=/#/ {input}");
+        let config = Config::builder()
+            .converter(converter.clone())
+            .converter_cache(dir.path().join("converted"))
+            .build()?;
+
+        let markdown = Conversions::new(&config).markdown(&path, &converter)?;
+        assert_eq!(&*markdown, "# Usage\n\nRun it.\n");
+        let cache_files: Vec<_> = std::fs::read_dir(dir.path().join("converted"))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(cache_files.len(), 1);
+
+        // The next run takes the cached output, as long as the file does not change
+        let mut cached: CachedConversion =
+            serde_json::from_slice(&std::fs::read(&cache_files[0])?)?;
+        cached.markdown = "# Cached\n".to_owned();
+        std::fs::write(&cache_files[0], serde_json::to_vec(&cached)?)?;
+        let markdown = Conversions::new(&config).markdown(&path, &converter)?;
+        assert_eq!(&*markdown, "# Cached\n");
+        std::fs::write(&path, "= Usage\n")?;
+        let markdown = Conversions::new(&config).markdown(&path, &converter)?;
+        assert_eq!(&*markdown, "# Usage\n");
+        Ok(())
+    }
+
+    #[test]
+    fn names_the_file_and_the_output_of_failed_commands() -> Result<(), Error> {
+        let dir = TempDir::new("converter-failure");
+        let path = dir.write("usage.adoc", "= Usage\n");
+        let converter = Converter::new(["adoc"], "sed -e s/^=/#/ {input} missing.adoc");
+        let config = Config::builder().converter(converter.clone()).build()?;
+        let error = Conversions::new(&config)
+            .markdown(&path, &converter)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains(&format!(
+            "Failed to convert {} with `sed -e s/^=/#/ {{input}} missing.adoc`",
+            path.display()
+        )));
+        assert!(error.contains("missing.adoc: No such file or directory"));
+        Ok(())
+    }
+}
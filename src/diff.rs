@@ -0,0 +1,140 @@
+use std::fmt::Write;
+
+/// How many unchanged lines are shown around every change.
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Create a unified diff from the old to the new lines. Returns an empty string if they are the
+/// same.
+pub(crate) fn unified_diff(old: &[&str], new: &[&str], old_name: &str, new_name: &str) -> String {
+    let lines = diff_lines(old, new);
+    if lines.iter().all(|line| matches!(line, Line::Same(_))) {
+        return String::new();
+    }
+
+    let mut diff = format!("--- {}\n+++ {}\n", old_name, new_name);
+    // The start of the current hunk in `lines`, and of the hunk in the old and new lines
+    let mut index = 0;
+    let (mut old_line, mut new_line) = (0, 0);
+    while index < lines.len() {
+        let Some(change) = lines[index..]
+            .iter()
+            .position(|line| !matches!(line, Line::Same(_)))
+        else {
+            break;
+        };
+        let context_before = change.min(CONTEXT);
+        let skipped = change - context_before;
+        index += skipped;
+        old_line += skipped;
+        new_line += skipped;
+
+        // The hunk ends once more than two times the context is unchanged
+        let mut end = index + context_before;
+        let mut unchanged = 0;
+        while end < lines.len() && unchanged <= 2 * CONTEXT {
+            unchanged = match lines[end] {
+                Line::Same(_) => unchanged + 1,
+                _ => 0,
+            };
+            end += 1;
+        }
+        let end = end - unchanged.saturating_sub(CONTEXT);
+
+        let hunk = &lines[index..end];
+        let old_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Added(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Removed(_)))
+            .count();
+        writeln!(
+            diff,
+            "@@ -{} +{} @@",
+            range(old_line, old_count),
+            range(new_line, new_count)
+        )
+        .unwrap();
+        for line in hunk {
+            match line {
+                Line::Same(line) => writeln!(diff, " {}", line),
+                Line::Removed(line) => writeln!(diff, "-{}", line),
+                Line::Added(line) => writeln!(diff, "+{}", line),
+            }
+            .unwrap();
+        }
+
+        index = end;
+        old_line += old_count;
+        new_line += new_count;
+    }
+    diff
+}
+
+/// The range of a hunk as `start,count`, where `start` is 1-based unless the hunk is empty.
+fn range(start: usize, count: usize) -> String {
+    if count == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, count)
+    }
+}
+
+/// Find the changes between the lines, using the longest common subsequence of the lines between
+/// their common prefix and suffix.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    // lengths[i][j] is the length of the longest common subsequence of old_middle[i..] and
+    // new_middle[j..]
+    let mut lengths = vec![vec![0usize; new_middle.len() + 1]; old_middle.len() + 1];
+    for i in (0..old_middle.len()).rev() {
+        for j in (0..new_middle.len()).rev() {
+            lengths[i][j] = if old_middle[i] == new_middle[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines: Vec<_> = old[..prefix].iter().map(|line| Line::Same(line)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < old_middle.len() || j < new_middle.len() {
+        if i < old_middle.len() && j < new_middle.len() && old_middle[i] == new_middle[j] {
+            lines.push(Line::Same(old_middle[i]));
+            i += 1;
+            j += 1;
+        } else if i < old_middle.len()
+            && (j == new_middle.len() || lengths[i + 1][j] >= lengths[i][j + 1])
+        {
+            lines.push(Line::Removed(old_middle[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(new_middle[j]));
+            j += 1;
+        }
+    }
+    lines.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| Line::Same(line)),
+    );
+    lines
+}
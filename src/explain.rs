@@ -0,0 +1,150 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    book::{Link, Summary, SummaryItem},
+    config::Config,
+    warnings::{Diagnostic, DiagnosticLevel},
+    Excluded,
+};
+
+/// How a file or directory became part of the generated summary, see
+/// [`GenerateSummary::explain`](crate::GenerateSummary::explain). Its [`Display`] is what
+/// `mdbook-generate-summary explain` prints.
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    /// The explained file or directory, relative to the src directory.
+    pub location: PathBuf,
+    /// Whether it exists in the src directory.
+    pub exists: bool,
+    /// Its chapter in the generated summary, without the nested chapters. [`Option::None`] if it
+    /// is not linked in the summary, e.g. as it is left out or a draft chapter.
+    pub chapter: Option<Link>,
+    /// Why it, or a directory containing it, is not part of the book.
+    pub excluded: Option<String>,
+    /// The decisions about it in the order they were made, like its position among the entries
+    /// of its directory and where its name came from, and the warnings about it.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Explanation {
+    /// Collect what the run recorded about the `explained` locations, the explained one followed
+    /// by the ones standing for it like its chapter file.
+    pub(crate) fn new(
+        explained: &[PathBuf],
+        src_dir: &Path,
+        summary: &Summary,
+        excluded: &Excluded,
+        diagnostics: Vec<Diagnostic>,
+        config: &Config,
+    ) -> Self {
+        let location = explained[0].clone();
+        let path = src_dir.join(&location);
+        // Converted files are linked at the location with the extension `.md`
+        let locations: Vec<_> = explained
+            .iter()
+            .flat_map(|location| match config.converter(location) {
+                Some(_) => vec![location.clone(), location.with_extension("md")],
+                None => vec![location.clone()],
+            })
+            .collect();
+        let chapter = summary
+            .prefix_chapters
+            .iter()
+            .chain(&summary.numbered_chapters)
+            .chain(&summary.suffix_chapters)
+            .find_map(|item| find_chapter(item, &locations))
+            .map(|link| Link {
+                nested_items: vec![],
+                ..link.clone()
+            });
+        let excluded = match &chapter {
+            Some(_) => None,
+            None => locations
+                .iter()
+                .find_map(|location| {
+                    location
+                        .ancestors()
+                        .find_map(|ancestor| excluded.get(ancestor))
+                })
+                .map(|reason| reason.to_string())
+                .or_else(|| {
+                    let is_file = path.is_file();
+                    let is_markdown = path.extension().is_some_and(|extension| extension == "md");
+                    (is_file && !is_markdown && config.converter(&path).is_none()).then(|| {
+                        "it is neither a markdown file nor converted by a `converter`".to_owned()
+                    })
+                }),
+        };
+        let diagnostics = diagnostics
+            .into_iter()
+            .filter(|diagnostic| {
+                diagnostic.path.as_ref().is_some_and(|path| {
+                    locations.iter().any(|location| Path::new(path) == location)
+                })
+            })
+            .collect();
+        Self {
+            location,
+            exists: path.exists(),
+            chapter,
+            excluded,
+            diagnostics,
+        }
+    }
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let location = self.location.display();
+        match (&self.chapter, &self.excluded) {
+            (
+                Some(Link {
+                    name,
+                    number: Some(number),
+                    ..
+                }),
+                _,
+            ) => writeln!(f, "{} is the chapter {} \"{}\"", location, number, name)?,
+            (Some(Link { name, .. }), _) => {
+                writeln!(f, "{} is the unnumbered chapter \"{}\"", location, name)?
+            }
+            (None, Some(reason)) => {
+                writeln!(f, "{} is not part of the book, {}", location, reason)?
+            }
+            (None, None) if self.exists => {
+                writeln!(f, "{} is not linked in the summary", location)?
+            }
+            (None, None) => writeln!(f, "{} does not exist in the src directory", location)?,
+        }
+        for diagnostic in &self.diagnostics {
+            match (diagnostic.level, diagnostic.code) {
+                (DiagnosticLevel::Info, "explanation") => writeln!(f, "  {}", diagnostic.message)?,
+                (DiagnosticLevel::Info, code) => writeln!(f, "  {}: {}", code, diagnostic.message)?,
+                (DiagnosticLevel::Warning, code) => {
+                    writeln!(f, "  warning ({}): {}", code, diagnostic.message)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The chapter linking one of `locations` in the item or its nested chapters.
+fn find_chapter<'a>(item: &'a SummaryItem, locations: &[PathBuf]) -> Option<&'a Link> {
+    let SummaryItem::Link(link) = item else {
+        return None;
+    };
+    if link
+        .location
+        .as_ref()
+        .is_some_and(|location| locations.contains(location))
+    {
+        return Some(link);
+    }
+    link.nested_items
+        .iter()
+        .find_map(|item| find_chapter(item, locations))
+}
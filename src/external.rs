@@ -0,0 +1,299 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Error};
+use log::debug;
+
+use crate::{
+    book::{Link, Summary, SummaryItem},
+    child_section,
+    config::{ChapterPosition, Mount},
+    summary_fs::FileKind,
+    Entry, Generator, Outcome,
+};
+
+/// The location of the external chapter at `path` within the book, its file name in the src
+/// directory. mdBook loads it from there, unless the preprocessor adds its content.
+pub(crate) fn external_location(path: &Path) -> PathBuf {
+    PathBuf::from(path.file_name().unwrap_or_default())
+}
+
+impl Generator<'_> {
+    /// Returns true if the top level markdown file at `location` is replaced by an external
+    /// chapter. It is a placeholder, e.g. created by mdBook for the location of the chapter.
+    pub(crate) fn is_external_location(&self, location: &Path) -> bool {
+        self.config
+            .external_chapters
+            .iter()
+            .any(|chapter| external_location(&chapter.path) == location)
+    }
+
+    /// The chapters of the `external_chapters` by their position. Fails if one of their files does
+    /// not exist.
+    pub(crate) fn external_chapters(&mut self) -> Result<Vec<(ChapterPosition, Link)>, Error> {
+        let mut chapters = vec![];
+        for chapter in &self.config.external_chapters {
+            let fs = self.hooks.fs();
+            let path = fs.canonicalize(&chapter.path).with_context(|| {
+                format!(
+                    "Failed to find the external chapter {}",
+                    chapter.path.display()
+                )
+            })?;
+            self.stats.metadata_queries += 1;
+            let kind = fs
+                .metadata(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?
+                .kind;
+            if kind != FileKind::File {
+                return Err(anyhow!(
+                    "The external chapter {} is not a file",
+                    chapter.path.display()
+                ));
+            }
+
+            let name = match &chapter.title {
+                Some(title) => title.clone(),
+                None => {
+                    let head = self.heads.get(&path, self.config)?;
+                    head.front_matter
+                        .get("title")
+                        .map(str::to_owned)
+                        .or_else(|| head.title.clone())
+                        .unwrap_or_else(|| {
+                            path.file_stem()
+                                .unwrap_or_default()
+                                .to_string_lossy()
+                                .into_owned()
+                        })
+                }
+            };
+            let location = external_location(&chapter.path);
+            debug!(
+                "Adding the external chapter {} as {}",
+                path.display(),
+                location.display()
+            );
+            chapters.push((
+                chapter.position,
+                Link {
+                    name,
+                    location: Some(location),
+                    nested_items: vec![],
+                    number: None,
+                },
+            ));
+        }
+        Ok(chapters)
+    }
+}
+
+/// Add the external `chapters` to the summary at their position, the numbered ones after the
+/// other numbered chapters.
+pub(crate) fn add_external_chapters(summary: &mut Summary, chapters: Vec<(ChapterPosition, Link)>) {
+    for (position, mut link) in chapters {
+        match position {
+            ChapterPosition::Prefix => summary.prefix_chapters.push(SummaryItem::Link(link)),
+            ChapterPosition::Numbered => {
+                let count = summary
+                    .numbered_chapters
+                    .iter()
+                    .filter(|item| matches!(item, SummaryItem::Link(link) if link.number.is_some()))
+                    .count();
+                link.number = Some(child_section(None, count + 1));
+                summary.numbered_chapters.push(SummaryItem::Link(link));
+            }
+            ChapterPosition::Suffix => summary.suffix_chapters.push(SummaryItem::Link(link)),
+        }
+    }
+}
+
+impl Generator<'_> {
+    /// Add the mounts whose path within the book is inside of the directory to its `entries`.
+    /// A mount replaces the entry of the same name, e.g. the placeholders mdBook creates for the
+    /// chapters of the mount in the src directory.
+    pub(crate) fn add_mounts(&mut self, dir_path: &Path, entries: &mut Vec<Entry>) {
+        let location = self.location(dir_path);
+        for mount in &self.config.mounts {
+            let Some(file_name) = mount.at.file_name() else {
+                continue;
+            };
+            if mount.at.parent() != Some(location.as_path()) {
+                continue;
+            }
+            let at = dir_path.join(file_name);
+            match self.resolve_mount(mount) {
+                Ok(source) => {
+                    debug!("Mounting {} at {}", source.display(), mount.at.display());
+                    entries.retain(|entry| entry.file_name() != file_name);
+                    self.mounted.push((source.clone(), mount.at.clone()));
+                    let mut entry = Entry::named(source, file_name.to_owned(), true);
+                    entry.title = mount.title.clone();
+                    entries.push(entry);
+                }
+                Err(error) => self.handle_error(&at, Outcome::Skipped, error),
+            }
+        }
+    }
+
+    /// The resolved source directory of the mount. Fails if it is not a directory.
+    fn resolve_mount(&self, mount: &Mount) -> Result<PathBuf, Error> {
+        let fs = self.hooks.fs();
+        let source = fs.canonicalize(&mount.source).with_context(|| {
+            format!(
+                "Failed to find the source {} of the mount at {}",
+                mount.source.display(),
+                mount.at.display()
+            )
+        })?;
+        let kind = fs
+            .metadata(&source)
+            .with_context(|| format!("Failed to read {}", source.display()))?
+            .kind;
+        if kind != FileKind::Directory {
+            return Err(anyhow!(
+                "The source {} of the mount at {} is not a directory",
+                mount.source.display(),
+                mount.at.display()
+            ));
+        }
+        Ok(source)
+    }
+
+    /// The location within the book of a `path` inside of a mounted directory, with '/' as the
+    /// separator like the other locations.
+    pub(crate) fn mounted_location(&self, path: &Path) -> Option<PathBuf> {
+        self.mounted.iter().find_map(|(source, at)| {
+            let relative_path = path.strip_prefix(source).ok()?;
+            let components: Vec<_> = at
+                .components()
+                .chain(relative_path.components())
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect();
+            Some(PathBuf::from(components.join("/")))
+        })
+    }
+
+    /// The path of the chapter file at `location` within the book, inside of the source of a
+    /// mount or the src directory.
+    pub(crate) fn chapter_path(&self, location: &Path) -> PathBuf {
+        self.mounted
+            .iter()
+            .find_map(|(source, at)| {
+                let relative_path = location.strip_prefix(at).ok()?;
+                Some(source.join(relative_path))
+            })
+            .unwrap_or_else(|| self.src_dir.join(location))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{Config, ExternalChapter},
+        summary_fs::MemoryFs,
+        test_util::{chapters, generate},
+    };
+
+    #[test]
+    fn adds_the_external_chapters_at_their_position() -> Result<(), Error> {
+        let fs = MemoryFs::new()
+            .with_file("/book/src/intro.md", "")
+            .with_file("/book/CHANGELOG.md", "# Changes\n")
+            .with_file("/book/LICENSE.md", "")
+            .with_file("/book/ROADMAP.md", "---\ntitle: Plans\n---\n# Roadmap\n");
+        let config = Config::builder()
+            .get_chapter_name_from_file(true)
+            .external_chapter(ExternalChapter::new("/book/CHANGELOG.md"))
+            .external_chapter(
+                ExternalChapter::new("/book/LICENSE.md")
+                    .title("License")
+                    .position(ChapterPosition::Prefix),
+            )
+            .external_chapter(
+                ExternalChapter::new("/book/ROADMAP.md").position(ChapterPosition::Numbered),
+            )
+            .build()?;
+        let (summary, _) = generate(fs, Path::new("/book/src"), &config);
+        let summary = summary?;
+        assert_eq!(chapters(&summary.prefix_chapters), ["License (LICENSE.md)"]);
+        assert_eq!(
+            chapters(&summary.numbered_chapters),
+            ["1. intro (intro.md)", "2. Plans (ROADMAP.md)"]
+        );
+        assert_eq!(
+            chapters(&summary.suffix_chapters),
+            ["Changes (CHANGELOG.md)"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_missing_external_chapters() -> Result<(), Error> {
+        for (path, message) in [
+            (
+                "/book/MISSING.md",
+                "Failed to find the external chapter /book/MISSING.md",
+            ),
+            (
+                "/book/assets",
+                "The external chapter /book/assets is not a file",
+            ),
+        ] {
+            let config = Config::builder()
+                .external_chapter(ExternalChapter::new(path))
+                .build()?;
+            let fs = MemoryFs::new()
+                .with_file("/book/src/intro.md", "")
+                .with_dir("/book/assets");
+            let (summary, _) = generate(fs, Path::new("/book/src"), &config);
+            assert_eq!(summary.unwrap_err().to_string(), message);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn mounts_directories_inside_of_the_book() -> Result<(), Error> {
+        let fs = MemoryFs::new()
+            .with_file("/book/src/intro.md", "")
+            .with_file("/book/src/guide/README.md", "")
+            .with_file("/book/src/guide/concepts/README.md", "")
+            .with_file("/shared/common-concepts/README.md", "")
+            .with_file("/shared/common-concepts/ownership.md", "# Ownership\n");
+        let config = Config::builder()
+            .get_chapter_name_from_file(true)
+            .mount(Mount::new("/shared/common-concepts", "guide/concepts").title("Core concepts"))
+            .build()?;
+        let (summary, _) = generate(fs, Path::new("/book/src"), &config);
+        assert_eq!(
+            chapters(&summary?.numbered_chapters),
+            [
+                "1. guide (guide/README.md)",
+                "1.1. Core concepts (guide/concepts/README.md)",
+                "1.1.1. Ownership (guide/concepts/ownership.md)",
+                "2. intro (intro.md)",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_mounts_without_a_source_directory() -> Result<(), Error> {
+        let fs = MemoryFs::new()
+            .with_file("/book/src/intro.md", "")
+            .with_file("/shared/notes.md", "");
+        let config = Config::builder()
+            .mount(Mount::new("/shared/missing", "missing"))
+            .mount(Mount::new("/shared/notes.md", "notes"))
+            .build()?;
+        let (summary, _) = generate(fs, Path::new("/book/src"), &config);
+        assert_eq!(
+            format!("{:#}", summary.unwrap_err()),
+            "Failed to generate the summary:\n\
+             Failed to find the source /shared/missing of the mount at missing: \
+             /shared/missing does not exist\n\
+             The source /shared/notes.md of the mount at notes is not a directory"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    collections::HashSet,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, Context, Error};
+
+/// The files inside of a directory which git tracks, see `git_tracked_only`.
+pub(crate) struct TrackedFiles {
+    /// The tracked files, relative to the directory.
+    files: HashSet<PathBuf>,
+    /// The directories containing tracked markdown files, relative to the directory.
+    directories: HashSet<PathBuf>,
+}
+
+impl TrackedFiles {
+    /// Ask git once for all files inside of `dir` which are in its index. Fails if git can not be
+    /// run or `dir` is not inside of a git work tree.
+    pub(crate) fn read(dir: &Path) -> Result<Self, Error> {
+        let stdout = String::from_utf8(git(dir, &["ls-files", "-z", "--cached", "--", "."])?)
+            .context("git listed a file whose name is not valid UTF-8")?;
+
+        let mut tracked = Self {
+            files: HashSet::new(),
+            directories: HashSet::new(),
+        };
+        for file in stdout.split('\0').filter(|file| !file.is_empty()) {
+            let file = PathBuf::from(file);
+            if file.extension() == Some(OsStr::new("md")) {
+                tracked.directories.extend(
+                    file.ancestors()
+                        .skip(1)
+                        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+                        .map(Path::to_path_buf),
+                );
+            }
+            tracked.files.insert(file);
+        }
+        Ok(tracked)
+    }
+
+    /// True if the file or directory at `location` (relative to the directory) is tracked, which
+    /// means that it contains a tracked markdown file for directories.
+    pub(crate) fn includes(&self, location: &Path, is_dir: bool) -> bool {
+        if is_dir {
+            self.directories.contains(location)
+        } else {
+            self.files.contains(location)
+        }
+    }
+
+    /// The number of tracked files.
+    pub(crate) fn len(&self) -> usize {
+        self.files.len()
+    }
+}
+
+/// Fails if `dir` is not inside of a git work tree or git can not be run.
+#[cfg(feature = "preprocessor")]
+pub(crate) fn check_work_tree(dir: &Path) -> Result<(), Error> {
+    git(dir, &["rev-parse", "--show-toplevel"]).map(drop)
+}
+
+/// Run git with `args` in `dir` and return its output. Fails with the error git printed if it
+/// does not succeed.
+fn git(dir: &Path, args: &[&str]) -> Result<Vec<u8>, Error> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, test_util::TempDir, SummaryItem};
+
+    /// A git repository in a new temporary directory with the files, of which the `tracked` ones
+    /// are added to the index. [`Option::None`] if git can not be run.
+    fn repository(files: &[&str], tracked: &[&str]) -> Option<TempDir> {
+        if Command::new("git").arg("--version").output().is_err() {
+            eprintln!("Skipping the test, git can not be run");
+            return None;
+        }
+        let dir = TempDir::new("git");
+        for file in files {
+            dir.write(file, "");
+        }
+        git(dir.path(), &["init", "--quiet"]).unwrap();
+        git(dir.path(), &[&["add", "--"], tracked].concat()).unwrap();
+        Some(dir)
+    }
+
+    #[test]
+    fn reads_the_tracked_files_and_their_directories() {
+        let files = [
+            "src/intro.md",
+            "src/guide/README.md",
+            "src/guide/notes.md",
+            "src/images/logo.png",
+            "src/scratch/ideas.md",
+            "book.toml",
+        ];
+        let tracked = ["src/intro.md", "src/guide/README.md", "src/images/logo.png"];
+        let Some(dir) = repository(&files, &tracked) else {
+            return;
+        };
+
+        let tracked_files = TrackedFiles::read(&dir.path().join("src")).unwrap();
+        assert_eq!(tracked_files.len(), 3);
+        for (location, is_dir, included) in [
+            ("intro.md", false, true),
+            ("guide/README.md", false, true),
+            ("guide/notes.md", false, false),
+            ("images/logo.png", false, true),
+            ("guide", true, true),
+            // Directories only count with tracked markdown files
+            ("images", true, false),
+            ("scratch", true, false),
+        ] {
+            assert_eq!(
+                tracked_files.includes(Path::new(location), is_dir),
+                included,
+                "{}",
+                location
+            );
+        }
+    }
+
+    #[test]
+    fn leaves_out_untracked_files_with_git_tracked_only() {
+        let files = [
+            "src/intro.md",
+            "src/guide/README.md",
+            "src/guide/setup.md",
+            "src/guide/notes.md",
+            "src/scratch/ideas.md",
+        ];
+        let tracked = ["src/intro.md", "src/guide/README.md", "src/guide/setup.md"];
+        let Some(dir) = repository(&files, &tracked) else {
+            return;
+        };
+        let names = |config: &Config| {
+            let summary = crate::generate_summary(&dir.path().join("src"), config).unwrap();
+            let SummaryItem::Link(guide) = &summary.numbered_chapters[0] else {
+                panic!("Expected the guide");
+            };
+            let nested = crate::test_util::names(&guide.nested_items).join(", ");
+            let names = crate::test_util::names(&summary.numbered_chapters).join(", ");
+            format!("{} ({})", names, nested)
+        };
+
+        let config = Config::builder()
+            .ignore_missing_chapter_files(true)
+            .build()
+            .unwrap();
+        assert_eq!(names(&config), "guide, intro, scratch (notes, setup)");
+        let config = Config::builder()
+            .ignore_missing_chapter_files(true)
+            .git_tracked_only(true)
+            .build()
+            .unwrap();
+        // Neither the untracked file nor the directory without tracked markdown files is added
+        assert_eq!(names(&config), "guide, intro (setup)");
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Error};
+use log::debug;
+
+use crate::{config::Config, created_chapter_file_content, git, write_guard::WriteGuard};
+
+/// The first line of the block of `.gitignore` listing the created chapter files.
+const BEGIN: &str = "# BEGIN mdbook-generate-summary created chapter files";
+/// The last line of the block of `.gitignore` listing the created chapter files.
+const END: &str = "# END mdbook-generate-summary created chapter files";
+
+/// The `.gitignore` of a book with the block listing the chapter files created by
+/// `create_missing_chapter_files`, see `gitignore_created_files`.
+pub(crate) struct CreatedFilesIgnore {
+    /// The resolved book root, the paths in the block are relative to it.
+    root: PathBuf,
+    path: PathBuf,
+}
+
+impl CreatedFilesIgnore {
+    /// The `.gitignore` in `root`. Fails if `root` is not inside of a git work tree, as ignoring
+    /// the files would have no effect.
+    pub(crate) fn new(root: &Path) -> Result<Self, Error> {
+        git::check_work_tree(root).with_context(|| {
+            format!(
+                "`preprocessor.generate-summary.gitignore_created_files` adds the created chapter \
+                 files to the .gitignore of the book, but {} is not inside of a git repository. \
+                 Disable `gitignore_created_files` or run `git init`",
+                root.display()
+            )
+        })?;
+        let root = root
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve the book root {}", root.display()))?;
+        Ok(Self {
+            path: root.join(".gitignore"),
+            root,
+        })
+    }
+
+    /// Add the `created_files` to the block, unless it lists them already, and remove the files
+    /// from it which were replaced by real ones: those which no longer exist, were changed or are
+    /// tracked by git. The `.gitignore` is only written if the block changed, and the block is
+    /// removed once it is empty.
+    pub(crate) fn update(&self, created_files: &[PathBuf], config: &Config) -> Result<(), Error> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => String::new(),
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("Failed to read {}", self.path.display()))
+            }
+        };
+        let (before, listed, after) = split_block(&content).with_context(|| {
+            format!(
+                "Failed to read the created files of {}",
+                self.path.display()
+            )
+        })?;
+
+        let tracked = git::TrackedFiles::read(&self.root).ok();
+        let mut files: Vec<PathBuf> = listed
+            .into_iter()
+            .filter(|file| {
+                let generated = self.is_generated(file)
+                    && !tracked
+                        .as_ref()
+                        .is_some_and(|tracked| tracked.includes(file, false));
+                if !generated {
+                    debug!(
+                        "Removing {} from {}, it is no longer a created chapter file",
+                        file.display(),
+                        self.path.display()
+                    );
+                }
+                generated
+            })
+            .collect();
+        for created_file in created_files {
+            let file = created_file.strip_prefix(&self.root).map_err(|_| {
+                anyhow!(
+                    "Can not add the created chapter file {} to {}, it is outside of the book",
+                    created_file.display(),
+                    self.path.display()
+                )
+            })?;
+            if !files.iter().any(|listed| listed == file) {
+                files.push(file.to_path_buf());
+            }
+        }
+
+        let mut new_content = before;
+        if !files.is_empty() {
+            if !new_content.is_empty() && !new_content.ends_with('\n') {
+                new_content.push('\n');
+            }
+            new_content.push_str(BEGIN);
+            new_content.push('\n');
+            for file in &files {
+                new_content.push_str(&escape(file));
+                new_content.push('\n');
+            }
+            new_content.push_str(END);
+            new_content.push('\n');
+        }
+        new_content.push_str(&after);
+        if new_content == content {
+            return Ok(());
+        }
+        WriteGuard::new(config).check(&self.path)?;
+        std::fs::write(&self.path, new_content)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// True if the file at `location` (relative to the book root) still has the content it was
+    /// created with.
+    fn is_generated(&self, location: &Path) -> bool {
+        let path = self.root.join(location);
+        match (path.parent(), std::fs::read_to_string(&path)) {
+            (Some(dir), Ok(content)) => content == created_chapter_file_content(dir),
+            _ => false,
+        }
+    }
+}
+
+/// Split the content of a `.gitignore` into the lines before the block, the files it lists and the
+/// lines after it. Without a block, everything is before it.
+fn split_block(content: &str) -> Result<(String, Vec<PathBuf>, String), Error> {
+    let Some(begin) = content
+        .match_indices(BEGIN)
+        .map(|(index, _)| index)
+        .find(|&index| index == 0 || content[..index].ends_with('\n'))
+    else {
+        return Ok((content.to_owned(), vec![], String::new()));
+    };
+    let block = &content[begin + BEGIN.len()..];
+    let end = block
+        .lines()
+        .position(|line| line.trim_end() == END)
+        .ok_or_else(|| anyhow!("The line `{}` is missing", END))?;
+    let files = block
+        .lines()
+        .take(end)
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty())
+        .map(unescape)
+        .collect();
+    let after = block
+        .split_inclusive('\n')
+        .skip(end + 1)
+        .collect::<String>();
+    Ok((content[..begin].to_owned(), files, after))
+}
+
+/// The pattern matching only the file at `location` (relative to the `.gitignore`). Spaces are
+/// escaped as well, git would drop trailing ones.
+fn escape(location: &Path) -> String {
+    let mut pattern = String::new();
+    for component in location.components() {
+        pattern.push('/');
+        for character in component.as_os_str().to_string_lossy().chars() {
+            if matches!(character, '*' | '?' | '[' | '\\' | ' ') {
+                pattern.push('\\');
+            }
+            pattern.push(character);
+        }
+    }
+    pattern
+}
+
+/// The location of the file matched by a pattern written by [`escape`].
+fn unescape(pattern: &str) -> PathBuf {
+    let mut location = String::new();
+    let mut characters = pattern.trim_start_matches('/').chars();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => location.extend(characters.next()),
+            _ => location.push(character),
+        }
+    }
+    PathBuf::from(location)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestBook;
+
+    /// The `.gitignore` of the `book`, without checking that it is a git work tree.
+    fn created_files_ignore(book: &TestBook) -> CreatedFilesIgnore {
+        CreatedFilesIgnore {
+            root: book.root().to_path_buf(),
+            path: book.root().join(".gitignore"),
+        }
+    }
+
+    /// Create the chapter file at `location` in the src directory like
+    /// `create_missing_chapter_files` does.
+    fn create(book: &TestBook, location: &str) -> PathBuf {
+        let path = book.src_dir().join(location);
+        let dir = path.parent().unwrap();
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(&path, created_chapter_file_content(dir)).unwrap();
+        path
+    }
+
+    #[test]
+    fn lists_the_created_files_until_they_are_replaced() -> Result<(), Error> {
+        let book = TestBook::new();
+        book.write()?;
+        let gitignore = book.root().join(".gitignore");
+        std::fs::write(&gitignore, "/book\n")?;
+        let ignore = created_files_ignore(&book);
+        let config = Config::default();
+        let guide = create(&book, "guide/README.md");
+        let api = create(&book, "api docs/README.md");
+
+        ignore.update(&[guide.clone(), api.clone()], &config)?;
+        let listed = "/book\n\
+                      # BEGIN mdbook-generate-summary created chapter files\n\
+                      /src/guide/README.md\n\
+                      /src/api\\ docs/README.md\n\
+                      # END mdbook-generate-summary created chapter files\n";
+        assert_eq!(std::fs::read_to_string(&gitignore)?, listed);
+        // Files are listed once, and the files of earlier runs are kept
+        ignore.update(std::slice::from_ref(&guide), &config)?;
+        assert_eq!(std::fs::read_to_string(&gitignore)?, listed);
+
+        // Replaced by real files, the block is removed once it is empty
+        std::fs::write(&api, "# API\n\nHow to use it.\n")?;
+        ignore.update(&[], &config)?;
+        assert!(!std::fs::read_to_string(&gitignore)?.contains("api"));
+        std::fs::remove_file(&guide)?;
+        ignore.update(&[], &config)?;
+        assert_eq!(std::fs::read_to_string(&gitignore)?, "/book\n");
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_the_lines_around_the_block() -> Result<(), Error> {
+        let content = "/book\n\
+                       # BEGIN mdbook-generate-summary created chapter files\n\
+                       /src/a\\*b.md\n\
+                       # END mdbook-generate-summary created chapter files\n\
+                       *.log\n";
+        let (before, files, after) = split_block(content)?;
+        assert_eq!(before, "/book\n");
+        assert_eq!(files, [PathBuf::from("src/a*b.md")]);
+        assert_eq!(after, "*.log\n");
+        assert_eq!(escape(&files[0]), "/src/a\\*b.md");
+
+        let unclosed = "# BEGIN mdbook-generate-summary created chapter files\n/src/a.md\n";
+        assert!(split_block(unclosed).is_err());
+        Ok(())
+    }
+}
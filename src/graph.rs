@@ -0,0 +1,229 @@
+use std::fmt::Write;
+
+use crate::{
+    book::{Link, Summary, SummaryItem},
+    heading_number::starts_with_number,
+};
+
+/// The format of a diagram of the structure of a summary, see [`summary_to_graph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// A Mermaid flowchart, e.g. for a markdown file rendered by GitHub.
+    Mermaid,
+    /// A Graphviz graph in the DOT language.
+    Dot,
+}
+
+/// Serialize the structure of the summary to a tree diagram in `format`.
+///
+/// The title of the summary ("Summary" without one) is the root, with an edge to each of its
+/// chapters and from each chapter to its nested chapters. Chapters are labeled with their section
+/// number and name, drafts are dashed. Part titles are highlighted nodes between the root and the
+/// numbered chapters following them. Separators are left out. With `depth`, only that many levels
+/// of chapters are included (parts do not count), e.g. only the top level chapters with 1.
+///
+/// # Example
+/// ```
+/// use std::path::Path;
+///
+/// use mdbook_generate_summary::{
+///     summary_to_graph, Config, GenerateSummary, GraphFormat, MemoryFs, PlannedChapter,
+/// };
+///
+/// let fs = MemoryFs::new()
+///     .with_file("/src/intro.md", "# Introduction\n")
+///     .with_file("/src/guide/README.md", "---\nsection: Manual\n---\n# Guide\n")
+///     .with_file("/src/guide/setup.md", "# Setup \"quick\" <Linux>\n");
+/// let config = Config::builder()
+///     .get_chapter_name_from_file(true)
+///     .planned("guide", [PlannedChapter::new("Troubleshooting")])
+///     .group_by_front_matter("section")
+///     .build()?;
+/// let summary = GenerateSummary::new()
+///     .with_fs(Box::new(fs))
+///     .generate_summary(Path::new("/src"), &config)?;
+///
+/// assert_eq!(
+///     summary_to_graph(&summary, GraphFormat::Mermaid, None),
+///     r#"graph TD
+///     classDef draft stroke-dasharray: 5 5
+///     classDef part font-weight: bold, fill: #eee
+///     n0["Summary"]
+///     n1["Manual"]:::part
+///     n0 --> n1
+///     n2["1. Guide"]
+///     n1 --> n2
+///     n3["1.1. Setup #34;quick#34; #60;Linux#62;"]
+///     n2 --> n3
+///     n4["1.2. Troubleshooting"]:::draft
+///     n2 --> n4
+///     n5["Other"]:::part
+///     n0 --> n5
+///     n6["2. Introduction"]
+///     n5 --> n6
+/// "#
+/// );
+/// assert_eq!(
+///     summary_to_graph(&summary, GraphFormat::Dot, Some(1)),
+///     r##"digraph summary {
+///     node [shape=box];
+///     n0 [label="Summary", shape=plaintext];
+///     n1 [label="Manual", style="filled,bold", fillcolor="#eeeeee"];
+///     n0 -> n1;
+///     n2 [label="1. Guide"];
+///     n1 -> n2;
+///     n3 [label="Other", style="filled,bold", fillcolor="#eeeeee"];
+///     n0 -> n3;
+///     n4 [label="2. Introduction"];
+///     n3 -> n4;
+/// }
+/// "##
+/// );
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn summary_to_graph(summary: &Summary, format: GraphFormat, depth: Option<usize>) -> String {
+    let mut graph = Graph {
+        format,
+        depth,
+        output: String::new(),
+        nodes: 0,
+    };
+    match format {
+        GraphFormat::Mermaid => graph.output.push_str(
+            "graph TD\n    classDef draft stroke-dasharray: 5 5\n    classDef part font-weight: \
+             bold, fill: #eee\n",
+        ),
+        GraphFormat::Dot => graph
+            .output
+            .push_str("digraph summary {\n    node [shape=box];\n"),
+    }
+
+    let root = graph.node(
+        summary.title.as_deref().unwrap_or("Summary"),
+        NodeKind::Root,
+        None,
+    );
+    graph.items(&summary.prefix_chapters, root, 1);
+    graph.items(&summary.numbered_chapters, root, 1);
+    graph.items(&summary.suffix_chapters, root, 1);
+
+    if format == GraphFormat::Dot {
+        graph.output.push_str("}\n");
+    }
+    graph.output
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    Root,
+    Part,
+    Chapter,
+    Draft,
+}
+
+struct Graph {
+    format: GraphFormat,
+    depth: Option<usize>,
+    output: String,
+    /// The number of nodes so far, used for their identifiers.
+    nodes: usize,
+}
+
+impl Graph {
+    /// Add the items at `level` (starting with 1) as children of the node `parent`. The numbered
+    /// chapters after a part title become its children.
+    fn items(&mut self, items: &[SummaryItem], parent: usize, level: usize) {
+        if self.depth.is_some_and(|depth| level > depth) {
+            return;
+        }
+        let mut current_parent = parent;
+        for item in items {
+            match item {
+                SummaryItem::Link(link) => self.link(link, current_parent, level),
+                SummaryItem::PartTitle(title) => {
+                    current_parent = self.node(title, NodeKind::Part, Some(parent));
+                }
+                SummaryItem::Separator => {}
+            }
+        }
+    }
+
+    fn link(&mut self, link: &Link, parent: usize, level: usize) {
+        let label = match &link.number {
+            Some(number) if !starts_with_number(&link.name, number) => {
+                format!("{} {}", number, link.name)
+            }
+            _ => link.name.clone(),
+        };
+        let kind = match link.location {
+            Some(_) => NodeKind::Chapter,
+            None => NodeKind::Draft,
+        };
+        let node = self.node(&label, kind, Some(parent));
+        self.items(&link.nested_items, node, level + 1);
+    }
+
+    /// Add a node with an edge from its parent and return its identifier.
+    fn node(&mut self, label: &str, kind: NodeKind, parent: Option<usize>) -> usize {
+        let node = self.nodes;
+        self.nodes += 1;
+        match self.format {
+            GraphFormat::Mermaid => {
+                let class = match kind {
+                    NodeKind::Root | NodeKind::Chapter => "",
+                    NodeKind::Part => ":::part",
+                    NodeKind::Draft => ":::draft",
+                };
+                writeln!(
+                    self.output,
+                    "    n{}[\"{}\"]{}",
+                    node,
+                    escape_mermaid(label),
+                    class
+                )
+                .unwrap();
+                if let Some(parent) = parent {
+                    writeln!(self.output, "    n{} --> n{}", parent, node).unwrap();
+                }
+            }
+            GraphFormat::Dot => {
+                let attributes = match kind {
+                    NodeKind::Root => ", shape=plaintext",
+                    NodeKind::Part => ", style=\"filled,bold\", fillcolor=\"#eeeeee\"",
+                    NodeKind::Chapter => "",
+                    NodeKind::Draft => ", style=dashed",
+                };
+                writeln!(
+                    self.output,
+                    "    n{} [label=\"{}\"{}];",
+                    node,
+                    escape_dot(label),
+                    attributes
+                )
+                .unwrap();
+                if let Some(parent) = parent {
+                    writeln!(self.output, "    n{} -> n{};", parent, node).unwrap();
+                }
+            }
+        }
+        node
+    }
+}
+
+/// Escape the characters which have a meaning inside of a quoted Mermaid label as entity codes.
+fn escape_mermaid(label: &str) -> String {
+    label
+        .replace('#', "#35;")
+        .replace('"', "#34;")
+        .replace('<', "#60;")
+        .replace('>', "#62;")
+        .replace(['\n', '\r'], " ")
+}
+
+/// Escape the characters which have a meaning inside of a quoted DOT string.
+fn escape_dot(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(['\n', '\r'], " ")
+}
@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+
+use anyhow::Error;
+use log::debug;
+
+use crate::{
+    book::{Link, SectionNumber, SummaryItem},
+    config::GroupStyle,
+    read_front_matter, renumber, Generator,
+};
+
+impl Generator<'_> {
+    /// Group the top level `items` by the front matter `key` of their chapter files, see
+    /// `group_by`. The groups are ordered like `group_order`, followed by the other groups in
+    /// alphabetical order and then by the `default_group` (unless it is listed), which gets the
+    /// chapters without the key and the drafts. The chapters keep their order within their group.
+    pub(crate) fn group_chapters(
+        &self,
+        items: Vec<SummaryItem>,
+        key: &str,
+    ) -> Result<Vec<SummaryItem>, Error> {
+        let mut groups: BTreeMap<String, Vec<SummaryItem>> = BTreeMap::new();
+        for item in items {
+            let group = match &item {
+                SummaryItem::Link(Link {
+                    location: Some(location),
+                    ..
+                }) => {
+                    let path = self.chapter_path(location);
+                    read_front_matter(&path, self.config, &self.heads)?
+                        .front_matter
+                        .get(key)
+                        .filter(|group| !group.is_empty())
+                        .map(str::to_owned)
+                }
+                _ => None,
+            };
+            let group = group.unwrap_or_else(|| self.config.default_group.clone());
+            groups.entry(group).or_default().push(item);
+        }
+
+        let mut ordered = vec![];
+        for group in &self.config.group_order {
+            if let Some(items) = groups.remove(group) {
+                ordered.push((group.clone(), items));
+            }
+        }
+        let default_items = groups.remove(&self.config.default_group);
+        ordered.extend(groups);
+        if let Some(items) = default_items {
+            ordered.push((self.config.default_group.clone(), items));
+        }
+
+        let mut grouped = vec![];
+        for (group, items) in ordered {
+            debug!("Adding {} chapters to the group {}", items.len(), group);
+            match self.config.group_style {
+                GroupStyle::Part => {
+                    grouped.push(SummaryItem::PartTitle(group));
+                    grouped.extend(items);
+                }
+                GroupStyle::Chapter => grouped.push(SummaryItem::Link(Link {
+                    name: group,
+                    location: None,
+                    nested_items: items,
+                    number: Some(SectionNumber::default()),
+                })),
+            }
+        }
+        renumber(&mut grouped, None);
+        Ok(grouped)
+    }
+}
@@ -0,0 +1,211 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    book::{SectionNumber, SummaryItem},
+    config::Config,
+    warnings::WarningKind,
+    Entry, Generator,
+};
+
+/// The number at the start of a heading like `3` or `3.2`, see `sort = "heading-number"`.
+/// Numbers are compared component-wise, e.g. 3 < 3.2 < 3.10 < 4.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct HeadingNumber(Vec<u32>);
+
+impl std::fmt::Display for HeadingNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let components: Vec<_> = self.0.iter().map(u32::to_string).collect();
+        write!(f, "{}", components.join("."))
+    }
+}
+
+/// The number at the start of the `heading` and the rest of it, e.g. 3.2 and "Configuring" for
+/// "3.2. Configuring". The number may end with a dot and has to be followed by whitespace and
+/// more text.
+pub(crate) fn split_heading_number(heading: &str) -> Option<(HeadingNumber, &str)> {
+    let end = heading
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(heading.len());
+    let (number, rest) = heading.split_at(end);
+    let number = number.strip_suffix('.').unwrap_or(number);
+    let components = number
+        .split('.')
+        .map(|component| component.parse().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let name = rest.trim_start();
+    if name.len() == rest.len() || name.trim_end().is_empty() {
+        return None;
+    }
+    Some((HeadingNumber(components), name))
+}
+
+/// Prefix the names of the numbered items and their nested items with their section number, see
+/// `number_in_names`. Names which already start with the same number are kept.
+pub(crate) fn number_names(items: &mut [SummaryItem], config: &Config) {
+    for item in items {
+        let SummaryItem::Link(link) = item else {
+            continue;
+        };
+        if let Some(number) = &link.number {
+            let is_draft = link.location.is_none();
+            if (!is_draft || config.number_in_draft_names)
+                && !starts_with_number(&link.name, number)
+            {
+                let components: Vec<_> = number.0.iter().map(u32::to_string).collect();
+                link.name = format!(
+                    "{}{}{}",
+                    components.join("."),
+                    config.number_separator,
+                    link.name
+                );
+            }
+        }
+        number_names(&mut link.nested_items, config);
+    }
+}
+
+/// Whether the name starts with the section `number`, e.g. with `number_in_names`.
+pub(crate) fn starts_with_number(name: &str, number: &SectionNumber) -> bool {
+    split_heading_number(name).is_some_and(|(heading_number, _)| heading_number.0 == number.0)
+}
+
+impl Generator<'_> {
+    /// Sort the entries of the directory by the number at the start of the heading of their
+    /// chapter file, the entries without one after them in their previous order. Entries with a
+    /// different sidecar weight stay apart. Warns about numbers used more than once.
+    pub(crate) fn sort_by_heading_number(&mut self, dir_path: &Path, entries: &mut Vec<Entry>) {
+        let mut numbered: Vec<_> = entries
+            .drain(..)
+            .map(|entry| (self.heading_number(&entry), entry))
+            .collect();
+        numbered.sort_by(|(a_number, a), (b_number, b)| {
+            a.sidecar
+                .weight
+                .cmp(&b.sidecar.weight)
+                .then_with(|| a_number.is_none().cmp(&b_number.is_none()))
+                .then_with(|| a_number.cmp(b_number))
+        });
+
+        let mut seen: HashMap<&HeadingNumber, &Entry> = HashMap::new();
+        let mut duplicates = vec![];
+        for (number, entry) in &numbered {
+            let Some(number) = number else {
+                continue;
+            };
+            if let Some(first) = seen.insert(number, entry) {
+                duplicates.push(format!(
+                    "{} and {} both have the heading number {} in {}, they are sorted by file name",
+                    first.file_name().to_string_lossy(),
+                    entry.file_name().to_string_lossy(),
+                    number,
+                    self.describe(dir_path)
+                ));
+            }
+        }
+        for duplicate in duplicates {
+            self.warnings
+                .warn_at(WarningKind::DuplicateHeadingNumbers, dir_path, duplicate);
+        }
+        entries.extend(numbered.into_iter().map(|(_, entry)| entry));
+    }
+
+    /// The number at the start of the heading of the chapter file of the entry, if it has one.
+    /// Errors reading it are reported once the chapter is added.
+    pub(crate) fn heading_number(&self, entry: &Entry) -> Option<HeadingNumber> {
+        let chapter_file = if entry.is_dir {
+            self.chapter_file(&entry.path)
+        } else {
+            entry.path.clone()
+        };
+        if entry.is_dir && !self.heads.exists(&chapter_file, self.config) {
+            return None;
+        }
+        let head = self.heads.get(&chapter_file, self.config).ok()?;
+        let (number, _) = split_heading_number(head.title.as_deref()?)?;
+        Some(number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Error;
+
+    use super::*;
+    use crate::{
+        config::SortOrder,
+        summary_fs::MemoryFs,
+        test_util::{generate, names},
+    };
+
+    #[test]
+    fn splits_the_number_from_the_heading() {
+        let split = |heading| {
+            split_heading_number(heading).map(|(number, name)| (number.to_string(), name))
+        };
+        assert_eq!(split("3 Daemon"), Some(("3".to_owned(), "Daemon")));
+        assert_eq!(split("3.2. Ports "), Some(("3.2".to_owned(), "Ports ")));
+        assert_eq!(split("3.10 Logs"), Some(("3.10".to_owned(), "Logs")));
+        assert_eq!(split("FAQ"), None);
+        assert_eq!(split("2024"), None);
+        assert_eq!(split("3.2.  "), None);
+        assert_eq!(split("3..2 Ports"), None);
+        assert_eq!(split("2nd edition"), None);
+    }
+
+    #[test]
+    fn sorts_by_the_heading_number() -> Result<(), Error> {
+        let fs = MemoryFs::new()
+            .with_file("/src/daemon.md", "# 3. Configuring the daemon\n")
+            .with_file("/src/install.md", "# 2 Installing\n")
+            .with_file("/src/logs.md", "# 3.10. Logs\n")
+            .with_file("/src/faq.md", "# FAQ\n")
+            .with_file("/src/ports.md", "# 3.2. Ports\n")
+            .with_file("/src/appendix.md", "# Appendix\n");
+        let config = Config::builder()
+            .get_chapter_name_from_file(true)
+            .sort(SortOrder::HeadingNumber)
+            .build()?;
+        let (summary, _) = generate(fs, Path::new("/src"), &config);
+        // The chapters without a number follow by name
+        assert_eq!(
+            names(&summary?.numbered_chapters),
+            [
+                "Installing",
+                "Configuring the daemon",
+                "Ports",
+                "Logs",
+                "Appendix",
+                "FAQ"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn warns_about_heading_numbers_used_twice() -> Result<(), Error> {
+        let fs = MemoryFs::new()
+            .with_file("/src/daemon.md", "# 3. Configuring the daemon\n")
+            .with_file("/src/ports.md", "# 3. Ports\n");
+        let config = Config::builder()
+            .get_chapter_name_from_file(true)
+            .sort(SortOrder::HeadingNumber)
+            .build()?;
+        let (summary, diagnostics) = generate(fs, Path::new("/src"), &config);
+        assert_eq!(
+            names(&summary?.numbered_chapters),
+            ["Configuring the daemon", "Ports"]
+        );
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "daemon.md and ports.md both have the heading number 3 in the src directory, \
+                 they are sorted by file name"
+            ]
+        );
+        Ok(())
+    }
+}
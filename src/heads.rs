@@ -0,0 +1,412 @@
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use anyhow::{Context, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Config, Encoding},
+    convert::Conversions,
+    summary_fs::SummaryFs,
+    title_cache::{Stamp, TitleCache},
+};
+
+/// The front matter of a chapter file, i.e. the `key: value` lines between two `---` lines at the
+/// very start of the file.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub(crate) struct FrontMatter(HashMap<String, String>);
+
+impl FrontMatter {
+    /// Returns true if the key is set to `true`.
+    pub(crate) fn get_bool(&self, key: &str) -> bool {
+        self.0.get(key).is_some_and(|val| val == "true")
+    }
+
+    /// The value of the key, if it is set.
+    pub(crate) fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+}
+
+/// What is used of the start of a chapter file, see [`Head::parse`].
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(crate) struct Head {
+    /// The title of the chapter, if the first line after the front matter looks like '# <title>'.
+    pub(crate) title: Option<String>,
+    /// True if the title can not be decoded, as the start of the file is binary or is not valid
+    /// UTF-8 up to the title (without `fallback_encoding`). It has no title then.
+    pub(crate) not_utf8: bool,
+    pub(crate) front_matter: FrontMatter,
+    /// The size of the content, missing in title caches written before it was measured.
+    #[serde(default)]
+    pub(crate) body: Option<Body>,
+}
+
+/// The size of the content of a chapter file without its front matter and first heading, which
+/// decides whether it is a stub.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct Body {
+    pub(crate) bytes: usize,
+    pub(crate) words: usize,
+    /// False if only the start of the file was measured.
+    pub(crate) complete: bool,
+}
+
+impl Body {
+    fn measure(bytes: &[u8], complete: bool) -> Self {
+        let content = String::from_utf8_lossy(bytes);
+        let mut lines = content.split_inclusive('\n').peekable();
+        if lines.peek().map(|line| line.trim_end()) == Some("---") {
+            lines.next();
+            for line in lines.by_ref() {
+                if line.trim_end() == "---" {
+                    break;
+                }
+            }
+        }
+        while lines.next_if(|line| line.trim().is_empty()).is_some() {}
+        lines.next_if(|line| line.starts_with('#'));
+        let body = lines.collect::<String>();
+        let body = body.trim();
+        Self {
+            bytes: body.len(),
+            words: body.split_whitespace().count(),
+            complete,
+        }
+    }
+
+    /// Returns true if the content is below every configured threshold, false without any.
+    fn is_stub(&self, config: &Config) -> bool {
+        let thresholds = [
+            (config.stub_threshold_bytes, self.bytes),
+            (config.stub_threshold_words, self.words),
+        ];
+        thresholds.iter().any(|(threshold, _)| threshold.is_some())
+            && thresholds
+                .iter()
+                .all(|(threshold, size)| threshold.is_none_or(|threshold| *size < threshold))
+    }
+}
+
+impl Head {
+    /// Parse the first `config.max_title_bytes` of a chapter file.
+    /// A front matter block at the start of the file is skipped for the title. If the title is not
+    /// complete within the bytes or can not be decoded, there is no title. The front matter is
+    /// empty if the file does not start with `---`.
+    fn parse(bytes: &[u8], config: &Config) -> Self {
+        let (text, lossy) = decode(bytes, config.fallback_encoding);
+        let (title, examined) = parse_title(&text, bytes.len() >= config.max_title_bytes);
+        // Binary content, or invalid bytes before or in the title
+        let not_utf8 =
+            text.contains('\0') || (lossy && examined.contains(char::REPLACEMENT_CHARACTER));
+        Self {
+            title: title.filter(|_| !not_utf8),
+            not_utf8,
+            front_matter: parse_front_matter(&text),
+            body: Some(Body::measure(bytes, bytes.len() < config.max_title_bytes)),
+        }
+    }
+}
+
+/// Decode the start of a chapter file as UTF-8, or with `fallback_encoding` if it is not valid
+/// UTF-8. Without one, invalid bytes are replaced by U+FFFD, which is returned as true.
+fn decode(bytes: &[u8], fallback_encoding: Option<Encoding>) -> (Cow<'_, str>, bool) {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (Cow::Borrowed(text), false),
+        // The limit may split the last character, only use the complete ones
+        Err(error) if error.error_len().is_none() => (
+            Cow::Borrowed(std::str::from_utf8(&bytes[..error.valid_up_to()]).unwrap()),
+            false,
+        ),
+        Err(_) => match fallback_encoding {
+            Some(Encoding::Latin1) => (bytes.iter().map(|&byte| char::from(byte)).collect(), false),
+            Some(Encoding::Windows1252) => (
+                bytes.iter().map(|&byte| windows_1252(byte)).collect(),
+                false,
+            ),
+            None => (String::from_utf8_lossy(bytes), true),
+        },
+    }
+}
+
+/// The character of the byte in Windows-1252. The five undefined bytes are the control characters
+/// of the same code point, like in Latin-1.
+fn windows_1252(byte: u8) -> char {
+    const HIGH_CONTROLS: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž',
+        '\u{8f}', '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}',
+        'ž', 'Ÿ',
+    ];
+    match byte {
+        0x80..=0x9f => HIGH_CONTROLS[usize::from(byte - 0x80)],
+        _ => char::from(byte),
+    }
+}
+
+fn parse_front_matter(text: &str) -> FrontMatter {
+    let mut lines = text.lines();
+    let mut front_matter = FrontMatter::default();
+    if lines.next().map(str::trim_end) != Some("---") {
+        return front_matter;
+    }
+    for line in lines {
+        if line.trim_end() == "---" {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim().trim_matches('"').trim_matches('\'');
+            front_matter
+                .0
+                .insert(key.trim().to_owned(), value.to_owned());
+        }
+    }
+    front_matter
+}
+
+/// The title in the first line after the front matter, if it looks like '# <title>', and the text
+/// up to the end of that line. `cut_off` is true if the text ends at the limit of the bytes read,
+/// a line cut off by it is not used as the title.
+fn parse_title(text: &str, cut_off: bool) -> (Option<String>, &str) {
+    let mut lines = text.split_inclusive('\n');
+    let mut first_line = lines.next().unwrap_or_default();
+    let mut examined = first_line.len();
+    if first_line.trim_end() == "---" {
+        // Skip the front matter and use the line following it
+        for line in lines.by_ref() {
+            examined += line.len();
+            if line.trim_end() == "---" {
+                break;
+            }
+        }
+        first_line = lines.next().unwrap_or_default();
+        examined += first_line.len();
+    }
+    let title = if !first_line.ends_with('\n') && cut_off {
+        None
+    } else {
+        first_line
+            .trim_end_matches(['\r', '\n'])
+            .strip_prefix("# ")
+            .map(str::to_owned)
+    };
+    (title, &text[..examined])
+}
+
+/// The heads of the chapter files of one run. Every file is parsed at most once, from the title
+/// cache if it did not change since, from its prefetched start or by reading it. The parsed heads
+/// are shared instead of cloned for every use.
+pub(crate) struct Heads<'a> {
+    fs: &'a dyn SummaryFs,
+    prefetched: RefCell<HashMap<PathBuf, Vec<u8>>>,
+    cache: Option<TitleCache>,
+    parsed: RefCell<HashMap<PathBuf, Rc<Head>>>,
+    /// The chapter files whose start was read, including the prefetched ones.
+    files_read: Cell<usize>,
+    /// The chapter files taken from the title cache.
+    cached: Cell<usize>,
+    /// The metadata queries of the title cache.
+    metadata_queries: Cell<usize>,
+    /// The markdown of the files converted by a `converter`, whose head is parsed instead.
+    conversions: Rc<Conversions>,
+}
+
+impl<'a> Heads<'a> {
+    pub(crate) fn new(
+        fs: &'a dyn SummaryFs,
+        prefetched: HashMap<PathBuf, Vec<u8>>,
+        cache: Option<TitleCache>,
+        conversions: Rc<Conversions>,
+    ) -> Self {
+        Self {
+            fs,
+            conversions,
+            files_read: Cell::new(prefetched.len()),
+            prefetched: RefCell::new(prefetched),
+            cache,
+            parsed: RefCell::new(HashMap::new()),
+            cached: Cell::new(0),
+            metadata_queries: Cell::new(0),
+        }
+    }
+
+    /// The head of the chapter file at `path`.
+    pub(crate) fn get(&self, path: &Path, config: &Config) -> Result<Rc<Head>, Error> {
+        self.try_get(path, config)
+            .with_context(|| format!("Failed to read chapter file {}", path.display()))
+    }
+
+    /// Returns true if the file at `path` exists. Instead of querying its metadata first, the file
+    /// is opened right away, so that reading its head later does not need another call. Files
+    /// which exist but can not be read count as existing, their error is reported once they are
+    /// used.
+    pub(crate) fn exists(&self, path: &Path, config: &Config) -> bool {
+        !matches!(
+            self.try_get(path, config),
+            Err(error) if error.kind() == ErrorKind::NotFound
+        )
+    }
+
+    fn try_get(&self, path: &Path, config: &Config) -> io::Result<Rc<Head>> {
+        if let Some(head) = self.parsed.borrow().get(path) {
+            return Ok(Rc::clone(head));
+        }
+        let head = match &self.cache {
+            Some(cache) => {
+                self.metadata_queries.set(self.metadata_queries.get() + 1);
+                let stamp = Stamp::of(&self.fs.metadata(path)?);
+                match cache.get(path, &stamp) {
+                    Some(head) => {
+                        self.cached.set(self.cached.get() + 1);
+                        head.clone()
+                    }
+                    None => self.read(path, config)?,
+                }
+            }
+            None => self.read(path, config)?,
+        };
+        let head = Rc::new(head);
+        self.parsed
+            .borrow_mut()
+            .insert(path.to_path_buf(), Rc::clone(&head));
+        Ok(head)
+    }
+
+    /// Parse the prefetched start of the file, or read it. Files converted by a `converter` are
+    /// converted instead.
+    fn read(&self, path: &Path, config: &Config) -> io::Result<Head> {
+        if let Some(converter) = config.converter(path) {
+            let markdown = self
+                .conversions
+                .markdown(path, converter)
+                .map_err(|error| match error.downcast_ref::<io::Error>() {
+                    // So that a missing file is not mistaken for one which fails to convert
+                    Some(io_error) if io_error.kind() == ErrorKind::NotFound => {
+                        io::Error::from(ErrorKind::NotFound)
+                    }
+                    _ => io::Error::other(format!("{:#}", error)),
+                })?;
+            let bytes = &markdown.as_bytes()[..markdown.len().min(config.max_title_bytes)];
+            return Ok(Head::parse(bytes, config));
+        }
+        let bytes = match self.prefetched.borrow_mut().remove(path) {
+            Some(bytes) => bytes,
+            None => {
+                let bytes = self.fs.read_prefix(path, config.max_title_bytes)?;
+                self.files_read.set(self.files_read.get() + 1);
+                bytes
+            }
+        };
+        Ok(Head::parse(&bytes, config))
+    }
+
+    /// Returns true if the chapter file at `path` is below `stub_threshold_bytes` and
+    /// `stub_threshold_words`. The start read for its title is measured, only if that is not
+    /// enough to decide the whole file is read.
+    pub(crate) fn is_stub(&self, path: &Path, config: &Config) -> Result<bool, Error> {
+        if config.stub_threshold_bytes.is_none() && config.stub_threshold_words.is_none() {
+            return Ok(false);
+        }
+        let body = match self.get(path, config)?.body {
+            Some(body) if body.complete || !body.is_stub(config) => body,
+            _ => match config.converter(path) {
+                Some(converter) => {
+                    Body::measure(self.conversions.markdown(path, converter)?.as_bytes(), true)
+                }
+                None => {
+                    let bytes = self.fs.read_prefix(path, usize::MAX).with_context(|| {
+                        format!("Failed to read chapter file {}", path.display())
+                    })?;
+                    self.files_read.set(self.files_read.get() + 1);
+                    Body::measure(&bytes, true)
+                }
+            },
+        };
+        Ok(body.is_stub(config))
+    }
+
+    /// The title cache, if there is one.
+    pub(crate) fn cache(&self) -> Option<&TitleCache> {
+        self.cache.as_ref()
+    }
+
+    /// The number of chapter files whose start was read, the number of chapter files taken from
+    /// the title cache and the number of metadata queries for it.
+    pub(crate) fn counts(&self) -> (usize, usize, usize) {
+        (
+            self.files_read.get(),
+            self.cached.get(),
+            self.metadata_queries.get(),
+        )
+    }
+
+    /// Write the heads of this run to the title cache, if there is one and anything changed.
+    pub(crate) fn save_cache(&self, src_dir: &Path, config: &Config) {
+        if let Some(cache) = &self.cache {
+            let heads = self
+                .parsed
+                .take()
+                .into_iter()
+                .map(|(path, head)| (path, Rc::unwrap_or_clone(head)))
+                .collect();
+            cache.save(heads, src_dir, config, self.fs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        summary_fs::MemoryFs,
+        test_util::{generate, names},
+    };
+
+    /// The names of the chapters, named by their heading, read with the `fallback_encoding`.
+    fn names_with_encoding(fallback_encoding: Option<Encoding>) -> Result<Vec<String>, Error> {
+        let fs = MemoryFs::new()
+            // "Über uns – Team" and "Größe" in Windows-1252
+            .with_file("/src/about.md", b"# \xdcber uns \x96 Team\n".to_vec())
+            .with_file("/src/size.md", b"# Size\n\nGr\xf6\xdfe\n".to_vec())
+            .with_file("/src/logo.md", b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec());
+        let mut builder = Config::builder().get_chapter_name_from_file(true);
+        if let Some(fallback_encoding) = fallback_encoding {
+            builder = builder.fallback_encoding(fallback_encoding);
+        }
+        let (summary, _) = generate(fs, Path::new("/src"), &builder.build()?);
+        let summary = summary?;
+        Ok(names(&summary.numbered_chapters)
+            .into_iter()
+            .map(str::to_owned)
+            .collect())
+    }
+
+    #[test]
+    fn reads_headings_in_the_fallback_encoding() -> Result<(), Error> {
+        // Without one, only valid UTF-8 is used. Binary content always falls back to the file name
+        assert_eq!(names_with_encoding(None)?, ["about", "logo", "Size"]);
+        assert_eq!(
+            names_with_encoding(Some(Encoding::Windows1252))?,
+            ["Über uns – Team", "logo", "Size"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_control_characters_decoded_with_the_fallback_encoding() {
+        // Latin-1 only has a control character for the dash
+        let error = names_with_encoding(Some(Encoding::Latin1)).unwrap_err();
+        assert_eq!(
+            format!("{:#}", error),
+            "The generated summary is invalid. Fix the chapters or set `validate = \"fix\"` to \
+             fix them automatically:\n\
+             `Über uns \\u{96} Team` (about.md) contains control characters in its name"
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Error;
+
+use crate::{
+    progress::ProgressReporter,
+    summary_fs::{StdFs, SummaryFs},
+};
+
+/// Decides the names of chapters instead of the built-in logic, see
+/// [`crate::GenerateSummary::with_namer`].
+pub trait ChapterNamer {
+    /// The name of the chapter for `path`, which is relative to the src directory. It is the
+    /// chapter file, or the directory for a directory without chapter file.
+    /// `default` is the file or directory name without extension.
+    /// Returning [`Option::None`] uses the built-in name, i.e. the title of the chapter file with
+    /// `get_chapter_name_from_file` or `default`.
+    fn name(&self, path: &Path, default: &str) -> Option<String>;
+}
+
+/// Decides the order of the chapters of a directory instead of sorting them by file name, see
+/// [`crate::GenerateSummary::with_sorter`].
+pub trait ChapterSorter {
+    /// Sort the `entries` of the directory `directory`, which is relative to the src directory.
+    /// The entries are sorted by file name before.
+    /// An error fails the generation like any other error of the directory, see `on_error`.
+    fn sort(&self, directory: &Path, entries: &mut [SortEntry]) -> Result<(), Error>;
+}
+
+/// Decides which markdown files and directories are part of the summary, see
+/// [`crate::GenerateSummary::with_filter`].
+pub trait ChapterFilter {
+    /// Returns false to leave the file or directory at `path`, which is relative to the src
+    /// directory, out of the summary. A directory is left out with everything inside of it.
+    /// The files and directories left out by the configuration are never passed to the filter.
+    /// The chapter file of a directory is passed as well, but leaving it out does not change the
+    /// chapter of the directory.
+    fn include(&self, path: &Path, kind: EntryKind) -> bool;
+}
+
+/// Sorts the chapters by their file name, which is how they are sorted without a
+/// [`ChapterSorter`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FileNameSorter;
+
+impl ChapterSorter for FileNameSorter {
+    fn sort(&self, _directory: &Path, entries: &mut [SortEntry]) -> Result<(), Error> {
+        entries.sort_by(|a, b| a.path.file_name().cmp(&b.path.file_name()));
+        Ok(())
+    }
+}
+
+/// A markdown file or directory to be sorted by a [`ChapterSorter`].
+#[derive(Debug, Clone)]
+pub struct SortEntry {
+    /// The path of the file or directory, relative to the src directory.
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    /// The name of the chapter from the `name_sources`, if it has one.
+    pub title: Option<String>,
+    /// The tags of its sidecar file, with `sidecar_files`.
+    pub tags: Vec<String>,
+    /// The position of the entry before sorting.
+    pub(crate) index: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+}
+
+/// The implementations registered on [`crate::GenerateSummary`] by library users.
+#[derive(Default)]
+pub(crate) struct Hooks {
+    pub(crate) namer: Option<Box<dyn ChapterNamer>>,
+    pub(crate) sorter: Option<Box<dyn ChapterSorter>>,
+    pub(crate) filter: Option<Box<dyn ChapterFilter>>,
+    pub(crate) fs: Option<Box<dyn SummaryFs>>,
+    pub(crate) progress: Option<Box<dyn ProgressReporter>>,
+}
+
+impl Hooks {
+    /// The file system of the src directory, [`StdFs`] unless another one was registered.
+    pub(crate) fn fs(&self) -> &dyn SummaryFs {
+        self.fs.as_deref().unwrap_or(&StdFs)
+    }
+}
+
+impl Debug for Hooks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("namer", &self.namer.as_ref().map(|_| "ChapterNamer"))
+            .field("sorter", &self.sorter.as_ref().map(|_| "ChapterSorter"))
+            .field("filter", &self.filter.as_ref().map(|_| "ChapterFilter"))
+            .field("fs", &self.fs.as_ref().map(|_| "SummaryFs"))
+            .field(
+                "progress",
+                &self.progress.as_ref().map(|_| "ProgressReporter"),
+            )
+            .finish()
+    }
+}
@@ -0,0 +1,257 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Error};
+use log::warn;
+use toml::value::{Table, Value};
+
+use crate::diff::unified_diff;
+
+/// The header of the preprocessor table in book.toml.
+const TABLE_HEADER: &str = "[preprocessor.generate-summary]";
+
+/// Add the `[preprocessor.generate-summary]` table to the book.toml, together with the `settings`
+/// which are not set yet. The file is edited as text, so that its formatting and comments are
+/// kept, and fails if the edited file does not parse to the same settings plus the added ones.
+/// Nothing is written if `dry_run` is true.
+/// Returns a unified diff of the changes, or [`Option::None`] if the file already contains
+/// everything.
+pub fn install(
+    book_toml: &Path,
+    settings: &[(&str, Value)],
+    dry_run: bool,
+) -> Result<Option<String>, Error> {
+    let content = std::fs::read_to_string(book_toml)
+        .with_context(|| format!("Failed to read {}", book_toml.display()))?;
+    let document: Table = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", book_toml.display()))?;
+    let existing_table = document
+        .get("preprocessor")
+        .and_then(|preprocessors| preprocessors.get("generate-summary"));
+
+    let missing_settings: Vec<&(&str, Value)> = settings
+        .iter()
+        .filter(
+            |(key, value)| match existing_table.and_then(|table| table.get(key)) {
+                None => true,
+                Some(existing) => {
+                    if existing != value {
+                        warn!(
+                            "`{}` is already set to {} in {}, not changing it",
+                            key,
+                            existing,
+                            book_toml.display()
+                        );
+                    }
+                    false
+                }
+            },
+        )
+        .collect();
+
+    if existing_table.is_some() && missing_settings.is_empty() {
+        return Ok(None);
+    }
+
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let setting_lines = missing_settings
+        .iter()
+        .map(|(key, value)| format!("{} = {}", key, value));
+    let mut lines: Vec<_> = content.lines().map(str::to_owned).collect();
+    if existing_table.is_none() {
+        if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push(TABLE_HEADER.to_owned());
+        lines.extend(setting_lines);
+    } else {
+        let header = lines
+            .iter()
+            .position(|line| is_table_header(line))
+            .ok_or_else(|| {
+                anyhow!(
+                    "`preprocessor.generate-summary` in {} is not written as a `{}` table, add the \
+                     settings to it manually",
+                    book_toml.display(),
+                    TABLE_HEADER
+                )
+            })?;
+        // Add the settings after the last line of the table, the next table starts with a line
+        // starting with `[` which is not inside of a multi-line array
+        let mut depth = 0;
+        let mut end = lines.len();
+        for (index, line) in lines.iter().enumerate().skip(header + 1) {
+            if depth == 0 && line.trim_start().starts_with('[') {
+                end = index;
+                break;
+            }
+            depth = bracket_depth(line, depth);
+        }
+        while end > header + 1 && lines[end - 1].trim().is_empty() {
+            end -= 1;
+        }
+        lines.splice(end..end, setting_lines);
+    }
+
+    let new_content = lines.join(newline) + newline;
+    // Editing the text could go wrong, e.g. with multi-line strings, so check that the result
+    // is the old document with the settings added
+    let mut expected = document.clone();
+    let table = expected
+        .entry("preprocessor")
+        .or_insert_with(|| Value::Table(Table::new()))
+        .as_table_mut()
+        .and_then(|preprocessors| {
+            preprocessors
+                .entry("generate-summary")
+                .or_insert_with(|| Value::Table(Table::new()))
+                .as_table_mut()
+        });
+    if let Some(table) = table {
+        for (key, value) in &missing_settings {
+            table.insert(key.to_string(), value.clone());
+        }
+    }
+    if toml::from_str::<Table>(&new_content).ok().as_ref() != Some(&expected) {
+        return Err(anyhow!(
+            "Failed to add the settings to `preprocessor.generate-summary` in {}, add them \
+             manually: {}",
+            book_toml.display(),
+            missing_settings
+                .iter()
+                .map(|(key, value)| format!("`{} = {}`", key, value))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    let name = book_toml.display().to_string();
+    let old_lines: Vec<_> = content.lines().collect();
+    let new_lines: Vec<_> = new_content.lines().collect();
+    let diff = unified_diff(&old_lines, &new_lines, &name, &name);
+
+    if !dry_run {
+        std::fs::write(book_toml, new_content)
+            .with_context(|| format!("Failed to write {}", book_toml.display()))?;
+    }
+    Ok(Some(diff))
+}
+
+/// Returns true if the line is the header of the preprocessor table, allowing for whitespace,
+/// quotes and a comment.
+fn is_table_header(line: &str) -> bool {
+    let line = line.split('#').next().unwrap_or_default();
+    let normalized: String = line
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '"' && *c != '\'')
+        .collect();
+    normalized == TABLE_HEADER
+}
+
+/// The nesting depth of arrays after `line`, if it starts at `depth`. Brackets in strings and
+/// comments are not counted.
+fn bracket_depth(line: &str, mut depth: usize) -> usize {
+    let mut quote = None;
+    let mut characters = line.chars();
+    while let Some(character) = characters.next() {
+        match (quote, character) {
+            (Some('"'), '\\') => {
+                characters.next();
+            }
+            (Some(open), _) if character == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(character),
+            (None, '#') => break,
+            (None, '[') => depth += 1,
+            (None, ']') => depth = depth.saturating_sub(1),
+            (None, _) => {}
+        }
+    }
+    depth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn settings() -> Vec<(&'static str, Value)> {
+        vec![
+            ("chapter_file_name", Value::String("index.md".to_owned())),
+            ("create_missing_chapter_files", Value::Boolean(true)),
+        ]
+    }
+
+    #[test]
+    fn adds_the_settings_after_multi_line_arrays() -> Result<(), Error> {
+        let dir = TempDir::new("install-arrays");
+        let book_toml = dir.write(
+            "book.toml",
+            "[book]\n\
+             title = \"Book\"\n\
+             \n\
+             [preprocessor.generate-summary]\n\
+             unnumbered_directories = [\n    \"appendix\",\n    [\"nested\"], # [\"not closed\"\n]\n\
+             \n\
+             [output.html]\n",
+        );
+        let diff = install(&book_toml, &settings(), false)?;
+        assert!(diff.is_some());
+        assert_eq!(
+            std::fs::read_to_string(&book_toml)?,
+            "[book]\n\
+             title = \"Book\"\n\
+             \n\
+             [preprocessor.generate-summary]\n\
+             unnumbered_directories = [\n    \"appendix\",\n    [\"nested\"], # [\"not closed\"\n]\n\
+             chapter_file_name = \"index.md\"\n\
+             create_missing_chapter_files = true\n\
+             \n\
+             [output.html]\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn does_nothing_when_run_twice() -> Result<(), Error> {
+        let dir = TempDir::new("install-twice");
+        let book_toml = dir.write("book.toml", "[book]\ntitle = \"Book\"\n");
+        let diff = install(&book_toml, &settings(), false)?.unwrap();
+        assert!(diff.contains("+[preprocessor.generate-summary]"));
+        assert!(diff.contains("+create_missing_chapter_files = true"));
+        let installed = std::fs::read_to_string(&book_toml)?;
+
+        assert_eq!(install(&book_toml, &settings(), false)?, None);
+        assert_eq!(std::fs::read_to_string(&book_toml)?, installed);
+        Ok(())
+    }
+
+    #[test]
+    fn writes_nothing_with_dry_run() -> Result<(), Error> {
+        let dir = TempDir::new("install-dry-run");
+        let content = "[book]\ntitle = \"Book\"\n";
+        let book_toml = dir.write("book.toml", content);
+        let diff = install(&book_toml, &settings(), true)?.unwrap();
+        assert!(diff.contains("+[preprocessor.generate-summary]"));
+        assert_eq!(std::fs::read_to_string(&book_toml)?, content);
+        // The same diff as when writing
+        assert_eq!(install(&book_toml, &settings(), false)?, Some(diff));
+        Ok(())
+    }
+
+    #[test]
+    fn fails_instead_of_breaking_multi_line_strings() -> Result<(), Error> {
+        let dir = TempDir::new("install-strings");
+        let content = "[preprocessor.generate-summary]\n\
+                       title = \"\"\"\n\
+                       [Contents]\n\
+                       \"\"\"\n";
+        let book_toml = dir.write("book.toml", content);
+        let error = install(&book_toml, &settings(), false).unwrap_err();
+        assert!(error.to_string().contains("add them manually"));
+        assert_eq!(std::fs::read_to_string(&book_toml)?, content);
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+use crate::book::{Link, Summary, SummaryItem};
+
+/// The version of the JSON format, increased whenever it changes incompatibly.
+const VERSION: u32 = 1;
+
+/// The JSON representation of a summary, see the README for its format.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct JsonSummary {
+    pub(crate) version: u32,
+    pub(crate) title: Option<String>,
+    /// The prefix, numbered and suffix chapters in the order of the summary.
+    pub(crate) chapters: Vec<JsonChapter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct JsonChapter {
+    pub(crate) name: String,
+    /// The path of the chapter file relative to the src directory, always using forward slashes.
+    /// Not set for drafts.
+    pub(crate) location: Option<String>,
+    /// The section number like "1.2", not set for unnumbered chapters.
+    pub(crate) number: Option<String>,
+    pub(crate) draft: bool,
+    pub(crate) children: Vec<JsonChapter>,
+}
+
+impl From<&Summary> for JsonSummary {
+    fn from(summary: &Summary) -> Self {
+        Self {
+            version: VERSION,
+            title: summary.title.clone(),
+            chapters: chapters(
+                summary
+                    .prefix_chapters
+                    .iter()
+                    .chain(&summary.numbered_chapters)
+                    .chain(&summary.suffix_chapters),
+            ),
+        }
+    }
+}
+
+impl From<&Link> for JsonChapter {
+    fn from(link: &Link) -> Self {
+        Self {
+            name: link.name.clone(),
+            location: link.location.as_ref().map(|location| {
+                location
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            }),
+            number: link.number.as_ref().map(|number| {
+                number
+                    .iter()
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(".")
+            }),
+            draft: link.location.is_none(),
+            children: chapters(&link.nested_items),
+        }
+    }
+}
+
+/// The chapters of the items, part titles and separators are left out.
+fn chapters<'a>(items: impl IntoIterator<Item = &'a SummaryItem>) -> Vec<JsonChapter> {
+    items
+        .into_iter()
+        .filter_map(|item| match item {
+            SummaryItem::Link(link) => Some(JsonChapter::from(link)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Serialize the summary to pretty printed JSON.
+pub(crate) fn summary_to_json(summary: &Summary) -> String {
+    serde_json::to_string_pretty(&JsonSummary::from(summary))
+        .expect("The summary can always be serialized")
+        + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_the_written_json() {
+        let summary = mdbook::book::parse_summary(
+            "# Summary\n\n\
+             [Preface](preface.md)\n\n\
+             # User guide\n\n\
+             - [Guide](guide/README.md)\n    \
+                 - [Setup](guide/setup.md)\n    \
+                 - [Advanced]()\n        \
+                     - [Tuning](guide/advanced/tuning.md)\n\n\
+             ---\n\n\
+             # Reference\n\n\
+             - [API](reference/api.md)\n\n\
+             [Changelog](changelog.md)\n",
+        )
+        .unwrap();
+
+        let json = summary_to_json(&summary);
+        let read: JsonSummary = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, JsonSummary::from(&summary));
+        assert_eq!(serde_json::to_string_pretty(&read).unwrap() + "\n", json);
+
+        // The part titles and the separator are left out
+        let names: Vec<_> = read.chapters.iter().map(|chapter| &chapter.name).collect();
+        assert_eq!(names, ["Preface", "Guide", "API", "Changelog"]);
+        let guide = &read.chapters[1];
+        assert_eq!(guide.location.as_deref(), Some("guide/README.md"));
+        assert_eq!(guide.number.as_deref(), Some("1"));
+        let advanced = &guide.children[1];
+        assert!(advanced.draft);
+        assert_eq!(advanced.location, None);
+        assert_eq!(advanced.number.as_deref(), Some("1.2"));
+        let tuning = &advanced.children[0];
+        assert_eq!(tuning.location.as_deref(), Some("guide/advanced/tuning.md"));
+        assert_eq!(tuning.number.as_deref(), Some("1.2.1"));
+        assert!(!tuning.draft);
+        assert_eq!(read.chapters[0].number, None);
+        assert_eq!(read.chapters[2].number.as_deref(), Some("2"));
+    }
+}
@@ -1,202 +1,3435 @@
 use std::{
-    ffi::OsStr,
-    fs::File,
-    io::{BufRead, BufReader, Write},
+    collections::{HashMap, HashSet},
+    ffi::{OsStr, OsString},
+    io::{self, ErrorKind},
     path::{Path, PathBuf},
-    str::FromStr,
+    rc::Rc,
+    time::Instant,
     vec,
 };
 
-use anyhow::Error;
-use mdbook::{
-    book::{Book, Link, SectionNumber, Summary, SummaryItem},
-    preprocess::{Preprocessor, PreprocessorContext},
-    MDBook,
+use anyhow::{anyhow, Context, Error};
+use log::{debug, log};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{
+    collapse::collapse_single_child,
+    convert::Conversions,
+    external::add_external_chapters,
+    git::TrackedFiles,
+    heading_number::{number_names, split_heading_number},
+    heads::{Head, Heads},
+    hooks::Hooks,
+    prefetch::Prefetched,
+    sidecar::{read_sidecar, sidecar_path, Sidecar},
+    title_cache::{CachedEntry, Stamp, TitleCache},
+    translations::check_translations,
+    validate::validate_summary,
+    warnings::Warnings,
+    write_guard::WriteGuard,
+};
+
+mod book;
+mod collapse;
+mod config;
+mod convert;
+mod diff;
+#[cfg(feature = "preprocessor")]
+mod explain;
+mod external;
+mod git;
+#[cfg(feature = "preprocessor")]
+mod gitignore;
+mod graph;
+mod group;
+mod heading_number;
+mod heads;
+mod hooks;
+mod install;
+#[cfg(feature = "preprocessor")]
+mod json;
+#[cfg(feature = "preprocessor")]
+mod links;
+#[cfg(feature = "preprocessor")]
+mod load;
+mod markdown;
+mod outline;
+mod prefetch;
+#[cfg(feature = "preprocessor")]
+mod preprocessor;
+mod progress;
+#[cfg(feature = "preprocessor")]
+mod render;
+mod sidecar;
+mod stats;
+mod summary_diff;
+mod summary_fs;
+#[cfg(feature = "preprocessor")]
+mod test_book;
+#[cfg(test)]
+mod test_util;
+mod title_cache;
+mod translations;
+mod validate;
+mod warnings;
+mod workspace;
+mod write_guard;
+
+/// The types of the summary returned by [`generate_summary`], so that using it does not require a
+/// dependency on mdBook. Without the `preprocessor` feature they are not the types of mdBook.
+pub use book::{Link, SectionNumber, Summary, SummaryItem};
+pub use config::{
+    ChapterPosition, Config, ConfigBuilder, Converter, CreateMissingChapterFiles, Encoding,
+    ExternalChapter, FileDirectoryCollision, GroupStyle, Mount, NameSource, OnError,
+    PlannedChapter, SortOrder, StubBehavior, Validation,
 };
+#[cfg(feature = "preprocessor")]
+pub use explain::Explanation;
+pub use graph::{summary_to_graph, GraphFormat};
+pub use hooks::{ChapterFilter, ChapterNamer, ChapterSorter, EntryKind, FileNameSorter, SortEntry};
+pub use install::install;
+pub use markdown::{summary_to_markdown, summary_to_toc};
+pub use progress::{ProgressReporter, TerminalProgress};
+pub use stats::Stats;
+pub use summary_diff::{diff_summaries, DiffEntry, MovedEntry, RenamedEntry, SummaryDiff};
+pub use summary_fs::{FileKind, FsEntry, FsMetadata, FsOperation, MemoryFs, StdFs, SummaryFs};
+#[cfg(feature = "preprocessor")]
+pub use test_book::{TestBook, TestOutput};
+pub use warnings::{Diagnostic, DiagnosticLevel, WarningKind};
+pub use workspace::{discover_books, WorkspaceBook};
+
+#[derive(Debug, Default)]
+pub struct GenerateSummary {
+    hooks: Hooks,
+}
+
+impl GenerateSummary {
+    pub fn new() -> GenerateSummary {
+        GenerateSummary::default()
+    }
+
+    /// Use `namer` to name the chapters, falling back to the built-in names where it returns
+    /// [`Option::None`].
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{ChapterNamer, Config, GenerateSummary, MemoryFs, SummaryItem};
+    ///
+    /// struct Uppercase;
+    ///
+    /// impl ChapterNamer for Uppercase {
+    ///     fn name(&self, _path: &Path, default: &str) -> Option<String> {
+    ///         Some(default.to_uppercase())
+    ///     }
+    /// }
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/intro.md", "# Introduction\n")
+    ///     .with_file("/src/guide/README.md", "# Guide\n")
+    ///     .with_file("/src/guide/setup.md", "# Setup\n");
+    ///
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .with_namer(Box::new(Uppercase))
+    ///     .generate_summary(Path::new("/src"), &Config::default())?;
+    /// let SummaryItem::Link(guide) = &summary.numbered_chapters[0] else {
+    ///     unreachable!()
+    /// };
+    /// assert_eq!(guide.name, "GUIDE");
+    /// let SummaryItem::Link(setup) = &guide.nested_items[0] else {
+    ///     unreachable!()
+    /// };
+    /// assert_eq!(setup.name, "SETUP");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn with_namer(mut self, namer: Box<dyn ChapterNamer>) -> Self {
+        self.hooks.namer = Some(namer);
+        self
+    }
+
+    /// Use `sorter` to order the chapters of every directory, instead of only sorting them by file
+    /// name.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{
+    ///     ChapterSorter, Config, GenerateSummary, MemoryFs, SortEntry, SummaryItem,
+    /// };
+    ///
+    /// struct Reverse;
+    ///
+    /// impl ChapterSorter for Reverse {
+    ///     fn sort(&self, _directory: &Path, entries: &mut [SortEntry]) -> anyhow::Result<()> {
+    ///         entries.reverse();
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut fs = MemoryFs::new();
+    /// for file in ["intro.md", "guide/README.md", "guide/install.md", "guide/setup.md"] {
+    ///     fs = fs.with_file(Path::new("/src").join(file), "");
+    /// }
+    ///
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .with_sorter(Box::new(Reverse))
+    ///     .generate_summary(Path::new("/src"), &Config::default())?;
+    /// fn names(items: &[SummaryItem]) -> Vec<&str> {
+    ///     items
+    ///         .iter()
+    ///         .filter_map(|item| match item {
+    ///             SummaryItem::Link(link) => Some(link.name.as_str()),
+    ///             _ => None,
+    ///         })
+    ///         .collect()
+    /// }
+    /// assert_eq!(names(&summary.numbered_chapters), ["intro", "guide"]);
+    /// let SummaryItem::Link(guide) = &summary.numbered_chapters[1] else {
+    ///     unreachable!()
+    /// };
+    /// assert_eq!(names(&guide.nested_items), ["setup", "install"]);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn with_sorter(mut self, sorter: Box<dyn ChapterSorter>) -> Self {
+        self.hooks.sorter = Some(sorter);
+        self
+    }
+
+    /// Use `filter` to leave files and directories out of the summary.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{
+    ///     ChapterFilter, Config, EntryKind, GenerateSummary, MemoryFs, SummaryItem,
+    /// };
+    ///
+    /// struct SkipInternal;
+    ///
+    /// impl ChapterFilter for SkipInternal {
+    ///     fn include(&self, path: &Path, _kind: EntryKind) -> bool {
+    ///         !path.to_string_lossy().contains("internal")
+    ///     }
+    /// }
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/intro.md", "")
+    ///     .with_file("/src/intro-internal.md", "")
+    ///     .with_file("/src/internal/README.md", "")
+    ///     .with_file("/src/internal/secret.md", "");
+    ///
+    /// let summary = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .with_filter(Box::new(SkipInternal))
+    ///     .generate_summary(Path::new("/src"), &Config::default())?;
+    /// let names: Vec<_> = summary
+    ///     .numbered_chapters
+    ///     .iter()
+    ///     .filter_map(|item| match item {
+    ///         SummaryItem::Link(link) => Some(link.name.as_str()),
+    ///         _ => None,
+    ///     })
+    ///     .collect();
+    /// assert_eq!(names, ["intro"]);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn with_filter(mut self, filter: Box<dyn ChapterFilter>) -> Self {
+        self.hooks.filter = Some(filter);
+        self
+    }
+
+    /// Report the progress of the traversal of the src directory to `progress`, e.g. to show it
+    /// with a [`TerminalProgress`] while a large book is generated.
+    ///
+    /// # Example
+    /// ```
+    /// use std::{cell::RefCell, path::Path, rc::Rc};
+    ///
+    /// use mdbook_generate_summary::{Config, GenerateSummary, MemoryFs, ProgressReporter, Stats};
+    ///
+    /// struct Recorder(Rc<RefCell<Vec<String>>>);
+    ///
+    /// impl ProgressReporter for Recorder {
+    ///     fn update(&self, stats: &Stats) {
+    ///         let files = stats.files_read + stats.cached_titles;
+    ///         let event = format!("{} directories, {} files", stats.directories, files);
+    ///         self.0.borrow_mut().push(event);
+    ///     }
+    ///
+    ///     fn finish(&self) {
+    ///         self.0.borrow_mut().push("finished".to_owned());
+    ///     }
+    /// }
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/intro.md", "# Introduction\n")
+    ///     .with_file("/src/guide/README.md", "# Guide\n")
+    ///     .with_file("/src/guide/setup.md", "# Setup\n");
+    /// let config = Config::builder().get_chapter_name_from_file(true).build()?;
+    /// let events = Rc::new(RefCell::new(vec![]));
+    /// GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .with_progress(Box::new(Recorder(events.clone())))
+    ///     .generate_summary(Path::new("/src"), &config)?;
+    ///
+    /// let events = events.borrow();
+    /// assert_eq!(events[0], "1 directories, 0 files");
+    /// assert_eq!(events[events.len() - 2..], ["2 directories, 3 files", "finished"]);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn with_progress(mut self, progress: Box<dyn ProgressReporter>) -> Self {
+        self.hooks.progress = Some(progress);
+        self
+    }
+
+    /// Read the src directory from `fs` and create missing chapter files in it, instead of using
+    /// the file system of the operating system. See [`MemoryFs`] for an example.
+    pub fn with_fs(mut self, fs: Box<dyn SummaryFs>) -> Self {
+        self.hooks.fs = Some(fs);
+        self
+    }
+
+    /// Generate the summary of the markdown files in `src_dir`, see [`generate_summary`].
+    pub fn generate_summary(&self, src_dir: &Path, config: &Config) -> Result<Summary, Error> {
+        let (summary, _) = self.generate_summary_with_stats(src_dir, config)?;
+        Ok(summary)
+    }
+
+    /// Like [`GenerateSummary::generate_summary`], but also return how many chapters were
+    /// generated and how long it took.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::{Config, GenerateSummary, MemoryFs};
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/intro.md", "# Introduction\n")
+    ///     .with_file("/src/guide/README.md", "# Guide\n")
+    ///     .with_file("/src/guide/advanced/usage.md", "# Usage\n");
+    ///
+    /// let config = Config::builder()
+    ///     .ignore_missing_chapter_files(true)
+    ///     .build()?;
+    /// let (_, stats) = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary_with_stats(Path::new("/src"), &config)?;
+    /// // guide/advanced has no chapter file, so it is a draft
+    /// assert_eq!(stats.chapters, 4);
+    /// assert_eq!(stats.drafts, 1);
+    /// assert_eq!(stats.directories, 3);
+    /// assert_eq!(stats.max_depth, 3);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn generate_summary_with_stats(
+        &self,
+        src_dir: &Path,
+        config: &Config,
+    ) -> Result<(Summary, Stats), Error> {
+        let mut warnings = Warnings::new(config.allow.clone(), config.fail_on_warnings);
+        for warning in &config.warnings {
+            warnings.warn(warning.kind, warning.message.clone());
+        }
+        let fs = self.hooks.fs();
+        let src_dir = fs.canonicalize(src_dir).with_context(|| {
+            format!("Failed to resolve the src directory {}", src_dir.display())
+        })?;
+        if !fs
+            .metadata(&src_dir)
+            .is_ok_and(|metadata| metadata.kind == FileKind::Directory)
+        {
+            return Err(anyhow!(
+                "The src directory {} is not a directory",
+                src_dir.display()
+            ));
+        }
+        let (summary, _, stats, ..) = build_summary(
+            &src_dir,
+            config,
+            &self.hooks,
+            false,
+            None,
+            None,
+            &mut warnings,
+        )?;
+        warnings.check()?;
+        log_stats(&src_dir, config, &stats);
+        Ok((summary, stats))
+    }
+}
+
+/// Generate the summary of the markdown files in `src_dir`, the same way the preprocessor does
+/// for the src directory of a book.
+/// Warnings are printed to stderr. Files are only created or written if `config` says so, e.g.
+/// missing chapter files with `create_missing_chapter_files`.
+///
+/// # Example
+/// ```
+/// use mdbook_generate_summary::{generate_summary, Config, SummaryItem};
+///
+/// // A directory of its own, so that concurrent runs do not share it
+/// let src_dir = std::env::temp_dir().join(format!(
+///     "generate-summary-doctest-{}",
+///     std::process::id()
+/// ));
+/// std::fs::create_dir_all(src_dir.join("guide"))?;
+/// std::fs::write(src_dir.join("intro.md"), "# Introduction\n")?;
+/// std::fs::write(src_dir.join("guide/README.md"), "# Guide\n")?;
+/// std::fs::write(src_dir.join("guide/setup.md"), "# Setup\n")?;
+///
+/// let summary = generate_summary(&src_dir, &Config::default())?;
+/// let names: Vec<_> = summary
+///     .numbered_chapters
+///     .iter()
+///     .filter_map(|item| match item {
+///         SummaryItem::Link(link) => Some(link.name.as_str()),
+///         _ => None,
+///     })
+///     .collect();
+/// assert_eq!(names, ["guide", "intro"]);
+/// # std::fs::remove_dir_all(&src_dir)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn generate_summary(src_dir: &Path, config: &Config) -> Result<Summary, Error> {
+    GenerateSummary::new().generate_summary(src_dir, config)
+}
+
+/// What [`build_summary`] returns.
+type Built = (Summary, Vec<PathBuf>, Stats, Excluded, Rc<Conversions>);
+
+/// Generate the summary of the resolved `src_dir` and validate it. The created chapter files are
+/// written to `manifest_path`, unless `dry_run` is set.
+/// Returns the summary, the created chapter files (which are only the missing ones with `dry_run`),
+/// the statistics of the generation, the locations of the excluded files and directories and the
+/// files converted to markdown.
+fn build_summary(
+    src_dir: &Path,
+    config: &Config,
+    hooks: &Hooks,
+    watching: bool,
+    manifest_path: Option<&Path>,
+    title_cache_path: Option<&Path>,
+    warnings: &mut Warnings,
+) -> Result<Built, Error> {
+    let start = Instant::now();
+    let fs = hooks.fs();
+    let title_cache = title_cache_path.map(|path| TitleCache::load(path, src_dir, config));
+    let prefetched = Prefetched::read(src_dir, config, title_cache.as_ref(), fs);
+    let tracked_files = if config.git_tracked_only {
+        read_tracked_files(src_dir, warnings)
+    } else {
+        None
+    };
+    let mut generator = Generator::new(
+        src_dir,
+        config,
+        hooks,
+        prefetched,
+        title_cache,
+        watching,
+        warnings,
+    );
+    generator.tracked_files = tracked_files;
+    // A link back to the src directory is left out like any other duplicate
+    generator.duplicate_of(src_dir);
+    let numbered_chapters = match &config.outline {
+        Some(outline) => generator.generate_outline(outline),
+        None => Ok(generator.generate_chapters(src_dir, Option::None)),
+    };
+    if let Some(progress) = &hooks.progress {
+        progress.finish();
+    }
+    let mut numbered_chapters = numbered_chapters?;
+    generator.stats.traversal = start.elapsed();
+    generator.report_created_files();
+    if let Some(manifest_path) = manifest_path.filter(|_| !config.dry_run) {
+        generator.write_created_files_manifest(manifest_path)?;
+    }
+    generator.check_errors()?;
+    if let Some(key) = &config.group_by {
+        numbered_chapters = generator.group_chapters(numbered_chapters, key)?;
+    }
+    let external_chapters = generator.external_chapters()?;
+    if numbered_chapters.is_empty() && generator.unnumbered_sections.is_empty() {
+        generator.warnings.warn_at(
+            WarningKind::EmptySummary,
+            src_dir,
+            format!(
+                "No markdown files found in {}, the summary is empty",
+                src_dir.display()
+            ),
+        );
+    }
+    let created_files = std::mem::take(&mut generator.created_files);
+    let mounted = std::mem::take(&mut generator.mounted);
+    let excluded = std::mem::take(&mut generator.excluded);
+    let conversions = Rc::clone(&generator.conversions);
+    let mut stats = std::mem::take(&mut generator.stats);
+    let metadata_queries;
+    (stats.files_read, stats.cached_titles, metadata_queries) = generator.heads.counts();
+    stats.metadata_queries += metadata_queries;
+    if !config.dry_run {
+        generator.heads.save_cache(src_dir, config);
+    }
+    if let Some(language) = &config.language {
+        check_translations(src_dir, language, config, fs, generator.warnings)?;
+    }
+    let mut summary = Summary {
+        title: config.title.clone(),
+        prefix_chapters: vec![],
+        numbered_chapters,
+        suffix_chapters: generator.into_unnumbered_chapters(),
+    };
+    let start = Instant::now();
+    validate_summary(
+        &mut summary,
+        src_dir,
+        &mounted,
+        &conversions.sources(),
+        config.validate,
+        fs,
+        warnings,
+    )?;
+    // They are not inside of the src directory, which is checked by the validation
+    add_external_chapters(&mut summary, external_chapters);
+    // After the validation, which expects the numbers to match the positions of the chapters
+    offset_section_numbers(
+        &mut summary.numbered_chapters,
+        config.first_section_number - 1,
+    );
+    if config.number_in_names {
+        number_names(&mut summary.numbered_chapters, config);
+    }
+    stats.validation = start.elapsed();
+    stats.count_chapters(&summary);
+    Ok((summary, created_files, stats, excluded, conversions))
+}
 
-/// Possible configuration options when running the preprocessor
-struct Config {
-    /// Use the first line of the file and parse '# <chapter_name>' if set. Defaults to false.
-    get_chapter_name_from_file: bool,
-    /// The file to use for chapters with children. Defaults to 'README'.
-    /// Do not include the file extension as it will be '.md' anyways.
-    chapter_file_name: String,
-    /// Creates empty file with name chapter_file_name if it is missing in a directory. Defaults to
-    /// false.
-    /// When false the preprocessor panics if the file is <chapter_file_name>.md is missing in a
-    /// directory.
+/// The files in `src_dir` which git tracks, for `git_tracked_only`. Outside of a git work tree
+/// the option is ignored with a warning.
+fn read_tracked_files(src_dir: &Path, warnings: &mut Warnings) -> Option<TrackedFiles> {
+    match TrackedFiles::read(src_dir) {
+        Ok(tracked_files) => {
+            debug!(
+                "git tracks {} files in {}",
+                tracked_files.len(),
+                src_dir.display()
+            );
+            Some(tracked_files)
+        }
+        Err(error) => {
+            warnings.warn_at(
+                WarningKind::NoGitRepository,
+                src_dir,
+                format!(
+                    "Ignoring `git_tracked_only`, the files tracked by git in {} can not be \
+                     read: {:#}",
+                    src_dir.display(),
+                    error
+                ),
+            );
+            None
+        }
+    }
+}
+
+/// Log the statistics of generating the summary of `src_dir`, on several lines with
+/// `verbose_stats`.
+fn log_stats(src_dir: &Path, config: &Config, stats: &Stats) {
+    if config.verbose_stats {
+        log!(
+            config.info_level(),
+            "Generated the summary of {}:\n{:#}",
+            src_dir.display(),
+            stats
+        );
+    } else {
+        log!(
+            config.info_level(),
+            "Generated the summary of {}: {}",
+            src_dir.display(),
+            stats
+        );
+    }
+}
+
+/// The locations of the files and directories which are not part of the book, with the reason.
+type Excluded = HashMap<PathBuf, &'static str>;
+
+/// State kept while generating the chapters of a book.
+struct Generator<'a> {
+    src_dir: &'a Path,
+    config: &'a Config,
+    hooks: &'a Hooks,
+    /// Unnumbered sections found so far, together with the key they are sorted by.
+    unnumbered_sections: Vec<(usize, SummaryItem)>,
+    /// True while generating the chapters inside of an unnumbered section.
+    in_unnumbered_section: bool,
+    /// Files to use as the chapter file of a directory if it does not have one, see
+    /// [`Generator::resolve_collisions`].
+    landing_pages: HashMap<PathBuf, PathBuf>,
+    /// The resolved source directories of the mounts added so far, with their path within the
+    /// book.
+    mounted: Vec<(PathBuf, PathBuf)>,
+    /// Create missing chapter files, see [`Config::create_missing_chapter_files`].
     create_missing_chapter_files: bool,
-    /// If a create_missing_chapter_files is false, but the file is missing the implementations
-    /// panics by default.
-    /// Set this to true to instead use ignore the missing file.
+    /// Ignore missing chapter files, see [`Config::ignore_missing_chapter_files`].
     ignore_missing_chapter_files: bool,
+    /// Chapter files which were created so far, or would have been created without `dry_run`.
+    created_files: Vec<PathBuf>,
+    /// The locations of the files and directories which were left out of the book or only added
+    /// as drafts, with the reason. Used to explain links to them, see `check_links`.
+    excluded: Excluded,
+    /// The files converted by a `converter`, shared with the [`Heads`].
+    conversions: Rc<Conversions>,
+    /// With `git_tracked_only`, the files in the src directory which git tracks.
+    tracked_files: Option<TrackedFiles>,
+    /// The resolved paths of the files and directories added so far with `follow_symlinks`, with
+    /// the path they were added as.
+    canonical_paths: HashMap<PathBuf, PathBuf>,
+    stats: Stats,
+    /// The directory listings read in parallel before generating the chapters.
+    prefetched: Prefetched,
+    heads: Heads<'a>,
+    /// How deep the directory currently generated is nested in the src directory.
+    depth: usize,
+    /// The markdown files and directories found so far, see `max_chapters`.
+    entries_found: usize,
+    /// True once there were more entries than `max_chapters`, nothing else is generated then.
+    aborted: bool,
+    /// Errors which occurred so far. They are collected so that all of them can be reported at
+    /// once.
+    errors: Vec<Error>,
+    /// Entries which failed, but were skipped or added as drafts due to `config.on_error`.
+    failures: FailureReport,
+    warnings: &'a mut Warnings,
 }
 
-impl From<&toml::map::Map<String, toml::value::Value>> for Config {
-    fn from(value: &toml::map::Map<String, toml::value::Value>) -> Self {
-        Self {
-            get_chapter_name_from_file: value
-                .get("get_chapter_name_from_file")
-                .map_or(false, |val| val.as_bool().unwrap()),
-            chapter_file_name: value
-                .get("chapter_file_name")
-                .map_or("README".to_owned(), |val| val.as_str().unwrap().to_owned()),
-            create_missing_chapter_files: value
-                .get("create_missing_chapter_files")
-                .map_or(false, |val| val.as_bool().unwrap()),
-            ignore_missing_chapter_files: value
-                .get("ignore_missing_chapter_files")
-                .map_or(false, |val| val.as_bool().unwrap()),
+/// What happened to an entry which failed, if `config.on_error` is not [`OnError::Fail`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Skipped,
+    Drafted,
+}
+
+/// An entry which failed, but did not fail the whole summary.
+struct Failure {
+    path: PathBuf,
+    error: Error,
+    outcome: Outcome,
+}
+
+/// All entries which failed while generating the summary, reported at once at the end.
+#[derive(Default)]
+struct FailureReport {
+    failures: Vec<Failure>,
+}
+
+impl FailureReport {
+    fn is_empty(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// The number of failed entries with the outcome.
+    fn count(&self, outcome: Outcome) -> usize {
+        self.failures
+            .iter()
+            .filter(|failure| failure.outcome == outcome)
+            .count()
+    }
+}
+
+impl std::fmt::Display for FailureReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Failed entries: {} (skipped: {}, drafted: {})",
+            self.failures.len(),
+            self.count(Outcome::Skipped),
+            self.count(Outcome::Drafted)
+        )?;
+        for failure in &self.failures {
+            let outcome = match failure.outcome {
+                Outcome::Skipped => "skipped",
+                Outcome::Drafted => "drafted",
+            };
+            write!(
+                f,
+                "\n  {} {}: {:#}",
+                outcome,
+                failure.path.display(),
+                failure.error
+            )?;
         }
+        Ok(())
     }
 }
 
-#[derive(Debug, Default)]
-pub struct GenerateSummary;
+/// A directory whose chapters are generated, see [`Generator::generate_chapters`].
+struct Frame {
+    dir_path: PathBuf,
+    section: Option<SectionNumber>,
+    /// The entries left to add, [`Option::None`] if the directory is not used at all.
+    entries: Option<vec::IntoIter<Entry>>,
+    /// The chapters of the entries added so far.
+    items: Vec<SummaryItem>,
+    /// The entry of the directory in the frame of its parent directory, [`Option::None`] for the
+    /// directory the chapters are generated for.
+    parent: Option<(Entry, OpenDirectory)>,
+}
 
-impl GenerateSummary {
-    pub fn new() -> GenerateSummary {
-        GenerateSummary
-    }
+/// A directory whose nested chapters are generated before its chapter is added, see
+/// [`Generator::open_entry`].
+struct OpenDirectory {
+    path_to_directory_content: Option<PathBuf>,
+    /// Its position among the unnumbered sections, if it is one.
+    unnumbered_position: Option<usize>,
+    section: SectionNumber,
+    /// Whether the parent directory is in an unnumbered section, restored once the directory is
+    /// closed.
+    was_in_unnumbered_section: bool,
 }
 
-impl Preprocessor for GenerateSummary {
-    fn name(&self) -> &str {
-        "generate-summary"
+impl<'a> Generator<'a> {
+    /// `watching` is true if the book is rebuilt whenever the src directory changes.
+    fn new(
+        src_dir: &'a Path,
+        config: &'a Config,
+        hooks: &'a Hooks,
+        mut prefetched: Prefetched,
+        title_cache: Option<TitleCache>,
+        watching: bool,
+        warnings: &'a mut Warnings,
+    ) -> Self {
+        let (create_missing_chapter_files, ignore_missing_chapter_files) =
+            match config.create_missing_chapter_files {
+                CreateMissingChapterFiles::Never => (false, config.ignore_missing_chapter_files),
+                CreateMissingChapterFiles::Always => (true, false),
+                // Creating files would trigger another build, ignore them until the next build
+                // which is not watching
+                CreateMissingChapterFiles::Once => (!watching, watching),
+            };
+
+        let conversions = Rc::new(Conversions::new(config));
+        Self {
+            src_dir,
+            config,
+            hooks,
+            create_missing_chapter_files,
+            ignore_missing_chapter_files,
+            created_files: vec![],
+            excluded: HashMap::new(),
+            tracked_files: None,
+            canonical_paths: HashMap::new(),
+            stats: Stats {
+                directories_read: prefetched.directories_read,
+                metadata_queries: prefetched.metadata_queries,
+                ..Stats::default()
+            },
+            heads: Heads::new(
+                hooks.fs(),
+                prefetched.take_heads(),
+                title_cache,
+                Rc::clone(&conversions),
+            ),
+            conversions,
+            prefetched,
+            failures: FailureReport::default(),
+            warnings,
+            unnumbered_sections: vec![],
+            in_unnumbered_section: false,
+            landing_pages: HashMap::new(),
+            mounted: vec![],
+            depth: 0,
+            entries_found: 0,
+            aborted: false,
+            errors: vec![],
+        }
     }
 
-    fn run(&self, ctx: &PreprocessorContext, _: Book) -> Result<Book, Error> {
-        let config = Config::from(ctx.config.get_preprocessor(self.name()).unwrap());
+    /// Create summary items out of the provided directory. If the section is `None` it means we
+    /// are in the src dir.
+    /// The subdirectories are visited with a stack of [`Frame`]s instead of recursively, so that
+    /// deeply nested directories do not overflow the stack.
+    /// Unnumbered sections are not returned, but collected to be retrieved with
+    /// [`Generator::into_unnumbered_chapters`].
+    /// Errors are collected to be checked with [`Generator::check_errors`].
+    fn generate_chapters(
+        &mut self,
+        dir_path: &Path,
+        section: Option<&SectionNumber>,
+    ) -> Vec<SummaryItem> {
+        let mut stack = vec![self.open_frame(dir_path.to_path_buf(), section.cloned(), None)];
+        loop {
+            let frame = stack
+                .last_mut()
+                .expect("The items are returned once it is empty");
+            let entry = match frame.entries.as_mut().and_then(Iterator::next) {
+                Some(entry) if !self.aborted => entry,
+                _ => {
+                    let Frame {
+                        dir_path,
+                        section,
+                        entries,
+                        mut items,
+                        parent,
+                    } = stack
+                        .pop()
+                        .expect("The items are returned once it is empty");
+                    if entries.is_some() {
+                        self.add_planned_chapters(&dir_path, section.as_ref(), &mut items);
+                    }
+                    let Some((entry, directory)) = parent else {
+                        return items;
+                    };
+                    let frame = stack
+                        .last_mut()
+                        .expect("Only the first frame has no parent");
+                    let result = self.close_directory(&entry, directory, items, &mut frame.items);
+                    if let Err(error) = result {
+                        let section = frame.section.as_ref();
+                        self.handle_entry_error(
+                            &entry.path,
+                            entry.name,
+                            section,
+                            &mut frame.items,
+                            error,
+                        );
+                    }
+                    self.report_progress();
+                    continue;
+                }
+            };
+            match self.open_entry(&entry, frame.section.as_ref(), &mut frame.items) {
+                Ok(None) => {}
+                Ok(Some(directory)) => {
+                    let section = Some(directory.section.clone());
+                    let frame =
+                        self.open_frame(entry.path.clone(), section, Some((entry, directory)));
+                    stack.push(frame);
+                    continue;
+                }
+                Err(error) => {
+                    let section = frame.section.as_ref();
+                    self.handle_entry_error(
+                        &entry.path,
+                        entry.name,
+                        section,
+                        &mut frame.items,
+                        error,
+                    )
+                }
+            }
+            self.report_progress();
+        }
+    }
+
+    /// Read the entries of the directory to generate its chapters, see
+    /// [`Generator::generate_chapters`]. `parent` is the entry of the directory in the frame of its
+    /// parent directory.
+    fn open_frame(
+        &mut self,
+        dir_path: PathBuf,
+        section: Option<SectionNumber>,
+        parent: Option<(Entry, OpenDirectory)>,
+    ) -> Frame {
+        let entries = self.directory_entries(&dir_path);
+        Frame {
+            items: Vec::with_capacity(entries.as_ref().map_or(0, Vec::len)),
+            entries: entries.map(Vec::into_iter),
+            dir_path,
+            section,
+            parent,
+        }
+    }
 
-        let book_dir = &ctx.root.join(&ctx.config.book.src);
+    /// The entries of the directory to add chapters for, [`Option::None`] if it is not used at all,
+    /// e.g. as it is nested too deep.
+    fn directory_entries(&mut self, dir_path: &Path) -> Option<Vec<Entry>> {
+        if self.aborted {
+            return None;
+        }
+        if self.depth > self.config.max_depth {
+            let error = anyhow!(
+                "The directory {} is nested deeper than {} directories. Increase `max_depth` if \
+                 this is intended",
+                dir_path.display(),
+                self.config.max_depth
+            );
+            self.handle_error(dir_path, Outcome::Skipped, error);
+            return None;
+        }
 
-        // Create summary using books src directory
-        let summary = Summary {
-            title: Option::None,
-            prefix_chapters: vec![],
-            numbered_chapters: generate_chapters(book_dir, Option::None, &config),
-            suffix_chapters: vec![],
+        debug!("Visiting {}", self.describe(dir_path));
+        self.stats.directories += 1;
+        self.report_progress();
+        let entries = self.sorted_entries(dir_path)?;
+        self.entries_found += entries.len();
+        if self.entries_found > self.config.max_chapters {
+            // Fails regardless of `on_error`, the src directory is most likely wrong
+            self.errors.push(anyhow!(
+                "Found {} markdown files and directories, more than `max_chapters` ({}) allows, \
+                 while scanning {}. Check that `book.src` points to the book, or increase \
+                 `max_chapters` if it is that large",
+                self.entries_found,
+                self.config.max_chapters,
+                dir_path.display()
+            ));
+            self.aborted = true;
+            return None;
+        }
+        Some(entries)
+    }
+
+    /// Pass the counters so far to the [`ProgressReporter`], if there is one.
+    fn report_progress(&self) {
+        let Some(progress) = &self.hooks.progress else {
+            return;
         };
+        let mut stats = self.stats.clone();
+        (stats.files_read, stats.cached_titles, _) = self.heads.counts();
+        progress.update(&stats);
+    }
 
-        Ok(MDBook::load_with_config_and_summary(&ctx.root, ctx.config.clone(), summary)?.book)
+    /// Handle the error of an entry which could not be added to `items`, by adding a draft named
+    /// `name` instead with `on_error = "draft"`.
+    fn handle_entry_error(
+        &mut self,
+        path: &Path,
+        name: String,
+        section: Option<&SectionNumber>,
+        items: &mut Vec<SummaryItem>,
+        error: Error,
+    ) {
+        if self.config.on_error == OnError::Draft {
+            items.push(SummaryItem::Link(Link {
+                name,
+                location: None,
+                nested_items: vec![],
+                number: Some(child_section(section, items.len() + 1)),
+            }));
+            self.handle_error(path, Outcome::Drafted, error);
+        } else {
+            self.handle_error(path, Outcome::Skipped, error);
+        }
     }
 
-    fn supports_renderer(&self, renderer: &str) -> bool {
-        renderer != "not-supported"
+    /// The markdown files and directories in the directory which pass the [`ChapterFilter`], in
+    /// the order of their chapters. Errors are handled with [`Generator::handle_error`], returns
+    /// [`Option::None`] if the directory can not be used at all.
+    fn sorted_entries(&mut self, dir_path: &Path) -> Option<Vec<Entry>> {
+        let Listing {
+            mut entries,
+            errors,
+            ..
+        } = match self.read_entries(dir_path) {
+            Ok(listing) => listing,
+            Err(error) => {
+                self.handle_error(dir_path, Outcome::Skipped, error);
+                return None;
+            }
+        };
+        for error in errors {
+            self.handle_error(dir_path, Outcome::Skipped, error);
+        }
+        self.add_mounts(dir_path, &mut entries);
+        if !self.config.converters.is_empty() {
+            self.skip_shadowed_conversions(&mut entries);
+        }
+
+        if let Some(filter) = &self.hooks.filter {
+            let count = entries.len();
+            let mut filtered = vec![];
+            entries.retain(|entry| {
+                let kind = if entry.is_dir {
+                    EntryKind::Directory
+                } else {
+                    EntryKind::File
+                };
+                let location = self.location(&entry.path);
+                let include = filter.include(&location, kind);
+                if !include {
+                    debug!(
+                        "Skipping {}, it is excluded by the filter",
+                        location.display()
+                    );
+                    filtered.push(location);
+                }
+                include
+            });
+            self.excluded.extend(
+                filtered
+                    .into_iter()
+                    .map(|location| (location, "it is excluded by the chapter filter")),
+            );
+            self.stats.filtered += count - entries.len();
+        }
+
+        if let Some(tracked_files) = &self.tracked_files {
+            let mut untracked = vec![];
+            entries.retain(|entry| {
+                // Mounted directories are outside of the src directory, git is not asked about them
+                let Ok(location) = entry.path.strip_prefix(self.src_dir) else {
+                    return true;
+                };
+                let include = tracked_files.includes(location, entry.is_dir);
+                if !include {
+                    debug!(
+                        "Skipping {}, it is not tracked by git",
+                        self.describe(&entry.path)
+                    );
+                    untracked.push(self.location(&entry.path));
+                }
+                include
+            });
+            self.excluded.extend(
+                untracked
+                    .into_iter()
+                    .map(|location| (location, "it is not tracked by git")),
+            );
+        }
+
+        if !self.config.excluded_directories.is_empty() {
+            let mut excluded = vec![];
+            entries.retain(|entry| {
+                let is_excluded =
+                    entry.is_dir && self.config.excluded_directories.contains(&entry.path);
+                if is_excluded {
+                    log!(
+                        self.config.info_level(),
+                        "Skipping {}, mdBook uses it for its build output or theme",
+                        self.describe(&entry.path)
+                    );
+                    excluded.push(self.location(&entry.path));
+                }
+                !is_excluded
+            });
+            self.excluded.extend(
+                excluded
+                    .into_iter()
+                    .map(|location| (location, "mdBook uses it for its build output or theme")),
+            );
+        }
+
+        if !self.config.descend_into_nested_books {
+            entries.retain(|entry| !entry.is_dir || !self.is_nested_book(&entry.path));
+        }
+
+        self.check_case_collisions(&entries);
+
+        if self.config.sidecar_files {
+            for entry in &mut entries {
+                entry.sidecar = self.read_sidecar(&entry.path, entry.is_dir);
+            }
+        }
+
+        // Sort by weight and filename
+        entries.sort_by(|a, b| {
+            a.sidecar
+                .weight
+                .cmp(&b.sidecar.weight)
+                .then_with(|| a.file_name().cmp(b.file_name()))
+        });
+        if self.config.sort == SortOrder::HeadingNumber && self.config.get_chapter_name_from_file {
+            self.sort_by_heading_number(dir_path, &mut entries);
+        }
+        self.resolve_collisions(&mut entries);
+        if let Err(error) = self.sort_entries(dir_path, &mut entries) {
+            self.handle_error(dir_path, Outcome::Skipped, error);
+            return None;
+        }
+        if !self.warnings.explained().is_empty() {
+            self.explain_order(dir_path, &entries);
+        }
+        Some(entries)
     }
-}
 
-/// Create summary items out of the provided directory. If the section is `None` it means we are in
-/// the src dir.
-fn generate_chapters(
-    dir_path: &PathBuf,
-    section: Option<&SectionNumber>,
-    config: &Config,
-) -> Vec<SummaryItem> {
-    let mut entries = get_markdown_files_and_directories(dir_path);
-
-    // Sort by filename
-    entries.sort_by_key(|a| a.file_name());
-
-    entries
-        .into_iter()
-        .map(|entry| {
-            let path = entry.path();
-            let filename = path.file_stem().unwrap().to_str().unwrap().to_owned();
-            (entry, filename)
-        })
-        .filter(|(entry, filename)| {
-            if section.is_none() && filename == "SUMMARY" {
-                // Do not keep 'SUMMARY.md' when in src file as we are the ones generating it
-                return false;
+    /// Record the position of the explained entries among the sorted `entries` of the directory
+    /// and what they were sorted by.
+    fn explain_order(&mut self, dir_path: &Path, entries: &[Entry]) {
+        for (index, entry) in entries.iter().enumerate() {
+            if !self.explains(&entry.path) {
+                continue;
+            }
+            let mut keys = vec![];
+            if self.config.sidecar_files {
+                keys.push(format!(
+                    "the weight {} of its sidecar",
+                    entry.sidecar.weight
+                ));
+            }
+            if self.config.sort == SortOrder::HeadingNumber
+                && self.config.get_chapter_name_from_file
+            {
+                keys.push(match self.heading_number(entry) {
+                    Some(number) => format!("the heading number {}", number),
+                    None => "no heading number, after the numbered ones".to_owned(),
+                });
+            }
+            keys.push(format!(
+                "its file name \"{}\"",
+                entry.file_name().to_string_lossy()
+            ));
+            if self.hooks.sorter.is_some() {
+                keys.push("then by the ChapterSorter".to_owned());
+            }
+            let directory = self.describe(dir_path);
+            self.explain(&entry.path, || {
+                format!(
+                    "Entry {} of {} of {}, sorted by {}",
+                    index + 1,
+                    entries.len(),
+                    directory,
+                    keys.join(", ")
+                )
+            });
+        }
+    }
+
+    /// Leave out the files converted by a `converter` whose location is taken by a markdown file,
+    /// or by another converted file before them in the order of their names.
+    fn skip_shadowed_conversions(&mut self, entries: &mut Vec<Entry>) {
+        entries.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+        let mut locations: HashSet<PathBuf> = entries
+            .iter()
+            .filter(|entry| !entry.is_dir && self.config.converter(&entry.path).is_none())
+            .map(|entry| entry.path.clone())
+            .collect();
+        entries.retain(|entry| {
+            if entry.is_dir || self.config.converter(&entry.path).is_none() {
+                return true;
+            }
+            let markdown = entry.path.with_extension("md");
+            if locations.insert(markdown.clone()) {
+                return true;
+            }
+            log!(
+                self.config.info_level(),
+                "Skipping {}, another file is already added as {}",
+                self.describe(&entry.path),
+                self.source_location(&markdown).display()
+            );
+            false
+        });
+    }
+
+    /// Remember that the file or directory at `path` is not part of the book, see
+    /// [`Generator::excluded`].
+    fn exclude(&mut self, path: &Path, reason: &'static str) {
+        self.excluded.insert(self.location(path), reason);
+    }
+
+    /// With `follow_symlinks`, the path of the file or directory added before which resolves to
+    /// the same one as `path`. Otherwise `path` is remembered as the one added for it.
+    fn duplicate_of(&mut self, path: &Path) -> Option<PathBuf> {
+        if !self.config.follow_symlinks {
+            return None;
+        }
+        let canonical = self.hooks.fs().canonicalize(path).ok()?;
+        if let Some(original) = self.canonical_paths.get(&canonical) {
+            return Some(original.clone());
+        }
+        self.canonical_paths.insert(canonical, path.to_owned());
+        None
+    }
+
+    /// Returns true if the directory contains a book.toml, so it is a book of its own.
+    fn is_nested_book(&mut self, path: &Path) -> bool {
+        self.stats.metadata_queries += 1;
+        let book_toml = path.join("book.toml");
+        let nested = self
+            .hooks
+            .fs()
+            .metadata(&book_toml)
+            .is_ok_and(|metadata| metadata.kind == FileKind::File);
+        if nested {
+            log!(
+                self.config.info_level(),
+                "Skipping {}, it contains a book.toml of its own book. Set \
+                 `descend_into_nested_books = true` to generate its chapters",
+                self.describe(path)
+            );
+            self.exclude(path, "it is a book of its own");
+        }
+        nested
+    }
+
+    /// Add the planned chapters of the directory as drafts to its `items`, at their position or
+    /// after the existing chapters, and number the items again. A planned chapter is left out
+    /// once a chapter with its title exists.
+    fn add_planned_chapters(
+        &self,
+        dir_path: &Path,
+        section: Option<&SectionNumber>,
+        items: &mut Vec<SummaryItem>,
+    ) {
+        let relative_path = self.location(dir_path);
+        let Some(planned) = self.config.planned.get(&relative_path) else {
+            return;
+        };
+
+        let existing: HashSet<String> = items
+            .iter()
+            .filter_map(|item| match item {
+                SummaryItem::Link(link) => Some(link.name.clone()),
+                _ => None,
+            })
+            .collect();
+        // Positioned chapters first, so that their positions refer to the final order
+        let mut planned: Vec<_> = planned.iter().collect();
+        planned.sort_by_key(|chapter| chapter.position.unwrap_or(usize::MAX));
+        for chapter in planned {
+            if existing.contains(&chapter.title) {
+                log!(
+                    self.config.info_level(),
+                    "Not adding the planned chapter \"{}\" to {}, a chapter with its title exists",
+                    chapter.title,
+                    self.describe(dir_path)
+                );
+                continue;
+            }
+            let index = chapter
+                .position
+                .map_or(items.len(), |position| (position - 1).min(items.len()));
+            items.insert(
+                index,
+                SummaryItem::Link(Link {
+                    name: chapter.title.clone(),
+                    location: None,
+                    nested_items: vec![],
+                    number: Some(SectionNumber::default()),
+                }),
+            );
+        }
+        renumber(items, section);
+    }
+
+    /// Add the chapter for the entry to `items`, or to the unnumbered sections.
+    fn add_entry(
+        &mut self,
+        entry: &Entry,
+        section: Option<&SectionNumber>,
+        items: &mut Vec<SummaryItem>,
+    ) -> Result<(), Error> {
+        let Some(directory) = self.open_entry(entry, section, items)? else {
+            return Ok(());
+        };
+        let nested_items = self.generate_chapters(&entry.path, Some(&directory.section));
+        self.close_directory(entry, directory, nested_items, items)
+    }
+
+    /// Like [`Generator::add_entry`], but returns the directory instead of generating its nested
+    /// chapters. Once they are generated, [`Generator::close_directory`] adds its chapter.
+    fn open_entry(
+        &mut self,
+        entry: &Entry,
+        section: Option<&SectionNumber>,
+        items: &mut Vec<SummaryItem>,
+    ) -> Result<Option<OpenDirectory>, Error> {
+        let path = &entry.path;
+        let filename = &entry.name;
+        let is_dir = entry.is_dir;
+
+        if section.is_none() && is_same_name(filename, "SUMMARY") {
+            // Do not keep 'SUMMARY.md' when in src file as we are the ones generating it
+            debug!("Skipping SUMMARY.md, it is generated");
+            self.explain(path, || {
+                "Left out, the summary is generated instead".to_owned()
+            });
+            return Ok(None);
+        }
+        if section.is_none() && !is_dir && self.is_external_location(&self.location(path)) {
+            debug!(
+                "Skipping {}, it is replaced by an external chapter",
+                self.location(path).display()
+            );
+            self.exclude(path, "it is replaced by an external chapter");
+            return Ok(None);
+        }
+        let dir = path.parent().unwrap_or(path);
+        if !is_dir && is_same_name(filename, &self.config.chapter_file_name_of(dir)) {
+            debug!(
+                "Using {} as the chapter file of its directory",
+                self.location(path).display()
+            );
+            let directory = self.describe(dir);
+            self.explain(path, || {
+                format!(
+                    "Not a chapter of its own, it is the chapter file of {}",
+                    directory
+                )
+            });
+            self.duplicate_of(path);
+            return Ok(None);
+        }
+        if let Some(original) = self.duplicate_of(path) {
+            log!(
+                self.config.info_level(),
+                "Skipping {}, it resolves to the same {} as {}",
+                self.describe(path),
+                if is_dir { "directory" } else { "file" },
+                self.describe(&original)
+            );
+            self.exclude(path, "it resolves to the same file as another chapter");
+            return Ok(None);
+        }
+
+        if !is_dir {
+            let stub_behavior = self.stub_behavior(path)?;
+            if stub_behavior == Some(StubBehavior::Skip) {
+                log!(
+                    self.config.info_level(),
+                    "Skipping {}, it is a stub",
+                    self.describe(path)
+                );
+                self.exclude(path, "it is a stub");
+                return Ok(None);
             }
-            entry.file_type().unwrap().is_dir() || filename != &config.chapter_file_name
+            let mut link = self.file_link(entry, child_section(section, items.len() + 1))?;
+            if stub_behavior == Some(StubBehavior::Draft) {
+                log!(
+                    self.config.info_level(),
+                    "Adding {} as a draft, it is a stub",
+                    self.describe(path)
+                );
+                self.exclude(path, "it is a stub");
+                link.location = None;
+            }
+            if entry.sidecar.draft {
+                self.exclude(path, "its sidecar file marks it as a draft");
+            }
+            self.explain(path, || describe_added(&link));
+            items.push(SummaryItem::Link(link));
+            return Ok(None);
+        }
+
+        let landing_page = self
+            .landing_pages
+            .remove(path)
+            .filter(|_| !self.heads.exists(&self.chapter_file(path), self.config));
+        let path_to_directory_content = if entry.sidecar.draft {
+            debug!(
+                "Adding {} as a draft, its sidecar file marks it as one",
+                self.location(path).display()
+            );
+            self.exclude(
+                &self.chapter_file(path),
+                "its directory is marked as a draft by its sidecar file",
+            );
+            None
+        } else if landing_page.is_some() {
+            // The file next to the directory replaces its missing chapter file
+            landing_page
+        } else if self.config.draft_empty_directories && !self.contains_markdown(path)? {
+            // Directories without any content become draft chapters
+            debug!(
+                "Adding {} as a draft, it does not contain any markdown files",
+                self.location(path).display()
+            );
+            None
+        } else {
+            match self.get_path_to_directory_content(path) {
+                Ok(path_to_directory_content) => path_to_directory_content,
+                Err(error) if self.config.on_error != OnError::Skip => {
+                    // Keep going, so that missing chapter files in subdirectories are
+                    // reported as well. The directory becomes a draft chapter
+                    self.handle_error(path, Outcome::Drafted, error);
+                    None
+                }
+                Err(error) => return Err(error),
+            }
+        };
+        if self.explains(path) {
+            match &path_to_directory_content {
+                Some(content) => {
+                    let location = self.source_location(content);
+                    self.explain(path, || {
+                        format!("Its chapter file is {}", location.display())
+                    });
+                    self.warnings.explain_as_well(location);
+                }
+                None => self.explain(path, || "It has no chapter file".to_owned()),
+            }
+        }
+        let unnumbered_position = if self.in_unnumbered_section {
+            None
+        } else {
+            self.unnumbered_position(path, &path_to_directory_content)?
+        };
+
+        let section = match unnumbered_position {
+            Some(_) => section.cloned().unwrap_or_default(),
+            None => child_section(section, items.len() + 1),
+        };
+
+        let directory = OpenDirectory {
+            path_to_directory_content,
+            unnumbered_position,
+            section,
+            was_in_unnumbered_section: self.in_unnumbered_section,
+        };
+        if self.is_leaf_directory(path, &directory.path_to_directory_content)? {
+            debug!(
+                "Skipping the children of the leaf directory {}",
+                self.location(path).display()
+            );
+            self.exclude(path, "it is inside of a leaf directory");
+            self.add_directory(entry, directory, vec![], items)?;
+            return Ok(None);
+        }
+        self.in_unnumbered_section |= unnumbered_position.is_some();
+        self.depth += 1;
+        Ok(Some(directory))
+    }
+
+    /// Add the chapter of the directory opened by [`Generator::open_entry`] with its
+    /// `nested_items` to `items`, or to the unnumbered sections.
+    fn close_directory(
+        &mut self,
+        entry: &Entry,
+        directory: OpenDirectory,
+        nested_items: Vec<SummaryItem>,
+        items: &mut Vec<SummaryItem>,
+    ) -> Result<(), Error> {
+        self.depth -= 1;
+        self.in_unnumbered_section = directory.was_in_unnumbered_section;
+        self.add_directory(entry, directory, nested_items, items)
+    }
+
+    /// Add the chapter of the directory with its `nested_items`, see
+    /// [`Generator::close_directory`].
+    fn add_directory(
+        &mut self,
+        entry: &Entry,
+        directory: OpenDirectory,
+        nested_items: Vec<SummaryItem>,
+        items: &mut Vec<SummaryItem>,
+    ) -> Result<(), Error> {
+        let path = &entry.path;
+        let OpenDirectory {
+            path_to_directory_content,
+            unnumbered_position,
+            section,
+            ..
+        } = directory;
+        // A stub only replaces the link of the directory, its name and nested chapters are kept
+        let stub_behavior = match &path_to_directory_content {
+            Some(content) => self.stub_behavior(content)?,
+            None => None,
+        };
+        let is_stub = matches!(
+            stub_behavior,
+            Some(StubBehavior::Draft | StubBehavior::Skip)
+        );
+        if is_stub {
+            log!(
+                self.config.info_level(),
+                "Adding {} as a draft, its chapter file is a stub",
+                self.describe(path)
+            );
+            if let Some(content) = &path_to_directory_content {
+                self.exclude(content, "it is a stub");
+            }
+        }
+        let name = match &entry.title {
+            Some(title) => {
+                self.explain(path, || {
+                    format!("Named \"{}\" by the `title` of its mount", title)
+                });
+                title.clone()
+            }
+            None => self.chapter_name(
+                path_to_directory_content.as_deref().unwrap_or(path),
+                path_to_directory_content.as_deref(),
+                &entry.sidecar,
+                &entry.name,
+            )?,
+        };
+        let mut link = Link {
+            name,
+            location: path_to_directory_content
+                .as_deref()
+                .filter(|_| !is_stub)
+                .map(|content| self.location(content)),
+            nested_items,
+            number: Some(section),
+        };
+        if self.config.collapse_single_child_dirs {
+            collapse_single_child(&mut link, self.config.collapse_joiner.as_deref());
+        }
+        self.explain(path, || match unnumbered_position {
+            Some(_) => format!("Added as the unnumbered chapter \"{}\"", link.name),
+            None => describe_added(&link),
+        });
+
+        match unnumbered_position {
+            Some(position) => {
+                debug!(
+                    "Adding {} as an unnumbered section",
+                    self.location(path).display()
+                );
+                remove_section_numbers(&mut link);
+                self.unnumbered_sections
+                    .push((position, SummaryItem::Link(link)));
+            }
+            None => items.push(SummaryItem::Link(link)),
+        }
+        Ok(())
+    }
+
+    /// The chapter of the markdown file, a draft if its sidecar file says so.
+    fn file_link(&mut self, entry: &Entry, number: SectionNumber) -> Result<Link, Error> {
+        let path = &entry.path;
+        Ok(Link {
+            name: self.chapter_name(path, Some(path), &entry.sidecar, &entry.name)?,
+            location: Some(self.location(path)).filter(|_| !entry.sidecar.draft),
+            nested_items: vec![],
+            number: Some(number),
         })
-        .enumerate()
-        .map(|(i, (entry, filename))| {
-            let mut section = section.cloned().unwrap_or_default();
-            section.push((i + 1) as u32);
-
-            let path = entry.path();
-            let (path_to_chapter_content, nested_items) = if entry.file_type().unwrap().is_file() {
-                (Some(path), vec![])
+    }
+
+    /// The name of the chapter for `path` (its chapter file or directory), asking the
+    /// [`ChapterNamer`] before using [`get_chapter_name`] with the chapter file and sidecar.
+    fn chapter_name(
+        &mut self,
+        path: &Path,
+        chapter_file: Option<&Path>,
+        sidecar: &Sidecar,
+        filename: &str,
+    ) -> Result<String, Error> {
+        if let Some(namer) = &self.hooks.namer {
+            if let Some(name) = namer.name(&self.location(path), filename) {
+                self.explain(path, || format!("Named \"{}\" by the ChapterNamer", name));
+                return Ok(name);
+            }
+        }
+        if self.explains(path) {
+            self.explain_name_sources(path, chapter_file, sidecar, filename)?;
+        }
+        let start = Instant::now();
+        let name = get_chapter_name(
+            chapter_file,
+            sidecar,
+            self.config,
+            &self.heads,
+            filename,
+            self.warnings,
+        );
+        self.stats.title_extraction += start.elapsed();
+        name
+    }
+
+    /// Record the name every one of `name_sources` has for the chapter of an explained file or
+    /// directory, see [`Generator::chapter_name`].
+    fn explain_name_sources(
+        &mut self,
+        path: &Path,
+        chapter_file: Option<&Path>,
+        sidecar: &Sidecar,
+        filename: &str,
+    ) -> Result<(), Error> {
+        let config = self.config;
+        let mut named = false;
+        for &source in &config.name_sources {
+            let title = title_from(
+                source,
+                chapter_file,
+                sidecar,
+                config,
+                &self.heads,
+                filename,
+                self.warnings,
+            )?;
+            let used = title.is_some() && !named;
+            let message = match title {
+                Some(title) if !used => format!(
+                    "Its {} has the name \"{}\", an earlier one of `name_sources` is used",
+                    source.name(),
+                    title
+                ),
+                Some(title) => format!(
+                    "Named \"{}\" after its {} (`name_sources`)",
+                    title,
+                    source.name()
+                ),
+                None if source == NameSource::Sidecar && !config.sidecar_files => {
+                    "Its sidecar is not read, `sidecar_files` is not set".to_owned()
+                }
+                None if source != NameSource::Sidecar && !config.get_chapter_name_from_file => {
+                    format!(
+                        "Its {} is not read, `get_chapter_name_from_file` is not set",
+                        source.name()
+                    )
+                }
+                None => format!("Its {} has no name", source.name()),
+            };
+            named |= used;
+            self.explain(path, || message);
+        }
+        if !named {
+            self.explain(path, || {
+                format!(
+                    "Named \"{}\" after its file name, none of `name_sources` has a name",
+                    filename
+                )
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns true if the directory or any of its subdirectories contains a markdown file.
+    fn contains_markdown(&mut self, dir_path: &Path) -> Result<bool, Error> {
+        self.stats.directories_read += 1;
+        let listing = get_markdown_files_and_directories(
+            dir_path,
+            None,
+            self.config,
+            self.hooks.fs(),
+            self.warnings,
+        )?;
+        if listing.entries.iter().any(|entry| !entry.is_dir) {
+            return Ok(true);
+        }
+        if let Some(error) = listing.errors.into_iter().next() {
+            return Err(error);
+        }
+        for entry in &listing.entries {
+            if self.contains_markdown(&entry.path)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// The markdown files and directories in the directory. With `incremental` they are taken
+    /// from the title cache if the directory did not change since, otherwise they are recorded in
+    /// it.
+    fn read_entries(&mut self, dir_path: &Path) -> Result<Listing, Error> {
+        let cache = self.heads.cache().filter(|_| self.config.incremental);
+        let stamp = match cache {
+            Some(_) => {
+                self.stats.metadata_queries += 1;
+                let metadata =
+                    self.hooks.fs().metadata(dir_path).with_context(|| {
+                        format!("Failed to read directory {}", dir_path.display())
+                    })?;
+                Some(Stamp::of(&metadata))
+            }
+            None => None,
+        };
+        if let (Some(cache), Some(stamp)) = (cache, &stamp) {
+            if let Some(entries) = cache.get_directory(dir_path, stamp) {
+                debug!("Using the cached entries of {}", self.describe(dir_path));
+                self.stats.cached_directories += 1;
+                cache.record_directory(dir_path, stamp.clone(), entries.to_vec());
+                return Ok(Listing {
+                    entries: entries
+                        .iter()
+                        .map(|entry| Entry::new(dir_path.join(&entry.name), entry.is_dir))
+                        .collect(),
+                    ..Listing::default()
+                });
+            }
+        }
+
+        let listing = self.prefetched.take_listing(dir_path);
+        if listing.is_none() {
+            self.stats.directories_read += 1;
+        }
+        let listing = get_markdown_files_and_directories(
+            dir_path,
+            listing,
+            self.config,
+            self.hooks.fs(),
+            self.warnings,
+        )?;
+        // Directories with failed entries are read again, so that the errors are reported
+        if let (Some(cache), Some(stamp), false, true) =
+            (cache, stamp, listing.warned, listing.errors.is_empty())
+        {
+            let entries = listing
+                .entries
+                .iter()
+                .map(|entry| CachedEntry {
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    is_dir: entry.is_dir,
+                })
+                .collect();
+            cache.record_directory(dir_path, stamp, entries);
+        }
+        Ok(listing)
+    }
+
+    /// The sidecar file of the markdown file or directory, or an empty one if it does not have one.
+    /// Sidecar files which can not be read are ignored with a warning.
+    fn read_sidecar(&mut self, path: &Path, is_dir: bool) -> Sidecar {
+        let sidecar_path = sidecar_path(path, is_dir);
+        match read_sidecar(self.hooks.fs(), &sidecar_path) {
+            Ok(sidecar) => sidecar.unwrap_or_default(),
+            Err(error) => {
+                self.warnings.warn_at(
+                    WarningKind::MalformedSidecars,
+                    &sidecar_path,
+                    format!(
+                        "Ignoring the sidecar file {}: {:#}",
+                        sidecar_path.display(),
+                        error
+                    ),
+                );
+                Sidecar::default()
+            }
+        }
+    }
+
+    /// Whether the decisions about the file or directory at `path` are recorded, see
+    /// [`GenerateSummary::explain`].
+    fn explains(&self, path: &Path) -> bool {
+        !self.warnings.explained().is_empty() && self.warnings.explains(&self.source_location(path))
+    }
+
+    /// Record a decision about the file or directory at `path` if it is explained, see
+    /// [`GenerateSummary::explain`]. The message is only formatted then.
+    fn explain(&mut self, path: &Path, message: impl FnOnce() -> String) {
+        if self.explains(path) {
+            let location = self.source_location(path);
+            self.warnings.explain(&location, message);
+        }
+    }
+
+    /// The path relative to the src directory for log messages.
+    fn describe(&self, path: &Path) -> String {
+        let location = self.source_location(path);
+        if location.as_os_str().is_empty() {
+            "the src directory".to_owned()
+        } else {
+            location.display().to_string()
+        }
+    }
+
+    /// The location of a chapter file in the summary, i.e. its path relative to the src directory.
+    /// Like in a hand-written SUMMARY.md, the location always uses forward slashes. Files converted
+    /// by a `converter` are at the location with the extension `.md`, which is remembered in the
+    /// [`Conversions`].
+    fn location(&self, path: &Path) -> PathBuf {
+        if self.config.converter(path).is_none() {
+            return self.source_location(path);
+        }
+        let location = self.source_location(&path.with_extension("md"));
+        self.conversions.add_location(location.clone(), path);
+        location
+    }
+
+    /// The path of the file or directory relative to the src directory, like
+    /// [`Generator::location`] but without converting the extension.
+    fn source_location(&self, path: &Path) -> PathBuf {
+        let Ok(relative_path) = path.strip_prefix(self.src_dir) else {
+            return self
+                .mounted_location(path)
+                .expect("Chapter files are always inside the src directory or a mounted one");
+        };
+        if std::path::MAIN_SEPARATOR == '/' {
+            // The entries are joined from the src directory, their paths are already normalized
+            return relative_path.to_path_buf();
+        }
+        with_forward_slashes(&relative_path.to_string_lossy(), std::path::MAIN_SEPARATOR)
+    }
+
+    /// Build the path to the file to be used as the directory's content.
+    /// If `self.create_missing_chapter_files` is true and the chapter file is missing create it,
+    /// unless `dry_run` is set.
+    /// If `self.ignore_missing_chapter_files` is true and the chapter file is missing return [`Option::None`].
+    ///
+    /// # Errors
+    /// If the content file is missing and both `self.create_missing_chapter_files` and `self.ignore_missing_chapter_files` are false.
+    fn get_path_to_directory_content(&mut self, path: &Path) -> Result<Option<PathBuf>, Error> {
+        let chapter_content = self.chapter_file(path);
+
+        if !self.heads.exists(&chapter_content, self.config) {
+            if self.create_missing_chapter_files && self.config.dry_run {
+                self.created_files.push(chapter_content);
+                return Ok(None);
+            } else if self.create_missing_chapter_files {
+                // Another instance (e.g. for a second renderer) might create the file at the same
+                // time, only one of them may write it. Use the same name as without the chapter
+                // file, so that the summary does not change once it exists
+                let content = created_chapter_file_content(path);
+                WriteGuard::new(self.config).check(&chapter_content)?;
+                match self
+                    .hooks
+                    .fs()
+                    .create_file(&chapter_content, content.as_bytes())
+                {
+                    Ok(()) => {
+                        self.warnings.record(
+                            "created-chapter-file",
+                            &chapter_content,
+                            "Created the missing chapter file",
+                        );
+                        self.created_files.push(chapter_content.clone());
+                    }
+                    Err(error) if error.kind() == ErrorKind::AlreadyExists => {}
+                    Err(error) => {
+                        return Err(error).with_context(|| {
+                            format!(
+                                "Failed to create missing chapter file {}",
+                                chapter_content.display()
+                            )
+                        })
+                    }
+                }
+            } else if self.ignore_missing_chapter_files {
+                debug!(
+                    "Ignoring the missing chapter file {}",
+                    self.location(&chapter_content).display()
+                );
+                return Ok(None);
             } else {
+                return Err(anyhow!(
+                    "Missing chapter file {}. Create it, or set `create_missing_chapter_files` or \
+                     `ignore_missing_chapter_files` to handle missing chapter files automatically",
+                    chapter_content.display()
+                ));
+            }
+        }
+        Ok(Some(chapter_content))
+    }
+
+    /// The chapter file of the directory at `path`. If there is no file with the configured
+    /// `chapter_file_name`, the other Unicode normalization form of it (NFC or NFD) is tried, so
+    /// that the path has the name the file has on disk.
+    fn chapter_file(&self, path: &Path) -> PathBuf {
+        let chapter_file = chapter_file_path(path, self.config);
+        let name = &*self.config.chapter_file_name_of(path);
+        if name.is_ascii() || self.heads.exists(&chapter_file, self.config) {
+            return chapter_file;
+        }
+        [name.nfc().collect::<String>(), name.nfd().collect()]
+            .into_iter()
+            .filter(|normalized| normalized != name)
+            .map(|normalized| path.join(format!("{}.md", normalized)))
+            .find(|normalized| self.heads.exists(normalized, self.config))
+            .unwrap_or(chapter_file)
+    }
+
+    /// Print all chapter files which were created at once.
+    fn report_created_files(&self) {
+        if self.created_files.is_empty() {
+            return;
+        }
+
+        let created_files: Vec<_> = self
+            .created_files
+            .iter()
+            .map(|path| format!("  {}", self.location(path).display()))
+            .collect();
+        let action = if self.config.dry_run {
+            "Dry run, not creating"
+        } else {
+            "Created"
+        };
+        log!(
+            self.config.info_level(),
+            "{} {} missing chapter files:\n{}",
+            action,
+            created_files.len(),
+            created_files.join("\n")
+        );
+    }
+
+    /// Write the src-relative paths of all created chapter files to the manifest, as a JSON array if
+    /// its extension is 'json', one per line otherwise. The manifest is written even if no files
+    /// were created.
+    fn write_created_files_manifest(&self, manifest_path: &Path) -> Result<(), Error> {
+        let created_files: Vec<_> = self
+            .created_files
+            .iter()
+            .map(|path| self.location(path).to_string_lossy().into_owned())
+            .collect();
+        let content = if manifest_path.extension() == Some(OsStr::new("json")) {
+            serde_json::to_string_pretty(&created_files)? + "\n"
+        } else {
+            created_files
+                .iter()
+                .map(|path| format!("{}\n", path))
+                .collect()
+        };
+        WriteGuard::new(self.config).check(manifest_path)?;
+        std::fs::write(manifest_path, content).with_context(|| {
+            format!(
+                "Failed to write the created files manifest {}",
+                manifest_path.display()
+            )
+        })
+    }
+
+    /// Order the entries of the directory with the [`ChapterSorter`], if there is one.
+    fn sort_entries(&mut self, dir_path: &Path, entries: &mut Vec<Entry>) -> Result<(), Error> {
+        let Some(sorter) = &self.hooks.sorter else {
+            return Ok(());
+        };
+
+        let mut sort_entries = Vec::with_capacity(entries.len());
+        for (index, entry) in entries.iter().enumerate() {
+            let path = &entry.path;
+            let (kind, chapter_file) = if entry.is_dir {
+                let chapter_file = self.chapter_file(path);
                 (
-                    get_path_to_directory_content(&path, config),
-                    generate_chapters(&path, Some(&section), config),
+                    EntryKind::Directory,
+                    Some(chapter_file)
+                        .filter(|chapter_file| self.heads.exists(chapter_file, self.config)),
                 )
+            } else {
+                (EntryKind::File, Some(path.clone()))
             };
+            // Errors reading the title are reported once the chapter is added
+            let start = Instant::now();
+            let title = read_title(
+                chapter_file.as_deref(),
+                &entry.sidecar,
+                self.config,
+                &self.heads,
+                &entry.name,
+                self.warnings,
+            )
+            .unwrap_or_default();
+            self.stats.title_extraction += start.elapsed();
+            sort_entries.push(SortEntry {
+                path: self.location(path),
+                kind,
+                title,
+                tags: entry.sidecar.tags.clone(),
+                index,
+            });
+        }
 
-            let link = Link {
-                name: get_chapter_name(&path_to_chapter_content, config, filename),
-                location: path_to_chapter_content,
-                nested_items,
-                number: Some(section),
-            };
-            SummaryItem::Link(link)
-        })
-        .collect()
-}
+        sorter.sort(&self.location(dir_path), &mut sort_entries)?;
+        let mut unsorted: Vec<_> = entries.drain(..).map(Some).collect();
+        entries.extend(
+            sort_entries
+                .iter()
+                .filter_map(|sort_entry| unsorted[sort_entry.index].take()),
+        );
+        Ok(())
+    }
 
-/// Build the path to the file to be used as the directory's content.
-/// If `config.create_missing_chapter_files` is true and the chapter file is missing create it.
-/// If `config.ignore_missing_chapter_files` is true and the chapter file is missing return [`Option::None`].
-///
-/// # Panics
-/// If the content file is missing and both `config.create_missing_chapter_files` and `config.ignore_missing_chapter_files` are false.
-fn get_path_to_directory_content(path: &Path, config: &Config) -> Option<PathBuf> {
-    let mut chapter_content = path.to_path_buf();
-    chapter_content.push(PathBuf::from_str(&format!("{}.md", config.chapter_file_name)).unwrap());
-
-    if !chapter_content.exists() {
-        if config.create_missing_chapter_files {
-            let mut file = File::create(&chapter_content).unwrap();
-            write!(file, "# {}.md", config.chapter_file_name).unwrap();
-        } else if config.ignore_missing_chapter_files {
-            return None;
+    /// Warn about entries of a directory whose names only differ in case, like `Setup.md` and
+    /// `setup.md` or a file and a directory. Case-insensitive file systems (the default ones of
+    /// macOS and Windows) can only keep one of them, so the summary would differ between
+    /// platforms. The names are compared, whatever the file system of the src directory is.
+    fn check_case_collisions(&mut self, entries: &[Entry]) {
+        let mut first_entries: HashMap<String, &Entry> = HashMap::new();
+        let mut collisions = vec![];
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            if let Some(first) = first_entries.insert(name, entry) {
+                collisions.push((
+                    first.path.clone(),
+                    first.is_dir,
+                    entry.path.clone(),
+                    entry.is_dir,
+                ));
+            }
+        }
+
+        let kind = |is_dir| if is_dir { "directory" } else { "file" };
+        for (first, first_is_dir, second, second_is_dir) in collisions {
+            self.warnings.warn_at(
+                WarningKind::CaseCollisions,
+                &second,
+                format!(
+                    "The {} {} and the {} {} only differ in case, case-insensitive file systems \
+                     (e.g. on macOS and Windows) can only keep one of them. Rename one of them",
+                    kind(first_is_dir),
+                    first.display(),
+                    kind(second_is_dir),
+                    second.display()
+                ),
+            );
+        }
+    }
+
+    /// Handle files `foo.md` next to a directory `foo/` according to
+    /// `config.file_directory_collision`, by removing one of the entries or by warning about them.
+    fn resolve_collisions(&mut self, entries: &mut Vec<Entry>) {
+        let directories: HashSet<&Path> = entries
+            .iter()
+            .filter(|entry| entry.is_dir)
+            .map(|entry| entry.path.as_path())
+            .collect();
+        if directories.is_empty() {
+            return;
+        }
+        let collisions: Vec<_> = entries
+            .iter()
+            .filter(|entry| !entry.is_dir)
+            .filter_map(|entry| {
+                let directory = entry.path.with_extension("");
+                directories
+                    .contains(directory.as_path())
+                    .then(|| (entry.path.clone(), directory))
+            })
+            .collect();
+
+        for (file, directory) in collisions {
+            match self.config.file_directory_collision {
+                FileDirectoryCollision::Warn => self.warnings.warn_at(
+                    WarningKind::FileDirectoryCollisions,
+                    &file,
+                    format!(
+                        "Both {} and {} exist. Set `file_directory_collision` to choose which \
+                         one to use",
+                        file.display(),
+                        directory.display()
+                    ),
+                ),
+                FileDirectoryCollision::KeepBoth => {}
+                FileDirectoryCollision::PreferDirectory => {
+                    debug!(
+                        "Skipping {} in favor of the directory {}",
+                        self.location(&file).display(),
+                        self.location(&directory).display()
+                    );
+                    entries.retain(|entry| entry.path != file);
+                    self.landing_pages.insert(directory, file);
+                }
+                FileDirectoryCollision::PreferFile => {
+                    debug!(
+                        "Skipping the directory {} in favor of {}",
+                        self.location(&directory).display(),
+                        self.location(&file).display()
+                    );
+                    entries.retain(|entry| entry.path != directory);
+                }
+            }
+        }
+    }
+
+    /// Record the error of the entry at `path`. Unless `config.on_error` is [`OnError::Fail`] it
+    /// is added to the failure report instead, with the outcome the caller chose for the entry.
+    fn handle_error(&mut self, path: &Path, outcome: Outcome, error: Error) {
+        if outcome == Outcome::Skipped {
+            self.exclude(path, "it was skipped after an error (`on_error`)");
+        }
+        if self.config.on_error == OnError::Fail {
+            self.errors.push(error);
         } else {
-            panic!("Missing chapter file: {:?}", chapter_content);
+            self.failures.failures.push(Failure {
+                path: path.to_owned(),
+                error,
+                outcome,
+            });
+        }
+    }
+
+    /// Returns a single error listing all errors which occurred so far (one per line), if there
+    /// were any.
+    /// Entries which were skipped or added as drafts are printed as a warning, they only fail the
+    /// summary if `config.fail_at_end` is true.
+    fn check_errors(&mut self) -> Result<(), Error> {
+        if !self.failures.is_empty() {
+            self.warnings
+                .warn(WarningKind::FailedEntries, self.failures.to_string());
+            for failure in &self.failures.failures {
+                let code = match failure.outcome {
+                    Outcome::Skipped => "skipped-entry",
+                    Outcome::Drafted => "drafted-entry",
+                };
+                self.warnings
+                    .record(code, &failure.path, format!("{:#}", failure.error));
+            }
+            if self.config.fail_at_end {
+                return Err(anyhow!(
+                    "Failed to generate the summary, `fail_at_end` is set and {} entries failed",
+                    self.failures.failures.len()
+                ));
+            }
+        }
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+
+        let errors: Vec<_> = self
+            .errors
+            .drain(..)
+            .map(|error| format!("{:#}", error))
+            .collect();
+        Err(anyhow!(
+            "Failed to generate the summary:\n{}",
+            errors.join("\n")
+        ))
+    }
+
+    /// Returns the unnumbered sections, ordered like `config.unnumbered_directories`. Sections
+    /// marked in their front matter follow in the order they were found.
+    fn into_unnumbered_chapters(mut self) -> Vec<SummaryItem> {
+        self.unnumbered_sections
+            .sort_by_key(|(position, _)| *position);
+        self.unnumbered_sections
+            .into_iter()
+            .map(|(_, item)| item)
+            .collect()
+    }
+
+    /// The `stub_behavior` if the chapter file is a stub, see [`Heads::is_stub`].
+    fn stub_behavior(&self, path: &Path) -> Result<Option<StubBehavior>, Error> {
+        if !self.heads.is_stub(path, self.config)? {
+            return Ok(None);
+        }
+        debug!("{} is a stub", self.location(path).display());
+        Ok(Some(self.config.stub_behavior))
+    }
+
+    /// A directory is a leaf if it is listed in `config.leaf_directories` or if its chapter file
+    /// sets `summary_leaf: true` in its front matter. Leaf directories do not get nested chapters.
+    fn is_leaf_directory(
+        &self,
+        path: &Path,
+        path_to_directory_content: &Option<PathBuf>,
+    ) -> Result<bool, Error> {
+        let relative_path = self.location(path);
+        if self.config.leaf_directories.contains(&relative_path) {
+            return Ok(true);
+        }
+
+        match path_to_directory_content {
+            Some(content) => Ok(read_front_matter(content, self.config, &self.heads)?
+                .front_matter
+                .get_bool("summary_leaf")),
+            None => Ok(false),
+        }
+    }
+
+    /// If the directory is listed in `config.unnumbered_directories` returns its index in the
+    /// list. If its chapter file sets `summary_unnumbered: true` in its front matter returns
+    /// [`usize::MAX`], so that it is sorted after the configured ones.
+    /// Returns [`Option::None`] for numbered directories.
+    fn unnumbered_position(
+        &self,
+        path: &Path,
+        path_to_directory_content: &Option<PathBuf>,
+    ) -> Result<Option<usize>, Error> {
+        let relative_path = self.location(path);
+        if let Some(position) = self
+            .config
+            .unnumbered_directories
+            .iter()
+            .position(|dir| *dir == relative_path)
+        {
+            return Ok(Some(position));
+        }
+
+        match path_to_directory_content {
+            Some(content)
+                if read_front_matter(content, self.config, &self.heads)?
+                    .front_matter
+                    .get_bool("summary_unnumbered") =>
+            {
+                Ok(Some(usize::MAX))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Number the numbered `items` within `section` by their position, and their nested items as well.
+fn renumber(items: &mut [SummaryItem], section: Option<&SectionNumber>) {
+    let mut index = 0;
+    for item in items {
+        if let SummaryItem::Link(link) = item {
+            if link.number.is_some() {
+                index += 1;
+                let number = child_section(section, index);
+                renumber(&mut link.nested_items, Some(&number));
+                link.number = Some(number);
+            }
+        }
+    }
+}
+
+/// Add `offset` to the top level component of the section numbers of the items and all of their
+/// nested items, see `first_section_number`.
+fn offset_section_numbers(items: &mut [SummaryItem], offset: usize) {
+    if offset == 0 {
+        return;
+    }
+    for item in items {
+        if let SummaryItem::Link(link) = item {
+            if let Some(first) = link.number.as_mut().and_then(|number| number.0.first_mut()) {
+                *first += offset as u32;
+            }
+            offset_section_numbers(&mut link.nested_items, offset);
+        }
+    }
+}
+
+/// Remove the section numbers of the link and all of its nested items.
+fn remove_section_numbers(link: &mut Link) {
+    link.number = None;
+    for item in &mut link.nested_items {
+        if let SummaryItem::Link(nested_link) = item {
+            remove_section_numbers(nested_link);
         }
     }
-    Some(chapter_content)
+}
+
+/// The relative path with its components separated by forward slashes instead of `separator`, the
+/// one of the platform. Forward slashes are kept, as Windows accepts both.
+fn with_forward_slashes(relative_path: &str, separator: char) -> PathBuf {
+    let components: Vec<_> = relative_path
+        .split([separator, '/'])
+        .filter(|component| !component.is_empty())
+        .collect();
+    PathBuf::from(components.join("/"))
+}
+
+/// Whether the file names are the same once both are normalized to NFC. macOS stores names
+/// decomposed (NFD), while they are usually composed (NFC) in book.toml, e.g. for an `ü`.
+fn is_same_name(name: &str, other: &str) -> bool {
+    name == other || name.nfc().eq(other.nfc())
+}
+
+/// The path of the chapter file of the directory, which might not exist.
+fn chapter_file_path(path: &Path, config: &Config) -> PathBuf {
+    path.join(format!("{}.md", config.chapter_file_name_of(path)))
+}
+
+/// The content of the chapter file created for the directory at `path`, a heading with the name of
+/// the directory.
+pub(crate) fn created_chapter_file_content(path: &Path) -> String {
+    let directory_name = path.file_name().unwrap_or_default().to_string_lossy();
+    format!("# {}\n", directory_name)
+}
+
+/// Describe the chapter added for an explained file or directory, see [`Generator::explain`].
+fn describe_added(link: &Link) -> String {
+    let kind = match link.location {
+        Some(_) => "chapter",
+        None => "draft chapter",
+    };
+    match &link.number {
+        Some(number) => format!("Added as the {} {} \"{}\"", kind, number, link.name),
+        None => format!("Added as the {} \"{}\"", kind, link.name),
+    }
+}
+
+/// The number of the `index`th chapter (counting from 1) within `section`, allocated at its final
+/// size right away.
+fn child_section(section: Option<&SectionNumber>, index: usize) -> SectionNumber {
+    let parent = section.map_or(&[][..], |section| &section.0);
+    let mut number = Vec::with_capacity(parent.len() + 1);
+    number.extend_from_slice(parent);
+    number.push(index as u32);
+    SectionNumber(number)
+}
+
+/// Read the head of the file at `path` for its front matter.
+/// Its front matter is empty if the file does not start with `---`.
+fn read_front_matter(path: &Path, config: &Config, heads: &Heads) -> Result<Rc<Head>, Error> {
+    heads.get(path, config)
+}
+
+/// A markdown file or directory in a directory of the book, with everything needed about it
+/// queried once.
+struct Entry {
+    path: PathBuf,
+    /// The file name within the book, which differs from the one of `path` for mounted
+    /// directories.
+    file_name: OsString,
+    /// The file name without the extension, the default chapter name.
+    name: String,
+    is_dir: bool,
+    /// Its sidecar file, only read with `sidecar_files`.
+    sidecar: Sidecar,
+    /// Replaces the name of its chapter, e.g. the `title` of a mount.
+    title: Option<String>,
+}
+
+impl Entry {
+    fn new(path: PathBuf, is_dir: bool) -> Self {
+        let file_name = path
+            .file_name()
+            .expect("Entries of a directory always have a file name")
+            .to_owned();
+        Self::named(path, file_name, is_dir)
+    }
+
+    /// The entry for `path`, which has the `file_name` within the book.
+    fn named(path: PathBuf, file_name: OsString, is_dir: bool) -> Self {
+        let name = Path::new(&file_name)
+            .file_stem()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        Self {
+            path,
+            file_name,
+            name,
+            is_dir,
+            sidecar: Sidecar::default(),
+            title: None,
+        }
+    }
+
+    fn file_name(&self) -> &OsStr {
+        &self.file_name
+    }
+}
+
+/// The markdown files and directories in a directory, see
+/// [`get_markdown_files_and_directories`].
+#[derive(Default)]
+struct Listing {
+    entries: Vec<Entry>,
+    /// The errors of single entries, in the order they occurred.
+    errors: Vec<Error>,
+    /// True if an entry was skipped with a warning, which would be lost if the listing was cached.
+    warned: bool,
 }
 
 /// Get all markdown files and directories in the specified directory. Ignore all other files.
-fn get_markdown_files_and_directories(dir_path: &PathBuf) -> Vec<std::fs::DirEntry> {
-    std::fs::read_dir(dir_path)
-        .unwrap()
-        .map(|entry| entry.unwrap())
-        .filter(|entry| {
-            let file_type = entry.file_type().unwrap();
-
-            if file_type.is_file() {
-                let path = entry.path();
-                let extension = path.extension();
-                // Only use .md files
-                extension.is_some() && extension.unwrap() == OsStr::new("md")
-            } else {
-                // or directories
-                file_type.is_dir()
+/// Symbolic links are only used with `follow_symlinks`, as what they point to.
+/// Entries whose name is not valid UTF-8 are skipped with a warning, as mdBook can not handle them.
+/// Fails if the directory can not be read. Errors of single entries are returned next to the
+/// entries. The directory is only read if its prefetched `listing` is not given.
+fn get_markdown_files_and_directories(
+    dir_path: &Path,
+    listing: Option<Vec<io::Result<FsEntry>>>,
+    config: &Config,
+    fs: &dyn SummaryFs,
+    warnings: &mut Warnings,
+) -> Result<Listing, Error> {
+    let read_error = || format!("Failed to read directory {}", dir_path.display());
+    let listing = match listing {
+        Some(listing) => listing,
+        None => fs.read_dir(dir_path).with_context(read_error)?,
+    };
+
+    let mut result = Listing {
+        entries: Vec::with_capacity(listing.len()),
+        ..Listing::default()
+    };
+    for entry in listing {
+        let entry = entry.with_context(read_error);
+        let (path, file_type) = match entry.and_then(file_type) {
+            Ok(entry) => entry,
+            Err(error) => {
+                result.errors.push(error);
+                continue;
             }
-        })
-        .collect()
+        };
+        if path.file_name().and_then(OsStr::to_str).is_none() {
+            warnings.warn_at(
+                WarningKind::NonUtf8Names,
+                &path,
+                format!("Skipping {}, its name is not valid UTF-8", path.display()),
+            );
+            result.warned = true;
+            continue;
+        }
+        let file_type = match file_type {
+            FileKind::Other if config.follow_symlinks => match fs.metadata(&path) {
+                Ok(metadata) => metadata.kind,
+                Err(error) => {
+                    debug!(
+                        "Skipping {}, it can not be followed: {}",
+                        path.display(),
+                        error
+                    );
+                    continue;
+                }
+            },
+            file_type => file_type,
+        };
+
+        let keep = match file_type {
+            // Only use .md files and the ones converted to markdown
+            FileKind::File => {
+                path.extension() == Some(OsStr::new("md")) || config.converter(&path).is_some()
+            }
+            // or directories
+            FileKind::Directory => true,
+            FileKind::Other => false,
+        };
+        if keep {
+            result
+                .entries
+                .push(Entry::new(path, file_type == FileKind::Directory));
+        } else {
+            debug!(
+                "Skipping {}, it is not a markdown file or directory",
+                path.display()
+            );
+        }
+    }
+    Ok(result)
 }
 
-/// If the chapter file exists, `config.get_chapter_name_from_file` is true and the first line of the file looks like '# <header>' use header as the chapter name.
-/// Otherwise return the filename.
-fn get_chapter_name(path: &Option<PathBuf>, config: &Config, filename: String) -> String {
-    match path {
-        Some(ref path) if config.get_chapter_name_from_file => {
-            let file = File::open(path).unwrap();
-            let mut reader = BufReader::new(file);
+/// Get the path and file type of the entry, adding its path to the error.
+fn file_type(entry: FsEntry) -> Result<(PathBuf, FileKind), Error> {
+    let path = entry.path;
+    let file_type = entry
+        .kind
+        .with_context(|| format!("Failed to get the file type of {}", path.display()))?;
+    Ok((path, file_type))
+}
 
-            let mut first_line = String::new();
-            reader.read_line(&mut first_line).unwrap();
+/// The title of the chapter from the first of `config.name_sources` which has one, see
+/// [`read_title`]. Otherwise return the filename.
+fn get_chapter_name(
+    path: Option<&Path>,
+    sidecar: &Sidecar,
+    config: &Config,
+    heads: &Heads,
+    filename: &str,
+    warnings: &mut Warnings,
+) -> Result<String, Error> {
+    Ok(
+        read_title(path, sidecar, config, heads, filename, warnings)?
+            .unwrap_or_else(|| filename.to_owned()),
+    )
+}
 
-            first_line
-                .strip_prefix("# ")
-                .map_or(filename, str::to_owned)
+/// Read the title of the chapter from the first of `config.name_sources` which has one. The
+/// chapter file at `path` is only read if `config.get_chapter_name_from_file` is true: its title is
+/// the first line of the file if it looks like '# <title>', a front matter block at the start of
+/// the file is skipped.
+/// Only the first `config.max_title_bytes` of the file are read, if the title is not complete within
+/// them or can not be decoded, there is no title. `filename` is only used for the warning.
+fn read_title(
+    path: Option<&Path>,
+    sidecar: &Sidecar,
+    config: &Config,
+    heads: &Heads,
+    filename: &str,
+    warnings: &mut Warnings,
+) -> Result<Option<String>, Error> {
+    for &source in &config.name_sources {
+        let title = title_from(source, path, sidecar, config, heads, filename, warnings)?;
+        if title.is_some() {
+            return Ok(title);
         }
-        _ => filename,
+    }
+    Ok(None)
+}
+
+/// The title of the chapter from `source`, see [`read_title`].
+fn title_from(
+    source: NameSource,
+    path: Option<&Path>,
+    sidecar: &Sidecar,
+    config: &Config,
+    heads: &Heads,
+    filename: &str,
+    warnings: &mut Warnings,
+) -> Result<Option<String>, Error> {
+    Ok(match (source, path) {
+        (NameSource::Sidecar, _) => sidecar.title.clone(),
+        (NameSource::FrontMatter, Some(path)) if config.get_chapter_name_from_file => heads
+            .get(path, config)?
+            .front_matter
+            .get("title")
+            .map(str::to_owned),
+        (NameSource::Heading, Some(path)) if config.get_chapter_name_from_file => {
+            let head = heads.get(path, config)?;
+            if head.not_utf8 {
+                warnings.warn_at(
+                    WarningKind::NonUtf8ChapterFiles,
+                    path,
+                    format!(
+                        "The title of {} can not be read, as it is binary or not valid \
+                         UTF-8 (see `fallback_encoding`), using \"{}\" as its chapter name",
+                        path.display(),
+                        filename
+                    ),
+                );
+            }
+            match &head.title {
+                Some(title)
+                    if config.sort == SortOrder::HeadingNumber && !config.keep_heading_numbers =>
+                {
+                    let name = split_heading_number(title).map_or(title.as_str(), |(_, name)| name);
+                    Some(name.to_owned())
+                }
+                title => title.clone(),
+            }
+        }
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{chapters, generate, names, TempDir};
+
+    #[test]
+    fn names_the_path_of_failed_entries() -> Result<(), Error> {
+        let fs = MemoryFs::new()
+            .with_file("/src/guide/setup.md", "# Setup\n")
+            .with_file("/src/intro.md", "# Introduction\n")
+            .with_error(
+                "/src/intro.md",
+                FsOperation::ReadPrefix,
+                ErrorKind::PermissionDenied,
+            );
+        let config = Config::builder().get_chapter_name_from_file(true).build()?;
+        let (summary, _) = generate(fs, Path::new("/src"), &config);
+        assert_eq!(
+            format!("{:#}", summary.unwrap_err()),
+            "Failed to generate the summary:\n\
+             Missing chapter file /src/guide/README.md. Create it, or set \
+             `create_missing_chapter_files` or `ignore_missing_chapter_files` to handle missing \
+             chapter files automatically\n\
+             Failed to read chapter file /src/intro.md: ReadPrefix failed for /src/intro.md"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reports_all_missing_chapter_files_at_once() {
+        let fs = MemoryFs::new()
+            .with_file("/src/api/usage.md", "")
+            .with_file("/src/guide/README.md", "")
+            .with_file("/src/guide/advanced/tuning.md", "")
+            .with_file("/src/tools/cli.md", "");
+        let (summary, _) = generate(fs, Path::new("/src"), &Config::default());
+        let error = format!("{:#}", summary.unwrap_err());
+        let missing: Vec<_> = error
+            .lines()
+            .filter_map(|line| line.strip_prefix("Missing chapter file "))
+            .filter_map(|line| line.split_once(". "))
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(
+            missing,
+            [
+                "/src/api/README.md",
+                "/src/guide/advanced/README.md",
+                "/src/tools/README.md"
+            ]
+        );
+    }
+
+    /// Set the modification time of the file or directory at `path` to `seconds` ago. The title
+    /// cache does not trust anything modified right before a run.
+    fn set_age(path: &Path, seconds: u64) -> Result<(), Error> {
+        let modified = std::time::SystemTime::now() - std::time::Duration::from_secs(seconds);
+        std::fs::File::open(path)?.set_modified(modified)?;
+        Ok(())
+    }
+
+    #[test]
+    fn skips_everything_below_leaf_directories() -> Result<(), Error> {
+        let fs = MemoryFs::new()
+            .with_file("/src/blog/README.md", "# Blog\n")
+            .with_file("/src/blog/2023/README.md", "# 2023\n")
+            .with_file("/src/blog/2023/01/README.md", "# January\n")
+            .with_file("/src/blog/2023/01/new-year.md", "# New year\n")
+            .with_file(
+                "/src/news/README.md",
+                "---\nsummary_leaf: true\n---\n# News\n",
+            )
+            .with_file("/src/news/archive/README.md", "# Archive\n")
+            .with_file("/src/news/archive/old/post.md", "# Post\n")
+            .with_file("/src/guide/README.md", "# Guide\n")
+            .with_file("/src/guide/setup.md", "# Setup\n");
+        let config = Config::builder()
+            .leaf_directories(["blog"])
+            .get_chapter_name_from_file(true)
+            .build()?;
+        let (summary, diagnostics) = generate(fs, Path::new("/src"), &config);
+        assert_eq!(
+            chapters(&summary?.numbered_chapters),
+            [
+                "1. Blog (blog/README.md)",
+                "2. Guide (guide/README.md)",
+                "2.1. Setup (guide/setup.md)",
+                "3. News (news/README.md)",
+            ]
+        );
+        assert!(diagnostics.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_file_directory_collisions() -> Result<(), Error> {
+        use FileDirectoryCollision::*;
+
+        let both = [
+            "1. intro (intro.md)",
+            "2. topic (draft)",
+            "2.1. details (topic/details.md)",
+            "3. topic (topic.md)",
+            "4. zebra (zebra.md)",
+        ];
+        let directory = [
+            "1. intro (intro.md)",
+            "2. topic (topic.md)",
+            "2.1. details (topic/details.md)",
+            "3. zebra (zebra.md)",
+        ];
+        let directory_with_readme = [
+            "1. intro (intro.md)",
+            "2. topic (topic/README.md)",
+            "2.1. details (topic/details.md)",
+            "3. zebra (zebra.md)",
+        ];
+        let file = [
+            "1. intro (intro.md)",
+            "2. topic (topic.md)",
+            "3. zebra (zebra.md)",
+        ];
+        for (collision, readme, expected, warned) in [
+            (Warn, false, both.as_slice(), true),
+            (KeepBoth, false, &both, false),
+            // The file is the chapter file of the directory, unless it has one
+            (PreferDirectory, false, &directory, false),
+            (PreferDirectory, true, &directory_with_readme, false),
+            (PreferFile, false, &file, false),
+        ] {
+            let mut fs = MemoryFs::new()
+                .with_file("/src/intro.md", "")
+                .with_file("/src/topic.md", "")
+                .with_file("/src/topic/details.md", "")
+                .with_file("/src/zebra.md", "");
+            if readme {
+                fs = fs.with_file("/src/topic/README.md", "");
+            }
+            let config = Config::builder()
+                .file_directory_collision(collision)
+                .ignore_missing_chapter_files(true)
+                .build()?;
+            let (summary, diagnostics) = generate(fs, Path::new("/src"), &config);
+            assert_eq!(chapters(&summary?.numbered_chapters), expected);
+            let warnings: Vec<_> = diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.message.as_str())
+                .collect();
+            if warned {
+                assert_eq!(
+                    warnings,
+                    ["Both /src/topic.md and /src/topic exist. Set `file_directory_collision` to \
+                      choose which one to use"]
+                );
+            } else {
+                assert!(warnings.is_empty(), "{:?}", warnings);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reports_failed_entries_with_on_error() -> Result<(), Error> {
+        let fs = || {
+            MemoryFs::new()
+                .with_file("/src/intro.md", "# Introduction\n")
+                .with_file("/src/broken.md", "# Broken\n")
+                .with_file("/src/guide/setup.md", "# Setup\n")
+                .with_file("/src/secret/README.md", "# Secret\n")
+                .with_error(
+                    "/src/broken.md",
+                    FsOperation::ReadPrefix,
+                    ErrorKind::PermissionDenied,
+                )
+                .with_error(
+                    "/src/secret",
+                    FsOperation::ReadDir,
+                    ErrorKind::PermissionDenied,
+                )
+        };
+        let skipped = ["1. Introduction (intro.md)", "2. Secret (secret/README.md)"];
+        // The directory which can not be read is still skipped, its chapter file is used
+        let drafted = [
+            "1. broken (draft)",
+            "2. guide (draft)",
+            "2.1. Setup (guide/setup.md)",
+            "3. Introduction (intro.md)",
+            "4. Secret (secret/README.md)",
+        ];
+        for (on_error, expected, report, code) in [
+            (
+                OnError::Skip,
+                skipped.as_slice(),
+                "Failed entries: 3 (skipped: 3, drafted: 0)",
+                "skipped-entry",
+            ),
+            (
+                OnError::Draft,
+                &drafted,
+                "Failed entries: 3 (skipped: 1, drafted: 2)",
+                "drafted-entry",
+            ),
+        ] {
+            let config = Config::builder()
+                .get_chapter_name_from_file(true)
+                .on_error(on_error)
+                .build()?;
+            let (summary, diagnostics) = generate(fs(), Path::new("/src"), &config);
+            assert_eq!(chapters(&summary?.numbered_chapters), expected);
+            let [failed, entries @ ..] = diagnostics.as_slice() else {
+                panic!("Expected the failed entries to be reported");
+            };
+            assert_eq!(failed.code, "failed-entries");
+            assert!(failed.message.starts_with(report), "{}", failed.message);
+            let entries: Vec<_> = entries
+                .iter()
+                .map(|diagnostic| (diagnostic.code, diagnostic.path.as_deref().unwrap()))
+                .collect();
+            assert_eq!(
+                entries,
+                [
+                    (code, "broken.md"),
+                    (code, "guide"),
+                    ("skipped-entry", "secret")
+                ]
+            );
+
+            let config = Config::builder()
+                .on_error(on_error)
+                .fail_at_end(true)
+                .build()?;
+            let (summary, _) = generate(fs(), Path::new("/src"), &config);
+            assert_eq!(
+                summary.unwrap_err().to_string(),
+                "Failed to generate the summary, `fail_at_end` is set and 2 entries failed"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn fails_on_warnings_unless_allowed() -> Result<(), Error> {
+        let generate = |config: &Config| {
+            let fs = MemoryFs::new()
+                .with_file("/src/Guide.md", "")
+                .with_file("/src/guide.md", "")
+                .with_file("/src/topic.md", "")
+                .with_file("/src/topic/details.md", "");
+            GenerateSummary::new()
+                .with_fs(Box::new(fs))
+                .generate_summary(Path::new("/src"), config)
+                .map(|summary| names(&summary.numbered_chapters).len())
+                .map_err(|error| format!("{:#}", error))
+        };
+        let builder = || Config::builder().ignore_missing_chapter_files(true);
+
+        // Only warnings by default
+        assert_eq!(generate(&builder().build()?), Ok(4));
+
+        let error = generate(&builder().fail_on_warnings(true).build()?).unwrap_err();
+        let lines: Vec<_> = error.lines().collect();
+        let [message, case, collision] = lines.as_slice() else {
+            panic!("Expected both warnings to be listed, got {}", error);
+        };
+        assert!(message.starts_with("Failed because of 2 warnings and `fail_on_warnings` is set"));
+        assert!(case.starts_with("[case-collisions] The file /src/Guide.md and the file"));
+        assert!(collision.starts_with("[file-directory-collisions] Both /src/topic.md"));
+
+        let config = builder()
+            .fail_on_warnings(true)
+            .allow([
+                WarningKind::CaseCollisions,
+                WarningKind::FileDirectoryCollisions,
+            ])
+            .build()?;
+        assert_eq!(generate(&config), Ok(4));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_names_which_are_not_utf8() -> Result<(), Error> {
+        use std::os::unix::ffi::OsStrExt;
+
+        // "café" and "résumé" in Latin-1
+        let dir = TempDir::new("non-utf8");
+        dir.write("intro.md", "# Introduction\n");
+        dir.write(OsStr::from_bytes(b"caf\xe9.md"), "# Café\n");
+        dir.write(
+            Path::new(OsStr::from_bytes(b"r\xe9sum\xe9")).join("README.md"),
+            "# Résumé\n",
+        );
+
+        let (summary, diagnostics) = generate(StdFs, dir.path(), &Config::default());
+        assert_eq!(names(&summary?.numbered_chapters), ["intro"]);
+        // Skipped with a warning showing the names with replacement characters
+        let mut skipped: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.path.as_deref().unwrap()))
+            .collect();
+        skipped.sort();
+        assert_eq!(
+            skipped,
+            [
+                ("non-utf8-names", "caf\u{fffd}.md"),
+                ("non-utf8-names", "r\u{fffd}sum\u{fffd}")
+            ]
+        );
+        let message = format!(
+            "Skipping {}, its name is not valid UTF-8",
+            dir.path().join("caf\u{fffd}.md").display()
+        );
+        assert!(diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.message == message));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn handles_unreadable_directories() -> Result<(), Error> {
+        use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+
+        let dir = TempDir::new("unreadable");
+        dir.write("intro.md", "# Introduction\n");
+        dir.write("secret/README.md", "# Secret\n");
+        dir.write("secret/plans.md", "# Plans\n");
+        // Its entries can not be listed, but its chapter file can still be read
+        let secret = dir.path().join("secret");
+        std::fs::set_permissions(&secret, Permissions::from_mode(0o300))?;
+        let readable = std::fs::read_dir(&secret).is_ok();
+        let generate = |config: &Config| {
+            let (summary, diagnostics) = generate(StdFs, dir.path(), config);
+            (summary.map_err(|error| format!("{:#}", error)), diagnostics)
+        };
+        let failed = generate(&Config::default());
+        let skipped = generate(&Config::builder().on_error(OnError::Skip).build()?);
+        std::fs::set_permissions(&secret, Permissions::from_mode(0o755))?;
+        if readable {
+            // Permissions are not enforced, e.g. for root
+            return Ok(());
+        }
+
+        let error = format!("Failed to read directory {}", secret.display());
+        assert!(failed.0.unwrap_err().contains(&error));
+        let (summary, diagnostics) = skipped;
+        let summary = summary.unwrap();
+        assert_eq!(names(&summary.numbered_chapters), ["intro", "secret"]);
+        let SummaryItem::Link(secret_chapter) = &summary.numbered_chapters[1] else {
+            panic!("Expected the chapter of the directory");
+        };
+        assert!(secret_chapter.nested_items.is_empty());
+        let failure = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.code == "failed-entries")
+            .expect("The failed entries are reported");
+        assert!(failure.message.contains(&error));
+        Ok(())
+    }
+
+    #[test]
+    fn uses_forward_slashes_in_locations() {
+        for (path, location) in [
+            ("README.md", "README.md"),
+            ("guide\\README.md", "guide/README.md"),
+            ("guide\\advanced\\tuning.md", "guide/advanced/tuning.md"),
+            ("guide/advanced\\tuning.md", "guide/advanced/tuning.md"),
+        ] {
+            assert_eq!(with_forward_slashes(path, '\\'), Path::new(location));
+        }
+        // The paths of other platforms are unchanged
+        assert_eq!(
+            with_forward_slashes("guide/advanced/tuning.md", '/'),
+            Path::new("guide/advanced/tuning.md")
+        );
+    }
+
+    /// A [`MemoryFs`] recording every operation and the path it was done on.
+    struct RecordingFs {
+        fs: MemoryFs,
+        operations: std::sync::Arc<std::sync::Mutex<Vec<(FsOperation, PathBuf)>>>,
+    }
+
+    impl RecordingFs {
+        fn record(&self, operation: FsOperation, path: &Path) {
+            self.operations
+                .lock()
+                .unwrap()
+                .push((operation, path.to_owned()));
+        }
+    }
+
+    impl SummaryFs for RecordingFs {
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<FsEntry>>> {
+            self.record(FsOperation::ReadDir, path);
+            self.fs.read_dir(path)
+        }
+
+        fn read_prefix(&self, path: &Path, max_bytes: usize) -> io::Result<Vec<u8>> {
+            self.record(FsOperation::ReadPrefix, path);
+            self.fs.read_prefix(path, max_bytes)
+        }
+
+        fn create_file(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+            self.record(FsOperation::CreateFile, path);
+            self.fs.create_file(path, content)
+        }
+
+        fn metadata(&self, path: &Path) -> io::Result<FsMetadata> {
+            self.record(FsOperation::Metadata, path);
+            self.fs.metadata(path)
+        }
+
+        fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+            self.fs.canonicalize(path)
+        }
+    }
+
+    #[test]
+    fn queries_the_file_system_once_per_entry() -> Result<(), Error> {
+        let operations = std::sync::Arc::default();
+        let fs = RecordingFs {
+            fs: MemoryFs::new()
+                .with_file("/src/intro.md", "# Introduction\n")
+                .with_file("/src/guide/README.md", "# Guide\n")
+                .with_file("/src/guide/setup.md", "# Setup\n")
+                .with_file("/src/guide/advanced/README.md", "# Advanced\n")
+                .with_file("/src/guide/advanced/tuning.md", "# Tuning\n")
+                .with_file("/src/notes.txt", ""),
+            operations: std::sync::Arc::clone(&operations),
+        };
+        let generator = GenerateSummary::new().with_fs(Box::new(fs));
+        // The validation checks the chapter files again after the traversal
+        let config = Config::builder()
+            .get_chapter_name_from_file(true)
+            .validate(Validation::Off)
+            .build()?;
+        let mut warnings = Warnings::new(vec![], false);
+        let (summary, _, stats, ..) = build_summary(
+            Path::new("/src"),
+            &config,
+            &generator.hooks,
+            false,
+            None,
+            None,
+            &mut warnings,
+        )?;
+        assert_eq!(
+            chapters(&summary.numbered_chapters),
+            [
+                "1. Guide (guide/README.md)",
+                "1.1. Advanced (guide/advanced/README.md)",
+                "1.1.1. Tuning (guide/advanced/tuning.md)",
+                "1.2. Setup (guide/setup.md)",
+                "2. Introduction (intro.md)",
+            ]
+        );
+
+        let operations = operations.lock().unwrap();
+        let count = |operation| {
+            operations
+                .iter()
+                .filter(|(done, _)| *done == operation)
+                .count()
+        };
+        assert_eq!(count(FsOperation::ReadDir), stats.directories_read);
+        assert_eq!(count(FsOperation::Metadata), stats.metadata_queries);
+        assert_eq!(count(FsOperation::ReadPrefix), stats.files_read);
+        // The only metadata queries look for a book.toml of a nested book in the subdirectories
+        assert_eq!(
+            (
+                stats.directories_read,
+                stats.files_read,
+                stats.metadata_queries
+            ),
+            (3, 5, 2)
+        );
+        // Nothing is done twice on the same path, e.g. checking that a file exists before reading it
+        let unique: HashSet<_> = operations.iter().collect();
+        assert_eq!(unique.len(), operations.len());
+        Ok(())
+    }
+
+    #[test]
+    fn creates_missing_chapter_files_concurrently() -> Result<(), Error> {
+        for attempt in 0..20 {
+            let dir = TempDir::new("concurrent-creation");
+            for directory in ["guide", "guide/advanced", "reference"] {
+                dir.write(format!("{}/page-{}.md", directory, attempt), "# Page\n");
+            }
+            let generate = || {
+                let config = Config::builder()
+                    .create_missing_chapter_files(CreateMissingChapterFiles::Always)
+                    .build()?;
+                generate_summary(dir.path(), &config)
+            };
+            let (first, second) = std::thread::scope(|scope| {
+                let first = scope.spawn(generate);
+                let second = scope.spawn(generate);
+                (first.join().unwrap(), second.join().unwrap())
+            });
+
+            // Both succeed with the same summary, and every file has the complete content
+            assert_eq!(first?, second?);
+            for directory in ["guide", "guide/advanced", "reference"] {
+                let path = dir.path().join(directory);
+                let content = std::fs::read_to_string(path.join("README.md"))?;
+                assert_eq!(content, created_chapter_file_content(&path));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reads_only_the_start_of_huge_files_for_their_title() -> Result<(), Error> {
+        let dir = TempDir::new("huge-files");
+        // 1 GiB without a newline, sparse after the first bytes
+        let huge = dir.write("huge.md", &format!("# {}", "a".repeat(10000)));
+        std::fs::File::options()
+            .write(true)
+            .open(&huge)?
+            .set_len(1 << 30)?;
+        dir.write("intro.md", "# Introduction\n");
+        std::fs::write(dir.path().join("binary.md"), [0xff, 0xfe, 0, 1, 2, 3])?;
+
+        let config = Config::builder().get_chapter_name_from_file(true).build()?;
+        let (summary, diagnostics) = generate(StdFs, dir.path(), &config);
+        assert_eq!(
+            names(&summary?.numbered_chapters),
+            ["binary", "huge", "Introduction"]
+        );
+        // Only the binary file is warned about, the title of the huge one is just not complete
+        let warned: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.path.as_deref().unwrap()))
+            .collect();
+        assert_eq!(warned, [("non-utf8-chapter-files", "binary.md")]);
+        Ok(())
+    }
+
+    #[test]
+    fn generates_the_same_summary_with_any_number_of_threads() -> Result<(), Error> {
+        let dir = TempDir::new("threads");
+        for section in 0..20 {
+            for topic in 0..10 {
+                let topic_dir = format!("section-{}/topic-{}", section, topic);
+                if topic % 3 != 0 {
+                    dir.write(
+                        format!("{}/README.md", topic_dir),
+                        &format!("# Topic {}\n", topic),
+                    );
+                }
+                for page in 0..10 {
+                    let title = format!("---\ntitle: Page {}\n---\n# {}\n", page, 10 - page);
+                    dir.write(format!("{}/page-{}.md", topic_dir, page), &title);
+                }
+            }
+        }
+
+        let generate = |threads| {
+            let config = Config::builder()
+                .get_chapter_name_from_file(true)
+                .name_sources([NameSource::FrontMatter, NameSource::Heading])
+                .ignore_missing_chapter_files(true)
+                .threads(threads)
+                .build()?;
+            GenerateSummary::new().generate_summary_with_stats(dir.path(), &config)
+        };
+        let (serial, stats) = generate(1)?;
+        assert_eq!(stats.chapters, 20 + 20 * 10 + 20 * 10 * 10);
+        for threads in [0, 2, 8] {
+            let (parallel, _) = generate(threads)?;
+            assert_eq!(parallel, serial, "{} threads", threads);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reads_no_unchanged_files_with_a_warm_title_cache() -> Result<(), Error> {
+        let dir = TempDir::new("title-cache");
+        let src_dir = dir.path().join("src");
+        let write = |location: &str, content: &str| set_age(&dir.write(location, content), 3600);
+        write("src/intro.md", "# Introduction\n")?;
+        write("src/guide/README.md", "# Guide\n")?;
+        write("src/guide/setup.md", "# Setup\n")?;
+        write("src/guide/usage.md", "# Usage\n")?;
+        let cache = dir.path().join(".generate-summary-cache.json");
+        let config = Config::builder().get_chapter_name_from_file(true).build()?;
+        let run = || -> Result<(Vec<String>, Stats), Error> {
+            let mut warnings = Warnings::new(vec![], false);
+            let (summary, _, stats, ..) = build_summary(
+                &src_dir,
+                &config,
+                &Hooks::default(),
+                false,
+                None,
+                Some(&cache),
+                &mut warnings,
+            )?;
+            Ok((chapters(&summary.numbered_chapters), stats))
+        };
+
+        let (cold, stats) = run()?;
+        assert_eq!((stats.files_read, stats.cached_titles), (4, 0));
+        let (warm, stats) = run()?;
+        assert_eq!(warm, cold);
+        assert_eq!((stats.files_read, stats.cached_titles), (0, 4));
+
+        // Only the changed file is read again
+        write("src/guide/setup.md", "# Installation\n")?;
+        let (changed, stats) = run()?;
+        assert!(changed.contains(&"1.1. Installation (guide/setup.md)".to_owned()));
+        assert_eq!((stats.files_read, stats.cached_titles), (1, 3));
+
+        // A broken cache is discarded
+        std::fs::write(&cache, "{ not json")?;
+        let (rebuilt, stats) = run()?;
+        assert_eq!(rebuilt, changed);
+        assert_eq!((stats.files_read, stats.cached_titles), (4, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn rescans_only_changed_directories_with_incremental() -> Result<(), Error> {
+        let dir = TempDir::new("incremental");
+        let src_dir = dir.path().join("src");
+        dir.write("src/intro.md", "# Introduction\n");
+        dir.write("src/guide/README.md", "# Guide\n");
+        dir.write("src/guide/setup.md", "# Setup\n");
+        dir.write("src/reference/README.md", "# Reference\n");
+        dir.write("src/reference/cli.md", "# CLI\n");
+        dir.write("src/reference/api.md", "# API\n");
+        for directory in ["src", "src/guide", "src/reference"] {
+            set_age(&dir.path().join(directory), 3600)?;
+        }
+        let cache = dir.path().join(".generate-summary-cache.json");
+        let builder = || Config::builder().title_cache(&cache).incremental(true);
+        let run = |config: &Config| -> Result<(Vec<String>, Stats), Error> {
+            let mut warnings = Warnings::new(vec![], false);
+            let (summary, _, stats, ..) = build_summary(
+                &src_dir,
+                config,
+                &Hooks::default(),
+                false,
+                None,
+                Some(&cache),
+                &mut warnings,
+            )?;
+            Ok((chapters(&summary.numbered_chapters), stats))
+        };
+        let config = builder().build()?;
+        let counts = |stats: &Stats| (stats.directories_read, stats.cached_directories);
+
+        let (cold, stats) = run(&config)?;
+        assert_eq!(counts(&stats), (3, 0));
+        let (warm, stats) = run(&config)?;
+        assert_eq!(warm, cold);
+        assert_eq!(counts(&stats), (0, 3));
+
+        // Every change is given a different age, the size of a directory may stay the same
+        dir.write("src/guide/usage.md", "# Usage\n");
+        set_age(&dir.path().join("src/guide"), 3000)?;
+        let (added, stats) = run(&config)?;
+        assert_eq!(
+            added,
+            [
+                "1. guide (guide/README.md)",
+                "1.1. setup (guide/setup.md)",
+                "1.2. usage (guide/usage.md)",
+                "2. intro (intro.md)",
+                "3. reference (reference/README.md)",
+                "3.1. api (reference/api.md)",
+                "3.2. cli (reference/cli.md)",
+            ]
+        );
+        assert_eq!(counts(&stats), (1, 2));
+
+        std::fs::remove_file(dir.path().join("src/reference/api.md"))?;
+        set_age(&dir.path().join("src/reference"), 2400)?;
+        let (removed, stats) = run(&config)?;
+        assert_eq!(
+            removed,
+            [
+                "1. guide (guide/README.md)",
+                "1.1. setup (guide/setup.md)",
+                "1.2. usage (guide/usage.md)",
+                "2. intro (intro.md)",
+                "3. reference (reference/README.md)",
+                "3.1. cli (reference/cli.md)",
+            ]
+        );
+        assert_eq!(counts(&stats), (1, 2));
+
+        std::fs::rename(
+            dir.path().join("src/guide/setup.md"),
+            dir.path().join("src/guide/installation.md"),
+        )?;
+        set_age(&dir.path().join("src/guide"), 1800)?;
+        let (renamed, stats) = run(&config)?;
+        assert_eq!(
+            renamed,
+            [
+                "1. guide (guide/README.md)",
+                "1.1. installation (guide/installation.md)",
+                "1.2. usage (guide/usage.md)",
+                "2. intro (intro.md)",
+                "3. reference (reference/README.md)",
+                "3.1. cli (reference/cli.md)",
+            ]
+        );
+        assert_eq!(counts(&stats), (1, 2));
+
+        // The result equals the one of a run without the cache
+        let (full, stats) = run(&builder().full_scan(true).build()?)?;
+        assert_eq!(full, renamed);
+        assert_eq!(counts(&stats), (3, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn generates_very_deep_trees() -> Result<(), Error> {
+        // 1000 levels of directories named "d", each with a chapter file
+        let dir = TempDir::new("deep-tree");
+        let mut location = PathBuf::new();
+        for _ in 0..1000 {
+            location.push("d");
+            dir.write(location.join("README.md"), "");
+        }
+
+        let summary = generate_summary(dir.path(), &Config::default())?;
+        let mut items = &summary.numbered_chapters;
+        let mut location = PathBuf::new();
+        for depth in 1..=1000 {
+            let [SummaryItem::Link(link)] = items.as_slice() else {
+                panic!("Expected a single chapter at depth {}", depth);
+            };
+            location.push("d");
+            assert_eq!(link.location, Some(location.join("README.md")));
+            assert_eq!(
+                link.number.as_ref().map(|number| number.0.clone()),
+                Some(vec![1; depth])
+            );
+            items = &link.nested_items;
+        }
+        assert!(items.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn matches_chapter_file_names_in_both_normalization_forms() -> Result<(), Error> {
+        let nfc: String = "übersicht".nfc().collect();
+        let nfd: String = "übersicht".nfd().collect();
+        assert_ne!(nfc, nfd);
+
+        for (configured, on_disk) in [(&nfc, &nfd), (&nfd, &nfc), (&nfd, &nfd)] {
+            let fs = MemoryFs::new()
+                .with_file(format!("/src/guide/{}.md", on_disk), "# Guide\n")
+                .with_file("/src/guide/setup.md", "# Setup\n");
+            let config = Config::builder()
+                .chapter_file_name(configured.as_str())
+                .build()?;
+            let summary = GenerateSummary::new()
+                .with_fs(Box::new(fs))
+                .generate_summary(Path::new("/src"), &config)?;
+
+            // The chapter file is not a chapter of its own, and is linked with its name on disk
+            let [SummaryItem::Link(guide)] = summary.numbered_chapters.as_slice() else {
+                panic!("Expected only the chapter of the directory");
+            };
+            let location = PathBuf::from(format!("guide/{}.md", on_disk));
+            assert_eq!(guide.location, Some(location));
+            assert_eq!(names(&guide.nested_items), ["setup"]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn adds_planned_chapters_as_drafts() -> Result<(), Error> {
+        let fs = MemoryFs::new()
+            .with_file("/src/intro.md", "")
+            .with_file("/src/guide/README.md", "")
+            .with_file("/src/guide/setup.md", "");
+        let config = Config::builder()
+            .planned("", [PlannedChapter::new("Roadmap")])
+            .planned(
+                "guide",
+                [
+                    PlannedChapter::new("Basics").at(1),
+                    PlannedChapter::new("setup"),
+                ],
+            )
+            .build()?;
+        let (summary, _) = generate(fs, Path::new("/src"), &config);
+        // At their position or after the existing chapters, unless a chapter with the title exists
+        assert_eq!(
+            chapters(&summary?.numbered_chapters),
+            [
+                "1. guide (guide/README.md)",
+                "1.1. Basics (draft)",
+                "1.2. setup (guide/setup.md)",
+                "2. intro (intro.md)",
+                "3. Roadmap (draft)",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn descends_into_nested_books_only_if_configured() -> Result<(), Error> {
+        let fs = || {
+            MemoryFs::new()
+                .with_file("/src/intro.md", "")
+                .with_file("/src/embedded/book.toml", "[book]\n")
+                .with_file("/src/embedded/src/SUMMARY.md", "- [Inner](inner.md)\n")
+                .with_file("/src/embedded/src/inner.md", "")
+                // Only a book.toml file makes a nested book
+                .with_file("/src/notes/book.toml/README.md", "")
+        };
+
+        let config = Config::builder()
+            .ignore_missing_chapter_files(true)
+            .build()?;
+        let (summary, _) = generate(fs(), Path::new("/src"), &config);
+        assert_eq!(
+            chapters(&summary?.numbered_chapters),
+            [
+                "1. intro (intro.md)",
+                "2. notes (draft)",
+                "2.1. book (notes/book.toml/README.md)",
+            ]
+        );
+
+        let config = Config::builder()
+            .descend_into_nested_books(true)
+            .ignore_missing_chapter_files(true)
+            .build()?;
+        let (summary, _) = generate(fs(), Path::new("/src"), &config);
+        // Like any other directory
+        assert_eq!(
+            chapters(&summary?.numbered_chapters),
+            [
+                "1. embedded (draft)",
+                "1.1. src (draft)",
+                "1.1.1. SUMMARY (embedded/src/SUMMARY.md)",
+                "1.1.2. inner (embedded/src/inner.md)",
+                "2. intro (intro.md)",
+                "3. notes (draft)",
+                "3.1. book (notes/book.toml/README.md)",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn drafts_or_skips_stub_chapters() -> Result<(), Error> {
+        let fs = || {
+            MemoryFs::new()
+                .with_file(
+                    "/src/intro.md",
+                    "# Introduction\nWhat this book is about.\n",
+                )
+                .with_file("/src/usage.md", "---\nauthor: someone\n---\n# Usage\nTBD\n")
+                .with_file("/src/guide/README.md", "# Guide\n")
+                .with_file("/src/guide/setup.md", "# Setup\nInstall it with cargo.\n")
+        };
+        // `usage.md` only has "TBD" and `guide/README.md` only its heading
+        let config = Config::builder().stub_threshold_words(2).build()?;
+        let (summary, _) = generate(fs(), Path::new("/src"), &config);
+        assert_eq!(
+            chapters(&summary?.numbered_chapters),
+            [
+                "1. guide (draft)",
+                "1.1. setup (guide/setup.md)",
+                "2. intro (intro.md)",
+                "3. usage (draft)",
+            ]
+        );
+
+        // Skipping the stub of a directory keeps its nested chapters
+        let config = Config::builder()
+            .stub_threshold_words(2)
+            .stub_behavior(StubBehavior::Skip)
+            .build()?;
+        let (summary, _) = generate(fs(), Path::new("/src"), &config);
+        assert_eq!(
+            chapters(&summary?.numbered_chapters),
+            [
+                "1. guide (draft)",
+                "1.1. setup (guide/setup.md)",
+                "2. intro (intro.md)",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn follows_symbolic_links_only_if_configured() -> Result<(), Error> {
+        let fs = || {
+            MemoryFs::new()
+                .with_file("/src/shared.md", "# Shared\n")
+                .with_file("/src/guide/README.md", "# Guide\n")
+                .with_symlink("/src/guide/shared.md", "../shared.md")
+                .with_symlink("/src/usage/shared.md", "/src/shared.md")
+                .with_file("/src/usage/README.md", "# Usage\n")
+                .with_file("/src/manual/README.md", "# Manual\n")
+                .with_file("/src/manual/install.md", "# Install\n")
+                .with_symlink("/src/manual-latest", "manual")
+        };
+        let (summary, _) = generate(fs(), Path::new("/src"), &Config::default());
+        assert_eq!(
+            chapters(&summary?.numbered_chapters),
+            [
+                "1. guide (guide/README.md)",
+                "2. manual (manual/README.md)",
+                "2.1. install (manual/install.md)",
+                "3. shared (shared.md)",
+                "4. usage (usage/README.md)",
+            ]
+        );
+
+        // The first path of a file in the order of the traversal wins, so the directory linked
+        // beside manual/ is left out as a whole
+        let config = Config::builder().follow_symlinks(true).build()?;
+        let (summary, _) = generate(fs(), Path::new("/src"), &config);
+        assert_eq!(
+            chapters(&summary?.numbered_chapters),
+            [
+                "1. guide (guide/README.md)",
+                "1.1. shared (guide/shared.md)",
+                "2. manual (manual/README.md)",
+                "2.1. install (manual/install.md)",
+                "3. usage (usage/README.md)",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn replaces_dir_in_the_chapter_file_name() -> Result<(), Error> {
+        let fs = MemoryFs::new()
+            .with_file("/src/src-index.md", "# Not a chapter\n")
+            .with_file("/src/intro.md", "# Introduction\n")
+            .with_file("/src/networking/networking-index.md", "# Networking\n")
+            .with_file("/src/networking/dns.md", "# DNS\n")
+            .with_file("/src/storage/disks.md", "# Disks\n");
+        let config = Config::builder()
+            .chapter_file_name("{dir}-index")
+            .create_missing_chapter_files(CreateMissingChapterFiles::Always)
+            .build()?;
+        let (summary, diagnostics) = generate(fs, Path::new("/src"), &config);
+        // The chapter file of the src directory is left out like src/README.md by default
+        assert_eq!(
+            chapters(&summary?.numbered_chapters),
+            [
+                "1. intro (intro.md)",
+                "2. networking (networking/networking-index.md)",
+                "2.1. dns (networking/dns.md)",
+                "3. storage (storage/storage-index.md)",
+                "3.1. disks (storage/disks.md)",
+            ]
+        );
+        let created: Vec<_> = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.code == "created-chapter-file")
+            .filter_map(|diagnostic| diagnostic.path.as_deref())
+            .collect();
+        assert_eq!(created, ["storage/storage-index.md"]);
+        Ok(())
     }
 }
@@ -0,0 +1,252 @@
+use std::{
+    collections::HashSet,
+    path::{Component, Path, PathBuf},
+};
+
+use mdbook::{book::Book, BookItem};
+
+use crate::{
+    summary_fs::SummaryFs,
+    warnings::{WarningKind, Warnings},
+    Excluded,
+};
+
+/// Warn about the relative links to markdown files in the chapters of the book, whose target is
+/// not a chapter of the book, see `check_links`. The warning explains why the target is missing,
+/// with the reason it was `excluded` from the book if there is one.
+pub(crate) fn check_links(
+    book: &Book,
+    src_dir: &Path,
+    excluded: &Excluded,
+    fs: &dyn SummaryFs,
+    warnings: &mut Warnings,
+) {
+    let chapters: HashSet<&Path> = book
+        .iter()
+        .filter_map(|item| match item {
+            BookItem::Chapter(chapter) => chapter.path.as_deref(),
+            _ => None,
+        })
+        .collect();
+    for item in book.iter() {
+        let BookItem::Chapter(chapter) = item else {
+            continue;
+        };
+        let Some(path) = &chapter.path else {
+            continue;
+        };
+        let directory = path.parent().unwrap_or(Path::new(""));
+        for (line, target) in markdown_links(&chapter.content) {
+            let Some(location) = resolve(directory, target) else {
+                continue;
+            };
+            if chapters.contains(location.as_path()) {
+                continue;
+            }
+            let reason = location
+                .ancestors()
+                .find_map(|ancestor| excluded.get(ancestor));
+            let problem = match reason {
+                Some(reason) => format!("which is not part of the book, {}", reason),
+                None if fs.metadata(&src_dir.join(&location)).is_ok() => {
+                    "which is not part of the book".to_owned()
+                }
+                None => "which does not exist".to_owned(),
+            };
+            warnings.warn_at(
+                WarningKind::BrokenLinks,
+                &src_dir.join(path),
+                format!(
+                    "{}:{} links to {}, {}",
+                    path.display(),
+                    line,
+                    target,
+                    problem
+                ),
+            );
+        }
+    }
+}
+
+/// The destinations of the inline links and link reference definitions in the markdown, with the
+/// number of their line (counting from 1). Links in fenced code blocks are left out.
+fn markdown_links(content: &str) -> Vec<(usize, &str)> {
+    let mut links = vec![];
+    let mut fence = None;
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if let Some(marker) = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker))
+        {
+            fence = Some(marker);
+            continue;
+        }
+
+        // A reference definition like `[setup]: setup.md`
+        if let Some((_, destination)) = trimmed
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once("]:"))
+        {
+            links.push((index + 1, link_destination(destination)));
+            continue;
+        }
+        let mut rest = line;
+        while let Some(start) = rest.find("](") {
+            rest = &rest[start + 2..];
+            links.push((index + 1, link_destination(rest)));
+        }
+    }
+    links
+}
+
+/// The destination at the start of the text, which may be enclosed in angle brackets.
+fn link_destination(text: &str) -> &str {
+    let text = text.trim_start();
+    match text.strip_prefix('<') {
+        Some(rest) => rest.split('>').next().unwrap_or(rest),
+        None => text
+            .split(|c: char| c.is_whitespace() || c == ')')
+            .next()
+            .unwrap_or(text),
+    }
+}
+
+/// The location within the src directory of the markdown file the `target` of a link in
+/// `directory` points to. Returns [`Option::None`] for anything but relative links to markdown
+/// files inside of the src directory, e.g. for URLs and links to images.
+fn resolve(directory: &Path, target: &str) -> Option<PathBuf> {
+    if target.contains(':') || target.starts_with('/') {
+        return None;
+    }
+    let file = percent_decode(target.split(['#', '?']).next()?);
+    if !file.ends_with(".md") {
+        return None;
+    }
+    let mut location = PathBuf::new();
+    for component in directory.join(file).components() {
+        match component {
+            Component::Normal(name) => location.push(name),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !location.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(location)
+}
+
+/// Decode the percent-encoded bytes of a link target, e.g. `%20` for a space. Invalid sequences
+/// are kept as they are.
+fn percent_decode(target: &str) -> String {
+    let bytes = target.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = bytes
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match byte {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Error;
+
+    use super::*;
+    use crate::{ChapterFilter, EntryKind, GenerateSummary, TestBook};
+
+    #[test]
+    fn warns_about_links_to_chapters_which_are_not_part_of_the_book() -> Result<(), Error> {
+        struct SkipInternal;
+
+        impl ChapterFilter for SkipInternal {
+            fn include(&self, path: &Path, _kind: EntryKind) -> bool {
+                !path.starts_with("internal")
+            }
+        }
+
+        let setup = "# Setup\n\
+                     \n\
+                     See the [guide](README.md#start), the [secrets](../internal/secret.md)\n\
+                     and the [FAQ](https://example.com/faq.md).\n\
+                     \n\
+                     ~~~markdown\n\
+                     [Example](example.md)\n\
+                     ~~~\n\
+                     \n\
+                     [removed]: ../removed.md\n";
+        let error = TestBook::new()
+            .file("internal/secret.md", "# Secret\n")
+            .file("guide/README.md", "# Guide\n")
+            .file("guide/setup.md", setup)
+            .config(toml::toml! {
+                check_links = true
+                fail_on_warnings = true
+            })
+            .generator(GenerateSummary::new().with_filter(Box::new(SkipInternal)))
+            .run()
+            .unwrap_err();
+        let error = format!("{:#}", error);
+        assert!(error.contains(
+            "guide/setup.md:3 links to ../internal/secret.md, which is not part of the book, it \
+             is excluded by the chapter filter"
+        ));
+        assert!(error.contains("guide/setup.md:10 links to ../removed.md, which does not exist"));
+        assert!(!error.contains("README.md") && !error.contains("faq.md"));
+        assert!(!error.contains("example.md"));
+        Ok(())
+    }
+
+    #[test]
+    fn finds_the_links_outside_of_code_blocks() {
+        let content = "[A](a.md) and [B](<b c.md> \"Title\")\n\
+                       ```\n\
+                       [C](c.md)\n\
+                       ```\n\
+                       [d]: d.md\n";
+        assert_eq!(
+            markdown_links(content),
+            [(1, "a.md"), (1, "b c.md"), (5, "d.md")]
+        );
+    }
+
+    #[test]
+    fn resolves_only_markdown_files_inside_of_the_src_directory() {
+        let resolve = |target| resolve(Path::new("guide"), target);
+        assert_eq!(resolve("setup.md#start"), Some("guide/setup.md".into()));
+        assert_eq!(
+            resolve("../C%23%20interop.md"),
+            Some("C# interop.md".into())
+        );
+        assert_eq!(
+            resolve("./advanced/../setup.md?x"),
+            Some("guide/setup.md".into())
+        );
+        assert_eq!(resolve("../../outside.md"), None);
+        assert_eq!(resolve("/absolute.md"), None);
+        assert_eq!(resolve("https://example.com/faq.md"), None);
+        assert_eq!(resolve("image.png"), None);
+    }
+}
@@ -0,0 +1,622 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Error};
+use log::debug;
+use mdbook::book::{Book, BookItem, Chapter, Link, SectionNumber, Summary, SummaryItem};
+
+use crate::{
+    config::{ChapterPosition, Config},
+    convert::Conversions,
+    markdown::{breadcrumbs, summary_to_toc},
+};
+
+/// The location of the table of contents chapter, see `generate_toc_chapter`.
+const TOC_LOCATION: &str = "toc.md";
+
+/// Replaced by the breadcrumbs of the chapter, see `inject_breadcrumbs`.
+const BREADCRUMBS_MARKER: &str = "<!-- breadcrumbs -->";
+
+/// Build the book of the summary, like mdBook does for a SUMMARY.md, without going through
+/// [`mdbook::MDBook`].
+/// Chapters which are already part of the incoming `book` (matched by their source path) keep its
+/// content, so that the changes of preprocessors running before this one are kept. Only the other
+/// chapters are read from the src directory, except for those inside of the `redirects`, the
+/// locations of external chapters and mounts with the file or directory outside of it, and the
+/// files converted to markdown by a `converter`, whose markdown is used.
+pub(crate) fn load_book(
+    summary: &Summary,
+    src_dir: &Path,
+    book: &Book,
+    redirects: &[(PathBuf, PathBuf)],
+    conversions: &Conversions,
+    config: &Config,
+) -> Result<Book, Error> {
+    let contents: HashMap<_, _> = book
+        .iter()
+        .filter_map(|item| match item {
+            BookItem::Chapter(chapter) => chapter
+                .source_path
+                .as_deref()
+                .map(|source_path| (source_path, chapter.content.as_str())),
+            _ => None,
+        })
+        .collect();
+    let loader = Loader {
+        src_dir,
+        contents,
+        redirects,
+        conversions,
+        config,
+    };
+
+    let mut generated_book = Book::new();
+    for item in summary
+        .prefix_chapters
+        .iter()
+        .chain(&summary.numbered_chapters)
+        .chain(&summary.suffix_chapters)
+    {
+        generated_book.push_item(loader.load_item(item, &[])?);
+    }
+    Ok(generated_book)
+}
+
+struct Loader<'a> {
+    src_dir: &'a Path,
+    /// The content of the chapters of the incoming book by their source path.
+    contents: HashMap<&'a Path, &'a str>,
+    /// The files and directories outside of the src directory by their location.
+    redirects: &'a [(PathBuf, PathBuf)],
+    conversions: &'a Conversions,
+    config: &'a Config,
+}
+
+impl Loader<'_> {
+    fn load_item(&self, item: &SummaryItem, parent_names: &[String]) -> Result<BookItem, Error> {
+        Ok(match item {
+            SummaryItem::Link(link) => BookItem::Chapter(self.load_chapter(link, parent_names)?),
+            SummaryItem::Separator => BookItem::Separator,
+            SummaryItem::PartTitle(title) => BookItem::PartTitle(title.clone()),
+        })
+    }
+
+    fn load_chapter(&self, link: &Link, parent_names: &[String]) -> Result<Chapter, Error> {
+        let mut chapter = match &link.location {
+            Some(location) => {
+                let converted = self.conversions.markdown_at(location, self.config);
+                // The incoming book only has a placeholder for external and mounted chapters
+                let content = match (
+                    converted,
+                    self.redirect(location),
+                    self.contents.get(location.as_path()),
+                ) {
+                    (Some(markdown), ..) => markdown?.to_string(),
+                    (None, Some(path), _) => read_chapter_file(&path)?,
+                    (None, None, Some(content)) => content.to_string(),
+                    (None, None, None) => read_chapter_file(&self.src_dir.join(location))?,
+                };
+                Chapter::new(&link.name, content, location, parent_names.to_vec())
+            }
+            None => Chapter::new_draft(&link.name, parent_names.to_vec()),
+        };
+        chapter.number = link.number.clone();
+
+        let mut nested_parent_names = parent_names.to_vec();
+        nested_parent_names.push(link.name.clone());
+        chapter.sub_items = link
+            .nested_items
+            .iter()
+            .map(|item| self.load_item(item, &nested_parent_names))
+            .collect::<Result<_, _>>()?;
+        Ok(chapter)
+    }
+
+    /// The file outside of the src directory for the `location`, if it is redirected.
+    fn redirect(&self, location: &Path) -> Option<PathBuf> {
+        self.redirects.iter().find_map(|(prefix, path)| {
+            let relative_path = location.strip_prefix(prefix).ok()?;
+            Some(if relative_path.as_os_str().is_empty() {
+                path.clone()
+            } else {
+                path.join(relative_path)
+            })
+        })
+    }
+}
+
+fn read_chapter_file(path: &Path) -> Result<String, Error> {
+    debug!("Loading {}", path.display());
+    let mut content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read chapter file {}", path.display()))?;
+    // Like mdBook, remove the byte order mark
+    if content.starts_with('\u{feff}') {
+        content.drain(..'\u{feff}'.len_utf8());
+    }
+    Ok(content)
+}
+
+/// Add the table of contents of the `summary` as a chapter at `toc_position`, see
+/// `generate_toc_chapter`. It only exists in the book, not in the src directory. Fails if a
+/// chapter of the book has its location.
+pub(crate) fn add_toc_chapter(
+    book: &mut Book,
+    summary: &Summary,
+    config: &Config,
+) -> Result<(), Error> {
+    let location = Path::new(TOC_LOCATION);
+    let taken = book.iter().any(
+        |item| matches!(item, BookItem::Chapter(chapter) if chapter.path.as_deref() == Some(location)),
+    );
+    if taken {
+        return Err(anyhow!(
+            "Failed to add the table of contents chapter, the book already has a chapter {}",
+            TOC_LOCATION
+        ));
+    }
+
+    let content = summary_to_toc(summary, &config.toc_title, location);
+    let mut chapter = Chapter::new(&config.toc_title, content, location, vec![]);
+    // There is no file of the chapter
+    chapter.source_path = None;
+    debug!("Adding the table of contents as {}", TOC_LOCATION);
+    match config.toc_position {
+        ChapterPosition::Prefix => book.sections.insert(0, BookItem::Chapter(chapter)),
+        ChapterPosition::Numbered => {
+            let (index, count) = numbered_end(book);
+            chapter.number = Some(SectionNumber(vec![
+                (count + config.first_section_number) as u32,
+            ]));
+            book.sections.insert(index, BookItem::Chapter(chapter));
+        }
+        ChapterPosition::Suffix => {
+            book.push_item(chapter);
+        }
+    }
+    Ok(())
+}
+
+/// The index after the last numbered chapter of the book, before the suffix chapters, and the
+/// number of numbered chapters at the top level.
+fn numbered_end(book: &Book) -> (usize, usize) {
+    let is_numbered =
+        |item: &BookItem| matches!(item, BookItem::Chapter(chapter) if chapter.number.is_some());
+    let count = book
+        .sections
+        .iter()
+        .filter(|item| is_numbered(item))
+        .count();
+    let index = book
+        .sections
+        .iter()
+        .rposition(is_numbered)
+        .map_or(book.sections.len(), |index| index + 1);
+    (index, count)
+}
+
+/// Add the chapters of the incoming `book` which preprocessors running before this one added
+/// without a file, see `drop_foreign_chapters`. They keep their name, content and nested chapters,
+/// and are added to the end of the chapter which was their parent if it is part of the `generated`
+/// book, otherwise after the numbered chapters, or at the end of the book if they are not
+/// numbered. With `foreign_chapters_part` they are added to that part instead.
+pub(crate) fn add_foreign_chapters(generated: &mut Book, book: &Book, config: &Config) {
+    let locations: HashSet<&Path> = generated
+        .iter()
+        .filter_map(|item| match item {
+            BookItem::Chapter(chapter) => chapter.path.as_deref(),
+            _ => None,
+        })
+        .collect();
+    let mut foreign = vec![];
+    collect_foreign_chapters(&book.sections, None, &locations, &mut foreign);
+    if foreign.is_empty() {
+        return;
+    }
+
+    if let Some(title) = &config.foreign_chapters_part {
+        let (mut index, count) = numbered_end(generated);
+        generated
+            .sections
+            .insert(index, BookItem::PartTitle(title.clone()));
+        for (number, (_, mut chapter)) in (count + config.first_section_number..).zip(foreign) {
+            debug!(
+                "Keeping the chapter {} of another preprocessor in the part {}",
+                chapter.name, title
+            );
+            move_chapter(
+                &mut chapter,
+                Some(SectionNumber(vec![number as u32])),
+                vec![],
+            );
+            index += 1;
+            generated.sections.insert(index, BookItem::Chapter(chapter));
+        }
+        return;
+    }
+    for (parent, mut chapter) in foreign {
+        debug!(
+            "Keeping the chapter {} of another preprocessor",
+            chapter.name
+        );
+        let parent = parent.and_then(|parent| find_chapter_mut(&mut generated.sections, &parent));
+        match parent {
+            Some(parent) => {
+                let count = parent
+                    .sub_items
+                    .iter()
+                    .filter(|item| matches!(item, BookItem::Chapter(_)))
+                    .count();
+                let number = parent.number.clone().map(|SectionNumber(mut number)| {
+                    number.push(count as u32 + 1);
+                    SectionNumber(number)
+                });
+                let mut parent_names = parent.parent_names.clone();
+                parent_names.push(parent.name.clone());
+                move_chapter(&mut chapter, number, parent_names);
+                parent.sub_items.push(BookItem::Chapter(chapter));
+            }
+            None if chapter.number.is_some() => {
+                let (index, count) = numbered_end(generated);
+                let number = SectionNumber(vec![(count + config.first_section_number) as u32]);
+                move_chapter(&mut chapter, Some(number), vec![]);
+                generated.sections.insert(index, BookItem::Chapter(chapter));
+            }
+            None => {
+                move_chapter(&mut chapter, None, vec![]);
+                generated.push_item(chapter);
+            }
+        }
+    }
+}
+
+/// Collect the chapters of `items` without a file, which are not at one of the `locations` of
+/// the generated book, with the location of their `parent` chapter. Their nested chapters stay
+/// with them.
+/// The location of a chapter with a file is its source path, as mdBook's `index` preprocessor
+/// changes the path of README.md chapters to index.md.
+fn collect_foreign_chapters(
+    items: &[BookItem],
+    parent: Option<&Path>,
+    locations: &HashSet<&Path>,
+    foreign: &mut Vec<(Option<PathBuf>, Chapter)>,
+) {
+    for item in items {
+        let BookItem::Chapter(chapter) = item else {
+            continue;
+        };
+        match &chapter.path {
+            Some(path) if chapter.source_path.is_none() && !locations.contains(path.as_path()) => {
+                foreign.push((parent.map(Path::to_owned), chapter.clone()));
+            }
+            _ => collect_foreign_chapters(
+                &chapter.sub_items,
+                chapter.source_path.as_deref().or(chapter.path.as_deref()),
+                locations,
+                foreign,
+            ),
+        }
+    }
+}
+
+/// The chapter at the location among the items and their nested items.
+fn find_chapter_mut<'a>(items: &'a mut [BookItem], location: &Path) -> Option<&'a mut Chapter> {
+    for item in items {
+        let BookItem::Chapter(chapter) = item else {
+            continue;
+        };
+        if chapter.path.as_deref() == Some(location) {
+            return Some(chapter);
+        }
+        if let Some(nested) = find_chapter_mut(&mut chapter.sub_items, location) {
+            return Some(nested);
+        }
+    }
+    None
+}
+
+/// Give a chapter which is moved to another place its new number and parent names, and update
+/// those of its nested chapters.
+fn move_chapter(chapter: &mut Chapter, number: Option<SectionNumber>, parent_names: Vec<String>) {
+    let mut nested_parent_names = parent_names.clone();
+    nested_parent_names.push(chapter.name.clone());
+    chapter.number = number;
+    chapter.parent_names = parent_names;
+    let mut count = 0;
+    for item in &mut chapter.sub_items {
+        if let BookItem::Chapter(nested) = item {
+            count += 1;
+            let number = chapter.number.clone().map(|SectionNumber(mut number)| {
+                number.push(count);
+                SectionNumber(number)
+            });
+            move_chapter(nested, number, nested_parent_names.clone());
+        }
+    }
+}
+
+/// Remove the front matter at the start of the content of every chapter, see `strip_frontmatter`.
+pub(crate) fn strip_front_matter(book: &mut Book) {
+    book.for_each_mut(|item| {
+        if let BookItem::Chapter(chapter) = item {
+            if let Some(content) = without_front_matter(&chapter.content) {
+                debug!("Removing the front matter of the chapter {}", chapter.name);
+                chapter.content = content.to_string();
+            }
+        }
+    });
+}
+
+/// Add the breadcrumbs of every nested numbered chapter to its content, see `inject_breadcrumbs`.
+/// They replace the first `<!-- breadcrumbs -->` marker, or start the content after its front
+/// matter without one. The chapters at the top level have no parents and are left out, as are
+/// prefix and suffix chapters and drafts.
+pub(crate) fn inject_breadcrumbs(book: &mut Book, config: &Config) {
+    add_breadcrumbs(&mut book.sections, &mut vec![], config);
+}
+
+/// Add the breadcrumbs to the numbered chapters among `items`, whose parents are `ancestors`.
+fn add_breadcrumbs(
+    items: &mut [BookItem],
+    ancestors: &mut Vec<(String, Option<PathBuf>)>,
+    config: &Config,
+) {
+    for item in items {
+        let BookItem::Chapter(chapter) = item else {
+            continue;
+        };
+        if chapter.number.is_none() {
+            continue;
+        }
+        if let Some(path) = chapter.path.as_deref().filter(|_| !ancestors.is_empty()) {
+            let line = breadcrumbs(ancestors, &chapter.name, path, &config.breadcrumb_separator);
+            debug!("Adding the breadcrumbs {} to {}", line, path.display());
+            match chapter.content.find(BREADCRUMBS_MARKER) {
+                Some(start) => chapter
+                    .content
+                    .replace_range(start..start + BREADCRUMBS_MARKER.len(), &line),
+                None => {
+                    let content = &chapter.content;
+                    let start = content.len()
+                        - without_front_matter(content).map_or(content.len(), str::len);
+                    chapter.content.insert_str(start, &format!("{}\n\n", line));
+                }
+            }
+        }
+        ancestors.push((chapter.name.clone(), chapter.path.clone()));
+        add_breadcrumbs(&mut chapter.sub_items, ancestors, config);
+        ancestors.pop();
+    }
+}
+
+/// The content after the front matter block, which has to start at the very beginning (after an
+/// optional byte order mark) with a `---` or `+++` line and end with the same line.
+/// [`Option::None`] if there is no such block or it is never closed.
+fn without_front_matter(content: &str) -> Option<&str> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let mut lines = content.split_inclusive('\n');
+    let opening = lines.next()?;
+    let delimiter = opening.trim_end();
+    if delimiter != "---" && delimiter != "+++" {
+        return None;
+    }
+    let mut end = opening.len();
+    for line in lines {
+        end += line.len();
+        if line.trim_end() == delimiter {
+            return Some(&content[end..]);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use mdbook::MDBook;
+
+    use super::*;
+    use crate::TestBook;
+
+    #[test]
+    fn loads_the_same_book_as_mdbook() -> Result<(), Error> {
+        let book = TestBook::new()
+            .file("preface.md", "\u{feff}# Preface\n")
+            .file("guide/README.md", "# Guide\n")
+            .file("guide/setup.md", "# Setup\n")
+            .file("guide/advanced/tuning.md", "# Tuning\n")
+            .file("reference/api.md", "# API\n")
+            .file("changelog.md", "# Changelog\n");
+        book.write()?;
+        let summary = mdbook::book::parse_summary(
+            "# Summary\n\n\
+             [Preface](preface.md)\n\n\
+             # User guide\n\n\
+             - [Guide](guide/README.md)\n    \
+                 - [Setup](guide/setup.md)\n    \
+                 - [Advanced]()\n        \
+                     - [Tuning](guide/advanced/tuning.md)\n\n\
+             ---\n\n\
+             # Reference\n\n\
+             - [API](reference/api.md)\n\n\
+             [Changelog](changelog.md)\n",
+        )?;
+
+        let mut book_config = mdbook::Config::default();
+        book_config.build.create_missing = false;
+        let expected =
+            MDBook::load_with_config_and_summary(book.root(), book_config, summary.clone())?;
+        let config = Config::default();
+        let loaded = load_book(
+            &summary,
+            &book.src_dir(),
+            &Book::new(),
+            &[],
+            &Conversions::new(&config),
+            &config,
+        )?;
+        assert_eq!(loaded.iter().count(), 10);
+        assert_eq!(loaded, expected.book);
+        Ok(())
+    }
+
+    #[test]
+    fn names_the_chapter_file_which_can_not_be_read() -> Result<(), Error> {
+        let book = TestBook::new();
+        book.write()?;
+        let summary = mdbook::book::parse_summary("- [Missing](guide/missing.md)\n")?;
+        let config = Config::default();
+        let error = load_book(
+            &summary,
+            &book.src_dir(),
+            &Book::new(),
+            &[],
+            &Conversions::new(&config),
+            &config,
+        )
+        .unwrap_err();
+        let path = book.src_dir().join("guide/missing.md");
+        assert!(format!("{:#}", error)
+            .starts_with(&format!("Failed to read chapter file {}: ", path.display())));
+        Ok(())
+    }
+
+    #[test]
+    fn strips_only_the_front_matter_at_the_start() -> Result<(), Error> {
+        let intro = "---\ntitle: Introduction\n---\n# Introduction\n";
+        let usage = "# Usage\n```yaml\n---\nkey: value\n---\n```\n";
+        let book = TestBook::new()
+            .file("intro.md", intro)
+            .file("usage.md", usage)
+            .config(toml::toml! { strip_frontmatter = true });
+        let output = book.run()?;
+        assert_eq!(output.content("intro.md"), Some("# Introduction\n"));
+        assert_eq!(output.content("usage.md"), Some(usage));
+        // The files are not changed
+        assert_eq!(
+            std::fs::read_to_string(book.src_dir().join("intro.md"))?,
+            intro
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn finds_the_end_of_the_front_matter() {
+        assert_eq!(without_front_matter("---\na: 1\n---\n# A\n"), Some("# A\n"));
+        assert_eq!(
+            without_front_matter("+++\na = 1\n+++\n# A\n"),
+            Some("# A\n")
+        );
+        assert_eq!(
+            without_front_matter("\u{feff}---\n---\n# A\n"),
+            Some("# A\n")
+        );
+        // The block has to end with the line it started with
+        assert_eq!(without_front_matter("---\na = 1\n+++\n# A\n"), None);
+        assert_eq!(without_front_matter("---\na: 1\n"), None);
+        assert_eq!(without_front_matter("# A\n---\n"), None);
+    }
+
+    #[test]
+    fn injects_breadcrumbs_into_nested_chapters() -> Result<(), Error> {
+        let internals = "# Internals\n<!-- breadcrumbs -->\n";
+        let book = TestBook::new()
+            .file("guide/README.md", "# Guide\n")
+            .file("guide/setup.md", "---\nweight: 1\n---\n# Setup\n")
+            .file("guide/advanced/README.md", "# Advanced\n")
+            .file("guide/advanced/internals.md", internals)
+            .config(toml::toml! { inject_breadcrumbs = true });
+        let output = book.run()?;
+        let contents: Vec<_> = output
+            .book
+            .iter()
+            .filter_map(|item| match item {
+                BookItem::Chapter(chapter) => Some(chapter.content.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            contents,
+            [
+                // Chapters at the top level have no parents
+                "# Guide\n",
+                "[guide](../../guide/README.md) › advanced\n\n# Advanced\n",
+                // In place of the marker
+                "# Internals\n\
+                 [guide](../../guide/README.md) › [advanced](../../guide/advanced/README.md) › \
+                 internals\n",
+                // After the front matter
+                "---\nweight: 1\n---\n[guide](../guide/README.md) › setup\n\n# Setup\n",
+            ]
+        );
+        // The files are not changed
+        assert_eq!(
+            std::fs::read_to_string(book.src_dir().join("guide/advanced/internals.md"))?,
+            internals
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_the_chapters_of_other_preprocessors() -> Result<(), Error> {
+        use mdbook::preprocess::{Preprocessor, PreprocessorContext};
+
+        struct GenerateApiPages;
+
+        impl Preprocessor for GenerateApiPages {
+            fn name(&self) -> &str {
+                "generate-api-pages"
+            }
+
+            fn run(&self, _: &PreprocessorContext, mut book: Book) -> mdbook::errors::Result<Book> {
+                let synthetic = |name: &str, path: &str| {
+                    let mut chapter = Chapter::new(name, format!("# {}\n", name), path, vec![]);
+                    chapter.source_path = None;
+                    chapter
+                };
+                book.for_each_mut(|item| match item {
+                    BookItem::Chapter(usage) if usage.name == "Usage" => {
+                        let api = synthetic("API", "usage/api.md");
+                        usage.sub_items.push(BookItem::Chapter(api));
+                    }
+                    _ => {}
+                });
+                book.push_item(synthetic("Changelog", "changelog.md"));
+                Ok(book)
+            }
+        }
+
+        let chapters = |config: toml::Value| -> Result<Vec<String>, Error> {
+            let output = TestBook::new()
+                .file("SUMMARY.md", "# Summary\n\n- [Usage](usage/README.md)\n")
+                .file("intro.md", "# Introduction\n")
+                .file("usage/README.md", "# Usage\n")
+                .file("usage/cli.md", "# CLI\n")
+                .config(config)
+                .preprocessor(GenerateApiPages)
+                .run()?;
+            Ok(output.chapter_names())
+        };
+        // At the end of their parent, or of the book for unnumbered ones
+        assert_eq!(
+            chapters(toml::Value::Table(Default::default()))?,
+            ["1. intro", "2. usage", "2.1. cli", "2.2. API", "Changelog"]
+        );
+        assert_eq!(
+            chapters(toml::toml! { foreign_chapters_part = "Reference" })?,
+            [
+                "1. intro",
+                "2. usage",
+                "2.1. cli",
+                "Reference",
+                "3. API",
+                "4. Changelog"
+            ]
+        );
+        assert_eq!(
+            chapters(toml::toml! { drop_foreign_chapters = true })?,
+            ["1. intro", "2. usage", "2.1. cli"]
+        );
+        Ok(())
+    }
+}
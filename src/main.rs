@@ -1,39 +1,397 @@
-use clap::{Arg, ArgMatches, Command};
+use anyhow::Context;
+use clap::{Arg, ArgAction, ArgMatches, Command};
 use mdbook::errors::Error;
 use mdbook::preprocess::{CmdPreprocessor, Preprocessor};
-use mdbook_generate_summary::GenerateSummary;
+use mdbook::renderer::RenderContext;
+use mdbook_generate_summary::{
+    discover_books, install, GenerateSummary, GraphFormat, TerminalProgress, WorkspaceBook,
+};
 use semver::{Version, VersionReq};
-use std::io;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
 use std::process;
 
 pub fn make_app() -> Command {
-    Command::new("nop-preprocessor")
-        .about("A mdbook preprocessor which does precisely nothing")
+    Command::new("mdbook-generate-summary")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("An mdBook preprocessor generating the summary of a book from its src directory")
+        .long_about(
+            "An mdBook preprocessor generating the summary of a book from its src directory.\n\n\
+             Without a command it is run by mdBook, reading the book as JSON from stdin and \
+             writing the processed book to stdout. Add `[preprocessor.generate-summary]` to \
+             book.toml (see the `install` command) to use it. As the renderer of \
+             `[output.generate-summary]`, it writes the structure of the book to SUMMARY.md and \
+             summary.json in its output directory instead.",
+        )
+        .arg(
+            Arg::new("quiet")
+                .long("quiet")
+                .short('q')
+                .action(ArgAction::SetTrue)
+                .global(true)
+                .help("Do not show the progress of commands on the terminal"),
+        )
         .subcommand(
             Command::new("supports")
                 .arg(Arg::new("renderer").required(true))
-                .about("Check whether a renderer is supported by this preprocessor"),
+                .about(
+                    "Check whether the preprocessor runs for a renderer, exits with 0 if it does \
+                     and 1 otherwise",
+                ),
+        )
+        .subcommand(
+            Command::new("check")
+                .arg(
+                    Arg::new("book-dir")
+                        .long("book-dir")
+                        .value_name("PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .default_value(".")
+                        .conflicts_with("workspace")
+                        .help("The directory containing book.toml"),
+                )
+                .args(workspace_args())
+                .about(
+                    "Check whether src/SUMMARY.md matches the generated summary. Exits with 0 if \
+                     it does, 1 if it does not (printing a diff) and 2 on errors. With \
+                     --workspace, exits with 2 if any book failed and 1 if any is not up to date",
+                ),
         )
+        .subcommand(
+            Command::new("validate")
+                .arg(
+                    Arg::new("book-dir")
+                        .long("book-dir")
+                        .value_name("PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .default_value(".")
+                        .help("The directory containing book.toml"),
+                )
+                .about(
+                    "Check the configuration and the src directory for problems which would make \
+                     a build fail, without writing anything. Exits with 0 if there are none, 1 if \
+                     there are (listing them) and 2 on errors",
+                ),
+        )
+        .subcommand(
+            Command::new("explain")
+                .arg(
+                    Arg::new("path")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("The file or directory to explain, relative to the src directory"),
+                )
+                .arg(
+                    Arg::new("book-dir")
+                        .long("book-dir")
+                        .value_name("PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .default_value(".")
+                        .help("The directory containing book.toml"),
+                )
+                .about(
+                    "Explain why a file or directory is part of the summary the way it is: which \
+                     filter left it out, where its name came from, its position among its \
+                     siblings and its section number. Writes nothing. Exits with 0 if it exists, \
+                     1 if it does not and 2 on errors",
+                ),
+        )
+        .subcommand(
+            Command::new("scaffold")
+                .arg(
+                    Arg::new("book-dir")
+                        .long("book-dir")
+                        .value_name("PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .default_value(".")
+                        .help("The directory containing book.toml"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Only list the missing chapter files instead of creating them"),
+                )
+                .about(
+                    "Create the missing chapter files of all directories. With --dry-run, exits \
+                     with 1 if chapter files are missing. Exits with 2 on errors",
+                ),
+        )
+        .subcommand(
+            Command::new("generate")
+                .arg(
+                    Arg::new("src")
+                        .long("src")
+                        .value_name("PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required_unless_present("workspace")
+                        .conflicts_with("workspace")
+                        .help("The directory to generate the summary of"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .value_name("PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .conflicts_with("workspace")
+                        .help("Write the summary to this file instead of stdout"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(["markdown", "json"])
+                        .default_value("markdown")
+                        .conflicts_with("workspace")
+                        .help("Write the summary as SUMMARY.md markdown or as JSON"),
+                )
+                .args(config_args())
+                .args(workspace_args())
+                .about(
+                    "Generate the SUMMARY.md of a directory without mdBook. The configuration is \
+                     read from the nearest book.toml, the options override it. With --workspace, \
+                     the src/SUMMARY.md of every book is written instead, exiting with 1 if any \
+                     book failed",
+                ),
+        )
+        .subcommand(
+            Command::new("graph")
+                .arg(
+                    Arg::new("book-dir")
+                        .long("book-dir")
+                        .value_name("PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .default_value(".")
+                        .help("The directory containing book.toml"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(["mermaid", "dot"])
+                        .default_value("mermaid")
+                        .help("Write a Mermaid flowchart or a Graphviz graph"),
+                )
+                .arg(
+                    Arg::new("depth")
+                        .long("depth")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .help("Only include this many levels of chapters"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .value_name("PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("Write the diagram to this file instead of stdout"),
+                )
+                .about(
+                    "Draw the structure of the book as a tree diagram, with the configuration \
+                     from book.toml",
+                ),
+        )
+        .subcommand(
+            Command::new("install")
+                .arg(
+                    Arg::new("dir")
+                        .long("dir")
+                        .value_name("PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .default_value(".")
+                        .help("The directory of the book.toml, or one of its subdirectories"),
+                )
+                .arg(
+                    Arg::new("chapter-file-name")
+                        .long("chapter-file-name")
+                        .value_name("NAME")
+                        .help("Set `chapter_file_name`"),
+                )
+                .arg(
+                    Arg::new("create-missing")
+                        .long("create-missing")
+                        .action(ArgAction::SetTrue)
+                        .help("Set `create_missing_chapter_files = true`"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Only print the changes instead of writing them"),
+                )
+                .about("Add the preprocessor to book.toml"),
+        )
+}
+
+/// Options of the `generate` subcommand which set the key of the same name in the
+/// `preprocessor.generate-summary` table.
+fn config_args() -> Vec<Arg> {
+    let flag = |key: &'static str, long: &'static str, help: &'static str| {
+        Arg::new(key)
+            .long(long)
+            .action(ArgAction::SetTrue)
+            .help(help)
+    };
+    let value = |key: &'static str, long: &'static str, help: &'static str| {
+        Arg::new(key).long(long).value_name("VALUE").help(help)
+    };
+    let paths = |key: &'static str, long: &'static str, help: &'static str| {
+        Arg::new(key)
+            .long(long)
+            .value_name("PATH")
+            .action(ArgAction::Append)
+            .help(help)
+    };
+    vec![
+        flag(
+            "get_chapter_name_from_file",
+            "get-chapter-name-from-file",
+            "Use the first heading of the files as chapter names",
+        ),
+        value(
+            "chapter_file_name",
+            "chapter-file-name",
+            "The file to use for chapters with children, without extension",
+        ),
+        flag(
+            "create_missing_chapter_files",
+            "create-missing-chapter-files",
+            "Create missing chapter files",
+        ),
+        flag(
+            "ignore_missing_chapter_files",
+            "ignore-missing-chapter-files",
+            "Ignore missing chapter files",
+        ),
+        flag(
+            "draft_empty_directories",
+            "draft-empty-directories",
+            "Add directories without markdown files as drafts",
+        ),
+        paths(
+            "leaf_directories",
+            "leaf-directory",
+            "A directory whose children are not added, can be repeated",
+        ),
+        paths(
+            "unnumbered_directories",
+            "unnumbered-directory",
+            "A directory added without section numbers, can be repeated",
+        ),
+        value(
+            "on_error",
+            "on-error",
+            "What to do with entries which can not be added: fail, skip or draft",
+        ),
+        value(
+            "file_directory_collision",
+            "file-directory-collision",
+            "How to handle a file next to a directory of the same name",
+        ),
+        flag(
+            "full_scan",
+            "full-scan",
+            "Ignore the title cache and read every directory and file again",
+        ),
+    ]
+}
+
+/// Options of the `generate` and `check` subcommands for handling every book in a directory.
+fn workspace_args() -> Vec<Arg> {
+    vec![
+        Arg::new("workspace")
+            .long("workspace")
+            .value_name("ROOT")
+            .value_parser(clap::value_parser!(PathBuf))
+            .num_args(0..=1)
+            .default_missing_value(".")
+            .help(
+                "Handle every book.toml in ROOT and its subdirectories, each with its own \
+                 configuration, and print a table of the results",
+            ),
+        Arg::new("exclude")
+            .long("exclude")
+            .value_name("GLOB")
+            .action(ArgAction::Append)
+            .requires("workspace")
+            .help(
+                "Do not search the directories matching the glob (relative to ROOT, `**` matches \
+                 any number of directories) for books, can be repeated",
+            ),
+    ]
 }
 
 fn main() {
+    // Like mdBook, log info messages unless `RUST_LOG` says otherwise
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
     let matches = make_app().get_matches();
 
     // Users will want to construct their own preprocessor here
-    let preprocessor = GenerateSummary::new();
+    let mut preprocessor = GenerateSummary::new();
+    // mdBook does not expect the preprocessor to write to the terminal
+    if matches.subcommand().is_some() {
+        preprocessor = with_progress(preprocessor, &matches);
+    }
 
     if let Some(sub_args) = matches.subcommand_matches("supports") {
         handle_supports(&preprocessor, sub_args);
+    } else if let Some(sub_args) = matches.subcommand_matches("check") {
+        handle_check(&preprocessor, sub_args);
+    } else if let Some(sub_args) = matches.subcommand_matches("validate") {
+        handle_validate(&preprocessor, sub_args);
+    } else if let Some(sub_args) = matches.subcommand_matches("explain") {
+        handle_explain(&preprocessor, sub_args);
+    } else if let Some(sub_args) = matches.subcommand_matches("scaffold") {
+        handle_scaffold(&preprocessor, sub_args);
+    } else if let Some(sub_args) = matches.subcommand_matches("install") {
+        if let Err(e) = handle_install(sub_args) {
+            eprintln!("{:#}", e);
+            process::exit(1);
+        }
+    } else if let Some(sub_args) = matches.subcommand_matches("graph") {
+        if let Err(e) = handle_graph(&preprocessor, sub_args) {
+            eprintln!("{:#}", e);
+            process::exit(1);
+        }
+    } else if let Some(sub_args) = matches.subcommand_matches("generate") {
+        if let Err(e) = handle_generate(&preprocessor, sub_args) {
+            eprintln!("{:#}", e);
+            process::exit(1);
+        }
     } else if let Err(e) = handle_preprocessing(&preprocessor) {
-        eprintln!("{}", e);
+        eprintln!("{:#}", e);
         process::exit(1);
     }
 }
 
-fn handle_preprocessing(pre: &dyn Preprocessor) -> Result<(), Error> {
-    let (ctx, book) = CmdPreprocessor::parse_input(io::stdin())?;
+fn handle_preprocessing(pre: &GenerateSummary) -> Result<(), Error> {
+    if io::stdin().is_terminal() {
+        eprintln!(
+            "mdbook-generate-summary is run by mdBook, which passes the book on stdin. Add \
+             `[preprocessor.generate-summary]` to book.toml and run `mdbook build`, or see \
+             `mdbook-generate-summary --help` for the commands to run it by hand"
+        );
+        process::exit(1);
+    }
+    let mut input = vec![];
+    io::stdin().read_to_end(&mut input)?;
+    // mdBook passes a preprocessor a `[context, book]` array and a renderer (for
+    // `[output.generate-summary]`) a `RenderContext` object
+    if input.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'{') {
+        let ctx = RenderContext::from_json(input.as_slice())?;
+        check_version(pre, &ctx.version)?;
+        return pre.render(&ctx);
+    }
+    let (ctx, book) = CmdPreprocessor::parse_input(input.as_slice())?;
+    check_version(pre, &ctx.mdbook_version)?;
+
+    let processed_book = pre.run(&ctx, book)?;
+    serde_json::to_writer(io::stdout(), &processed_book)?;
+
+    Ok(())
+}
 
-    let book_version = Version::parse(&ctx.mdbook_version)?;
+/// Warn if mdBook has another version than the one this was built against.
+fn check_version(pre: &GenerateSummary, mdbook_version: &str) -> Result<(), Error> {
+    let book_version = Version::parse(mdbook_version)?;
     let version_req = VersionReq::parse(mdbook::MDBOOK_VERSION)?;
 
     if !version_req.matches(&book_version) {
@@ -42,21 +400,21 @@ fn handle_preprocessing(pre: &dyn Preprocessor) -> Result<(), Error> {
              but we're being called from version {}",
             pre.name(),
             mdbook::MDBOOK_VERSION,
-            ctx.mdbook_version
+            mdbook_version
         );
     }
-
-    let processed_book = pre.run(&ctx, book)?;
-    serde_json::to_writer(io::stdout(), &processed_book)?;
-
     Ok(())
 }
 
-fn handle_supports(pre: &dyn Preprocessor, sub_args: &ArgMatches) -> ! {
+fn handle_supports(pre: &GenerateSummary, sub_args: &ArgMatches) -> ! {
     let renderer = sub_args
         .get_one::<String>("renderer")
         .expect("Required argument");
-    let supported = pre.supports_renderer(renderer);
+    let supported = supports_renderer(pre, renderer).unwrap_or_else(|error| {
+        // The error is reported again once the preprocessor runs
+        log::warn!("{:#}", error);
+        pre.supports_renderer(renderer)
+    });
 
     // Signal whether the renderer is supported by exiting with 1 or 0.
     if supported {
@@ -65,3 +423,347 @@ fn handle_supports(pre: &dyn Preprocessor, sub_args: &ArgMatches) -> ! {
         process::exit(1);
     }
 }
+
+fn handle_check(pre: &GenerateSummary, sub_args: &ArgMatches) -> ! {
+    if sub_args.contains_id("workspace") {
+        let results = handle_workspace(sub_args, |book| {
+            match with_progress(book.generator(), sub_args).check(&book.root) {
+                Ok(None) => Ok(BookResult::UpToDate),
+                Ok(Some(diff)) => {
+                    print!("{}", diff);
+                    Ok(BookResult::Drifted)
+                }
+                Err(e) => Err(e),
+            }
+        });
+        process::exit(workspace_exit_code(&results));
+    }
+
+    let book_dir = sub_args
+        .get_one::<PathBuf>("book-dir")
+        .expect("Has a default value");
+
+    match pre.check(book_dir) {
+        Ok(None) => process::exit(0),
+        Ok(Some(diff)) => {
+            print!("{}", diff);
+            eprintln!("SUMMARY.md is not up to date with the generated summary");
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(2);
+        }
+    }
+}
+
+fn handle_validate(pre: &GenerateSummary, sub_args: &ArgMatches) -> ! {
+    let book_dir = sub_args
+        .get_one::<PathBuf>("book-dir")
+        .expect("Has a default value");
+
+    match pre.validate(book_dir) {
+        Ok(problems) if problems.is_empty() => {
+            eprintln!("No problems found");
+            process::exit(0);
+        }
+        Ok(problems) => {
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            eprintln!("Found {} problems", problems.len());
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(2);
+        }
+    }
+}
+
+fn handle_explain(pre: &GenerateSummary, sub_args: &ArgMatches) -> ! {
+    let book_dir = sub_args
+        .get_one::<PathBuf>("book-dir")
+        .expect("Has a default value");
+    let path = sub_args
+        .get_one::<PathBuf>("path")
+        .expect("Required argument");
+
+    match pre.explain(book_dir, path) {
+        Ok(explanation) => {
+            print!("{}", explanation);
+            if explanation.exists || explanation.chapter.is_some() {
+                process::exit(0);
+            }
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(2);
+        }
+    }
+}
+
+fn handle_scaffold(pre: &GenerateSummary, sub_args: &ArgMatches) -> ! {
+    let book_dir = sub_args
+        .get_one::<PathBuf>("book-dir")
+        .expect("Has a default value");
+    let dry_run = sub_args.get_flag("dry-run");
+
+    match pre.scaffold(book_dir, dry_run) {
+        Ok(files) => {
+            for file in &files {
+                println!("{}", file.display());
+            }
+            if files.is_empty() {
+                eprintln!("No chapter files are missing");
+            } else if dry_run {
+                eprintln!("{} chapter files are missing", files.len());
+                process::exit(1);
+            } else {
+                eprintln!("Created {} chapter files", files.len());
+            }
+            process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(2);
+        }
+    }
+}
+
+fn handle_generate(pre: &GenerateSummary, sub_args: &ArgMatches) -> anyhow::Result<()> {
+    if sub_args.contains_id("workspace") {
+        let results = handle_workspace(sub_args, |book| {
+            match with_progress(book.generator(), sub_args).update_summary_file(&book.root)? {
+                true => Ok(BookResult::Written),
+                false => Ok(BookResult::UpToDate),
+            }
+        });
+        if results.iter().any(Option::is_none) {
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let src = sub_args
+        .get_one::<PathBuf>("src")
+        .expect("Required argument");
+    let src = std::path::absolute(src)
+        .with_context(|| format!("Failed to resolve the src directory {}", src.display()))?;
+
+    // Use the configuration of the book the directory belongs to, if there is one
+    let (root, mut book_config) = match find_book_toml(&src) {
+        Some(book_toml) => {
+            let mut book_config = mdbook::Config::from_disk(&book_toml)
+                .with_context(|| format!("Failed to load {}", book_toml.display()))?;
+            book_config.update_from_env();
+            (book_toml.parent().unwrap().to_owned(), book_config)
+        }
+        None => (std::env::current_dir()?, mdbook::Config::default()),
+    };
+    book_config.book.src = src;
+
+    let table = format!("preprocessor.{}", pre.name());
+    for arg in config_args() {
+        let key = arg.get_id().as_str();
+        let index = format!("{}.{}", table, key);
+        match arg.get_action() {
+            ArgAction::SetTrue if sub_args.get_flag(key) => book_config.set(index, true)?,
+            ArgAction::Append => {
+                if let Some(paths) = sub_args.get_many::<String>(key) {
+                    book_config.set(index, paths.collect::<Vec<_>>())?;
+                }
+            }
+            ArgAction::Set => {
+                if let Some(value) = sub_args.get_one::<String>(key) {
+                    book_config.set(index, value)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    if book_config.get_preprocessor(pre.name()).is_none() {
+        // Without the table the default configuration is used, but it is not reported as missing
+        book_config.set(table, toml::value::Table::new())?;
+    }
+
+    let summary = match sub_args.get_one::<String>("format").map(String::as_str) {
+        Some("json") => pre.generate_json(&root, &book_config)?,
+        _ => pre.generate_markdown(&root, &book_config)?,
+    };
+    match sub_args.get_one::<PathBuf>("output") {
+        Some(output) => std::fs::write(output, summary)
+            .with_context(|| format!("Failed to write {}", output.display()))?,
+        None => print!("{}", summary),
+    }
+    Ok(())
+}
+
+fn handle_graph(pre: &GenerateSummary, sub_args: &ArgMatches) -> anyhow::Result<()> {
+    let book_dir = sub_args
+        .get_one::<PathBuf>("book-dir")
+        .expect("Has a default value");
+    let format = match sub_args.get_one::<String>("format").map(String::as_str) {
+        Some("dot") => GraphFormat::Dot,
+        _ => GraphFormat::Mermaid,
+    };
+    let depth = sub_args
+        .get_one::<u64>("depth")
+        .map(|&depth| depth as usize);
+
+    let graph = pre.generate_graph(book_dir, format, depth)?;
+    match sub_args.get_one::<PathBuf>("output") {
+        Some(output) => std::fs::write(output, graph)
+            .with_context(|| format!("Failed to write {}", output.display()))?,
+        None => print!("{}", graph),
+    }
+    Ok(())
+}
+
+fn handle_install(sub_args: &ArgMatches) -> anyhow::Result<()> {
+    let dir = sub_args
+        .get_one::<PathBuf>("dir")
+        .expect("Has a default value");
+    let dir = std::path::absolute(dir)?;
+    let book_toml = find_book_toml(&dir)
+        .with_context(|| format!("No book.toml found in {} or its parents", dir.display()))?;
+
+    let mut settings = vec![];
+    if let Some(chapter_file_name) = sub_args.get_one::<String>("chapter-file-name") {
+        settings.push((
+            "chapter_file_name",
+            toml::Value::String(chapter_file_name.clone()),
+        ));
+    }
+    if sub_args.get_flag("create-missing") {
+        settings.push(("create_missing_chapter_files", toml::Value::Boolean(true)));
+    }
+
+    let dry_run = sub_args.get_flag("dry-run");
+    match install(&book_toml, &settings, dry_run)? {
+        None => eprintln!("{} is already up to date", book_toml.display()),
+        Some(diff) => {
+            print!("{}", diff);
+            if dry_run {
+                eprintln!("Dry run, {} was not changed", book_toml.display());
+            } else {
+                eprintln!("Updated {}", book_toml.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// What happened to a book of the workspace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BookResult {
+    UpToDate,
+    /// The SUMMARY.md differs from the generated summary.
+    Drifted,
+    /// The SUMMARY.md was written.
+    Written,
+}
+
+/// Show the progress of generating a summary on stderr, unless `--quiet` is passed.
+fn with_progress(generator: GenerateSummary, args: &ArgMatches) -> GenerateSummary {
+    if args.get_flag("quiet") {
+        return generator;
+    }
+    generator.with_progress(Box::new(TerminalProgress::stderr()))
+}
+
+/// Run `handle` for every book found by the `workspace` and `exclude` options, then print a table of
+/// the results. Errors are printed once they occur and the book is listed as failed.
+/// Returns the result of every book, [`Option::None`] for failed ones. Exits with 2 if the books
+/// can not be found.
+fn handle_workspace(
+    sub_args: &ArgMatches,
+    handle: impl Fn(&WorkspaceBook) -> anyhow::Result<BookResult>,
+) -> Vec<Option<BookResult>> {
+    let root = sub_args
+        .get_one::<PathBuf>("workspace")
+        .expect("Has a default value");
+    let exclude: Vec<String> = sub_args
+        .get_many::<String>("exclude")
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+    let books = match discover_books(root, &exclude) {
+        Ok(books) => books,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(2);
+        }
+    };
+    if books.is_empty() {
+        eprintln!("No book.toml found in {}", root.display());
+    }
+
+    let mut rows = vec![];
+    let mut results = vec![];
+    for book in &books {
+        let name = std::env::current_dir()
+            .ok()
+            .and_then(|dir| dir.canonicalize().ok())
+            .and_then(|dir| book.root.strip_prefix(dir).ok().map(Path::to_path_buf))
+            .filter(|name| !name.as_os_str().is_empty())
+            .unwrap_or_else(|| book.root.clone());
+        let result = handle(book)
+            .map_err(|e| eprintln!("{}: {:#}", name.display(), e))
+            .ok();
+        let description = match result {
+            Some(BookResult::UpToDate) => "up to date",
+            Some(BookResult::Drifted) => "not up to date",
+            Some(BookResult::Written) => "written",
+            None => "failed",
+        };
+        rows.push((name.display().to_string(), description));
+        results.push(result);
+    }
+
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain(["BOOK".len()])
+        .max()
+        .unwrap_or_default();
+    println!("{:width$}  RESULT", "BOOK");
+    for (name, description) in rows {
+        println!("{:width$}  {}", name, description);
+    }
+    results
+}
+
+/// The exit code of `check --workspace`, like the one of `check` for the worst book.
+fn workspace_exit_code(results: &[Option<BookResult>]) -> i32 {
+    if results.iter().any(Option::is_none) {
+        2
+    } else if results.contains(&Some(BookResult::Drifted)) {
+        1
+    } else {
+        0
+    }
+}
+
+/// Check `renderers` and `skip_renderers` of the book mdBook runs in. mdBook runs the command in its
+/// working directory, which is usually the book root.
+fn supports_renderer(pre: &GenerateSummary, renderer: &str) -> Result<bool, Error> {
+    let book_config = match find_book_toml(&std::env::current_dir()?) {
+        Some(book_toml) => {
+            let mut book_config = mdbook::Config::from_disk(&book_toml)
+                .with_context(|| format!("Failed to load {}", book_toml.display()))?;
+            book_config.update_from_env();
+            book_config
+        }
+        None => mdbook::Config::default(),
+    };
+    pre.supports_renderer_with_config(renderer, &book_config)
+}
+
+/// Find the book.toml in the directory or in one of its parents.
+fn find_book_toml(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join("book.toml"))
+        .find(|book_toml| book_toml.is_file())
+}
@@ -0,0 +1,376 @@
+use std::{fmt::Write, path::Path};
+
+use crate::{
+    book::{Link, Summary, SummaryItem},
+    heading_number::starts_with_number,
+};
+
+/// The first line of a SUMMARY.md written by `write_summary_file`.
+#[cfg(feature = "preprocessor")]
+pub(crate) const GENERATED_HEADER: &str =
+    "<!-- Generated by mdbook-generate-summary, changes to this file are overwritten -->";
+
+/// Serialize the summary to the markdown of a SUMMARY.md file, which mdBook parses back to the
+/// same summary.
+///
+/// The title is the heading (`# Summary` without one), followed by the prefix chapters as plain
+/// links, the numbered chapters as a nested list with part titles as headings and separators as
+/// `---`, and the suffix chapters as plain links. Drafts are links without a location like
+/// `[Name]()`, brackets and backslashes in names are escaped. Spaces in locations are written as
+/// `%20` and parentheses, angle brackets and backslashes are escaped, so that mdBook reads the same
+/// path. Prefix and suffix chapters can not be nested in a SUMMARY.md, their nested chapters are
+/// added after them instead.
+///
+/// # Examples
+/// ```
+/// use std::path::PathBuf;
+///
+/// use mdbook_generate_summary::{summary_to_markdown, Link, SectionNumber, Summary, SummaryItem};
+///
+/// // A chapter, numbered if `number` is not empty
+/// let link = |name: &str, location: Option<&str>, number: &[u32], nested_items| {
+///     SummaryItem::Link(Link {
+///         name: name.to_owned(),
+///         location: location.map(PathBuf::from),
+///         number: Some(SectionNumber(number.to_vec())).filter(|_| !number.is_empty()),
+///         nested_items,
+///     })
+/// };
+/// let summary = Summary {
+///     title: Some("Contents".to_owned()),
+///     prefix_chapters: vec![link("Preface", Some("preface.md"), &[], vec![])],
+///     numbered_chapters: vec![
+///         SummaryItem::PartTitle("Guide".to_owned()),
+///         link(
+///             "Setup [Linux]",
+///             Some("setup/README.md"),
+///             &[1],
+///             vec![link("Planned", None, &[1, 1], vec![])],
+///         ),
+///         SummaryItem::Separator,
+///         link("Usage", Some("usage.md"), &[2], vec![]),
+///     ],
+///     suffix_chapters: vec![link("Credits", Some("credits.md"), &[], vec![])],
+/// };
+///
+/// let markdown = summary_to_markdown(&summary);
+/// assert_eq!(
+///     markdown,
+///     "# Contents
+///
+/// [Preface](preface.md)
+///
+///
+/// ## Guide
+///
+/// - [Setup \\[Linux\\]](setup/README.md)
+///     - [Planned]()
+///
+/// ---
+///
+/// - [Usage](usage.md)
+///
+/// [Credits](credits.md)
+/// "
+/// );
+/// // mdBook parses it back to the same summary
+/// # #[cfg(feature = "preprocessor")]
+/// assert_eq!(mdbook::book::parse_summary(&markdown)?, summary);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// Locations with characters which have a meaning in a link:
+/// ```
+/// use std::path::Path;
+///
+/// use mdbook_generate_summary::{summary_to_markdown, Config, GenerateSummary, MemoryFs};
+///
+/// let fs = MemoryFs::new()
+///     .with_file("/src/FAQ (draft).md", "# FAQ\n")
+///     .with_file("/src/C# interop.md", "# C# interop\n")
+///     .with_file("/src/a <b>/README.md", "# Brackets\n")
+///     .with_file("/src/a <b>/c\\d.md", "# Escaped\n");
+/// let config = Config::builder().get_chapter_name_from_file(true).build()?;
+/// let summary = GenerateSummary::new()
+///     .with_fs(Box::new(fs))
+///     .generate_summary(Path::new("/src"), &config)?;
+///
+/// let markdown = summary_to_markdown(&summary);
+/// assert_eq!(
+///     markdown,
+///     r"# Summary
+///
+/// - [C# interop](C#%20interop.md)
+/// - [FAQ](FAQ%20\(draft\).md)
+/// - [Brackets](a%20\<b\>/README.md)
+///     - [Escaped](a%20\<b\>/c\\d.md)
+/// "
+/// );
+/// // The summary keeps the paths as they are, and mdBook reads them back from the markdown
+/// # #[cfg(feature = "preprocessor")]
+/// assert_eq!(
+///     mdbook::book::parse_summary(&markdown)?.numbered_chapters,
+///     summary.numbered_chapters
+/// );
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn summary_to_markdown(summary: &Summary) -> String {
+    let mut markdown = format!(
+        "# {}\n\n",
+        escape_name(summary.title.as_deref().unwrap_or("Summary"))
+    );
+
+    if !summary.prefix_chapters.is_empty() {
+        write_items(&mut markdown, &summary.prefix_chapters, None);
+        markdown.push('\n');
+    }
+    if !summary.numbered_chapters.is_empty() {
+        write_items(&mut markdown, &summary.numbered_chapters, Some(0));
+        markdown.push('\n');
+    }
+    if !summary.suffix_chapters.is_empty() {
+        write_items(&mut markdown, &summary.suffix_chapters, None);
+        markdown.push('\n');
+    }
+
+    // Only keep a single newline at the end
+    markdown.truncate(markdown.trim_end().len());
+    markdown.push('\n');
+    markdown
+}
+
+/// The markdown of a table of contents page at `location`, see `generate_toc_chapter`.
+///
+/// The title is the heading, followed by all chapters of the summary as a nested list of links
+/// relative to `location`, with their section number before their name. Drafts are listed without a
+/// link, part titles are bold lines between the lists and separators are left out.
+///
+/// # Example
+/// ```
+/// use std::path::Path;
+///
+/// use mdbook_generate_summary::{
+///     summary_to_toc, Config, GenerateSummary, MemoryFs, PlannedChapter,
+/// };
+///
+/// let fs = MemoryFs::new()
+///     .with_file("/src/intro.md", "---\nsection: Basics\n---\n# Introduction\n")
+///     .with_file("/src/guide/README.md", "---\nsection: Guide\n---\n# Guide\n")
+///     .with_file("/src/guide/setup.md", "# Setup [Linux]\n")
+///     .with_file("/src/guide/advanced/README.md", "# Advanced\n")
+///     .with_file("/src/guide/advanced/tuning.md", "# Tuning\n");
+/// let config = Config::builder()
+///     .get_chapter_name_from_file(true)
+///     .planned("guide", [PlannedChapter::new("Troubleshooting")])
+///     .group_by_front_matter("section")
+///     .group_order(["Basics", "Guide"])
+///     .build()?;
+/// let summary = GenerateSummary::new()
+///     .with_fs(Box::new(fs))
+///     .generate_summary(Path::new("/src"), &config)?;
+///
+/// assert_eq!(
+///     summary_to_toc(&summary, "Contents", Path::new("print/toc.md")),
+///     "# Contents
+///
+/// **Basics**
+///
+/// - [1. Introduction](../intro.md)
+///
+/// **Guide**
+///
+/// - [2. Guide](../guide/README.md)
+///     - [2.1. Advanced](../guide/advanced/README.md)
+///         - [2.1.1. Tuning](../guide/advanced/tuning.md)
+///     - [2.2. Setup \\[Linux\\]](../guide/setup.md)
+///     - 2.3. Troubleshooting
+/// "
+/// );
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn summary_to_toc(summary: &Summary, title: &str, location: &Path) -> String {
+    let parent = "../".repeat(
+        location
+            .parent()
+            .map_or(0, |parent| parent.components().count()),
+    );
+    let mut markdown = format!("# {}\n\n", title);
+    for items in [
+        &summary.prefix_chapters,
+        &summary.numbered_chapters,
+        &summary.suffix_chapters,
+    ] {
+        if !items.is_empty() {
+            write_toc_items(&mut markdown, items, 0, &parent);
+            markdown.push('\n');
+        }
+    }
+
+    // Only keep a single newline at the end
+    markdown.truncate(markdown.trim_end().len());
+    markdown.push('\n');
+    markdown
+}
+
+/// The breadcrumbs of the chapter at `location` named `name`, see `inject_breadcrumbs`: the names
+/// of its `ancestors` linking to their location, unless they are drafts, followed by its own name.
+#[cfg(feature = "preprocessor")]
+pub(crate) fn breadcrumbs(
+    ancestors: &[(String, Option<std::path::PathBuf>)],
+    name: &str,
+    location: &Path,
+    separator: &str,
+) -> String {
+    let parent = "../".repeat(
+        location
+            .parent()
+            .map_or(0, |parent| parent.components().count()),
+    );
+    let mut crumbs: Vec<_> = ancestors
+        .iter()
+        .map(|(name, location)| match location {
+            Some(location) => format!(
+                "[{}]({}{})",
+                escape_name(name),
+                parent,
+                percent_encode(location)
+            ),
+            None => escape_name(name),
+        })
+        .collect();
+    crumbs.push(escape_name(name));
+    crumbs.join(separator)
+}
+
+/// Write the items of the table of contents as a nested list at the given level, linking to their
+/// location inside of `parent`.
+fn write_toc_items(markdown: &mut String, items: &[SummaryItem], level: usize, parent: &str) {
+    for item in items {
+        match item {
+            SummaryItem::Link(link) => {
+                let name = match &link.number {
+                    Some(number) if !starts_with_number(&link.name, number) => {
+                        format!("{} {}", number, link.name)
+                    }
+                    _ => link.name.clone(),
+                };
+                let indent = "    ".repeat(level);
+                match &link.location {
+                    Some(location) => writeln!(
+                        markdown,
+                        "{}- [{}]({}{})",
+                        indent,
+                        escape_name(&name),
+                        parent,
+                        percent_encode(location)
+                    ),
+                    None => writeln!(markdown, "{}- {}", indent, name),
+                }
+                .unwrap();
+                write_toc_items(markdown, &link.nested_items, level + 1, parent);
+            }
+            SummaryItem::Separator => {}
+            SummaryItem::PartTitle(title) => {
+                if !markdown.ends_with("\n\n") {
+                    markdown.push('\n');
+                }
+                writeln!(markdown, "**{}**\n", title).unwrap();
+            }
+        }
+    }
+}
+
+/// Write the items, as a nested list with the given level of indentation if it is set.
+/// Otherwise (for prefix and suffix chapters) as plain links, one per line.
+fn write_items(markdown: &mut String, items: &[SummaryItem], level: Option<usize>) {
+    for item in items {
+        match item {
+            SummaryItem::Link(link) => write_link(markdown, link, level),
+            SummaryItem::Separator => markdown.push_str("\n---\n\n"),
+            SummaryItem::PartTitle(title) => {
+                writeln!(markdown, "\n# {}\n", escape_name(title)).unwrap();
+            }
+        }
+    }
+}
+
+/// Write the link and all of its nested items.
+fn write_link(markdown: &mut String, link: &Link, level: Option<usize>) {
+    let location = link
+        .location
+        .as_ref()
+        .map(|location| escape_location(&location.to_string_lossy()))
+        .unwrap_or_default();
+
+    match level {
+        Some(level) => {
+            writeln!(
+                markdown,
+                "{}- [{}]({})",
+                "    ".repeat(level),
+                escape_name(&link.name),
+                location
+            )
+            .unwrap();
+            write_items(markdown, &link.nested_items, Some(level + 1));
+        }
+        None => {
+            writeln!(markdown, "[{}]({})", escape_name(&link.name), location).unwrap();
+            // Prefix and suffix chapters can not be nested in a SUMMARY.md, keep their nested
+            // chapters as well by adding them after them
+            write_items(markdown, &link.nested_items, None);
+        }
+    }
+}
+
+/// Escape the location of a chapter in a SUMMARY.md, so that mdBook parses it back to the same
+/// path. mdBook only decodes `%20`, the other characters which end or break the destination of a
+/// link are escaped with a backslash. Fragments are not split off, so `#` is kept as it is.
+fn escape_location(location: &str) -> String {
+    let mut escaped = String::with_capacity(location.len());
+    for c in location.chars() {
+        match c {
+            ' ' => escaped.push_str("%20"),
+            '(' | ')' | '<' | '>' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Percent-encode the location of a chapter for a link in the content of a chapter, which the
+/// renderers turn into a URL with `/` between its components. Spaces, `#`, `%`, `?` and
+/// parentheses would otherwise start a fragment or query, or end the link.
+fn percent_encode(location: &Path) -> String {
+    let mut encoded = String::new();
+    for (index, component) in location.iter().enumerate() {
+        if index > 0 {
+            encoded.push('/');
+        }
+        for c in component.to_string_lossy().chars() {
+            match c {
+                ' ' | '"' | '#' | '%' | '(' | ')' | '<' | '>' | '?' | '\\' | '[' | ']' | '`' => {
+                    write!(encoded, "%{:02X}", c as u32).unwrap()
+                }
+                c if c.is_control() => {
+                    let mut bytes = [0; 4];
+                    for byte in c.encode_utf8(&mut bytes).bytes() {
+                        write!(encoded, "%{:02X}", byte).unwrap();
+                    }
+                }
+                c => encoded.push(c),
+            }
+        }
+    }
+    encoded
+}
+
+/// Escape the characters which have a meaning inside of the brackets of a link.
+fn escape_name(name: &str) -> String {
+    name.replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
@@ -0,0 +1,607 @@
+use std::{
+    collections::HashSet,
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Error};
+use log::debug;
+use serde::Deserialize;
+
+use crate::{
+    book::{Link, SectionNumber, SummaryItem},
+    is_same_name, renumber,
+    summary_fs::{FileKind, SummaryFs},
+    warnings::WarningKind,
+    Entry, Generator,
+};
+
+/// The structure of the book declared in the `outline` file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Outline {
+    /// The name of the part or chapter the unlisted markdown files and directories are appended
+    /// to. They are appended at the end if it is not set.
+    unlisted: Option<String>,
+    #[serde(default)]
+    chapters: Vec<OutlineEntry>,
+}
+
+/// An entry of the outline file as written, see [`OutlineEntry::into_item`].
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OutlineEntry {
+    part: Option<String>,
+    #[serde(default)]
+    separator: bool,
+    file: Option<PathBuf>,
+    dir: Option<PathBuf>,
+    title: Option<String>,
+    #[serde(default)]
+    chapters: Vec<OutlineEntry>,
+}
+
+/// A checked entry of the outline.
+enum OutlineItem {
+    /// A part title, only at the top level.
+    Part(String),
+    /// A separator, only at the top level.
+    Separator,
+    /// A markdown file (relative to the src directory) with the nested chapters of the outline.
+    File {
+        path: PathBuf,
+        title: Option<String>,
+        chapters: Vec<OutlineItem>,
+    },
+    /// A directory (relative to the src directory), whose chapters are generated from its content.
+    Dir {
+        path: PathBuf,
+        title: Option<String>,
+    },
+    /// A draft chapter containing the nested chapters of the outline.
+    Section {
+        title: String,
+        chapters: Vec<OutlineItem>,
+    },
+}
+
+impl OutlineEntry {
+    /// Check that the entry is exactly one of a part, a separator, a file, a directory or a
+    /// section (a `title` with `chapters`), and that parts and separators are only used at the top
+    /// level. `position` is the position of the entry like "2.1", for the errors.
+    fn into_item(self, position: &str) -> Result<OutlineItem, Error> {
+        let kinds = [
+            self.part.is_some(),
+            self.separator,
+            self.file.is_some(),
+            self.dir.is_some(),
+        ];
+        if kinds.into_iter().filter(|kind| *kind).count() > 1 {
+            return Err(anyhow!(
+                "Entry {} of the outline has more than one of `part`, `separator`, `file` and \
+                 `dir`",
+                position
+            ));
+        }
+
+        if self.part.is_some() || self.separator {
+            if self.title.is_some() || !self.chapters.is_empty() {
+                return Err(anyhow!(
+                    "Entry {} of the outline is a part or separator, which can not have a \
+                     `title` or `chapters`",
+                    position
+                ));
+            }
+            if position.contains('.') {
+                return Err(anyhow!(
+                    "Entry {} of the outline is a part or separator, which are only allowed at \
+                     the top level",
+                    position
+                ));
+            }
+            return Ok(match self.part {
+                Some(part) => OutlineItem::Part(part),
+                None => OutlineItem::Separator,
+            });
+        }
+        if let Some(path) = self.file {
+            return Ok(OutlineItem::File {
+                path: check_path(path, position)?,
+                title: self.title,
+                chapters: into_items(self.chapters, Some(position))?,
+            });
+        }
+        if let Some(path) = self.dir {
+            if !self.chapters.is_empty() {
+                return Err(anyhow!(
+                    "Entry {} of the outline is a directory, its chapters are generated from its \
+                     content and can not be listed",
+                    position
+                ));
+            }
+            return Ok(OutlineItem::Dir {
+                path: check_path(path, position)?,
+                title: self.title,
+            });
+        }
+        match self.title {
+            Some(title) => Ok(OutlineItem::Section {
+                title,
+                chapters: into_items(self.chapters, Some(position))?,
+            }),
+            None => Err(anyhow!(
+                "Entry {} of the outline needs one of `part`, `separator`, `file`, `dir` or \
+                 `title`",
+                position
+            )),
+        }
+    }
+}
+
+/// Check the entries of the outline, nested in the entry at `parent` unless they are the top
+/// level. See [`OutlineEntry::into_item`].
+fn into_items(entries: Vec<OutlineEntry>, parent: Option<&str>) -> Result<Vec<OutlineItem>, Error> {
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let position = match parent {
+                Some(parent) => format!("{}.{}", parent, index + 1),
+                None => (index + 1).to_string(),
+            };
+            entry.into_item(&position)
+        })
+        .collect()
+}
+
+/// Paths of the outline have to stay inside of the src directory.
+fn check_path(path: PathBuf, position: &str) -> Result<PathBuf, Error> {
+    if path.as_os_str().is_empty()
+        || !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(anyhow!(
+            "Entry {} of the outline has the path {}, which is not relative to the src directory \
+             or contains '..'",
+            position,
+            path.display()
+        ));
+    }
+    Ok(path)
+}
+
+/// Read and check the outline file at `path`.
+fn read_outline(
+    fs: &dyn SummaryFs,
+    path: &Path,
+) -> Result<(Option<String>, Vec<OutlineItem>), Error> {
+    let content = fs.read_prefix(path, usize::MAX)?;
+    let content = String::from_utf8(content).context("It is not valid UTF-8")?;
+    let outline: Outline = toml::from_str(&content)?;
+    Ok((outline.unlisted, into_items(outline.chapters, None)?))
+}
+
+/// The files and directories listed in the outline, as paths inside of the src directory.
+#[derive(Default)]
+struct Listed {
+    files: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl Listed {
+    /// Returns true if the directory contains a listed file or directory.
+    fn contains_listed(&self, dir: &Path) -> bool {
+        self.files
+            .iter()
+            .chain(&self.dirs)
+            .any(|path| path.starts_with(dir))
+    }
+}
+
+impl Generator<'_> {
+    /// Generate the chapters declared in the `outline` file at `path`, followed by the markdown
+    /// files and directories of the src directory which it does not list.
+    /// Fails if the outline can not be read or is invalid. Listed paths which do not exist are
+    /// errors of their entries, like the errors of [`Generator::generate_chapters`].
+    pub(crate) fn generate_outline(&mut self, path: &Path) -> Result<Vec<SummaryItem>, Error> {
+        let path = self.src_dir.join(path);
+        let (unlisted_target, outline) = read_outline(self.hooks.fs(), &path)
+            .with_context(|| format!("Failed to read the outline {}", path.display()))?;
+
+        let mut listed = Listed::default();
+        let mut items = vec![];
+        for item in outline {
+            self.add_outline_item(item, &mut listed, &mut items);
+        }
+
+        let mut unlisted = vec![];
+        self.find_unlisted(self.src_dir, &listed, &mut unlisted);
+        let target = unlisted_target
+            .as_deref()
+            .unwrap_or("the end of the summary");
+        let mut unlisted_items = vec![];
+        for entry in unlisted {
+            self.warnings.warn_at(
+                WarningKind::UnlistedChapters,
+                &entry.path,
+                format!(
+                    "{} is not listed in the outline {}, adding it to {}",
+                    self.location(&entry.path).display(),
+                    path.display(),
+                    target
+                ),
+            );
+            let result = if entry.is_dir {
+                self.add_entry(&entry, None, &mut unlisted_items)
+            } else {
+                self.file_link(&entry, SectionNumber::default())
+                    .map(|link| unlisted_items.push(SummaryItem::Link(link)))
+            };
+            if let Err(error) = result {
+                self.handle_entry_error(&entry.path, entry.name, None, &mut unlisted_items, error);
+            }
+        }
+
+        match unlisted_target {
+            None => items.extend(unlisted_items),
+            Some(target) => {
+                if !insert_unlisted(&mut items, &target, unlisted_items) {
+                    return Err(anyhow!(
+                        "The outline {} sets `unlisted = \"{}\"`, but has no part or chapter \
+                         with that name",
+                        path.display(),
+                        target
+                    ));
+                }
+            }
+        }
+        renumber(&mut items, None);
+        Ok(items)
+    }
+
+    /// Add the chapter of the outline `item` to `items` and record the paths it lists. The
+    /// section numbers are set by [`renumber`] once all chapters are added.
+    fn add_outline_item(
+        &mut self,
+        item: OutlineItem,
+        listed: &mut Listed,
+        items: &mut Vec<SummaryItem>,
+    ) {
+        match item {
+            OutlineItem::Part(title) => items.push(SummaryItem::PartTitle(title)),
+            OutlineItem::Separator => items.push(SummaryItem::Separator),
+            OutlineItem::File {
+                path,
+                title,
+                chapters,
+            } => {
+                let path = self.src_dir.join(path);
+                listed.files.insert(path.clone());
+                let mut nested_items = vec![];
+                for chapter in chapters {
+                    self.add_outline_item(chapter, listed, &mut nested_items);
+                }
+                let entry = self.outline_entry(&path, FileKind::File);
+                let name = title.clone().unwrap_or_else(|| entry_name(&path));
+                let count = items.len();
+                match entry.and_then(|entry| self.file_link(&entry, SectionNumber::default())) {
+                    Ok(link) => items.push(SummaryItem::Link(link)),
+                    Err(error) => self.handle_entry_error(&path, name, None, items, error),
+                }
+                if let Some(SummaryItem::Link(link)) = items.get_mut(count) {
+                    link.name = title.unwrap_or_else(|| link.name.clone());
+                    link.nested_items = nested_items;
+                }
+            }
+            OutlineItem::Dir { path, title } => {
+                let path = self.src_dir.join(path);
+                listed.dirs.insert(path.clone());
+                let name = title.clone().unwrap_or_else(|| entry_name(&path));
+                let count = items.len();
+                let result = self
+                    .outline_entry(&path, FileKind::Directory)
+                    .and_then(|entry| self.add_entry(&entry, None, items));
+                if let Err(error) = result {
+                    self.handle_entry_error(&path, name, None, items, error);
+                }
+                if let (Some(SummaryItem::Link(link)), Some(title)) = (items.get_mut(count), title)
+                {
+                    link.name = title;
+                }
+            }
+            OutlineItem::Section { title, chapters } => {
+                let mut nested_items = vec![];
+                for chapter in chapters {
+                    self.add_outline_item(chapter, listed, &mut nested_items);
+                }
+                items.push(SummaryItem::Link(Link {
+                    name: title,
+                    location: None,
+                    nested_items,
+                    number: Some(SectionNumber::default()),
+                }));
+            }
+        }
+    }
+
+    /// The entry of a file or directory listed in the outline, which has to exist.
+    fn outline_entry(&mut self, path: &Path, kind: FileKind) -> Result<Entry, Error> {
+        let location = self.location(path);
+        let expected = match kind {
+            FileKind::Directory => "a directory",
+            _ => "a file",
+        };
+        self.stats.metadata_queries += 1;
+        match self.hooks.fs().metadata(path) {
+            Ok(metadata) if metadata.kind == kind => {}
+            Ok(_) => {
+                return Err(anyhow!(
+                    "The outline lists {}, which is not {}",
+                    location.display(),
+                    expected
+                ))
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                return Err(anyhow!(
+                    "The outline lists {}, which does not exist",
+                    location.display()
+                ))
+            }
+            Err(error) => {
+                return Err(error).with_context(|| format!("Failed to read {}", path.display()))
+            }
+        }
+        let is_dir = kind == FileKind::Directory;
+        let mut entry = Entry::new(path.to_path_buf(), is_dir);
+        if self.config.sidecar_files {
+            entry.sidecar = self.read_sidecar(path, is_dir);
+        }
+        Ok(entry)
+    }
+
+    /// Collect the markdown files and directories in `dir` which are not part of the chapters of
+    /// the outline. Directories containing listed paths are searched, the others are collected as
+    /// a whole. Chapter files are collected as well, unless they belong to the src directory.
+    fn find_unlisted(&mut self, dir: &Path, listed: &Listed, unlisted: &mut Vec<Entry>) {
+        let Some(entries) = self.sorted_entries(dir) else {
+            return;
+        };
+        for entry in entries {
+            if entry.is_dir {
+                if listed.dirs.contains(&entry.path) {
+                    continue;
+                }
+                if listed.contains_listed(&entry.path) {
+                    self.find_unlisted(&entry.path, listed, unlisted);
+                    continue;
+                }
+            } else if listed.files.contains(&entry.path)
+                || (dir == self.src_dir
+                    && (is_same_name(&entry.name, "SUMMARY")
+                        || is_same_name(&entry.name, &self.config.chapter_file_name_of(dir))
+                        || self.is_external_location(&self.location(&entry.path))))
+            {
+                continue;
+            }
+            debug!(
+                "{} is not listed in the outline",
+                self.location(&entry.path).display()
+            );
+            unlisted.push(entry);
+        }
+    }
+}
+
+/// The default name of the chapter of a file or directory listed in the outline.
+fn entry_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Add the `unlisted` chapters to the end of the part or the nested chapters of the chapter named
+/// `target`. Returns false if there is no such part or chapter.
+fn insert_unlisted(items: &mut Vec<SummaryItem>, target: &str, unlisted: Vec<SummaryItem>) -> bool {
+    let part = items
+        .iter()
+        .position(|item| matches!(item, SummaryItem::PartTitle(title) if title == target));
+    if let Some(part) = part {
+        let end = items[part + 1..]
+            .iter()
+            .position(|item| matches!(item, SummaryItem::PartTitle(_)))
+            .map_or(items.len(), |next| part + 1 + next);
+        items.splice(end..end, unlisted);
+        return true;
+    }
+    match find_chapter(items, target) {
+        Some(link) => {
+            link.nested_items.extend(unlisted);
+            true
+        }
+        None => false,
+    }
+}
+
+/// The first chapter named `name` among the items and their nested items.
+fn find_chapter<'a>(items: &'a mut [SummaryItem], name: &str) -> Option<&'a mut Link> {
+    for item in items {
+        if let SummaryItem::Link(link) = item {
+            if link.name == name {
+                return Some(link);
+            }
+            if let Some(link) = find_chapter(&mut link.nested_items, name) {
+                return Some(link);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        summary_fs::MemoryFs,
+        test_util::{chapters, generate},
+        Summary,
+    };
+
+    /// Generate the summary of a src directory with the `outline` and some chapters, together with
+    /// the diagnostics of the run.
+    fn generate_with_outline(outline: &str) -> (Result<Summary, Error>, Vec<String>) {
+        let fs = MemoryFs::new()
+            .with_file("/src/outline.toml", outline)
+            .with_file("/src/intro.md", "")
+            .with_file("/src/faq.md", "")
+            .with_file("/src/api.md", "")
+            .with_file("/src/guide/README.md", "")
+            .with_file("/src/guide/setup.md", "")
+            .with_file("/src/notes/cli.md", "")
+            .with_file("/src/notes/extra.md", "")
+            .with_file("/src/drafts/README.md", "")
+            .with_file("/src/drafts/todo.md", "");
+        let config = Config::builder().outline("outline.toml").build().unwrap();
+        let (summary, diagnostics) = generate(fs, Path::new("/src"), &config);
+        let diagnostics = diagnostics
+            .into_iter()
+            .map(|diagnostic| format!("[{}] {}", diagnostic.code, diagnostic.message))
+            .collect();
+        (summary, diagnostics)
+    }
+
+    #[test]
+    fn generates_the_structure_of_the_outline() -> Result<(), Error> {
+        let outline = r#"
+            unlisted = "Appendix"
+
+            [[chapters]]
+            file = "intro.md"
+            title = "Introduction"
+
+            [[chapters]]
+            part = "Guide"
+
+            [[chapters]]
+            dir = "guide"
+
+            [[chapters]]
+            title = "Reference"
+            chapters = [{ file = "api.md" }, { file = "notes/cli.md", title = "Command line" }]
+
+            [[chapters]]
+            separator = true
+
+            [[chapters]]
+            title = "Appendix"
+        "#;
+        let (summary, diagnostics) = generate_with_outline(outline);
+        assert_eq!(
+            chapters(&summary?.numbered_chapters),
+            [
+                "1. Introduction (intro.md)",
+                "# Guide",
+                "2. guide (guide/README.md)",
+                "2.1. setup (guide/setup.md)",
+                "3. Reference (draft)",
+                "3.1. api (api.md)",
+                "3.2. Command line (notes/cli.md)",
+                "---",
+                // The files and directories missing in the outline
+                "4. Appendix (draft)",
+                "4.1. drafts (drafts/README.md)",
+                "4.1.1. todo (drafts/todo.md)",
+                "4.2. faq (faq.md)",
+                "4.3. extra (notes/extra.md)",
+            ]
+        );
+        let unlisted = "is not listed in the outline /src/outline.toml, adding it to Appendix";
+        assert_eq!(
+            diagnostics,
+            [
+                format!("[unlisted-chapters] drafts {}", unlisted),
+                format!("[unlisted-chapters] faq.md {}", unlisted),
+                format!("[unlisted-chapters] notes/extra.md {}", unlisted),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn adds_unlisted_entries_at_the_end_by_default() -> Result<(), Error> {
+        let outline = "[[chapters]]\nfile = \"intro.md\"\n\n[[chapters]]\ndir = \"guide\"\n\n\
+                       [[chapters]]\nfile = \"notes/cli.md\"\n";
+        let (summary, diagnostics) = generate_with_outline(outline);
+        assert_eq!(
+            chapters(&summary?.numbered_chapters),
+            [
+                "1. intro (intro.md)",
+                "2. guide (guide/README.md)",
+                "2.1. setup (guide/setup.md)",
+                "3. cli (notes/cli.md)",
+                "4. api (api.md)",
+                "5. drafts (drafts/README.md)",
+                "5.1. todo (drafts/todo.md)",
+                "6. faq (faq.md)",
+                "7. extra (notes/extra.md)",
+            ]
+        );
+        assert_eq!(diagnostics.len(), 4);
+        assert!(diagnostics[0].ends_with("adding it to the end of the summary"));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_outlines() {
+        for (outline, expected) in [
+            (
+                "[[chapters]]\nfile = \"missing.md\"\n",
+                "The outline lists missing.md, which does not exist",
+            ),
+            (
+                "[[chapters]]\ndir = \"intro.md\"\n",
+                "The outline lists intro.md, which is not a directory",
+            ),
+            (
+                "[[chapters]]\ntitle = \"Guide\"\nchapters = [{ part = \"Nested\" }]\n",
+                "Entry 1.1 of the outline is a part or separator, which are only allowed at the \
+                 top level",
+            ),
+            (
+                "[[chapters]]\npart = \"Guide\"\ntitle = \"Guide\"\n",
+                "Entry 1 of the outline is a part or separator, which can not have a `title` or \
+                 `chapters`",
+            ),
+            (
+                "[[chapters]]\ndir = \"guide\"\nchapters = [{ file = \"api.md\" }]\n",
+                "Entry 1 of the outline is a directory, its chapters are generated from its \
+                 content and can not be listed",
+            ),
+            (
+                "[[chapters]]\nfile = \"../outside.md\"\n",
+                "Entry 1 of the outline has the path ../outside.md, which is not relative to the \
+                 src directory or contains '..'",
+            ),
+            (
+                "[[chapters]]\nfile = \"api.md\"\ndir = \"guide\"\n",
+                "Entry 1 of the outline has more than one of `part`, `separator`, `file` and \
+                 `dir`",
+            ),
+            (
+                "[[chapters]]\nfile = \"api.md\"\n\n[[chapters]]\n",
+                "Entry 2 of the outline needs one of `part`, `separator`, `file`, `dir` or \
+                 `title`",
+            ),
+            (
+                "unlisted = \"Nowhere\"\n",
+                "The outline /src/outline.toml sets `unlisted = \"Nowhere\"`, but has no part or \
+                 chapter with that name",
+            ),
+        ] {
+            let (summary, _) = generate_with_outline(outline);
+            let error = format!("{:#}", summary.unwrap_err());
+            assert!(error.contains(expected), "{}", error);
+        }
+    }
+}
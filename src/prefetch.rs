@@ -0,0 +1,176 @@
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    io,
+    path::{Path, PathBuf},
+};
+
+use log::debug;
+
+use crate::{
+    config::Config,
+    summary_fs::{FileKind, FsEntry, SummaryFs},
+    title_cache::TitleCache,
+};
+
+/// The directory listings and the heads of the markdown files in the src directory, read in
+/// parallel before the chapters are generated.
+/// The chapters are still generated one after the other with the prefetched results, so that their
+/// order, section numbers and warnings do not depend on the threads. Anything which failed is not
+/// prefetched, but read again while generating the chapters, which reports the error.
+#[derive(Default)]
+pub(crate) struct Prefetched {
+    listings: HashMap<PathBuf, Vec<io::Result<FsEntry>>>,
+    heads: HashMap<PathBuf, Vec<u8>>,
+    /// The directories listed by the threads, see [`Stats::directories_read`](crate::Stats::directories_read).
+    pub(crate) directories_read: usize,
+    /// See [`Stats::metadata_queries`](crate::Stats::metadata_queries).
+    pub(crate) metadata_queries: usize,
+}
+
+impl Prefetched {
+    /// Read the directories of `src_dir` up to `config.max_depth` with `config.thread_count()`
+    /// threads, one level of directories after the other. Nothing is read with a single thread.
+    /// No further level is read once the levels so far have more entries than
+    /// `config.max_chapters`, which fails the generation anyway.
+    /// Files which did not change since they were added to the title `cache` are not read.
+    pub(crate) fn read(
+        src_dir: &Path,
+        config: &Config,
+        cache: Option<&TitleCache>,
+        fs: &dyn SummaryFs,
+    ) -> Self {
+        let mut prefetched = Self::default();
+        let threads = config.thread_count();
+        if threads <= 1 {
+            return prefetched;
+        }
+
+        let mut level = vec![src_dir.to_path_buf()];
+        let mut entries = 0;
+        for _ in 0..=config.max_depth {
+            if level.is_empty() || entries > config.max_chapters {
+                break;
+            }
+            let chunk_size = level.len().div_ceil(threads);
+            let results: Vec<_> = std::thread::scope(|scope| {
+                let handles: Vec<_> = level
+                    .chunks(chunk_size)
+                    .map(|chunk| scope.spawn(|| read_directories(chunk, config, cache, fs)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap_or_default())
+                    .collect()
+            });
+            level = vec![];
+            for result in results {
+                prefetched.directories_read += result.directories_read;
+                prefetched.metadata_queries += result.metadata_queries;
+                level.extend(result.directories);
+                entries += result
+                    .listings
+                    .iter()
+                    .map(|(_, listing)| listing.len())
+                    .sum::<usize>();
+                prefetched.listings.extend(result.listings);
+                prefetched.heads.extend(result.heads);
+            }
+        }
+        debug!(
+            "Prefetched {} directories and {} chapter files with {} threads",
+            prefetched.listings.len(),
+            prefetched.heads.len(),
+            threads
+        );
+        prefetched
+    }
+
+    /// Take the entries of the directory, if it was listed.
+    pub(crate) fn take_listing(&mut self, dir_path: &Path) -> Option<Vec<io::Result<FsEntry>>> {
+        self.listings.remove(dir_path)
+    }
+
+    /// Take the heads of the files which were read.
+    pub(crate) fn take_heads(&mut self) -> HashMap<PathBuf, Vec<u8>> {
+        std::mem::take(&mut self.heads)
+    }
+}
+
+/// What one thread read of a level of directories.
+#[derive(Default)]
+struct LevelResult {
+    /// The subdirectories, which make up the next level.
+    directories: Vec<PathBuf>,
+    listings: Vec<(PathBuf, Vec<io::Result<FsEntry>>)>,
+    heads: Vec<(PathBuf, Vec<u8>)>,
+    directories_read: usize,
+    metadata_queries: usize,
+}
+
+/// List the directories and read the heads of their markdown files.
+fn read_directories(
+    directories: &[PathBuf],
+    config: &Config,
+    cache: Option<&TitleCache>,
+    fs: &dyn SummaryFs,
+) -> LevelResult {
+    let mut result = LevelResult::default();
+    for dir_path in directories {
+        if cache.is_some_and(|cache| cache.caches_directories()) {
+            result.metadata_queries += 1;
+        }
+        if let Some(entries) = cache.and_then(|cache| cache.get_fresh_directory(dir_path, fs)) {
+            // The directory did not change, only its files and subdirectories may have
+            for entry in entries {
+                let path = dir_path.join(&entry.name);
+                if entry.is_dir {
+                    result.directories.push(path);
+                } else if let Some(head) = read_changed_head(&path, config, cache, fs, &mut result)
+                {
+                    result.heads.push((path, head));
+                }
+            }
+            continue;
+        }
+        result.directories_read += 1;
+        let Ok(entries) = fs.read_dir(dir_path) else {
+            continue;
+        };
+        for entry in entries.iter().flatten() {
+            let Ok(kind) = entry.kind else {
+                continue;
+            };
+            let path = &entry.path;
+            if kind == FileKind::Directory {
+                result.directories.push(path.clone());
+            } else if kind == FileKind::File && path.extension() == Some(OsStr::new("md")) {
+                if let Some(head) = read_changed_head(path, config, cache, fs, &mut result) {
+                    result.heads.push((path.clone(), head));
+                }
+            }
+        }
+        result.listings.push((dir_path.clone(), entries));
+    }
+    result
+}
+
+/// Read the head of the file, unless it did not change since it was added to the title `cache`.
+/// The metadata queries for the cache are counted in `result`.
+fn read_changed_head(
+    path: &Path,
+    config: &Config,
+    cache: Option<&TitleCache>,
+    fs: &dyn SummaryFs,
+    result: &mut LevelResult,
+) -> Option<Vec<u8>> {
+    let cached = cache.is_some_and(|cache| {
+        result.metadata_queries += 1;
+        fs.metadata(path)
+            .is_ok_and(|metadata| cache.is_fresh(path, &metadata))
+    });
+    if cached {
+        return None;
+    }
+    fs.read_prefix(path, config.max_title_bytes).ok()
+}
@@ -0,0 +1,1665 @@
+use std::{
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
+
+use anyhow::{anyhow, Context, Error};
+use log::{debug, log, warn};
+use mdbook::{
+    book::Book,
+    preprocess::{Preprocessor, PreprocessorContext},
+};
+
+use crate::{
+    build_summary,
+    config::with_config_file,
+    convert::Conversions,
+    diff::unified_diff,
+    explain::Explanation,
+    external::external_location,
+    gitignore::CreatedFilesIgnore,
+    graph::{summary_to_graph, GraphFormat},
+    json::summary_to_json,
+    links::check_links,
+    load::{
+        add_foreign_chapters, add_toc_chapter, inject_breadcrumbs, load_book, strip_front_matter,
+    },
+    log_stats,
+    markdown::{summary_to_markdown, GENERATED_HEADER},
+    summary_diff::diff_summaries,
+    warnings::Warnings,
+    write_guard::WriteGuard,
+    Config, Excluded, GenerateSummary, Stats, Summary, SummaryItem, WarningKind,
+};
+
+impl GenerateSummary {
+    /// Compare the summary generated for the book in `root` with its `SUMMARY.md`, for checking
+    /// that a committed SUMMARY.md is up to date.
+    /// Returns [`Option::None`] if they are the same, otherwise a unified diff from the existing to
+    /// the generated summary. Trailing whitespace and line endings are ignored.
+    pub fn check(&self, root: &Path) -> Result<Option<String>, Error> {
+        let book_config = load_book_config(root)?;
+        let Generated {
+            config,
+            src_dir,
+            summary,
+            stats,
+            warnings,
+            ..
+        } = self.generate(root, &book_config, None, false)?;
+        warnings.check()?;
+        log_stats(&src_dir, &config, &stats);
+
+        let summary_path = src_dir.join("SUMMARY.md");
+        let existing = match std::fs::read_to_string(&summary_path) {
+            Ok(existing) => existing,
+            Err(error) if error.kind() == ErrorKind::NotFound => String::new(),
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("Failed to read {}", summary_path.display()))
+            }
+        };
+        // A file written by `write_summary_file` only differs by its header
+        let existing = existing
+            .strip_prefix(GENERATED_HEADER)
+            .map_or(existing.as_str(), str::trim_start);
+
+        let existing = normalize_lines(existing);
+        let generated = summary_to_markdown(&summary);
+        let generated = normalize_lines(&generated);
+        if existing == generated {
+            Ok(None)
+        } else {
+            Ok(Some(unified_diff(
+                &existing,
+                &generated,
+                &summary_path.display().to_string(),
+                "generated summary",
+            )))
+        }
+    }
+
+    /// Create the missing chapter files of the book in `root` at once, instead of while mdBook
+    /// builds it with `create_missing_chapter_files`. The directories are visited with the same
+    /// configuration as when generating the summary, so that e.g. the directories inside of leaf
+    /// directories and the ones left out by the [`ChapterFilter`](crate::ChapterFilter) do not get
+    /// chapter files.
+    /// Returns the created chapter files relative to the src directory. With `dry_run` nothing is
+    /// created and the missing chapter files are returned instead.
+    ///
+    /// # Example
+    /// ```
+    /// use mdbook_generate_summary::GenerateSummary;
+    ///
+    /// let root = std::env::temp_dir().join(format!(
+    ///     "generate-summary-scaffold-doctest-{}",
+    ///     std::process::id()
+    /// ));
+    /// std::fs::create_dir_all(root.join("src/guide/advanced"))?;
+    /// std::fs::write(root.join("book.toml"), "[preprocessor.generate-summary]\n")?;
+    /// std::fs::write(root.join("src/intro.md"), "# Introduction\n")?;
+    /// std::fs::write(root.join("src/guide/setup.md"), "# Setup\n")?;
+    /// std::fs::write(root.join("src/guide/advanced/usage.md"), "# Usage\n")?;
+    ///
+    /// let missing = GenerateSummary::new().scaffold(&root, true)?;
+    /// assert_eq!(missing.len(), 2);
+    /// assert!(!root.join("src/guide/README.md").exists());
+    ///
+    /// let created = GenerateSummary::new().scaffold(&root, false)?;
+    /// assert_eq!(created, missing);
+    /// assert!(root.join("src/guide/README.md").exists());
+    /// assert!(root.join("src/guide/advanced/README.md").exists());
+    /// assert!(GenerateSummary::new().scaffold(&root, true)?.is_empty());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn scaffold(&self, root: &Path, dry_run: bool) -> Result<Vec<PathBuf>, Error> {
+        let mut book_config = load_book_config(root)?;
+        let table = format!("preprocessor.{}", self.name());
+        book_config.set(format!("{}.create_missing_chapter_files", table), true)?;
+        book_config.set(format!("{}.ignore_missing_chapter_files", table), false)?;
+        book_config.set(format!("{}.dry_run", table), dry_run)?;
+
+        let Generated {
+            config,
+            src_dir,
+            created_files,
+            stats,
+            warnings,
+            ..
+        } = self.generate(root, &book_config, None, false)?;
+        warnings.check()?;
+        log_stats(&src_dir, &config, &stats);
+        Ok(created_files
+            .iter()
+            .map(|path| path.strip_prefix(&src_dir).unwrap_or(path).to_owned())
+            .collect())
+    }
+
+    /// Check the book in `root` for problems which would make a build fail, without writing
+    /// anything. The configuration is checked like with `strict_config`, then the files and
+    /// directories it refers to and the src directory, which is traversed like for a build (with
+    /// `dry_run`) if they exist.
+    /// Returns the problems, which is empty if there are none. Fails if the book.toml can not be
+    /// loaded.
+    ///
+    /// # Example
+    /// ```
+    /// use mdbook_generate_summary::GenerateSummary;
+    ///
+    /// let root = std::env::temp_dir().join(format!(
+    ///     "generate-summary-validate-doctest-{}",
+    ///     std::process::id()
+    /// ));
+    /// std::fs::create_dir_all(root.join("src/guide"))?;
+    /// std::fs::write(root.join("src/intro.md"), "# Introduction\n")?;
+    /// std::fs::write(root.join("src/guide/setup.md"), "# Setup\n")?;
+    /// let book_toml = "[preprocessor.generate-summary]\noutline = \"outline.toml\"\nunknown = 1\n";
+    /// std::fs::write(root.join("book.toml"), book_toml)?;
+    ///
+    /// let problems = GenerateSummary::new().validate(&root)?;
+    /// assert_eq!(problems.len(), 1);
+    /// assert!(problems[0].contains("Unknown configuration keys"));
+    ///
+    /// std::fs::write(root.join("book.toml"), "[preprocessor.generate-summary]\noutline = \"outline.toml\"\n")?;
+    /// let problems = GenerateSummary::new().validate(&root)?;
+    /// assert!(problems[0].contains("outline.toml"));
+    ///
+    /// let book_toml = "[build]\ncreate-missing = false\n[preprocessor.generate-summary]\n";
+    /// std::fs::write(root.join("book.toml"), book_toml)?;
+    /// let problems = GenerateSummary::new().validate(&root)?;
+    /// assert!(problems[0].contains("Missing chapter file"));
+    ///
+    /// std::fs::write(root.join("src/guide/README.md"), "# Guide\n")?;
+    /// assert!(GenerateSummary::new().validate(&root)?.is_empty());
+    /// // Nothing was written
+    /// assert!(!root.join("src/SUMMARY.md").exists());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn validate(&self, root: &Path) -> Result<Vec<String>, Error> {
+        let mut book_config = load_book_config(root)?;
+        let table = format!("preprocessor.{}", self.name());
+        book_config.set(format!("{}.strict_config", table), true)?;
+        book_config.set(format!("{}.dry_run", table), true)?;
+
+        let config = match self.read_config(root, &book_config, None) {
+            Ok(config) => config,
+            Err(error) => return Ok(vec![format!("{:#}", error)]),
+        };
+        let mut problems = missing_paths(root, &book_config, &config);
+        // Traversing would only fail because of them again
+        if problems.is_empty() {
+            let generated = self.generate(root, &book_config, None, false);
+            if let Err(error) = generated.and_then(|generated| generated.warnings.check()) {
+                problems.push(format!("{:#}", error));
+            }
+        }
+        Ok(problems)
+    }
+
+    /// Generate the summary of the book in `root` like [`GenerateSummary::validate`] does, without
+    /// writing anything, and explain how the file or directory at `location` (relative to the src
+    /// directory) became part of it: whether it was left out and why, its position among the
+    /// entries of its directory, where its name came from and its chapter in the summary.
+    ///
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use mdbook_generate_summary::GenerateSummary;
+    ///
+    /// let root = std::env::temp_dir().join(format!(
+    ///     "generate-summary-explain-doctest-{}",
+    ///     std::process::id()
+    /// ));
+    /// std::fs::create_dir_all(root.join("src/guide"))?;
+    /// let book_toml = "[preprocessor.generate-summary]\nget_chapter_name_from_file = true\n\
+    ///     name_sources = [\"front_matter\", \"heading\"]\n";
+    /// std::fs::write(root.join("book.toml"), book_toml)?;
+    /// std::fs::write(root.join("src/intro.md"), "# Introduction\n")?;
+    /// std::fs::write(root.join("src/notes.txt"), "Remember the milk\n")?;
+    /// std::fs::write(root.join("src/guide/README.md"), "# Guide\n")?;
+    /// std::fs::write(root.join("src/guide/setup.md"), "---\ntitle: Installing\n---\n# Setup\n")?;
+    /// let generator = GenerateSummary::new();
+    ///
+    /// let explanation = generator.explain(&root, Path::new("guide/setup.md"))?;
+    /// assert_eq!(
+    ///     explanation.to_string(),
+    ///     "guide/setup.md is the chapter 1.1. \"Installing\"\n\
+    ///     \x20 Entry 2 of 2 of guide, sorted by its file name \"setup.md\"\n\
+    ///     \x20 Named \"Installing\" after its front_matter (`name_sources`)\n\
+    ///     \x20 Its heading has the name \"Setup\", an earlier one of `name_sources` is used\n\
+    ///     \x20 Added as the chapter 1.1. \"Installing\"\n"
+    /// );
+    ///
+    /// // A directory is explained together with its chapter file
+    /// let explanation = generator.explain(&root, Path::new("guide"))?;
+    /// assert_eq!(explanation.chapter.unwrap().name, "Guide");
+    /// assert!(explanation.diagnostics[1].message.contains("chapter file is guide/README.md"));
+    ///
+    /// let explanation = generator.explain(&root, Path::new("notes.txt"))?;
+    /// assert_eq!(
+    ///     explanation.to_string(),
+    ///     "notes.txt is not part of the book, it is neither a markdown file nor converted by a \
+    ///      `converter`\n"
+    /// );
+    /// let explanation = generator.explain(&root, Path::new("todo.md"))?;
+    /// assert_eq!(explanation.to_string(), "todo.md does not exist in the src directory\n");
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn explain(&self, root: &Path, location: &Path) -> Result<Explanation, Error> {
+        let mut book_config = load_book_config(root)?;
+        book_config.set(format!("preprocessor.{}.dry_run", self.name()), true)?;
+        let location = PathBuf::from(
+            location
+                .components()
+                .filter_map(|component| match component {
+                    Component::Normal(name) => Some(name.to_string_lossy()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("/"),
+        );
+        let Generated {
+            config,
+            src_dir,
+            summary,
+            excluded,
+            warnings,
+            ..
+        } = self.generate_explaining(root, &book_config, None, false, Some(&location))?;
+        Ok(Explanation::new(
+            warnings.explained(),
+            &src_dir,
+            &summary,
+            &excluded,
+            warnings.diagnostics(&src_dir),
+            &config,
+        ))
+    }
+
+    /// Generate the summary of the book in `root` and write it to SUMMARY.md in its src directory,
+    /// like `write_summary_file` does while mdBook builds it. Nothing is written with `dry_run`.
+    /// Returns true if the file changed, or would have changed with `dry_run`.
+    pub fn update_summary_file(&self, root: &Path) -> Result<bool, Error> {
+        let book_config = load_book_config(root)?;
+        let Generated {
+            config,
+            src_dir,
+            summary,
+            stats,
+            warnings,
+            ..
+        } = self.generate(root, &book_config, None, false)?;
+        warnings.check()?;
+        log_stats(&src_dir, &config, &stats);
+        if config.assert_no_writes && !config.dry_run {
+            return Err(anyhow!(
+                "Not writing {}, `assert_no_writes` is set",
+                src_dir.join("SUMMARY.md").display()
+            ));
+        }
+        write_summary_file(&src_dir, &summary, &config)
+    }
+
+    /// Returns true if the preprocessor runs for `renderer`, according to `renderers` and
+    /// `skip_renderers` in `book_config` and [`Preprocessor::supports_renderer`] otherwise.
+    /// mdBook only asks the preprocessor through `supports` without passing the configuration, use
+    /// this to answer it. A `config_file` is read relative to the working directory, which is the
+    /// book root when mdBook asks.
+    pub fn supports_renderer_with_config(
+        &self,
+        renderer: &str,
+        book_config: &mdbook::Config,
+    ) -> Result<bool, Error> {
+        self.runs_for_renderer(Path::new("."), renderer, book_config)
+    }
+
+    /// Like [`GenerateSummary::supports_renderer_with_config`] for the book in `root`.
+    fn runs_for_renderer(
+        &self,
+        root: &Path,
+        renderer: &str,
+        book_config: &mdbook::Config,
+    ) -> Result<bool, Error> {
+        let config = self.read_config(root, book_config, Some(renderer))?;
+        Ok(config.runs_for_renderer(renderer) && self.supports_renderer(renderer))
+    }
+
+    /// Read the configuration of the preprocessor in `book_config` and its configuration file in
+    /// `root`, for `renderer` if it is known.
+    pub(crate) fn read_config(
+        &self,
+        root: &Path,
+        book_config: &mdbook::Config,
+        renderer: Option<&str>,
+    ) -> Result<Config, Error> {
+        let empty_table = toml::value::Table::new();
+        let table = book_config
+            .get_preprocessor(self.name())
+            .unwrap_or_else(|| {
+                debug!(
+                    "No `preprocessor.{}` table found, using the default configuration",
+                    self.name()
+                );
+                &empty_table
+            });
+        let table = with_config_file(table, root)?;
+        // Like mdBook, use the html renderer if none is configured
+        let configured_renderers: Vec<_> = match book_config.get("output") {
+            Some(toml::Value::Table(output)) => output.keys().map(String::as_str).collect(),
+            _ => vec!["html"],
+        };
+        Config::for_renderer(
+            &table,
+            renderer,
+            &configured_renderers,
+            &book_config.book.src,
+            book_config.build.create_missing,
+        )
+    }
+
+    /// Generate the summary of the book in `root` configured by `book_config` and serialize it to
+    /// the markdown of a SUMMARY.md, without mdBook driving the preprocessor.
+    /// The build directory and the theme directory are left out, if they are inside of the src
+    /// directory.
+    ///
+    /// # Example
+    /// ```
+    /// use mdbook_generate_summary::{GenerateSummary, TestBook};
+    ///
+    /// let book = TestBook::new().file("intro.md", "# Introduction\n");
+    /// book.run()?;
+    /// let book_config = mdbook::Config::from_disk(book.root().join("book.toml"))?;
+    /// let summary = GenerateSummary::new().generate_markdown(book.root(), &book_config)?;
+    /// assert_eq!(summary, "# Summary\n\n- [intro](intro.md)\n");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn generate_markdown(
+        &self,
+        root: &Path,
+        book_config: &mdbook::Config,
+    ) -> Result<String, Error> {
+        let Generated {
+            config,
+            src_dir,
+            summary,
+            stats,
+            warnings,
+            ..
+        } = self.generate(root, book_config, None, false)?;
+        warnings.check()?;
+        log_stats(&src_dir, &config, &stats);
+        Ok(summary_to_markdown(&summary))
+    }
+
+    /// Like [`GenerateSummary::generate_markdown`], but serialize the summary to JSON (see the
+    /// README for its format).
+    pub fn generate_json(
+        &self,
+        root: &Path,
+        book_config: &mdbook::Config,
+    ) -> Result<String, Error> {
+        let Generated {
+            config,
+            src_dir,
+            summary,
+            stats,
+            warnings,
+            ..
+        } = self.generate(root, book_config, None, false)?;
+        warnings.check()?;
+        log_stats(&src_dir, &config, &stats);
+        Ok(summary_to_json(&summary))
+    }
+
+    /// Generate the summary of the book in `root` with the configuration of its book.toml and
+    /// serialize it to a tree diagram, see [`summary_to_graph`].
+    pub fn generate_graph(
+        &self,
+        root: &Path,
+        format: GraphFormat,
+        depth: Option<usize>,
+    ) -> Result<String, Error> {
+        let book_config = load_book_config(root)?;
+        let Generated {
+            config,
+            src_dir,
+            summary,
+            stats,
+            warnings,
+            ..
+        } = self.generate(root, &book_config, None, false)?;
+        warnings.check()?;
+        log_stats(&src_dir, &config, &stats);
+        Ok(summary_to_graph(&summary, format, depth))
+    }
+
+    /// Generate the summary of the book in `root` for `renderer`, up to the point where it is used.
+    /// Without a renderer, the renderer tables of the configuration are not used.
+    fn generate(
+        &self,
+        root: &Path,
+        book_config: &mdbook::Config,
+        renderer: Option<&str>,
+        watching: bool,
+    ) -> Result<Generated, Error> {
+        self.generate_explaining(root, book_config, renderer, watching, None)
+    }
+
+    /// Like [`GenerateSummary::generate`], recording the decisions about the `explained` location
+    /// (relative to the src directory) as diagnostics, see [`GenerateSummary::explain`].
+    fn generate_explaining(
+        &self,
+        root: &Path,
+        book_config: &mdbook::Config,
+        renderer: Option<&str>,
+        watching: bool,
+        explained: Option<&Path>,
+    ) -> Result<Generated, Error> {
+        let mut config = self.read_config(root, book_config, renderer)?;
+        let mut warnings = Warnings::new(config.allow.clone(), config.fail_on_warnings);
+        for warning in std::mem::take(&mut config.warnings) {
+            warnings.warn(warning.kind, warning.message);
+        }
+
+        let book_dir = &root.join(&book_config.book.src);
+        if !book_dir.is_dir() {
+            let absolute_book_dir = std::path::absolute(book_dir).unwrap_or(book_dir.clone());
+            let problem = if book_dir.exists() {
+                "is not a directory"
+            } else {
+                "does not exist"
+            };
+            return Err(anyhow!(
+                "The src directory {} (`book.src = \"{}\"`) {}. Create it or change `book.src` \
+                 in book.toml",
+                absolute_book_dir.display(),
+                book_config.book.src.display(),
+                problem
+            ));
+        }
+
+        // Resolve the src directory once, `book.src` may be absolute or contain '..'
+        let book_dir = book_dir.canonicalize().with_context(|| {
+            format!("Failed to resolve the src directory {}", book_dir.display())
+        })?;
+        if let Some(location) = explained {
+            warnings.explain_location(location.to_path_buf());
+        }
+        for chapter in &mut config.external_chapters {
+            chapter.path = root.join(&chapter.path);
+        }
+        for mount in &mut config.mounts {
+            mount.source = root.join(&mount.source);
+        }
+        config.converter_cache = config
+            .converter_cache
+            .take()
+            .map(|path| resolve_output_path("converter_cache", &root.join(path), &book_dir))
+            .transpose()?;
+        config.excluded_directories = output_directories(root, book_config)
+            .into_iter()
+            .filter(|directory| directory.starts_with(&book_dir))
+            .collect();
+        let manifest_path = config
+            .created_files_manifest
+            .as_ref()
+            .map(|path| resolve_output_path("created_files_manifest", &root.join(path), &book_dir))
+            .transpose()?;
+        let summary_json_path = config
+            .summary_json
+            .as_ref()
+            .map(|path| resolve_output_path("summary_json", &root.join(path), &book_dir))
+            .transpose()?;
+        let title_cache_path = config
+            .title_cache
+            .as_ref()
+            .map(|path| resolve_output_path("title_cache", &root.join(path), &book_dir))
+            .transpose()?;
+        if let Some(path) = config.diagnostics_json.as_ref().filter(|_| !config.dry_run) {
+            let path = resolve_output_path("diagnostics_json", &root.join(path), &book_dir)?;
+            WriteGuard::new(&config).check(&path)?;
+            warnings.write_diagnostics_to(path, book_dir.clone());
+        }
+
+        let created_files_ignore = config
+            .gitignore_created_files
+            .then(|| CreatedFilesIgnore::new(root))
+            .transpose()?;
+
+        let (summary, created_files, stats, excluded, conversions) = build_summary(
+            &book_dir,
+            &config,
+            &self.hooks,
+            watching,
+            manifest_path.as_deref(),
+            title_cache_path.as_deref(),
+            &mut warnings,
+        )?;
+        if let Some(created_files_ignore) = created_files_ignore.filter(|_| !config.dry_run) {
+            created_files_ignore.update(&created_files, &config)?;
+        }
+
+        Ok(Generated {
+            config,
+            src_dir: book_dir,
+            summary,
+            summary_json_path,
+            created_files,
+            stats,
+            excluded,
+            conversions,
+            warnings,
+        })
+    }
+
+    /// Generate the summary of the book in `root` configured by `book_config` and build the book
+    /// passed on to the `renderer` from it, see [`Preprocessor::run`]. The incoming `book` is the
+    /// one mdBook built from SUMMARY.md.
+    pub(crate) fn preprocess(
+        &self,
+        root: &Path,
+        book_config: &mdbook::Config,
+        renderer: &str,
+        book: Book,
+    ) -> Result<Preprocessed, Error> {
+        if !self.runs_for_renderer(root, renderer, book_config)? {
+            debug!(
+                "Not running for the renderer {}, passing the book on unchanged",
+                renderer
+            );
+            return Ok(Preprocessed::unchanged(book));
+        }
+        let config = self.read_config(root, book_config, Some(renderer))?;
+        if config.prefer_manual {
+            if let Some(summary_path) = manual_summary(&root.join(&book_config.book.src))? {
+                log!(
+                    config.info_level(),
+                    "Deferring to the hand-written {} (`prefer_manual`), passing the book on \
+                     unchanged",
+                    summary_path.display()
+                );
+                return Ok(Preprocessed::unchanged(book));
+            }
+        }
+
+        let Generated {
+            config,
+            src_dir,
+            summary,
+            summary_json_path,
+            stats,
+            excluded,
+            conversions,
+            mut warnings,
+            ..
+        } = self.generate(root, book_config, Some(renderer), is_watching(book_config))?;
+        check_existing_summary(&src_dir, &summary, &config, &mut warnings)?;
+        if config.diff_against_existing {
+            print_summary_diff(&src_dir, &summary, &config)?;
+        }
+        if config.dry_run {
+            warn!(
+                "Dry run (`dry_run` is set), the book is not changed. The generated summary is:\n{}",
+                summary_to_markdown(&summary)
+            );
+            warnings.check()?;
+            log_stats(&src_dir, &config, &stats);
+            return Ok(Preprocessed {
+                book,
+                summary: Some(summary),
+                warnings: Some(warnings),
+            });
+        }
+        if config.write_summary_file {
+            write_summary_file(&src_dir, &summary, &config)?;
+        }
+        if let Some(summary_json_path) = summary_json_path {
+            WriteGuard::new(&config).check(&summary_json_path)?;
+            std::fs::write(&summary_json_path, summary_to_json(&summary)).with_context(|| {
+                format!(
+                    "Failed to write the summary to {}",
+                    summary_json_path.display()
+                )
+            })?;
+        }
+        warnings.check()?;
+
+        let redirects: Vec<_> = config
+            .external_chapters
+            .iter()
+            .map(|chapter| (external_location(&chapter.path), chapter.path.clone()))
+            .chain(
+                config
+                    .mounts
+                    .iter()
+                    .map(|mount| (mount.at.clone(), mount.source.clone())),
+            )
+            .collect();
+        let mut generated =
+            load_book(&summary, &src_dir, &book, &redirects, &conversions, &config)?;
+        if !config.drop_foreign_chapters {
+            add_foreign_chapters(&mut generated, &book, &config);
+        }
+        let mut book = generated;
+        // Before the front matter is stripped, so that the lines match the files
+        if config.check_links {
+            check_links(&book, &src_dir, &excluded, self.hooks.fs(), &mut warnings);
+            warnings.check()?;
+        }
+        if config.strip_frontmatter {
+            strip_front_matter(&mut book);
+        }
+        if config.inject_breadcrumbs {
+            inject_breadcrumbs(&mut book, &config);
+        }
+        if config.generate_toc_chapter {
+            add_toc_chapter(&mut book, &summary, &config)?;
+        }
+        log_stats(&src_dir, &config, &stats);
+        Ok(Preprocessed {
+            book,
+            summary: Some(summary),
+            warnings: Some(warnings),
+        })
+    }
+}
+
+/// The book built from the generated summary, see [`GenerateSummary::preprocess`].
+pub(crate) struct Preprocessed {
+    pub(crate) book: Book,
+    /// The generated summary, unless the book was passed on unchanged without generating one.
+    pub(crate) summary: Option<Summary>,
+    /// The warnings of generating the summary, they are written to `diagnostics_json` once
+    /// dropped.
+    pub(crate) warnings: Option<Warnings>,
+}
+
+impl Preprocessed {
+    /// The incoming book, passed on without generating a summary.
+    fn unchanged(book: Book) -> Self {
+        Self {
+            book,
+            summary: None,
+            warnings: None,
+        }
+    }
+}
+
+/// A generated summary together with everything needed to use it.
+struct Generated {
+    config: Config,
+    /// The resolved src directory.
+    src_dir: PathBuf,
+    summary: Summary,
+    /// Where to write the summary as JSON, see `summary_json`.
+    summary_json_path: Option<PathBuf>,
+    /// The created chapter files, or the missing ones with `dry_run`.
+    created_files: Vec<PathBuf>,
+    stats: Stats,
+    /// The locations of the files and directories which are not part of the book.
+    excluded: Excluded,
+    /// The files converted to markdown by a `converter`.
+    conversions: Rc<Conversions>,
+    /// The warnings emitted so far, they still have to be checked.
+    warnings: Warnings,
+}
+
+/// Split the text into lines without line endings and trailing whitespace. Trailing empty lines
+/// are removed.
+fn normalize_lines(text: &str) -> Vec<&str> {
+    let mut lines: Vec<_> = text.lines().map(str::trim_end).collect();
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines
+}
+
+impl Preprocessor for GenerateSummary {
+    fn name(&self) -> &str {
+        "generate-summary"
+    }
+
+    /// Generate the summary and build the book from it.
+    fn run(&self, ctx: &PreprocessorContext, book: Book) -> Result<Book, Error> {
+        let preprocessed = self.preprocess(&ctx.root, &ctx.config, &ctx.renderer, book)?;
+        Ok(preprocessed.book)
+    }
+
+    fn supports_renderer(&self, renderer: &str) -> bool {
+        renderer != "not-supported"
+    }
+}
+
+/// The directories mdBook writes the book to (`build.build-dir`) and reads the theme of the html
+/// renderer from (`output.html.theme`), resolved against `root` if they exist.
+fn output_directories(root: &Path, book_config: &mdbook::Config) -> Vec<PathBuf> {
+    let theme = book_config
+        .get("output.html.theme")
+        .and_then(toml::Value::as_str)
+        .unwrap_or("theme");
+    [root.join(&book_config.build.build_dir), root.join(theme)]
+        .into_iter()
+        .filter_map(|directory| directory.canonicalize().ok())
+        .collect()
+}
+
+/// Load the book.toml of the book in `root`, including the overrides of the environment.
+fn load_book_config(root: &Path) -> Result<mdbook::Config, Error> {
+    let book_toml = root.join("book.toml");
+    let mut book_config = mdbook::Config::from_disk(&book_toml)
+        .with_context(|| format!("Failed to load {}", book_toml.display()))?;
+    book_config.update_from_env();
+    Ok(book_config)
+}
+
+/// The problems of the files and directories of the book in `root` which the configuration
+/// refers to and which do not exist, including the src directory.
+fn missing_paths(root: &Path, book_config: &mdbook::Config, config: &Config) -> Vec<String> {
+    let src_dir = root.join(&book_config.book.src);
+    if !src_dir.is_dir() {
+        return vec![format!(
+            "The src directory {} (`book.src = \"{}\"`) does not exist",
+            src_dir.display(),
+            book_config.book.src.display()
+        )];
+    }
+
+    let mut problems = vec![];
+    if let Some(outline) = &config.outline {
+        if !src_dir.join(outline).is_file() {
+            problems.push(format!(
+                "The outline {} (`outline`) does not exist",
+                src_dir.join(outline).display()
+            ));
+        }
+    }
+    for chapter in &config.external_chapters {
+        if !root.join(&chapter.path).is_file() {
+            problems.push(format!(
+                "The external chapter {} (`external_chapters`) does not exist",
+                root.join(&chapter.path).display()
+            ));
+        }
+    }
+    for mount in &config.mounts {
+        if !root.join(&mount.source).is_dir() {
+            problems.push(format!(
+                "The source {} of the mount at {} (`mount`) does not exist",
+                root.join(&mount.source).display(),
+                mount.at.display()
+            ));
+        }
+    }
+    problems
+}
+
+/// Make the path of a file written by the preprocessor (configured by `key`) absolute. It must not
+/// be inside of the src directory, as writing it would make `mdbook serve` rebuild the book again.
+fn resolve_output_path(key: &str, path: &Path, book_dir: &Path) -> Result<PathBuf, Error> {
+    let path = std::path::absolute(path)
+        .with_context(|| format!("Failed to resolve the path of `{}` {}", key, path.display()))?;
+    // The file does not exist before the first run, resolve its directory instead
+    let directory = path.parent().and_then(|parent| parent.canonicalize().ok());
+    if directory.is_some_and(|directory| directory.starts_with(book_dir)) {
+        return Err(anyhow!(
+            "`{}` ({}) must not be inside of the src directory {}, as writing it would trigger \
+             another build",
+            key,
+            path.display(),
+            book_dir.display()
+        ));
+    }
+    Ok(path)
+}
+
+/// Returns true if mdBook rebuilds the book whenever the src directory changes.
+/// `mdbook serve` is detected by the live reload endpoint it configures, other watchers can set the
+/// `MDBOOK_GENERATE_SUMMARY_WATCHING` environment variable.
+fn is_watching(book_config: &mdbook::Config) -> bool {
+    book_config
+        .get("output.html.live-reload-endpoint")
+        .is_some()
+        || std::env::var_os("MDBOOK_GENERATE_SUMMARY_WATCHING").is_some()
+}
+
+/// The path of the SUMMARY.md in `book_dir` if it is written by hand, see `prefer_manual`. A
+/// SUMMARY.md without chapters (like the placeholder `# Summary`) or one written by
+/// `write_summary_file` is not.
+fn manual_summary(book_dir: &Path) -> Result<Option<PathBuf>, Error> {
+    let summary_path = book_dir.join("SUMMARY.md");
+    if !summary_path.is_file() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&summary_path)
+        .with_context(|| format!("Failed to read {}", summary_path.display()))?;
+    if content.starts_with(GENERATED_HEADER) {
+        return Ok(None);
+    }
+    let summary = mdbook::book::parse_summary(&content)
+        .with_context(|| format!("Failed to parse {}", summary_path.display()))?;
+    Ok(Some(summary_path).filter(|_| !summary_entries(&summary).is_empty()))
+}
+
+/// Warn if the src directory contains a SUMMARY.md which differs from the generated summary, as
+/// it is ignored. Fails instead if `fail_if_summary_differs` is set.
+/// A SUMMARY.md without any chapters is only a placeholder for mdBook and never reported, neither is
+/// a SUMMARY.md written by [`write_summary_file`].
+fn check_existing_summary(
+    book_dir: &Path,
+    summary: &Summary,
+    config: &Config,
+    warnings: &mut Warnings,
+) -> Result<(), Error> {
+    let summary_path = book_dir.join("SUMMARY.md");
+    if !summary_path.is_file() {
+        return Ok(());
+    }
+    let content = std::fs::read_to_string(&summary_path)
+        .with_context(|| format!("Failed to read {}", summary_path.display()))?;
+    if content.starts_with(GENERATED_HEADER) {
+        return Ok(());
+    }
+    let existing_summary = mdbook::book::parse_summary(&content)
+        .with_context(|| format!("Failed to parse {}", summary_path.display()))?;
+
+    if summary_entries(&existing_summary).is_empty()
+        || summary_to_markdown(&existing_summary) == summary_to_markdown(summary)
+    {
+        return Ok(());
+    }
+
+    let mut message = format!(
+        "{} differs from the generated summary, its content is ignored.",
+        summary_path.display()
+    );
+    let diff = diff_summaries(&existing_summary, summary);
+    if diff.is_empty() {
+        message.push_str(
+            "\n  The chapters are the same, but the part titles, separators or the chapters \
+             without a section number differ.",
+        );
+    } else {
+        message.push_str(&format!("\n{}", diff));
+    }
+
+    if config.fail_if_summary_differs {
+        Err(anyhow!(message))
+    } else {
+        message.push_str(
+            "\n  Remove the chapters from SUMMARY.md or set `fail_if_summary_differs = true` to \
+             fail instead",
+        );
+        warnings.warn_at(WarningKind::SummaryDiffers, &summary_path, message);
+        Ok(())
+    }
+}
+
+/// Print how the generated summary differs from SUMMARY.md in the src directory, if it exists.
+/// Unlike [`check_existing_summary`] this includes a SUMMARY.md written by [`write_summary_file`],
+/// so that changes of the structure are noticed before it is overwritten.
+fn print_summary_diff(book_dir: &Path, summary: &Summary, config: &Config) -> Result<(), Error> {
+    let summary_path = book_dir.join("SUMMARY.md");
+    if !summary_path.is_file() {
+        return Ok(());
+    }
+    let content = std::fs::read_to_string(&summary_path)
+        .with_context(|| format!("Failed to read {}", summary_path.display()))?;
+    let content = content.strip_prefix(GENERATED_HEADER).unwrap_or(&content);
+    let existing_summary = mdbook::book::parse_summary(content)
+        .with_context(|| format!("Failed to parse {}", summary_path.display()))?;
+
+    let diff = diff_summaries(&existing_summary, summary);
+    if diff.is_empty() {
+        debug!(
+            "The generated summary has the same structure as {}",
+            summary_path.display()
+        );
+    } else {
+        log!(
+            config.info_level(),
+            "Changes of the generated summary compared to {}:\n{}",
+            summary_path.display(),
+            diff
+        );
+    }
+    Ok(())
+}
+
+/// Write the summary to SUMMARY.md in the src directory. The file is only written if its content
+/// changes, so that watchers do not rebuild the book again. Returns true if it changed, it is not
+/// written with `dry_run`.
+fn write_summary_file(book_dir: &Path, summary: &Summary, config: &Config) -> Result<bool, Error> {
+    let summary_path = book_dir.join("SUMMARY.md");
+    let content = format!("{}\n\n{}", GENERATED_HEADER, summary_to_markdown(summary));
+    if std::fs::read_to_string(&summary_path).is_ok_and(|existing| existing == content) {
+        return Ok(false);
+    }
+    if config.dry_run {
+        log!(
+            config.info_level(),
+            "Dry run, not writing the generated summary to {}",
+            summary_path.display()
+        );
+        return Ok(true);
+    }
+
+    WriteGuard::new(config).check(&summary_path)?;
+    std::fs::write(&summary_path, content)
+        .with_context(|| format!("Failed to write {}", summary_path.display()))?;
+    log!(
+        config.info_level(),
+        "Wrote the generated summary to {}",
+        summary_path.display()
+    );
+    Ok(true)
+}
+
+/// Describe every chapter of the summary by its name and location, in the order of the summary.
+fn summary_entries(summary: &Summary) -> Vec<String> {
+    fn add_entries(items: &[SummaryItem], entries: &mut Vec<String>) {
+        for item in items {
+            if let SummaryItem::Link(link) = item {
+                entries.push(match &link.location {
+                    Some(location) => format!("{} ({})", link.name, location.display()),
+                    None => format!("{} (draft)", link.name),
+                });
+                add_entries(&link.nested_items, entries);
+            }
+        }
+    }
+
+    let mut entries = vec![];
+    add_entries(&summary.prefix_chapters, &mut entries);
+    add_entries(&summary.numbered_chapters, &mut entries);
+    add_entries(&summary.suffix_chapters, &mut entries);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, time::SystemTime};
+
+    use mdbook::BookItem;
+
+    use super::*;
+    use crate::TestBook;
+
+    /// Preprocess the book in `root` written before for the html renderer, like mdBook does with
+    /// the `book_config`.
+    fn preprocess(root: &Path, book_config: &mdbook::Config) -> Result<Preprocessed, Error> {
+        GenerateSummary::new().preprocess(root, book_config, "html", Book::new())
+    }
+
+    /// The names of all chapters of the book, depth first.
+    fn chapter_names(book: &Book) -> Vec<&str> {
+        book.iter()
+            .filter_map(|item| match item {
+                BookItem::Chapter(chapter) => Some(chapter.name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The paths of all chapters of the book relative to the src directory, depth first.
+    fn chapter_paths(book: &Book) -> Vec<String> {
+        book.iter()
+            .filter_map(|item| match item {
+                BookItem::Chapter(chapter) => chapter.path.as_ref(),
+                _ => None,
+            })
+            .map(|path| path.display().to_string())
+            .collect()
+    }
+
+    /// The content and modification time of every file in `dir` and its subdirectories by their
+    /// path, to check that nothing was written.
+    fn files(dir: &Path) -> BTreeMap<PathBuf, (Vec<u8>, SystemTime)> {
+        let mut files = BTreeMap::new();
+        let mut dirs = vec![dir.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(&dir).unwrap() {
+                let path = entry.unwrap().path();
+                let metadata = std::fs::metadata(&path).unwrap();
+                if metadata.is_dir() {
+                    dirs.push(path);
+                } else {
+                    let content = std::fs::read(&path).unwrap();
+                    files.insert(path, (content, metadata.modified().unwrap()));
+                }
+            }
+        }
+        files
+    }
+
+    #[test]
+    fn uses_the_defaults_without_a_preprocessor_table() -> Result<(), Error> {
+        let book = TestBook::new()
+            .file("intro.md", "# Introduction\n")
+            .file("guide/README.md", "# Guide\n")
+            .file("guide/setup.md", "# Setup\n");
+        book.write()?;
+
+        let without_table = mdbook::Config::default();
+        assert!(without_table.get_preprocessor("generate-summary").is_none());
+        let empty_table = "[preprocessor.generate-summary]".parse()?;
+        for book_config in [without_table, empty_table] {
+            let preprocessed = preprocess(book.root(), &book_config)?;
+            assert_eq!(
+                chapter_names(&preprocessed.book),
+                ["guide", "setup", "intro"]
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn supports_src_directories_outside_of_the_root() -> Result<(), Error> {
+        let shared = TestBook::new()
+            .file("intro.md", "# Introduction\n")
+            .file("guide/README.md", "# Guide\n")
+            .file("guide/setup.md", "# Setup\n");
+        shared.write()?;
+        let book = TestBook::new();
+        book.write()?;
+
+        let relative = Path::new("..")
+            .join(shared.root().file_name().unwrap())
+            .join("src");
+        for src in [shared.src_dir(), relative] {
+            let mut book_config = mdbook::Config::default();
+            book_config.book.src = src;
+            let preprocessed = preprocess(book.root(), &book_config)?;
+            assert_eq!(
+                chapter_paths(&preprocessed.book),
+                ["guide/README.md", "guide/setup.md", "intro.md"]
+            );
+            let BookItem::Chapter(intro) = &preprocessed.book.sections[1] else {
+                panic!("Expected the introduction");
+            };
+            assert_eq!(intro.content, "# Introduction\n");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn uses_locations_relative_to_src_with_an_absolute_root() -> Result<(), Error> {
+        let book = TestBook::new()
+            .file("intro.md", "# Introduction\n")
+            .file("guide/README.md", "# Guide\n")
+            .file("reference/api.md", "# API\n")
+            .config(toml::toml! { create_missing_chapter_files = true });
+        book.write()?;
+
+        // Like mdBook invoked from another directory, with a root which is not normalized
+        let root = book
+            .root()
+            .join(".")
+            .join("..")
+            .join(book.root().file_name().unwrap());
+        assert!(root.is_absolute());
+        let preprocessed = preprocess(&root, &mdbook::Config::default())?;
+        // The created chapter file of reference/ as well
+        let expected = [
+            "guide/README.md",
+            "intro.md",
+            "reference/README.md",
+            "reference/api.md",
+        ];
+        assert_eq!(chapter_paths(&preprocessed.book), expected);
+        let source_paths: Vec<_> = preprocessed
+            .book
+            .iter()
+            .filter_map(|item| match item {
+                BookItem::Chapter(chapter) => chapter.source_path.as_ref(),
+                _ => None,
+            })
+            .map(|path| path.display().to_string())
+            .collect();
+        assert_eq!(source_paths, expected);
+        assert!(book.src_dir().join("reference/README.md").is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn does_not_write_anything_on_the_second_run() -> Result<(), Error> {
+        let book = TestBook::new()
+            .file("intro.md", "# Introduction\n")
+            .file("guide/setup.md", "# Setup\n")
+            .file("reference/api/usage.md", "# Usage\n")
+            .config(toml::toml! { create_missing_chapter_files = true });
+        book.write()?;
+        let book_config = mdbook::Config::default();
+
+        let first = preprocess(book.root(), &book_config)?;
+        let written = files(book.root());
+        assert!(written.contains_key(&book.src_dir().join("reference/api/README.md")));
+        let second = preprocess(book.root(), &book_config)?;
+        assert_eq!(files(book.root()), written);
+        assert_eq!(chapter_paths(&second.book), chapter_paths(&first.book));
+        Ok(())
+    }
+
+    #[test]
+    fn creates_files_only_when_not_watching_with_once() -> Result<(), Error> {
+        let book = TestBook::new()
+            .file("guide/setup.md", "# Setup\n")
+            .config(toml::toml! { create_missing_chapter_files = "once" });
+        book.write()?;
+        let written = files(book.root());
+
+        // `mdbook serve` sets the endpoint, the file would trigger another build
+        let watching = "[output.html]\nlive-reload-endpoint = \"reload\"".parse()?;
+        let preprocessed = preprocess(book.root(), &watching)?;
+        assert_eq!(chapter_paths(&preprocessed.book), ["guide/setup.md"]);
+        assert_eq!(files(book.root()), written);
+
+        let preprocessed = preprocess(book.root(), &mdbook::Config::default())?;
+        assert_eq!(
+            chapter_paths(&preprocessed.book),
+            ["guide/README.md", "guide/setup.md"]
+        );
+        assert!(book.src_dir().join("guide/README.md").is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn generates_the_same_summary_on_every_run() -> Result<(), Error> {
+        for (create, ignore) in [
+            (toml::Value::from(false), false),
+            (false.into(), true),
+            (true.into(), false),
+            ("once".into(), false),
+        ] {
+            let config = [
+                ("create_missing_chapter_files", create.clone()),
+                ("ignore_missing_chapter_files", ignore.into()),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect();
+            let book = TestBook::new()
+                .file("intro.md", "# Introduction\n")
+                .file("guide/setup.md", "# Setup\n")
+                .file("guide/advanced/tuning.md", "# Tuning\n")
+                .file("reference/README.md", "# Reference\n")
+                .config(toml::Value::Table(config));
+            book.write()?;
+            let run = || {
+                preprocess(book.root(), &mdbook::Config::default())
+                    .map(|preprocessed| preprocessed.summary)
+                    .map_err(|error| format!("{:#}", error))
+            };
+
+            let first = run();
+            // Only fails if missing chapter files are neither created nor ignored
+            assert_eq!(first.is_ok(), create != false.into() || ignore);
+            let written = files(book.root());
+            let second = run();
+            assert_eq!(first, second, "create: {}, ignore: {}", create, ignore);
+            assert_eq!(files(book.root()), written);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn warns_about_a_differing_summary_file() -> Result<(), Error> {
+        let book = TestBook::new();
+        book.write()?;
+        let src_dir = book.src_dir();
+        let generated = mdbook::book::parse_summary(
+            "# Summary\n\n- [guide](guide/README.md)\n    - [setup](guide/setup.md)\n\
+             - [intro](intro.md)\n",
+        )?;
+        let check = |content: &str, config: &Config| {
+            std::fs::write(src_dir.join("SUMMARY.md"), content).unwrap();
+            let mut warnings = Warnings::new(vec![], false);
+            check_existing_summary(&src_dir, &generated, config, &mut warnings)
+                .map(|()| warnings.diagnostics(&src_dir))
+                .map_err(|error| format!("{:#}", error))
+        };
+        let manual = "# Summary\n\n- [Introduction](intro.md)\n- [Old](old.md)\n";
+
+        let diagnostics = check(manual, &Config::default()).unwrap();
+        let [diagnostic] = diagnostics.as_slice() else {
+            panic!("Expected a single warning, got {:?}", diagnostics);
+        };
+        let differences = "differs from the generated summary, its content is ignored.\n  \
+                           Added (2):\n    \
+                           guide (guide/README.md)\n    \
+                           setup (guide/setup.md)\n  \
+                           Removed (1):\n    \
+                           Old (old.md)\n  \
+                           Renamed (1):\n    \
+                           intro (intro.md): `Introduction` -> `intro`";
+        assert_eq!(diagnostic.code, "summary-differs");
+        assert!(diagnostic.message.contains(differences));
+        assert!(diagnostic.message.ends_with(
+            "Remove the chapters from SUMMARY.md or set `fail_if_summary_differs = true` to fail \
+             instead"
+        ));
+
+        let config = Config::builder().fail_if_summary_differs(true).build()?;
+        assert!(check(manual, &config).unwrap_err().contains(differences));
+
+        // Unless it has no chapters, is the same or was generated
+        for content in [
+            "# Summary\n".to_owned(),
+            summary_to_markdown(&generated),
+            format!("{}\n{}", GENERATED_HEADER, manual),
+        ] {
+            assert_eq!(check(&content, &config), Ok(vec![]));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn writes_a_summary_file_mdbook_parses_back() -> Result<(), Error> {
+        let book = TestBook::new()
+            .file("intro.md", "# Introduction\n")
+            .file("setup [linux].md", "# Setup [Linux] \\ (x86)\n")
+            .file("my guide/README.md", "# My guide\n")
+            .file("my guide/drafts/plans.md", "# Plans\n")
+            .file("appendix/credits.md", "# Credits\n")
+            .config(toml::toml! {
+                get_chapter_name_from_file = true
+                ignore_missing_chapter_files = true
+                unnumbered_directories = ["appendix"]
+                write_summary_file = true
+            });
+        book.write()?;
+        let run = || preprocess(book.root(), &mdbook::Config::default());
+
+        let mut summary = run()?.summary.unwrap();
+        let content = std::fs::read_to_string(book.src_dir().join("SUMMARY.md"))?;
+        assert!(content.starts_with(GENERATED_HEADER));
+        // mdBook uses the default title, and unnumbered chapters can not be nested in a SUMMARY.md
+        summary.title = Some("Summary".to_owned());
+        let SummaryItem::Link(appendix) = &mut summary.suffix_chapters[0] else {
+            panic!("Expected the appendix");
+        };
+        let credits = std::mem::take(&mut appendix.nested_items);
+        summary.suffix_chapters.extend(credits);
+        assert_eq!(mdbook::book::parse_summary(&content)?, summary);
+
+        // It is only written if it changed
+        let written = files(book.root());
+        run()?;
+        assert_eq!(files(book.root()), written);
+        Ok(())
+    }
+
+    #[test]
+    fn lists_the_created_files_in_the_manifest() -> Result<(), Error> {
+        for manifest in ["created.json", "created.txt"] {
+            let mut config = toml::toml! { create_missing_chapter_files = true };
+            config
+                .as_table_mut()
+                .unwrap()
+                .insert("created_files_manifest".to_owned(), manifest.into());
+            let book = TestBook::new()
+                .file("intro.md", "# Introduction\n")
+                .file("guide/setup.md", "# Setup\n")
+                .file("guide/advanced/tuning.md", "# Tuning\n")
+                .file("reference/README.md", "# Reference\n")
+                .file("reference/api/usage.md", "# Usage\n")
+                .config(config);
+            book.write()?;
+            let manifest_path = book.root().join(manifest);
+            let read_manifest = || -> Result<Vec<PathBuf>, Error> {
+                let content = std::fs::read_to_string(&manifest_path)?;
+                Ok(if manifest.ends_with(".json") {
+                    serde_json::from_str(&content)?
+                } else {
+                    content.lines().map(PathBuf::from).collect()
+                })
+            };
+
+            let before = files(book.root());
+            preprocess(book.root(), &mdbook::Config::default())?;
+            let mut created: Vec<_> = files(book.root())
+                .into_keys()
+                .filter(|path| !before.contains_key(path) && *path != manifest_path)
+                .map(|path| path.strip_prefix(book.src_dir()).unwrap().to_owned())
+                .collect();
+            assert_eq!(created.len(), 3);
+            let mut listed = read_manifest()?;
+            created.sort();
+            listed.sort();
+            assert_eq!(listed, created);
+
+            // Nothing is created by the next run
+            preprocess(book.root(), &mdbook::Config::default())?;
+            assert!(read_manifest()?.is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn loads_unnumbered_sections_in_the_configured_order() -> Result<(), Error> {
+        let output = TestBook::new()
+            .file("intro.md", "# Introduction\n")
+            .file("appendix/README.md", "# Appendix\n")
+            .file("appendix/glossary.md", "# Glossary\n")
+            .file("contributing/README.md", "# Contributing\n")
+            .file("contributing/style/README.md", "# Style\n")
+            .file("contributing/style/naming.md", "# Naming\n")
+            .file(
+                "faq/README.md",
+                "---\nsummary_unnumbered: true\n---\n# FAQ\n",
+            )
+            .file("guide/README.md", "# Guide\n")
+            .config(toml::toml! {
+                get_chapter_name_from_file = true
+                unnumbered_directories = ["contributing", "appendix"]
+            })
+            .run()?;
+        // Like the configuration, then the ones marked in their front matter
+        assert_eq!(
+            output.chapter_names(),
+            [
+                "1. Guide",
+                "2. Introduction",
+                "Contributing",
+                "Style",
+                "Naming",
+                "Appendix",
+                "Glossary",
+                "FAQ",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn runs_for_the_configured_renderers() -> Result<(), Error> {
+        let renderers = ["html", "linkcheck", "json", "not-supported"];
+        for (table, expected) in [
+            ("", [true, true, true, false]),
+            (
+                "renderers = [\"html\", \"linkcheck\"]",
+                [true, true, false, false],
+            ),
+            ("skip_renderers = [\"json\"]", [true, true, false, false]),
+            ("renderers = []", [false, false, false, false]),
+        ] {
+            let book_config = format!("[preprocessor.generate-summary]\n{}", table).parse()?;
+            let supported = renderers.map(|renderer| {
+                GenerateSummary::new()
+                    .supports_renderer_with_config(renderer, &book_config)
+                    .unwrap()
+            });
+            assert_eq!(supported, expected, "{}", table);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn creates_missing_chapter_files_like_configured_or_like_mdbook() -> Result<(), Error> {
+        // `build.create-missing`, `create_missing_chapter_files` and if the file is created
+        let cases = [
+            (true, Some(true), true),
+            (true, Some(false), false),
+            (false, Some(true), true),
+            (false, Some(false), false),
+            // Without the key of the preprocessor, like mdBook
+            (true, None, true),
+            (false, None, false),
+        ];
+        for (create_missing, create_missing_chapter_files, created) in cases {
+            let book_config = format!("[build]\ncreate-missing = {}", create_missing).parse()?;
+            let mut config = toml::map::Map::new();
+            if let Some(value) = create_missing_chapter_files {
+                config.insert("create_missing_chapter_files".to_owned(), value.into());
+            }
+            let book = TestBook::new()
+                .file("guide/setup.md", "# Setup\n")
+                .book_config(book_config)
+                .config(toml::Value::Table(config));
+            let result = book.run();
+
+            let case = format!("{:?}", (create_missing, create_missing_chapter_files));
+            assert_eq!(
+                book.src_dir().join("guide/README.md").is_file(),
+                created,
+                "{}",
+                case
+            );
+            match result {
+                Ok(output) => {
+                    assert!(created, "{}", case);
+                    assert_eq!(
+                        chapter_paths(&output.book),
+                        ["guide/README.md", "guide/setup.md"]
+                    );
+                }
+                Err(error) => {
+                    assert!(!created, "{}", case);
+                    assert!(
+                        format!("{:#}", error).contains("Missing chapter file"),
+                        "{}",
+                        case
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn prefers_a_hand_written_summary_file() -> Result<(), Error> {
+        let book = |summary: &str| {
+            TestBook::new()
+                .file("SUMMARY.md", summary)
+                .file("intro.md", "# Introduction\n")
+                .file("usage.md", "# Usage\n")
+                .config(toml::toml! {
+                    prefer_manual = true
+                    write_summary_file = true
+                    created_files_manifest = "created.json"
+                })
+        };
+
+        // The hand-written summary is kept and nothing is written
+        let manual = "# Summary\n\n- [Usage](usage.md)\n";
+        let hand_written = book(manual);
+        let output = hand_written.run()?;
+        // The test book writes its files again on every run, so only compare which exist
+        let written: Vec<_> = files(hand_written.root()).into_keys().collect();
+        assert_eq!(output.chapter_names(), ["1. Usage"]);
+        assert!(output.summary.is_none());
+        hand_written.run()?;
+        assert!(files(hand_written.root()).into_keys().eq(written));
+        let summary_path = hand_written.src_dir().join("SUMMARY.md");
+        assert_eq!(std::fs::read_to_string(summary_path)?, manual);
+        assert!(!hand_written.root().join("created.json").exists());
+
+        // Without chapters in SUMMARY.md, the summary is generated as usual
+        let generated = book("# Summary\n");
+        assert_eq!(generated.run()?.chapter_names(), ["1. intro", "2. usage"]);
+        assert!(generated.root().join("created.json").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn escapes_special_characters_in_the_summary_file_and_the_toc() -> Result<(), Error> {
+        let book = TestBook::new()
+            .file("C# interop.md", "# C# interop\n")
+            .file("FAQ (draft).md", "# FAQ\n\nSee [C#](C%23%20interop.md).\n")
+            .config(toml::toml! {
+                write_summary_file = true
+                generate_toc_chapter = true
+                check_links = true
+                fail_on_warnings = true
+            });
+        book.write()?;
+
+        // The second build reads the SUMMARY.md written by the first one
+        for _ in 0..2 {
+            let mut mdbook = mdbook::MDBook::load(book.root())?;
+            mdbook.with_preprocessor(GenerateSummary::new());
+            mdbook.build()?;
+        }
+        let summary = std::fs::read_to_string(book.src_dir().join("SUMMARY.md"))?;
+        assert!(summary
+            .ends_with("- [C# interop](C#%20interop.md)\n- [FAQ (draft)](FAQ%20\\(draft\\).md)\n"));
+        assert!(book.root().join("book/C# interop.html").exists());
+        let faq = std::fs::read_to_string(book.root().join("book/FAQ (draft).html"))?;
+        assert!(faq.contains("<a href=\"C%23%20interop.html\">C#</a>"));
+        let toc = std::fs::read_to_string(book.root().join("book/toc.html"))?;
+        assert!(toc.contains("<a href=\"C%23%20interop.html\">1. C# interop</a>"));
+        assert!(toc.contains("<a href=\"FAQ%20%28draft%29.html\">2. FAQ (draft)</a>"));
+        Ok(())
+    }
+
+    #[test]
+    fn numbers_the_book_from_the_first_section_number() -> Result<(), Error> {
+        let output = TestBook::new()
+            .file("install.md", "# Installing\n")
+            .file("usage/README.md", "# Usage\n")
+            .file("usage/cli.md", "# CLI\n")
+            .config(toml::toml! {
+                first_section_number = 7
+                generate_toc_chapter = true
+                toc_position = "numbered"
+            })
+            .run()?;
+        // Including the table of contents, after the offset
+        assert_eq!(
+            output.chapter_names(),
+            ["7. install", "8. usage", "8.1. cli", "9. Table of contents"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_the_options_which_write_with_assert_no_writes() -> Result<(), Error> {
+        let writing: [(&str, toml::Value); 9] = [
+            ("create_missing_chapter_files", true.into()),
+            ("create_missing_chapter_files", "once".into()),
+            ("write_summary_file", true.into()),
+            ("created_files_manifest", "created.txt".into()),
+            ("gitignore_created_files", true.into()),
+            ("summary_json", "summary.json".into()),
+            ("diagnostics_json", "diagnostics.json".into()),
+            ("title_cache", ".generate-summary-cache.json".into()),
+            ("converter_cache", ".generate-summary-converted".into()),
+        ];
+        for (key, value) in writing {
+            let mut config = toml::toml! { assert_no_writes = true };
+            config.as_table_mut().unwrap().insert(key.to_owned(), value);
+            let book = TestBook::new()
+                .file("intro.md", "# Introduction\n")
+                .config(config);
+            let error = format!("{:#}", book.run().unwrap_err());
+            assert!(error.contains(&format!("`{}` would write to the book", key)));
+        }
+
+        // mdBook's `build.create-missing` is not used, so missing chapter files fail the build
+        let book = TestBook::new()
+            .file("guide/setup.md", "# Setup\n")
+            .config(toml::toml! { assert_no_writes = true });
+        let error = format!("{:#}", book.run().unwrap_err());
+        assert!(error.contains("Missing chapter file"));
+        assert!(!book.src_dir().join("guide/README.md").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn adds_converted_files_with_their_markdown() -> Result<(), Error> {
+        let book = TestBook::new()
+            .file("intro.md", "# Introduction\n")
+            .file("usage.adoc", "= Usage\n\nRun it.\n")
+            .config(toml::toml! {
+                get_chapter_name_from_file = true
+
+                [[converter]]
+                extensions = ["adoc"]
+                command = "sed -e s/^=/#/ {input}"
+            });
+        let output = book.run()?;
+        assert_eq!(output.chapter_names(), ["1. Introduction", "2. Usage"]);
+        // At the location with the extension `.md`, which is not written
+        assert_eq!(output.content("usage.md"), Some("# Usage\n\nRun it.\n"));
+        assert!(!book.src_dir().join("usage.md").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn writes_the_diagnostics_with_stable_codes() -> Result<(), Error> {
+        let book = TestBook::new()
+            .file("intro.md", "# Introduction\n")
+            .file("guide.md", "# Guide\n")
+            .file("guide/setup.md", "# Setup\n")
+            .file("reference/api.md", "# API\n")
+            .config(toml::toml! {
+                create_missing_chapter_files = true
+                diagnostics_json = "diagnostics.json"
+                unknwon = true
+            });
+        book.run()?;
+
+        let content = std::fs::read_to_string(book.root().join("diagnostics.json"))?;
+        let json: serde_json::Value = serde_json::from_str(&content)?;
+        assert_eq!(json["version"], 1);
+        let diagnostics = json["diagnostics"].as_array().unwrap();
+        for diagnostic in diagnostics {
+            let keys: Vec<_> = diagnostic.as_object().unwrap().keys().collect();
+            assert_eq!(keys, ["code", "level", "message", "path"]);
+        }
+        let find = |code: &str| {
+            diagnostics
+                .iter()
+                .find(|diagnostic| diagnostic["code"] == code)
+                .unwrap_or_else(|| panic!("Expected a {} diagnostic in {}", code, content))
+        };
+
+        let unknown = find("unknown-config-keys");
+        assert_eq!(unknown["level"], "warning");
+        assert_eq!(unknown["path"], serde_json::Value::Null);
+        assert!(unknown["message"].as_str().unwrap().contains("unknwon"));
+        let collision = find("file-directory-collisions");
+        assert_eq!(collision["level"], "warning");
+        assert_eq!(collision["path"], "guide.md");
+        assert_eq!(find("created-chapter-file")["level"], "info");
+        let created: Vec<_> = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic["code"] == "created-chapter-file")
+            .map(|diagnostic| diagnostic["path"].as_str().unwrap())
+            .collect();
+        assert_eq!(created, ["guide/README.md", "reference/README.md"]);
+        Ok(())
+    }
+
+    #[test]
+    fn leaves_out_the_build_and_theme_directories_inside_of_src() -> Result<(), Error> {
+        let book = TestBook::new()
+            .file("intro.md", "# Introduction\n")
+            .file("theme/README.md", "# Theme\n")
+            .book_config(toml::toml! {
+                [build]
+                build-dir = "src/book"
+
+                [output.html]
+                theme = "src/theme"
+            });
+        let first = book.run()?;
+        assert_eq!(first.chapter_names(), ["1. intro"]);
+
+        // Like the output of the markdown renderer, which must not become part of the next build
+        let output = book.src_dir().join("book");
+        std::fs::create_dir_all(&output)?;
+        std::fs::write(output.join("intro.md"), "# Introduction\n")?;
+        std::fs::write(output.join("README.md"), "# Build\n")?;
+        for _ in 0..2 {
+            let rebuilt = book.run()?;
+            assert_eq!(rebuilt.chapter_names(), first.chapter_names());
+            assert_eq!(rebuilt.summary, first.summary);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    cell::{Cell, RefCell},
+    io::{self, IsTerminal, Stderr, Write},
+    time::{Duration, Instant},
+};
+
+use crate::Stats;
+
+/// How often [`TerminalProgress`] redraws its line at most.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Reports the progress while the src directory is traversed, see
+/// [`crate::GenerateSummary::with_progress`].
+pub trait ProgressReporter {
+    /// Called whenever a directory is visited or an entry of it was added, with the counters so
+    /// far. Only the counters of the traversal are set, e.g. `directories` and `files_read`.
+    fn update(&self, stats: &Stats);
+
+    /// Called once the traversal ended, before the summary is validated and anything else is
+    /// logged. Also called if it failed.
+    fn finish(&self) {}
+}
+
+/// Shows the directories scanned and files processed so far on a single line of a terminal,
+/// which is redrawn with carriage returns and cleared once the traversal ends. Nothing is written
+/// if the output is not a terminal, e.g. if it is redirected to a file.
+///
+/// # Example
+/// ```
+/// use mdbook_generate_summary::{ProgressReporter, Stats, TerminalProgress};
+///
+/// let stats = Stats {
+///     directories: 3,
+///     files_read: 5,
+///     cached_titles: 2,
+///     ..Stats::default()
+/// };
+///
+/// let progress = TerminalProgress::new(Vec::new(), true);
+/// progress.update(&stats);
+/// progress.finish();
+/// assert_eq!(
+///     String::from_utf8(progress.into_inner())?,
+///     "\r\x1b[2KScanned 3 directories, processed 7 files\r\x1b[2K"
+/// );
+///
+/// // Without a terminal, there are no control characters or anything else
+/// let progress = TerminalProgress::new(Vec::new(), false);
+/// progress.update(&stats);
+/// progress.finish();
+/// assert!(progress.into_inner().is_empty());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct TerminalProgress<W: Write> {
+    writer: RefCell<W>,
+    is_terminal: bool,
+    last_redraw: Cell<Option<Instant>>,
+    /// Whether the line was drawn since it was cleared last.
+    drawn: Cell<bool>,
+}
+
+impl TerminalProgress<Stderr> {
+    /// Show the progress on stderr, if it is a terminal.
+    pub fn stderr() -> Self {
+        Self::new(io::stderr(), io::stderr().is_terminal())
+    }
+}
+
+impl<W: Write> TerminalProgress<W> {
+    /// Show the progress on `writer`, only if `is_terminal` is set.
+    pub fn new(writer: W, is_terminal: bool) -> Self {
+        Self {
+            writer: RefCell::new(writer),
+            is_terminal,
+            last_redraw: Cell::new(None),
+            drawn: Cell::new(false),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
+    /// Write the `text` after clearing the line. Failing to show the progress does not fail the
+    /// generation.
+    fn redraw(&self, text: &str) {
+        let mut writer = self.writer.borrow_mut();
+        let _ = write!(writer, "\r\x1b[2K{}", text);
+        let _ = writer.flush();
+    }
+}
+
+impl<W: Write> ProgressReporter for TerminalProgress<W> {
+    fn update(&self, stats: &Stats) {
+        if !self.is_terminal {
+            return;
+        }
+        let now = Instant::now();
+        if self
+            .last_redraw
+            .get()
+            .is_some_and(|last_redraw| now.duration_since(last_redraw) < REDRAW_INTERVAL)
+        {
+            return;
+        }
+        self.last_redraw.set(Some(now));
+        self.drawn.set(true);
+        self.redraw(&format!(
+            "Scanned {} directories, processed {} files",
+            stats.directories,
+            stats.files_read + stats.cached_titles
+        ));
+    }
+
+    fn finish(&self) {
+        self.last_redraw.set(None);
+        if self.drawn.replace(false) {
+            self.redraw("");
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::{Context, Error};
+use log::{debug, log};
+use mdbook::{book::Book, renderer::RenderContext, BookItem};
+
+use crate::{
+    book::{Link, Summary, SummaryItem},
+    json::summary_to_json,
+    markdown::summary_to_markdown,
+    GenerateSummary,
+};
+
+/// The files the renderer writes to its output directory.
+const SUMMARY_FILE: &str = "SUMMARY.md";
+const SUMMARY_JSON_FILE: &str = "summary.json";
+
+impl GenerateSummary {
+    /// Render the structure of the book to SUMMARY.md and summary.json in the output directory
+    /// of the renderer, when run as the `generate-summary` renderer (`[output.generate-summary]`).
+    /// The structure is the one of the final book, after every preprocessor ran, with the same
+    /// format as `write_summary_file` and `summary_json`.
+    ///
+    /// # Example
+    /// ```
+    /// use mdbook::{book::Book, renderer::RenderContext, BookItem, Config};
+    /// use mdbook::book::{Chapter, SectionNumber};
+    /// use mdbook_generate_summary::GenerateSummary;
+    ///
+    /// let root = std::env::temp_dir().join(format!(
+    ///     "generate-summary-render-doctest-{}",
+    ///     std::process::id()
+    /// ));
+    /// let mut book = Book::new();
+    /// book.push_item(Chapter::new("Introduction", String::new(), "intro.md", vec![]));
+    /// let mut guide = Chapter::new("Guide", String::new(), "guide/README.md", vec![]);
+    /// guide.number = Some(SectionNumber(vec![1]));
+    /// let mut setup = Chapter::new_draft("Setup", vec!["Guide".to_owned()]);
+    /// setup.number = Some(SectionNumber(vec![1, 1]));
+    /// guide.sub_items.push(BookItem::Chapter(setup));
+    /// book.push_item(guide);
+    /// book.push_item(BookItem::Separator);
+    /// book.push_item(Chapter::new("Credits", String::new(), "credits.md", vec![]));
+    ///
+    /// // mdBook passes the context as JSON on stdin
+    /// let destination = root.join("book/generate-summary");
+    /// let ctx = RenderContext::new(&root, book, Config::default(), &destination);
+    /// let ctx = RenderContext::from_json(serde_json::to_vec(&ctx)?.as_slice())?;
+    /// GenerateSummary::new().render(&ctx)?;
+    ///
+    /// assert_eq!(
+    ///     std::fs::read_to_string(destination.join("SUMMARY.md"))?,
+    ///     "# Summary
+    ///
+    /// [Introduction](intro.md)
+    ///
+    /// - [Guide](guide/README.md)
+    ///     - [Setup]()
+    ///
+    /// [Credits](credits.md)
+    /// "
+    /// );
+    /// let json: serde_json::Value =
+    ///     serde_json::from_str(&std::fs::read_to_string(destination.join("summary.json"))?)?;
+    /// assert_eq!(json["chapters"][1]["children"][0]["number"], "1.1");
+    /// assert_eq!(json["chapters"][1]["children"][0]["draft"], true);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn render(&self, ctx: &RenderContext) -> Result<(), Error> {
+        let config = self.read_config(&ctx.root, &ctx.config, None)?;
+        let mut summary = book_to_summary(&ctx.book);
+        summary.title = config.title.clone();
+
+        std::fs::create_dir_all(&ctx.destination)
+            .with_context(|| format!("Failed to create {}", ctx.destination.display()))?;
+        for (file_name, content) in [
+            (SUMMARY_FILE, summary_to_markdown(&summary)),
+            (SUMMARY_JSON_FILE, summary_to_json(&summary)),
+        ] {
+            let path = ctx.destination.join(file_name);
+            std::fs::write(&path, content)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        log!(
+            config.info_level(),
+            "Wrote the structure of the book to {}",
+            ctx.destination.display()
+        );
+        Ok(())
+    }
+}
+
+/// The summary of the structure of the `book`. The unnumbered chapters before the first numbered
+/// chapter or part title are prefix chapters, those after the last numbered chapter are suffix
+/// chapters.
+fn book_to_summary(book: &Book) -> Summary {
+    let is_numbered = |item: &BookItem| match item {
+        BookItem::Chapter(chapter) => chapter.number.is_some(),
+        BookItem::PartTitle(_) => true,
+        BookItem::Separator => false,
+    };
+    let start = book
+        .sections
+        .iter()
+        .position(is_numbered)
+        .unwrap_or(book.sections.len());
+    let end = book
+        .sections
+        .iter()
+        .rposition(is_numbered)
+        .map_or(start, |index| index + 1);
+    debug!(
+        "Rendering {} prefix, {} numbered and {} suffix items",
+        start,
+        end - start,
+        book.sections.len() - end
+    );
+
+    // Separators only exist between the numbered chapters in a SUMMARY.md
+    let prefix_or_suffix = |items: &[BookItem]| {
+        items
+            .iter()
+            .filter(|item| !matches!(item, BookItem::Separator))
+            .map(summary_item)
+            .collect()
+    };
+    Summary {
+        title: None,
+        prefix_chapters: prefix_or_suffix(&book.sections[..start]),
+        numbered_chapters: book.sections[start..end].iter().map(summary_item).collect(),
+        suffix_chapters: prefix_or_suffix(&book.sections[end..]),
+    }
+}
+
+fn summary_item(item: &BookItem) -> SummaryItem {
+    match item {
+        BookItem::Chapter(chapter) => SummaryItem::Link(Link {
+            name: chapter.name.clone(),
+            location: chapter.path.clone(),
+            number: chapter.number.clone(),
+            nested_items: chapter.sub_items.iter().map(summary_item).collect(),
+        }),
+        BookItem::Separator => SummaryItem::Separator,
+        BookItem::PartTitle(title) => SummaryItem::PartTitle(title.clone()),
+    }
+}
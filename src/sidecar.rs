@@ -0,0 +1,53 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Error};
+use serde::Deserialize;
+
+use crate::summary_fs::SummaryFs;
+
+/// The name of the sidecar file of a directory, inside of it.
+const DIRECTORY_SIDECAR: &str = "dir.toml";
+
+/// The metadata of a markdown file or directory from its sidecar file, see `sidecar_files`. It is
+/// used instead of front matter, for files which are also used by tools not supporting it.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Sidecar {
+    /// The name of the chapter, see `name_sources`.
+    pub(crate) title: Option<String>,
+    /// The chapters of a directory are sorted by their weight first, the default is 0.
+    #[serde(default)]
+    pub(crate) weight: i64,
+    /// Add the chapter as a draft, even if its file exists.
+    #[serde(default)]
+    pub(crate) draft: bool,
+    /// Passed to the [`ChapterSorter`](crate::ChapterSorter), they are not used otherwise.
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+}
+
+/// The path of the sidecar file of the markdown file or directory, `foo.md.toml` next to `foo.md`
+/// and `dir.toml` inside of a directory.
+pub(crate) fn sidecar_path(path: &Path, is_dir: bool) -> PathBuf {
+    if is_dir {
+        path.join(DIRECTORY_SIDECAR)
+    } else {
+        let mut sidecar = path.as_os_str().to_owned();
+        sidecar.push(".toml");
+        PathBuf::from(sidecar)
+    }
+}
+
+/// Read the sidecar file at `path`, [`Option::None`] if it does not exist.
+pub(crate) fn read_sidecar(fs: &dyn SummaryFs, path: &Path) -> Result<Option<Sidecar>, Error> {
+    let content = match fs.read_prefix(path, usize::MAX) {
+        Ok(content) => content,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error).context("Failed to read it"),
+    };
+    let content = String::from_utf8(content).context("It is not valid UTF-8")?;
+    Ok(Some(toml::from_str(&content)?))
+}
@@ -0,0 +1,114 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+use crate::book::{Summary, SummaryItem};
+
+/// Counters and timings of generating a summary, see
+/// [`GenerateSummary::generate_summary_with_stats`](crate::GenerateSummary::generate_summary_with_stats).
+///
+/// It is displayed on one line, or on one line per counter with the alternate flag (`{:#}`).
+#[derive(Debug, Default, Clone)]
+pub struct Stats {
+    /// The chapters of the summary, including drafts.
+    pub chapters: usize,
+    /// The draft chapters of the summary.
+    pub drafts: usize,
+    /// The directories which were visited, including the src directory.
+    pub directories: usize,
+    /// The visited directories whose entries were taken from the title cache with `incremental`,
+    /// as they did not change since the last run.
+    pub cached_directories: usize,
+    /// How deep the chapters are nested, 1 if no chapter has nested chapters and 0 for an empty
+    /// summary.
+    pub max_depth: usize,
+    /// The files and directories left out by the [`ChapterFilter`](crate::ChapterFilter).
+    pub filtered: usize,
+    /// The chapter files whose start was read for their title and front matter.
+    pub files_read: usize,
+    /// The chapter files whose title and front matter were taken from the title cache instead,
+    /// as they did not change since the last run.
+    pub cached_titles: usize,
+    /// The directories whose entries were read from the file system, including the ones read in
+    /// parallel with `threads`.
+    pub directories_read: usize,
+    /// The metadata queries of the file system, e.g. for the modification times compared with
+    /// the title cache.
+    pub metadata_queries: usize,
+    /// The wall-clock time of visiting the src directory, including reading titles and the
+    /// directories read in parallel.
+    pub traversal: Duration,
+    /// The part of `traversal` spent reading the titles of chapter files, apart from reading
+    /// their start in parallel beforehand with `threads`.
+    pub title_extraction: Duration,
+    /// The wall-clock time of validating the summary, see `validate`.
+    pub validation: Duration,
+}
+
+impl Stats {
+    /// Count the chapters of the summary.
+    pub(crate) fn count_chapters(&mut self, summary: &Summary) {
+        fn count(stats: &mut Stats, items: &[SummaryItem], depth: usize) {
+            for item in items {
+                if let SummaryItem::Link(link) = item {
+                    stats.chapters += 1;
+                    if link.location.is_none() {
+                        stats.drafts += 1;
+                    }
+                    stats.max_depth = stats.max_depth.max(depth);
+                    count(stats, &link.nested_items, depth + 1);
+                }
+            }
+        }
+
+        for items in [
+            &summary.prefix_chapters,
+            &summary.numbered_chapters,
+            &summary.suffix_chapters,
+        ] {
+            count(self, items, 1);
+        }
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(
+                f,
+                "  Chapters: {} ({} of them drafts)\n  Directories visited: {} ({} of them from the title \
+                 cache)\n  Maximum \
+                 nesting depth: {}\n  Left out by the filter: {}\n  Chapter files read: {} ({} more from the \
+                 title cache)\n  Directories read: {}\n  Metadata queries: {}\n  \
+                 Traversal: {:.2?} ({:.2?} of \
+                 it reading titles)\n  Validation: {:.2?}",
+                self.chapters,
+                self.drafts,
+                self.directories,
+                self.cached_directories,
+                self.max_depth,
+                self.filtered,
+                self.files_read,
+                self.cached_titles,
+                self.directories_read,
+                self.metadata_queries,
+                self.traversal,
+                self.title_extraction,
+                self.validation
+            )
+        } else {
+            write!(
+                f,
+                "{} chapters ({} of them drafts) in {} directories, nested {} deep, {} left out by \
+                 the filter, took {:.2?}",
+                self.chapters,
+                self.drafts,
+                self.directories,
+                self.max_depth,
+                self.filtered,
+                self.traversal + self.validation
+            )
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    path::Component,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::book::{Link, Summary, SummaryItem};
+
+/// How many changes of each kind are listed before the rest is only counted.
+const MAX_LISTED_CHANGES: usize = 10;
+
+/// The structural changes between two summaries, see [`diff_summaries`]. Chapters are matched by
+/// their location, drafts by the names of the chapters leading to them, so that a renamed or moved
+/// draft is removed and added.
+///
+/// Its [`Display`] implementation lists at most 10 changes of each kind, for logging. Serialized
+/// (e.g. with `serde_json`), all of them are included.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SummaryDiff {
+    /// The chapters only in the new summary, in its order.
+    pub added: Vec<DiffEntry>,
+    /// The chapters only in the old summary, in its order.
+    pub removed: Vec<DiffEntry>,
+    /// The chapters with another name in the new summary.
+    pub renamed: Vec<RenamedEntry>,
+    /// The chapters with another parent in the new summary.
+    pub moved: Vec<MovedEntry>,
+    /// The chapters which kept their parent, but not their order relative to their siblings.
+    /// Chapters which only moved up or down as others were added or removed are not included.
+    pub reordered: Vec<DiffEntry>,
+}
+
+/// A chapter of a [`SummaryDiff`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffEntry {
+    /// The name of the chapter in the summary it is part of, the new one if it is in both.
+    pub name: String,
+    /// The path of the chapter file relative to the src directory, always using forward slashes.
+    /// Not set for drafts.
+    pub location: Option<String>,
+}
+
+/// A chapter whose name changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenamedEntry {
+    /// The path of the chapter file relative to the src directory.
+    pub location: String,
+    pub old_name: String,
+    pub new_name: String,
+}
+
+/// A chapter whose parent changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MovedEntry {
+    pub chapter: DiffEntry,
+    /// The parent chapter in the old summary, [`Option::None`] at the top.
+    pub old_parent: Option<DiffEntry>,
+    /// The parent chapter in the new summary, [`Option::None`] at the top.
+    pub new_parent: Option<DiffEntry>,
+}
+
+/// Compare the structure of two summaries, e.g. a hand-written SUMMARY.md and the generated
+/// summary. Unlike a diff of their markdown, a chapter which moved to another directory is
+/// reported once as moved, not as removed and added.
+///
+/// # Example
+/// ```
+/// use std::path::Path;
+///
+/// use mdbook_generate_summary::{diff_summaries, Config, DiffEntry, GenerateSummary, MemoryFs};
+///
+/// let generate = |fs: MemoryFs| {
+///     GenerateSummary::new()
+///         .with_fs(Box::new(fs))
+///         .generate_summary(Path::new("/src"), &Config::default())
+/// };
+/// let old = generate(
+///     MemoryFs::new()
+///         .with_file("/src/intro.md", "# Intro\n")
+///         .with_file("/src/guide/README.md", "# Guide\n")
+///         .with_file("/src/guide/setup.md", "# Setup\n")
+///         .with_file("/src/faq.md", "# FAQ\n"),
+/// )?;
+/// let new = generate(
+///     MemoryFs::new()
+///         .with_file("/src/intro.md", "# Intro\n")
+///         .with_file("/src/guide/README.md", "# Guide\n")
+///         .with_file("/src/setup.md", "# Setup\n")
+///         .with_file("/src/zz.md", "# ZZ\n"),
+/// )?;
+///
+/// let diff = diff_summaries(&old, &new);
+/// assert_eq!(
+///     diff.added,
+///     [
+///         DiffEntry { name: "setup".to_owned(), location: Some("setup.md".to_owned()) },
+///         DiffEntry { name: "zz".to_owned(), location: Some("zz.md".to_owned()) },
+///     ]
+/// );
+/// assert_eq!(diff.removed.len(), 2);
+/// assert_eq!(
+///     diff.to_string(),
+///     "  Added (2):\n    setup (setup.md)\n    zz (zz.md)\n  \
+///      Removed (2):\n    faq (faq.md)\n    setup (guide/setup.md)"
+/// );
+/// assert!(serde_json::to_string(&diff)?.starts_with(r#"{"added":[{"name":"setup""#));
+/// assert!(diff_summaries(&new, &new).is_empty());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn diff_summaries(old: &Summary, new: &Summary) -> SummaryDiff {
+    SummaryDiff::new(old, new)
+}
+
+impl SummaryDiff {
+    fn new(old: &Summary, new: &Summary) -> Self {
+        let old_entries = entries(old);
+        let new_entries = entries(new);
+        let old_by_key = by_key(&old_entries);
+        let new_by_key = by_key(&new_entries);
+        let mut diff = Self::default();
+
+        for entry in &old_entries {
+            if !new_by_key.contains_key(entry.key.as_str()) {
+                diff.removed.push(entry.diff_entry());
+            }
+        }
+        // The children of every parent which kept their parent, in the old order
+        let mut old_siblings: HashMap<Option<&str>, Vec<&str>> = HashMap::new();
+        let mut new_siblings: HashMap<Option<&str>, Vec<&str>> = HashMap::new();
+        for entry in &new_entries {
+            let Some(old_entry) = old_by_key.get(entry.key.as_str()) else {
+                diff.added.push(entry.diff_entry());
+                continue;
+            };
+            if old_entry.link.name != entry.link.name {
+                diff.renamed.push(RenamedEntry {
+                    location: entry.key.clone(),
+                    old_name: old_entry.link.name.clone(),
+                    new_name: entry.link.name.clone(),
+                });
+            }
+            if old_entry.parent != entry.parent {
+                diff.moved.push(MovedEntry {
+                    chapter: entry.diff_entry(),
+                    old_parent: parent(old_entry, &old_by_key),
+                    new_parent: parent(entry, &new_by_key),
+                });
+            } else {
+                new_siblings
+                    .entry(entry.parent.as_deref())
+                    .or_default()
+                    .push(&entry.key);
+            }
+        }
+        for entry in &old_entries {
+            let kept_parent = new_by_key
+                .get(entry.key.as_str())
+                .is_some_and(|new_entry| new_entry.parent == entry.parent);
+            if kept_parent {
+                old_siblings
+                    .entry(entry.parent.as_deref())
+                    .or_default()
+                    .push(&entry.key);
+            }
+        }
+
+        for entry in &new_entries {
+            // Report every parent once, when reaching its first child
+            let Some(siblings) = new_siblings.remove(&entry.parent.as_deref()) else {
+                continue;
+            };
+            let old = old_siblings
+                .get(&entry.parent.as_deref())
+                .map_or(&[][..], Vec::as_slice);
+            let unchanged = longest_common_subsequence(old, &siblings);
+            for key in siblings {
+                if !unchanged.contains(&key) {
+                    diff.reordered.push(new_by_key[key].diff_entry());
+                }
+            }
+        }
+        diff
+    }
+
+    /// True if the summaries have the same chapters in the same order and nesting. Part titles and
+    /// separators are not compared.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.renamed.is_empty()
+            && self.moved.is_empty()
+            && self.reordered.is_empty()
+    }
+}
+
+impl Display for SummaryDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let to_strings = |entries: &[DiffEntry]| -> Vec<String> {
+            entries.iter().map(DiffEntry::to_string).collect()
+        };
+        let renamed = self
+            .renamed
+            .iter()
+            .map(|renamed| {
+                format!(
+                    "{} ({}): `{}` -> `{}`",
+                    renamed.new_name, renamed.location, renamed.old_name, renamed.new_name
+                )
+            })
+            .collect();
+        let moved = self
+            .moved
+            .iter()
+            .map(|moved| {
+                format!(
+                    "{}: from {} to {}",
+                    moved.chapter,
+                    parent_name(moved.old_parent.as_ref()),
+                    parent_name(moved.new_parent.as_ref())
+                )
+            })
+            .collect();
+        let groups = [
+            ("Added", to_strings(&self.added)),
+            ("Removed", to_strings(&self.removed)),
+            ("Renamed", renamed),
+            ("Moved", moved),
+            ("Reordered", to_strings(&self.reordered)),
+        ];
+        let mut first = true;
+        for (title, changes) in groups {
+            if changes.is_empty() {
+                continue;
+            }
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+            write!(f, "  {} ({}):", title, changes.len())?;
+            for change in changes.iter().take(MAX_LISTED_CHANGES) {
+                write!(f, "\n    {}", change)?;
+            }
+            if changes.len() > MAX_LISTED_CHANGES {
+                write!(
+                    f,
+                    "\n    ... and {} more",
+                    changes.len() - MAX_LISTED_CHANGES
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for DiffEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({})",
+            self.name,
+            self.location.as_deref().unwrap_or("draft")
+        )
+    }
+}
+
+/// A chapter of a summary.
+struct Entry<'a> {
+    /// The location of the chapter, or the names of the chapters leading to a draft.
+    key: String,
+    link: &'a Link,
+    /// The key of the parent chapter, [`Option::None`] for chapters at the top.
+    parent: Option<String>,
+}
+
+impl Entry<'_> {
+    fn diff_entry(&self) -> DiffEntry {
+        DiffEntry {
+            name: self.link.name.clone(),
+            location: self.link.location.as_ref().map(|_| self.key.clone()),
+        }
+    }
+}
+
+/// All chapters of the summary in order.
+fn entries(summary: &Summary) -> Vec<Entry<'_>> {
+    fn add_entries<'a>(
+        items: &'a [SummaryItem],
+        parent: Option<&str>,
+        parent_names: &str,
+        entries: &mut Vec<Entry<'a>>,
+    ) {
+        for item in items {
+            let SummaryItem::Link(link) = item else {
+                continue;
+            };
+            let names = format!("{}{} > ", parent_names, link.name);
+            let key = match &link.location {
+                // Ignore './' and similar, which SUMMARY.md may contain
+                Some(location) => location
+                    .components()
+                    .filter(|component| !matches!(component, Component::CurDir))
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/"),
+                None => format!("draft {}", names),
+            };
+            entries.push(Entry {
+                key: key.clone(),
+                link,
+                parent: parent.map(str::to_owned),
+            });
+            add_entries(&link.nested_items, Some(&key), &names, entries);
+        }
+    }
+
+    let mut entries = vec![];
+    for items in [
+        &summary.prefix_chapters,
+        &summary.numbered_chapters,
+        &summary.suffix_chapters,
+    ] {
+        add_entries(items, None, "", &mut entries);
+    }
+    entries
+}
+
+/// The entries by their key. If a chapter is part of the summary more than once, its first entry is
+/// used.
+fn by_key<'a, 'b>(entries: &'b [Entry<'a>]) -> HashMap<&'b str, &'b Entry<'a>> {
+    let mut by_key = HashMap::new();
+    for entry in entries {
+        by_key.entry(entry.key.as_str()).or_insert(entry);
+    }
+    by_key
+}
+
+/// The parent chapter of `entry`, [`Option::None`] at the top.
+fn parent(entry: &Entry, by_key: &HashMap<&str, &Entry>) -> Option<DiffEntry> {
+    entry
+        .parent
+        .as_deref()
+        .and_then(|parent| by_key.get(parent))
+        .map(|parent| parent.diff_entry())
+}
+
+fn parent_name(parent: Option<&DiffEntry>) -> String {
+    match parent {
+        Some(parent) => format!("under `{}`", parent.name),
+        None => "the top".to_owned(),
+    }
+}
+
+/// The longest common subsequence of `a` and `b`, which are the entries keeping their order.
+fn longest_common_subsequence<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<&'a str> {
+    // lengths[i][j] is the length of the longest common subsequence of a[i..] and b[j..]
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut subsequence = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            subsequence.push(a[i]);
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    subsequence
+}
@@ -0,0 +1,429 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{File, OpenOptions},
+    io::{self, ErrorKind, Read, Write},
+    path::{Component, Path, PathBuf},
+    sync::{Mutex, PoisonError},
+    time::SystemTime,
+};
+
+/// The file system the src directory is read from and missing chapter files are created in, see
+/// [`crate::GenerateSummary::with_fs`]. [`StdFs`] is used by default, [`MemoryFs`] keeps a book in
+/// memory, e.g. for tests.
+/// The implementation is shared by the threads reading the directories, see `threads`. Files
+/// outside of the src directory, like the title cache or the created files manifest, are always
+/// accessed with [`std::fs`].
+pub trait SummaryFs: Sync {
+    /// The entries of the directory at `path`, in any order. Errors of single entries are
+    /// returned in their place.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<FsEntry>>>;
+
+    /// At most the first `max_bytes` of the file at `path`.
+    fn read_prefix(&self, path: &Path, max_bytes: usize) -> io::Result<Vec<u8>>;
+
+    /// Create the file at `path` with `content`. Fails with [`ErrorKind::AlreadyExists`] if it
+    /// exists already, so that only one of several instances creates it.
+    fn create_file(&self, path: &Path, content: &[u8]) -> io::Result<()>;
+
+    /// The metadata of the file or directory at `path`, following symbolic links.
+    fn metadata(&self, path: &Path) -> io::Result<FsMetadata>;
+
+    /// The absolute path of `path`, with symbolic links resolved. Fails if it does not exist.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+}
+
+/// An entry of a directory, see [`SummaryFs::read_dir`].
+#[derive(Debug)]
+pub struct FsEntry {
+    /// The path of the entry, i.e. the path of the directory joined with its name.
+    pub path: PathBuf,
+    /// The kind of the entry, without following symbolic links.
+    pub kind: io::Result<FileKind>,
+}
+
+/// What a path of a [`SummaryFs`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    File,
+    Directory,
+    /// Anything else, e.g. a symbolic link or a socket. It is not part of the summary, unless it
+    /// is a symbolic link to a file or directory and `follow_symlinks` is set.
+    Other,
+}
+
+/// The metadata of a file or directory, see [`SummaryFs::metadata`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsMetadata {
+    pub kind: FileKind,
+    /// When the file or directory was modified, [`Option::None`] if it is not known.
+    pub modified: Option<SystemTime>,
+    /// The size in bytes.
+    pub len: u64,
+}
+
+/// The [`SummaryFs`] of the operating system.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdFs;
+
+impl SummaryFs for StdFs {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<FsEntry>>> {
+        Ok(std::fs::read_dir(path)?
+            .map(|entry| {
+                let entry = entry?;
+                Ok(FsEntry {
+                    path: entry.path(),
+                    kind: entry.file_type().map(|file_type| {
+                        if file_type.is_dir() {
+                            FileKind::Directory
+                        } else if file_type.is_file() {
+                            FileKind::File
+                        } else {
+                            FileKind::Other
+                        }
+                    }),
+                })
+            })
+            .collect())
+    }
+
+    fn read_prefix(&self, path: &Path, max_bytes: usize) -> io::Result<Vec<u8>> {
+        let file = File::open(path)?;
+        let mut prefix = Vec::with_capacity(max_bytes.min(64 * 1024));
+        file.take(max_bytes as u64).read_to_end(&mut prefix)?;
+        Ok(prefix)
+    }
+
+    fn create_file(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?
+            .write_all(content)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FsMetadata> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(FsMetadata {
+            kind: if metadata.is_dir() {
+                FileKind::Directory
+            } else if metadata.is_file() {
+                FileKind::File
+            } else {
+                FileKind::Other
+            },
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+}
+
+/// An operation of a [`SummaryFs`], to make it fail in a [`MemoryFs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FsOperation {
+    ReadDir,
+    /// Getting the kind of an entry while reading its directory.
+    FileKind,
+    ReadPrefix,
+    CreateFile,
+    Metadata,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    File(Vec<u8>),
+    Directory,
+    Other,
+    /// A symbolic link to the absolute path.
+    Symlink(PathBuf),
+}
+
+/// How many symbolic links are resolved for a path at most, like the limit of Linux.
+const MAX_SYMLINKS: usize = 40;
+
+/// A [`SummaryFs`] which keeps a book in memory. Paths are used as they are, they should be
+/// absolute and must not contain `.` or `..`. Every file also creates its parent directories.
+/// Symbolic links are followed like by [`StdFs`], except when reading the kind of an entry.
+/// The modification time of every file and directory is [`Option::None`], so the title cache
+/// reads every file again.
+///
+/// # Example
+/// ```
+/// use std::path::Path;
+///
+/// use mdbook_generate_summary::{Config, GenerateSummary, MemoryFs, SummaryItem};
+///
+/// let fs = MemoryFs::new()
+///     .with_file("/book/src/intro.md", "# Introduction\n")
+///     .with_file("/book/src/guide/README.md", "# Guide\n")
+///     .with_file("/book/src/guide/setup.md", "# Setup\n");
+///
+/// let config = Config::builder().get_chapter_name_from_file(true).build()?;
+/// let summary = GenerateSummary::new()
+///     .with_fs(Box::new(fs))
+///     .generate_summary(Path::new("/book/src"), &config)?;
+/// let names: Vec<_> = summary
+///     .numbered_chapters
+///     .iter()
+///     .filter_map(|item| match item {
+///         SummaryItem::Link(link) => Some(link.name.as_str()),
+///         _ => None,
+///     })
+///     .collect();
+/// assert_eq!(names, ["Guide", "Introduction"]);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    nodes: Mutex<BTreeMap<PathBuf, Node>>,
+    errors: HashMap<(PathBuf, FsOperation), ErrorKind>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the file at `path` with `content`.
+    pub fn with_file(self, path: impl AsRef<Path>, content: impl Into<Vec<u8>>) -> Self {
+        self.insert(path.as_ref(), Node::File(content.into()))
+    }
+
+    /// Add the (empty) directory at `path`.
+    pub fn with_dir(self, path: impl AsRef<Path>) -> Self {
+        self.insert(path.as_ref(), Node::Directory)
+    }
+
+    /// Add something at `path` which is neither a file nor a directory, like a socket.
+    pub fn with_other(self, path: impl AsRef<Path>) -> Self {
+        self.insert(path.as_ref(), Node::Other)
+    }
+
+    /// Add a symbolic link at `path` to `target`, which is relative to the directory of the link
+    /// unless it is absolute.
+    pub fn with_symlink(self, path: impl AsRef<Path>, target: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let mut resolved = path.parent().unwrap_or(Path::new("/")).to_path_buf();
+        for component in target.as_ref().components() {
+            match component {
+                Component::ParentDir => {
+                    resolved.pop();
+                }
+                Component::CurDir => {}
+                component => resolved.push(component),
+            }
+        }
+        self.insert(path, Node::Symlink(resolved))
+    }
+
+    /// Make `operation` on `path` fail with an error of `kind`, e.g. [`ErrorKind::PermissionDenied`].
+    ///
+    /// # Example
+    /// ```
+    /// use std::{io::ErrorKind, path::Path};
+    ///
+    /// use mdbook_generate_summary::{
+    ///     Config, CreateMissingChapterFiles, FsOperation, GenerateSummary, MemoryFs, OnError,
+    /// };
+    ///
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/intro.md", "")
+    ///     .with_file("/src/secret/README.md", "")
+    ///     .with_error("/src/secret", FsOperation::ReadDir, ErrorKind::PermissionDenied);
+    /// let generator = GenerateSummary::new().with_fs(Box::new(fs));
+    ///
+    /// let error = generator
+    ///     .generate_summary(Path::new("/src"), &Config::default())
+    ///     .unwrap_err();
+    /// assert!(format!("{:#}", error).contains("Failed to read directory /src/secret"));
+    ///
+    /// // Its entries are skipped with a warning instead, its chapter file is still used
+    /// let config = Config::builder().on_error(OnError::Skip).build()?;
+    /// let (_, stats) = generator.generate_summary_with_stats(Path::new("/src"), &config)?;
+    /// assert_eq!(stats.chapters, 2);
+    ///
+    /// // A chapter file which can not be created fails the summary
+    /// let fs = MemoryFs::new()
+    ///     .with_file("/src/guide/setup.md", "")
+    ///     .with_error("/src/guide/README.md", FsOperation::CreateFile, ErrorKind::PermissionDenied);
+    /// let config = Config::builder()
+    ///     .create_missing_chapter_files(CreateMissingChapterFiles::Always)
+    ///     .build()?;
+    /// let error = GenerateSummary::new()
+    ///     .with_fs(Box::new(fs))
+    ///     .generate_summary(Path::new("/src"), &config)
+    ///     .unwrap_err();
+    /// assert!(format!("{:#}", error).contains("Failed to create missing chapter file"));
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn with_error(
+        mut self,
+        path: impl AsRef<Path>,
+        operation: FsOperation,
+        kind: ErrorKind,
+    ) -> Self {
+        self.errors
+            .insert((path.as_ref().to_path_buf(), operation), kind);
+        self
+    }
+
+    /// The content of the file at `path`, e.g. of a created chapter file.
+    pub fn file(&self, path: impl AsRef<Path>) -> Option<Vec<u8>> {
+        match self.nodes().get(path.as_ref()) {
+            Some(Node::File(content)) => Some(content.clone()),
+            _ => None,
+        }
+    }
+
+    fn insert(self, path: &Path, node: Node) -> Self {
+        {
+            let mut nodes = self.nodes();
+            for ancestor in path.ancestors().skip(1) {
+                nodes.insert(ancestor.to_path_buf(), Node::Directory);
+            }
+            nodes.insert(path.to_path_buf(), node);
+        }
+        self
+    }
+
+    fn nodes(&self) -> std::sync::MutexGuard<'_, BTreeMap<PathBuf, Node>> {
+        self.nodes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The injected error of `operation` on `path`, if there is one.
+    fn check(&self, path: &Path, operation: FsOperation) -> io::Result<()> {
+        match self.errors.get(&(path.to_path_buf(), operation)) {
+            Some(kind) => Err(io::Error::new(
+                *kind,
+                format!("{:?} failed for {}", operation, path.display()),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// The path with the symbolic links among it and its ancestors resolved.
+    fn resolve(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> io::Result<PathBuf> {
+        let mut path = path.to_path_buf();
+        for _ in 0..MAX_SYMLINKS {
+            let symlink = path
+                .ancestors()
+                .find_map(|ancestor| match nodes.get(ancestor) {
+                    Some(Node::Symlink(target)) => Some((ancestor, target)),
+                    _ => None,
+                });
+            let Some((link, target)) = symlink else {
+                return Ok(path);
+            };
+            path = match path.strip_prefix(link) {
+                Ok(rest) if !rest.as_os_str().is_empty() => target.join(rest),
+                _ => target.clone(),
+            };
+        }
+        Err(io::Error::other(format!(
+            "Too many levels of symbolic links in {}",
+            path.display()
+        )))
+    }
+
+    fn not_found(path: &Path) -> io::Error {
+        io::Error::new(
+            ErrorKind::NotFound,
+            format!("{} does not exist", path.display()),
+        )
+    }
+}
+
+impl SummaryFs for MemoryFs {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<FsEntry>>> {
+        self.check(path, FsOperation::ReadDir)?;
+        let nodes = self.nodes();
+        let resolved = Self::resolve(&nodes, path)?;
+        match nodes.get(&resolved) {
+            Some(Node::Directory) => {}
+            Some(_) => {
+                return Err(io::Error::other(format!(
+                    "{} is not a directory",
+                    path.display()
+                )))
+            }
+            None => return Err(Self::not_found(path)),
+        }
+        Ok(nodes
+            .iter()
+            .filter(|(entry, _)| entry.parent() == Some(&resolved))
+            .map(|(entry, node)| {
+                // The entries of a linked directory are inside of the link
+                let entry = path.join(entry.file_name().unwrap_or_default());
+                let kind = self
+                    .check(&entry, FsOperation::FileKind)
+                    .map(|_| match node {
+                        Node::File(_) => FileKind::File,
+                        Node::Directory => FileKind::Directory,
+                        Node::Other | Node::Symlink(_) => FileKind::Other,
+                    });
+                Ok(FsEntry { path: entry, kind })
+            })
+            .collect())
+    }
+
+    fn read_prefix(&self, path: &Path, max_bytes: usize) -> io::Result<Vec<u8>> {
+        self.check(path, FsOperation::ReadPrefix)?;
+        let nodes = self.nodes();
+        match nodes.get(&Self::resolve(&nodes, path)?) {
+            Some(Node::File(content)) => Ok(content[..content.len().min(max_bytes)].to_vec()),
+            Some(_) => Err(io::Error::other(format!(
+                "{} is not a file",
+                path.display()
+            ))),
+            None => Err(Self::not_found(path)),
+        }
+    }
+
+    fn create_file(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        self.check(path, FsOperation::CreateFile)?;
+        let mut nodes = self.nodes();
+        let path = &Self::resolve(&nodes, path)?;
+        if nodes.contains_key(path) {
+            return Err(io::Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} exists already", path.display()),
+            ));
+        }
+        if !matches!(
+            path.parent().and_then(|parent| nodes.get(parent)),
+            Some(Node::Directory)
+        ) {
+            return Err(Self::not_found(path));
+        }
+        nodes.insert(path.to_path_buf(), Node::File(content.to_vec()));
+        Ok(())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FsMetadata> {
+        self.check(path, FsOperation::Metadata)?;
+        let nodes = self.nodes();
+        let (kind, len) = match nodes.get(&Self::resolve(&nodes, path)?) {
+            Some(Node::File(content)) => (FileKind::File, content.len() as u64),
+            Some(Node::Directory) => (FileKind::Directory, 0),
+            Some(Node::Other) => (FileKind::Other, 0),
+            Some(Node::Symlink(_)) | None => return Err(Self::not_found(path)),
+        };
+        Ok(FsMetadata {
+            kind,
+            modified: None,
+            len,
+        })
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let nodes = self.nodes();
+        let resolved = Self::resolve(&nodes, path)?;
+        if !nodes.contains_key(&resolved) {
+            return Err(Self::not_found(path));
+        }
+        Ok(resolved)
+    }
+}
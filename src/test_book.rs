@@ -0,0 +1,344 @@
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::{Context, Error};
+use mdbook::{
+    book::Book,
+    preprocess::{Preprocessor, PreprocessorContext},
+    renderer::HtmlHandlebars,
+    BookItem, MDBook,
+};
+use toml::{map::Map, Value};
+
+use crate::{
+    config::DEFAULT_CONFIG_FILE, markdown::summary_to_markdown, warnings::Diagnostic,
+    DiagnosticLevel, GenerateSummary, Summary,
+};
+
+/// Test books created so far by this process, so that their directories differ.
+static TEST_BOOKS: AtomicUsize = AtomicUsize::new(0);
+
+/// A book in a temporary directory for tests of the summary generated for it, e.g. regression tests
+/// of a book or tests of a preprocessor running before this one. [`TestBook::run`] preprocesses
+/// it like `mdbook build` does for the html renderer, including mdBook's default preprocessors.
+/// The directory is removed once the test book is dropped.
+///
+/// The configuration is written to `generate-summary.toml`, so that mdBook does not run the
+/// installed `mdbook-generate-summary` as well.
+///
+/// # Example
+/// ```
+/// use mdbook_generate_summary::TestBook;
+///
+/// let output = TestBook::new()
+///     .file("intro.md", "# Introduction\n")
+///     .file("guide/README.md", "# Guide\n")
+///     .file("guide/setup.md", "# Setup\n")
+///     .dir("guide/drafts")
+///     .config(toml::toml! {
+///         get_chapter_name_from_file = true
+///         draft_empty_directories = true
+///     })
+///     .run()?;
+/// assert_eq!(
+///     output.chapter_names(),
+///     ["1. Guide", "1.1. drafts", "1.2. Setup", "2. Introduction"]
+/// );
+/// assert_eq!(output.content("guide/setup.md"), Some("# Setup\n"));
+/// assert!(output.diagnostics.is_empty());
+///
+/// // Failures are returned as errors
+/// let error = TestBook::new()
+///     .file("intro.md", "# Introduction\n")
+///     .file("usage.md", "# Usage\n")
+///     .config(toml::toml! { max_chapters = 1 })
+///     .run()
+///     .unwrap_err();
+/// assert!(format!("{:#}", error).contains("more than `max_chapters` (1) allows"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct TestBook {
+    root: PathBuf,
+    /// The files to write, relative to the src directory.
+    files: Vec<(PathBuf, String)>,
+    /// The directories to create, relative to the src directory.
+    dirs: Vec<PathBuf>,
+    config: Map<String, Value>,
+    book_config: Map<String, Value>,
+    generator: Rc<GenerateSummary>,
+    preprocessors: Vec<Rc<dyn Preprocessor>>,
+}
+
+/// What [`TestBook::run`] produced.
+#[derive(Debug)]
+pub struct TestOutput {
+    /// The book passed on to the renderer.
+    pub book: Book,
+    /// The generated summary, [`Option::None`] if the book was passed on unchanged without
+    /// generating one, e.g. with `prefer_manual`.
+    pub summary: Option<Summary>,
+    /// The warnings and notable decisions, as they would be written to `diagnostics_json`.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl TestBook {
+    /// An empty book in a new temporary directory, which is only created by [`TestBook::run`].
+    pub fn new() -> Self {
+        let root = std::env::temp_dir().join(format!(
+            "mdbook-generate-summary-test-{}-{}",
+            std::process::id(),
+            TEST_BOOKS.fetch_add(1, Ordering::Relaxed)
+        ));
+        Self {
+            root,
+            files: vec![],
+            dirs: vec![],
+            config: Map::new(),
+            book_config: Map::new(),
+            generator: Rc::new(GenerateSummary::new()),
+            preprocessors: vec![],
+        }
+    }
+
+    /// Add the file at `path` relative to the src directory with `content`.
+    pub fn file(mut self, path: impl Into<PathBuf>, content: impl Into<String>) -> Self {
+        self.files.push((path.into(), content.into()));
+        self
+    }
+
+    /// Add the directory at `path` relative to the src directory, e.g. an empty one.
+    pub fn dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.dirs.push(path.into());
+        self
+    }
+
+    /// Set the keys of the table, e.g. created with [`toml::toml!`], in the configuration of the
+    /// preprocessor.
+    ///
+    /// # Panics
+    /// If `config` is not a table.
+    pub fn config(mut self, config: Value) -> Self {
+        let Value::Table(table) = config else {
+            panic!(
+                "The configuration of a test book must be a table, not {}",
+                config
+            );
+        };
+        self.config.extend(table);
+        self
+    }
+
+    /// Set the keys of the table in book.toml, e.g. `build.create-missing`.
+    ///
+    /// # Panics
+    /// If `config` is not a table.
+    pub fn book_config(mut self, config: Value) -> Self {
+        let Value::Table(table) = config else {
+            panic!(
+                "The book.toml of a test book must be a table, not {}",
+                config
+            );
+        };
+        self.book_config.extend(table);
+        self
+    }
+
+    /// Generate the summary with `generator` instead of [`GenerateSummary::new`], e.g. with a
+    /// [`ChapterNamer`](crate::ChapterNamer).
+    pub fn generator(mut self, generator: GenerateSummary) -> Self {
+        self.generator = Rc::new(generator);
+        self
+    }
+
+    /// Run `preprocessor` before generating the summary, after mdBook's default preprocessors.
+    pub fn preprocessor(mut self, preprocessor: impl Preprocessor + 'static) -> Self {
+        self.preprocessors.push(Rc::new(preprocessor));
+        self
+    }
+
+    /// The root directory of the book, which contains its book.toml.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The src directory of the book.
+    pub fn src_dir(&self) -> PathBuf {
+        self.root.join("src")
+    }
+
+    /// Write the book and preprocess it. Files created by previous runs, like chapter files
+    /// created by `create_missing_chapter_files`, are kept. A `SUMMARY.md` is only written if
+    /// there is none, as mdBook needs one to load the book.
+    pub fn run(&self) -> Result<TestOutput, Error> {
+        self.write()?;
+        let mut book = MDBook::load(&self.root)?;
+        for preprocessor in &self.preprocessors {
+            book.with_preprocessor(Shared(Rc::clone(preprocessor)));
+        }
+        let captured = Rc::new(RefCell::new(None));
+        book.with_preprocessor(Capture {
+            generator: Rc::clone(&self.generator),
+            captured: Rc::clone(&captured),
+        });
+        let (book, _) = book.preprocess_book(&HtmlHandlebars::new())?;
+        let (summary, diagnostics) = captured.take().unwrap_or_default();
+        Ok(TestOutput {
+            book,
+            summary,
+            diagnostics,
+        })
+    }
+
+    /// Write the book like [`TestBook::run`] without preprocessing it, e.g. for tests which run
+    /// the preprocessor on it themselves.
+    pub(crate) fn write(&self) -> Result<(), Error> {
+        let src_dir = self.src_dir();
+        for dir in &self.dirs {
+            let path = src_dir.join(dir);
+            std::fs::create_dir_all(&path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+        }
+        let summary_path = src_dir.join("SUMMARY.md");
+        let mut files = vec![
+            (
+                self.root.join("book.toml"),
+                toml::to_string(&Value::Table(self.book_config.clone()))?,
+            ),
+            (
+                self.root.join(DEFAULT_CONFIG_FILE),
+                toml::to_string(&Value::Table(self.config.clone()))?,
+            ),
+        ];
+        if !summary_path.exists() {
+            files.push((summary_path, "# Summary\n".to_owned()));
+        }
+        files.extend(
+            self.files
+                .iter()
+                .map(|(path, content)| (src_dir.join(path), content.clone())),
+        );
+        for (path, content) in files {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            std::fs::write(&path, content)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for TestBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TestBook {
+    fn drop(&mut self) {
+        if self.root.exists() {
+            if let Err(error) = std::fs::remove_dir_all(&self.root) {
+                log::warn!(
+                    "Failed to remove the test book {}: {}",
+                    self.root.display(),
+                    error
+                );
+            }
+        }
+    }
+}
+
+impl TestOutput {
+    /// The names of the chapters of the book in order, after their section number like
+    /// `2.1. Setup` if they have one. Part titles are included as they are.
+    pub fn chapter_names(&self) -> Vec<String> {
+        self.book
+            .iter()
+            .filter_map(|item| match item {
+                BookItem::Chapter(chapter) => Some(match &chapter.number {
+                    Some(number) => format!("{} {}", number, chapter.name),
+                    None => chapter.name.clone(),
+                }),
+                BookItem::PartTitle(title) => Some(title.clone()),
+                BookItem::Separator => None,
+            })
+            .collect()
+    }
+
+    /// The content of the chapter at `location` (relative to the src directory) passed on to the
+    /// renderer.
+    pub fn content(&self, location: impl AsRef<Path>) -> Option<&str> {
+        self.book.iter().find_map(|item| match item {
+            BookItem::Chapter(chapter) if chapter.path.as_deref() == Some(location.as_ref()) => {
+                Some(chapter.content.as_str())
+            }
+            _ => None,
+        })
+    }
+
+    /// The generated summary as the markdown of a SUMMARY.md.
+    pub fn summary_markdown(&self) -> Option<String> {
+        self.summary.as_ref().map(summary_to_markdown)
+    }
+
+    /// The diagnostics which are warnings.
+    pub fn warnings(&self) -> Vec<&Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.level == DiagnosticLevel::Warning)
+            .collect()
+    }
+}
+
+/// What [`Capture`] captured: the generated summary and the diagnostics.
+type Captured = (Option<Summary>, Vec<Diagnostic>);
+
+/// Runs the preprocessor and keeps what it generated besides the book.
+struct Capture {
+    generator: Rc<GenerateSummary>,
+    captured: Rc<RefCell<Option<Captured>>>,
+}
+
+impl Preprocessor for Capture {
+    fn name(&self) -> &str {
+        self.generator.name()
+    }
+
+    fn run(&self, ctx: &PreprocessorContext, book: Book) -> Result<Book, Error> {
+        let preprocessed =
+            self.generator
+                .preprocess(&ctx.root, &ctx.config, &ctx.renderer, book)?;
+        let diagnostics = match &preprocessed.warnings {
+            Some(warnings) => warnings.diagnostics(&ctx.root.join(&ctx.config.book.src)),
+            None => vec![],
+        };
+        *self.captured.borrow_mut() = Some((preprocessed.summary, diagnostics));
+        Ok(preprocessed.book)
+    }
+
+    fn supports_renderer(&self, renderer: &str) -> bool {
+        self.generator.supports_renderer(renderer)
+    }
+}
+
+/// A preprocessor added with [`TestBook::preprocessor`], which may run more than once.
+struct Shared(Rc<dyn Preprocessor>);
+
+impl Preprocessor for Shared {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn run(&self, ctx: &PreprocessorContext, book: Book) -> Result<Book, Error> {
+        self.0.run(ctx, book)
+    }
+
+    fn supports_renderer(&self, renderer: &str) -> bool {
+        self.0.supports_renderer(renderer)
+    }
+}
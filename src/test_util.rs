@@ -0,0 +1,112 @@
+//! Helpers shared by the unit tests.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::Error;
+
+use crate::{
+    book::{Summary, SummaryItem},
+    build_summary,
+    config::Config,
+    summary_fs::SummaryFs,
+    warnings::{Diagnostic, Warnings},
+    GenerateSummary,
+};
+
+static TEMP_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// A new empty directory in the temporary directory, which is removed when it is dropped. For the
+/// tests which build without the `preprocessor` feature as well, the others use `TestBook`.
+pub(crate) struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "mdbook-generate-summary-{}-{}-{}",
+            name,
+            std::process::id(),
+            TEMP_DIRS.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).expect("Failed to create the temporary directory");
+        Self { path }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write the file at `location` (relative to the directory) with `content`, creating its
+    /// parent directories. Returns its path.
+    pub(crate) fn write(&self, location: impl AsRef<Path>, content: &str) -> PathBuf {
+        let path = self.path.join(location);
+        std::fs::create_dir_all(path.parent().unwrap()).expect("Failed to create the directory");
+        std::fs::write(&path, content).expect("Failed to write the file");
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// The names of the chapters among `items`, without their nested chapters.
+pub(crate) fn names(items: &[SummaryItem]) -> Vec<&str> {
+    items
+        .iter()
+        .filter_map(|item| match item {
+            SummaryItem::Link(link) => Some(link.name.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The chapters among `items` and their nested chapters, depth first, as
+/// "<number> <name> (<location>)" or "<name> (<location>)" without a section number. The location
+/// of drafts is "draft". Part titles are "# <title>" and separators "---".
+pub(crate) fn chapters(items: &[SummaryItem]) -> Vec<String> {
+    let mut chapters = vec![];
+    for item in items {
+        match item {
+            SummaryItem::Link(link) => {
+                let location = link.location.as_deref().unwrap_or(Path::new("draft"));
+                let chapter = format!("{} ({})", link.name, location.display());
+                chapters.push(match &link.number {
+                    Some(number) => format!("{} {}", number, chapter),
+                    None => chapter,
+                });
+                chapters.extend(self::chapters(&link.nested_items));
+            }
+            SummaryItem::PartTitle(title) => chapters.push(format!("# {}", title)),
+            SummaryItem::Separator => chapters.push("---".to_owned()),
+        }
+    }
+    chapters
+}
+
+/// Generate the summary of `src_dir` read from `fs`, together with the diagnostics of the run.
+pub(crate) fn generate(
+    fs: impl SummaryFs + 'static,
+    src_dir: &Path,
+    config: &Config,
+) -> (Result<Summary, Error>, Vec<Diagnostic>) {
+    let generator = GenerateSummary::new().with_fs(Box::new(fs));
+    let mut warnings = Warnings::new(vec![], false);
+    let summary = build_summary(
+        src_dir,
+        config,
+        &generator.hooks,
+        false,
+        None,
+        None,
+        &mut warnings,
+    )
+    .map(|(summary, ..)| summary);
+    (summary, warnings.diagnostics(src_dir))
+}
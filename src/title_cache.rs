@@ -0,0 +1,267 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    heads::Head,
+    summary_fs::{FsMetadata, SummaryFs},
+    write_guard::WriteGuard,
+};
+
+/// How much older than the last run a file or directory must be for its stamp to be trusted.
+/// Changes within the resolution of the modification time (up to 2 seconds on some filesystems)
+/// would keep the stamp unchanged.
+const UNTRUSTED_AGE: Duration = Duration::from_secs(2);
+
+/// The title cache file, see `title_cache`.
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    /// The version of the preprocessor which wrote the file, other versions discard it.
+    version: String,
+    /// The hash of the configuration of the run, other configurations discard the file.
+    config: u64,
+    /// When the run which wrote the file started, since the Unix epoch.
+    scanned_at: Duration,
+    /// The chapter files by their path relative to the src directory.
+    files: BTreeMap<PathBuf, CachedFile>,
+    /// The directories by their path relative to the src directory, only with `incremental`.
+    directories: BTreeMap<PathBuf, CachedDirectory>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedFile {
+    stamp: Stamp,
+    head: Head,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedDirectory {
+    stamp: Stamp,
+    entries: Vec<CachedEntry>,
+}
+
+/// A markdown file or directory in a cached directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CachedEntry {
+    pub(crate) name: String,
+    pub(crate) is_dir: bool,
+}
+
+/// When a file or directory was modified and its size. One with the same stamp is assumed to be
+/// unchanged, a directory changes its stamp when entries are added, removed or renamed in it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Stamp {
+    /// Since the Unix epoch, [`Option::None`] if the platform does not support it.
+    modified: Option<Duration>,
+    size: u64,
+}
+
+impl Stamp {
+    pub(crate) fn of(metadata: &FsMetadata) -> Self {
+        Self {
+            modified: metadata
+                .modified
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok()),
+            size: metadata.len,
+        }
+    }
+}
+
+/// The chapter files (and with `incremental` the directories) of the previous run, so that
+/// unchanged ones are not read again.
+pub(crate) struct TitleCache {
+    path: PathBuf,
+    /// When this run started.
+    started_at: Duration,
+    /// When the previous run started, stamps modified shortly before are not trusted.
+    scanned_at: Duration,
+    /// The chapter files of the previous run by their path in the src directory.
+    files: HashMap<PathBuf, CachedFile>,
+    /// The directories of the previous run by their path in the src directory.
+    directories: HashMap<PathBuf, CachedDirectory>,
+    /// The directories listed in this run.
+    listed: Mutex<BTreeMap<PathBuf, CachedDirectory>>,
+}
+
+impl TitleCache {
+    /// Load the cache file at `path` for the chapter files in `src_dir`. A missing, unreadable or
+    /// outdated file is discarded silently, the cache is empty then. So is a file written in the
+    /// future, e.g. due to a changed clock, and any file with `full_scan`.
+    pub(crate) fn load(path: &Path, src_dir: &Path, config: &Config) -> Self {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let file = std::fs::read(path)
+            .ok()
+            .filter(|_| !config.full_scan)
+            .and_then(|content| serde_json::from_slice::<CacheFile>(&content).ok())
+            .filter(|file| {
+                file.version == env!("CARGO_PKG_VERSION")
+                    && file.config == config_hash(config)
+                    && file.scanned_at <= started_at
+            });
+        let mut cache = Self {
+            path: path.to_path_buf(),
+            started_at,
+            scanned_at: Duration::ZERO,
+            files: HashMap::new(),
+            directories: HashMap::new(),
+            listed: Mutex::new(BTreeMap::new()),
+        };
+        let Some(file) = file else {
+            debug!("Not using the title cache {}", path.display());
+            return cache;
+        };
+        cache.scanned_at = file.scanned_at;
+        cache.files = file
+            .files
+            .into_iter()
+            .map(|(relative_path, file)| (src_dir.join(relative_path), file))
+            .collect();
+        if config.incremental {
+            cache.directories = file
+                .directories
+                .into_iter()
+                .map(|(relative_path, directory)| (src_dir.join(relative_path), directory))
+                .collect();
+        }
+        cache
+    }
+
+    /// Returns true if the stamp matches the cached one and is old enough to be trusted.
+    fn is_trusted(&self, cached: &Stamp, stamp: &Stamp) -> bool {
+        cached == stamp
+            && stamp
+                .modified
+                .is_some_and(|modified| modified + UNTRUSTED_AGE <= self.scanned_at)
+    }
+
+    /// The cached head of the file at `path`, if its stamp did not change.
+    pub(crate) fn get(&self, path: &Path, stamp: &Stamp) -> Option<&Head> {
+        self.files
+            .get(path)
+            .filter(|file| self.is_trusted(&file.stamp, stamp))
+            .map(|file| &file.head)
+    }
+
+    /// Returns true if the head of the file at `path` is cached and its stamp did not change.
+    pub(crate) fn is_fresh(&self, path: &Path, metadata: &FsMetadata) -> bool {
+        self.get(path, &Stamp::of(metadata)).is_some()
+    }
+
+    /// The cached entries of the directory at `path`, if its stamp did not change. Always
+    /// [`Option::None`] without `incremental`.
+    pub(crate) fn get_directory(&self, path: &Path, stamp: &Stamp) -> Option<&[CachedEntry]> {
+        self.directories
+            .get(path)
+            .filter(|directory| self.is_trusted(&directory.stamp, stamp))
+            .map(|directory| directory.entries.as_slice())
+    }
+
+    /// Returns true if directories of the previous run are cached.
+    pub(crate) fn caches_directories(&self) -> bool {
+        !self.directories.is_empty()
+    }
+
+    /// Like [`TitleCache::get_directory`], reading the stamp of the directory from `fs`.
+    pub(crate) fn get_fresh_directory(
+        &self,
+        path: &Path,
+        fs: &dyn SummaryFs,
+    ) -> Option<&[CachedEntry]> {
+        if !self.caches_directories() {
+            return None;
+        }
+        let metadata = fs.metadata(path).ok()?;
+        self.get_directory(path, &Stamp::of(&metadata))
+    }
+
+    /// Remember the entries of the directory at `path` as listed in this run, with the stamp the
+    /// directory had before it was listed.
+    pub(crate) fn record_directory(&self, path: &Path, stamp: Stamp, entries: Vec<CachedEntry>) {
+        self.listed
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(path.to_path_buf(), CachedDirectory { stamp, entries });
+    }
+
+    /// Write the heads and directories of this run to the cache file, unless all of them were taken
+    /// from it. Files which were not used in this run are left out. Nothing is written with
+    /// `dry_run`. The stamps of the files are read from `fs`.
+    pub(crate) fn save(
+        &self,
+        heads: HashMap<PathBuf, Head>,
+        src_dir: &Path,
+        config: &Config,
+        fs: &dyn SummaryFs,
+    ) {
+        let listed =
+            std::mem::take(&mut *self.listed.lock().unwrap_or_else(PoisonError::into_inner));
+        let mut changed = heads.len() != self.files.len() || listed.len() != self.directories.len();
+        let mut files = BTreeMap::new();
+        for (path, head) in heads {
+            let Ok(metadata) = fs.metadata(&path) else {
+                continue;
+            };
+            let stamp = Stamp::of(&metadata);
+            changed |= self.get(&path, &stamp).is_none();
+            if let Ok(relative_path) = path.strip_prefix(src_dir) {
+                files.insert(relative_path.to_path_buf(), CachedFile { stamp, head });
+            }
+        }
+        let mut directories = BTreeMap::new();
+        for (path, directory) in listed {
+            changed |= self.get_directory(&path, &directory.stamp).is_none();
+            if let Ok(relative_path) = path.strip_prefix(src_dir) {
+                directories.insert(relative_path.to_path_buf(), directory);
+            }
+        }
+        if !changed || config.dry_run {
+            return;
+        }
+        if let Err(error) = WriteGuard::new(config).check(&self.path) {
+            warn!("Failed to write the title cache: {:#}", error);
+            return;
+        }
+
+        let file = CacheFile {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            config: config_hash(config),
+            scanned_at: self.started_at,
+            files,
+            directories,
+        };
+        let result = serde_json::to_vec(&file)
+            .map_err(std::io::Error::from)
+            .and_then(|content| std::fs::write(&self.path, content));
+        if let Err(error) = result {
+            warn!(
+                "Failed to write the title cache {}: {}",
+                self.path.display(),
+                error
+            );
+        }
+    }
+}
+
+/// A hash of every option, so that a cache is only used with the configuration it was written
+/// with. `full_scan`, `dry_run` and `threads` do not change what is cached and are left out.
+fn config_hash(config: &Config) -> u64 {
+    let config = Config {
+        full_scan: false,
+        dry_run: false,
+        threads: 0,
+        ..config.clone()
+    };
+    let mut hasher = DefaultHasher::new();
+    format!("{:?}", config).hash(&mut hasher);
+    hasher.finish()
+}
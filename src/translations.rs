@@ -0,0 +1,159 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Error};
+use log::debug;
+
+use crate::{
+    config::Config,
+    summary_fs::{FileKind, SummaryFs},
+    warnings::{WarningKind, Warnings},
+};
+
+/// Warn about markdown files which exist in some of the `language_dirs` next to the src directory,
+/// but not in all of them. Nothing is compared unless the src directory is the one of `language`.
+pub(crate) fn check_translations(
+    src_dir: &Path,
+    language: &str,
+    config: &Config,
+    fs: &dyn SummaryFs,
+    warnings: &mut Warnings,
+) -> Result<(), Error> {
+    let (Some(root), true) = (
+        src_dir.parent(),
+        src_dir.file_name() == Some(OsStr::new(language)),
+    ) else {
+        debug!(
+            "Not comparing the translations, the src directory {} is not named like the language \
+             `{}`",
+            src_dir.display(),
+            language
+        );
+        return Ok(());
+    };
+
+    // The languages of every file, by its path relative to its language directory
+    let mut languages_of_files: BTreeMap<PathBuf, BTreeSet<&str>> = BTreeMap::new();
+    let mut languages = vec![];
+    for other_language in &config.language_dirs {
+        let language_dir = root.join(other_language);
+        if !fs
+            .metadata(&language_dir)
+            .is_ok_and(|metadata| metadata.kind == FileKind::Directory)
+        {
+            warnings.warn_at(
+                WarningKind::MissingTranslations,
+                &language_dir,
+                format!(
+                    "The directory {} of the language `{}` does not exist",
+                    language_dir.display(),
+                    other_language
+                ),
+            );
+            continue;
+        }
+        languages.push(other_language.as_str());
+        let mut files = vec![];
+        markdown_files(&language_dir, Path::new(""), fs, &mut files)?;
+        for file in files {
+            languages_of_files
+                .entry(file)
+                .or_default()
+                .insert(other_language);
+        }
+    }
+
+    for (file, present) in languages_of_files {
+        let missing: Vec<_> = languages
+            .iter()
+            .filter(|language| !present.contains(*language))
+            .map(|language| format!("`{}`", language))
+            .collect();
+        if missing.is_empty() {
+            continue;
+        }
+        let present: Vec<_> = present
+            .iter()
+            .map(|language| format!("`{}`", language))
+            .collect();
+        warnings.warn_at(
+            WarningKind::MissingTranslations,
+            &src_dir.join(&file),
+            format!(
+                "{} exists in {}, but not in {}",
+                file.display(),
+                present.join(", "),
+                missing.join(", ")
+            ),
+        );
+    }
+    Ok(())
+}
+
+/// Collect the markdown files in `dir` and its subdirectories, relative to the language directory.
+/// SUMMARY.md is left out, as it is generated.
+fn markdown_files(
+    dir: &Path,
+    relative: &Path,
+    fs: &dyn SummaryFs,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), Error> {
+    let read_error = || format!("Failed to read directory {}", dir.display());
+    for entry in fs.read_dir(dir).with_context(read_error)? {
+        let entry = entry.with_context(read_error)?;
+        let path = relative.join(entry.path.file_name().unwrap_or_default());
+        let file_type = entry
+            .kind
+            .with_context(|| format!("Failed to read {}", entry.path.display()))?;
+        if file_type == FileKind::Directory {
+            markdown_files(&entry.path, &path, fs, files)?;
+        } else if path.extension() == Some(OsStr::new("md")) && path != Path::new("SUMMARY.md") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "preprocessor"))]
+mod tests {
+    #[test]
+    fn warns_about_files_missing_in_a_language() -> Result<(), anyhow::Error> {
+        let output = crate::TestBook::new()
+            .file("de/SUMMARY.md", "# Summary\n")
+            .file("en/intro.md", "# Introduction\n")
+            .file("en/guide/README.md", "# Guide\n")
+            .file("en/guide/setup.md", "# Setup\n")
+            .file("de/intro.md", "# Einleitung\n")
+            .file("de/guide/README.md", "# Anleitung\n")
+            .book_config(toml::toml! {
+                [book]
+                src = "src/de"
+            })
+            .config(toml::toml! {
+                language_dirs = ["en", "de"]
+                title = "Summary"
+
+                [languages.de]
+                title = "Inhalt"
+                get_chapter_name_from_file = true
+            })
+            .run()?;
+
+        let [warning] = output.warnings()[..] else {
+            panic!("Expected a single warning, got {:?}", output.warnings());
+        };
+        assert_eq!(warning.code, "missing-translations");
+        assert_eq!(warning.path.as_deref(), Some("guide/setup.md"));
+        assert_eq!(
+            warning.message,
+            "guide/setup.md exists in `en`, but not in `de`"
+        );
+        // The language is the one of the src directory, so its table is used
+        assert_eq!(output.chapter_names(), ["1. Anleitung", "2. Einleitung"]);
+        assert_eq!(output.summary.unwrap().title.as_deref(), Some("Inhalt"));
+        Ok(())
+    }
+}
@@ -0,0 +1,322 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{anyhow, Error};
+
+use crate::{
+    book::{Link, SectionNumber, Summary, SummaryItem},
+    config::Validation,
+    summary_fs::{FileKind, SummaryFs},
+    warnings::{WarningKind, Warnings},
+};
+
+/// Check the generated summary before mdBook loads it, so that problems are reported with the
+/// chapter causing them instead of failing somewhere inside of mdBook.
+/// With [`Validation::Fix`] the problems are fixed instead and reported as a warning.
+/// The chapters inside of the `mounted` directories (by their source and location) are checked in
+/// their source, as are the `converted` files by their location.
+pub(crate) fn validate_summary(
+    summary: &mut Summary,
+    src_dir: &Path,
+    mounted: &[(PathBuf, PathBuf)],
+    converted: &HashMap<PathBuf, PathBuf>,
+    validation: Validation,
+    fs: &dyn SummaryFs,
+    warnings: &mut Warnings,
+) -> Result<(), Error> {
+    if validation == Validation::Off {
+        return Ok(());
+    }
+
+    let mut validator = Validator {
+        src_dir,
+        mounted,
+        converted,
+        fs,
+        fix: validation == Validation::Fix,
+        problems: vec![],
+    };
+    validator.validate_items(&mut summary.prefix_chapters, None);
+    validator.validate_items(
+        &mut summary.numbered_chapters,
+        Some(&SectionNumber::default()),
+    );
+    validator.validate_items(&mut summary.suffix_chapters, None);
+
+    if validator.problems.is_empty() {
+        return Ok(());
+    }
+    let problems = validator.problems.join("\n");
+    if validator.fix {
+        warnings.warn(
+            WarningKind::FixedChapters,
+            format!("Fixed invalid chapters of the summary:\n{}", problems),
+        );
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "The generated summary is invalid. Fix the chapters or set `validate = \"fix\"` to fix \
+             them automatically:\n{}",
+            problems
+        ))
+    }
+}
+
+struct Validator<'a> {
+    src_dir: &'a Path,
+    mounted: &'a [(PathBuf, PathBuf)],
+    /// The files converted to markdown by their location.
+    converted: &'a HashMap<PathBuf, PathBuf>,
+    fs: &'a dyn SummaryFs,
+    fix: bool,
+    problems: Vec<String>,
+}
+
+impl Validator<'_> {
+    /// Validate the items, which are numbered if the section of their parent is set.
+    fn validate_items(&mut self, items: &mut [SummaryItem], parent: Option<&SectionNumber>) {
+        let mut position = 0;
+        for item in items {
+            if let SummaryItem::Link(link) = item {
+                let number = parent.map(|parent| {
+                    position += 1;
+                    let mut number = parent.clone();
+                    number.push(position);
+                    number
+                });
+                self.validate_link(link, number);
+            }
+        }
+    }
+
+    fn validate_link(&mut self, link: &mut Link, number: Option<SectionNumber>) {
+        let location = link
+            .location
+            .as_ref()
+            .map_or("draft".into(), |location| location.to_string_lossy());
+        let chapter = format!(
+            "`{}` ({})",
+            link.name.escape_debug(),
+            location.escape_debug()
+        );
+
+        if link.name.chars().any(char::is_control) {
+            self.problems.push(format!(
+                "{} contains control characters in its name",
+                chapter
+            ));
+            if self.fix {
+                link.name = link
+                    .name
+                    .chars()
+                    .map(|c| if c.is_control() { ' ' } else { c })
+                    .collect();
+            }
+        }
+        if link.name.trim().is_empty() {
+            self.problems.push(format!("{} has an empty name", chapter));
+            if self.fix {
+                link.name = link
+                    .location
+                    .as_ref()
+                    .and_then(|location| location.file_stem())
+                    .map_or("Untitled".to_owned(), |stem| {
+                        stem.to_string_lossy().into_owned()
+                    });
+            }
+        }
+
+        if let Some(location) = &link.location {
+            let problem = if !location
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+            {
+                Some("is not inside of the src directory")
+            } else if !self
+                .fs
+                .metadata(&self.path(location))
+                .is_ok_and(|metadata| metadata.kind == FileKind::File)
+            {
+                Some("does not exist")
+            } else {
+                None
+            };
+            if let Some(problem) = problem {
+                self.problems.push(format!("{} {}", chapter, problem));
+                if self.fix {
+                    // Keep the chapter as a draft, so that its nested chapters are kept as well
+                    link.location = None;
+                }
+            }
+        }
+
+        if link.number != number {
+            let describe = |number: &Option<SectionNumber>| {
+                number
+                    .as_ref()
+                    .map_or("no section number".to_owned(), |number| {
+                        format!("section number {}", number)
+                    })
+            };
+            self.problems.push(format!(
+                "{} has {} instead of {}",
+                chapter,
+                describe(&link.number),
+                describe(&number)
+            ));
+            if self.fix {
+                link.number = number.clone();
+            }
+        }
+
+        self.validate_items(&mut link.nested_items, number.as_ref());
+    }
+
+    /// The path of the chapter file at `location`, which is in the source of a mount if it is
+    /// inside of one, or the file converted to it.
+    fn path(&self, location: &Path) -> PathBuf {
+        if let Some(source) = self.converted.get(location) {
+            return source.clone();
+        }
+        self.mounted
+            .iter()
+            .find_map(|(source, at)| {
+                let relative_path = location.strip_prefix(at).ok()?;
+                Some(source.join(relative_path))
+            })
+            .unwrap_or_else(|| self.src_dir.join(location))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryFs;
+
+    fn link(name: &str, location: &str, number: &[u32], nested: Vec<SummaryItem>) -> SummaryItem {
+        SummaryItem::Link(Link {
+            name: name.to_owned(),
+            location: Some(PathBuf::from(location)),
+            number: (!number.is_empty()).then(|| SectionNumber(number.to_vec())),
+            nested_items: nested,
+        })
+    }
+
+    /// A summary with a problem in every chapter but the first one.
+    fn summary() -> Summary {
+        Summary {
+            title: None,
+            prefix_chapters: vec![],
+            numbered_chapters: vec![
+                link("Introduction", "intro.md", &[1], vec![]),
+                link("Bell\u{7}", "bell.md", &[2], vec![]),
+                link(
+                    " ",
+                    "guide/README.md",
+                    &[3],
+                    vec![link("Setup", "guide/setup.md", &[3, 5], vec![])],
+                ),
+                link("Outside", "../outside.md", &[4], vec![]),
+                link("Missing", "missing.md", &[5], vec![]),
+            ],
+            suffix_chapters: vec![link("Notes", "notes.md", &[1], vec![])],
+        }
+    }
+
+    /// Validate the `summary` of the src directory `/src`, in which all chapters without a problem
+    /// exist.
+    fn validate(summary: &mut Summary, validation: Validation) -> (Result<(), String>, Warnings) {
+        let fs = MemoryFs::new()
+            .with_file("/src/intro.md", "")
+            .with_file("/src/bell.md", "")
+            .with_file("/src/guide/README.md", "")
+            .with_file("/src/guide/setup.md", "")
+            .with_file("/src/notes.md", "");
+        let mut warnings = Warnings::new(vec![], false);
+        let result = validate_summary(
+            summary,
+            Path::new("/src"),
+            &[],
+            &HashMap::new(),
+            validation,
+            &fs,
+            &mut warnings,
+        )
+        .map_err(|error| format!("{:#}", error));
+        (result, warnings)
+    }
+
+    const PROBLEMS: &str = "`Bell\\u{7}` (bell.md) contains control characters in its name\n\
+                            ` ` (guide/README.md) has an empty name\n\
+                            `Setup` (guide/setup.md) has section number 3.5. instead of section \
+                            number 3.1.\n\
+                            `Outside` (../outside.md) is not inside of the src directory\n\
+                            `Missing` (missing.md) does not exist\n\
+                            `Notes` (notes.md) has section number 1. instead of no section number";
+
+    #[test]
+    fn names_every_invalid_chapter() {
+        let mut summary = summary();
+        let (result, _) = validate(&mut summary, Validation::Error);
+        assert_eq!(
+            result.unwrap_err(),
+            format!(
+                "The generated summary is invalid. Fix the chapters or set `validate = \"fix\"` \
+                 to fix them automatically:\n{}",
+                PROBLEMS
+            )
+        );
+        assert_eq!(summary, self::summary());
+    }
+
+    #[test]
+    fn fixes_invalid_chapters() {
+        let mut summary = summary();
+        let (result, warnings) = validate(&mut summary, Validation::Fix);
+        assert_eq!(result, Ok(()));
+        let draft = |name: &str, number: u32| {
+            SummaryItem::Link(Link {
+                name: name.to_owned(),
+                location: None,
+                number: Some(SectionNumber(vec![number])),
+                nested_items: vec![],
+            })
+        };
+        let mut fixed = self::summary();
+        fixed.numbered_chapters = vec![
+            link("Introduction", "intro.md", &[1], vec![]),
+            link("Bell ", "bell.md", &[2], vec![]),
+            link(
+                "README",
+                "guide/README.md",
+                &[3],
+                vec![link("Setup", "guide/setup.md", &[3, 1], vec![])],
+            ),
+            draft("Outside", 4),
+            draft("Missing", 5),
+        ];
+        fixed.suffix_chapters = vec![link("Notes", "notes.md", &[], vec![])];
+        assert_eq!(summary, fixed);
+
+        let diagnostics = warnings.diagnostics(Path::new("/src"));
+        let [diagnostic] = diagnostics.as_slice() else {
+            panic!("Expected a single warning, got {:?}", diagnostics);
+        };
+        assert_eq!(diagnostic.code, "fixed-chapters");
+        assert_eq!(
+            diagnostic.message,
+            format!("Fixed invalid chapters of the summary:\n{}", PROBLEMS)
+        );
+    }
+
+    #[test]
+    fn does_not_validate_when_off() {
+        let mut summary = summary();
+        let (result, warnings) = validate(&mut summary, Validation::Off);
+        assert_eq!(result, Ok(()));
+        assert_eq!(summary, self::summary());
+        assert!(warnings.diagnostics(Path::new("/src")).is_empty());
+    }
+}
@@ -0,0 +1,318 @@
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, Context, Error};
+use log::{debug, warn};
+use serde::Serialize;
+
+/// The categories of warnings. Each of them can be allowed with `allow = ["<name>"]`.
+///
+/// # Example
+/// With `fail_on_warnings`, any warning fails the generation unless its category is allowed:
+/// ```
+/// use std::path::Path;
+///
+/// use mdbook_generate_summary::{Config, GenerateSummary, MemoryFs, WarningKind};
+///
+/// // Only one of them survives on a case-insensitive file system
+/// let fs = || {
+///     MemoryFs::new()
+///         .with_file("/src/Setup.md", "# Setup\n")
+///         .with_file("/src/setup.md", "# Setup\n")
+///         .with_file("/src/guide/README.md", "# Guide\n")
+///         .with_file("/src/Guide.md", "# Guide\n")
+/// };
+/// let config = Config::builder().fail_on_warnings(true).build()?;
+/// let error = GenerateSummary::new()
+///     .with_fs(Box::new(fs()))
+///     .generate_summary(Path::new("/src"), &config)
+///     .unwrap_err();
+/// let error = format!("{:#}", error);
+/// assert!(error.contains("The file /src/Setup.md and the file /src/setup.md only differ in case"));
+/// // A file differing from the directory next to it only in case does not collide
+/// assert!(!error.contains("Guide.md"));
+///
+/// let config = Config::builder()
+///     .fail_on_warnings(true)
+///     .allow([WarningKind::CaseCollisions])
+///     .build()?;
+/// let summary = GenerateSummary::new()
+///     .with_fs(Box::new(fs()))
+///     .generate_summary(Path::new("/src"), &config)?;
+/// assert_eq!(summary.numbered_chapters.len(), 4);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    UnknownConfigKeys,
+    ChapterFileExtension,
+    NonUtf8Names,
+    NonUtf8ChapterFiles,
+    FileDirectoryCollisions,
+    SummaryDiffers,
+    FailedEntries,
+    EmptySummary,
+    FixedChapters,
+    MissingTranslations,
+    MalformedSidecars,
+    UnlistedChapters,
+    DuplicateHeadingNumbers,
+    CaseCollisions,
+    BrokenLinks,
+    NoGitRepository,
+}
+
+impl WarningKind {
+    pub(crate) const ALL: [WarningKind; 16] = [
+        WarningKind::UnknownConfigKeys,
+        WarningKind::ChapterFileExtension,
+        WarningKind::NonUtf8Names,
+        WarningKind::NonUtf8ChapterFiles,
+        WarningKind::FileDirectoryCollisions,
+        WarningKind::SummaryDiffers,
+        WarningKind::FailedEntries,
+        WarningKind::EmptySummary,
+        WarningKind::FixedChapters,
+        WarningKind::MissingTranslations,
+        WarningKind::MalformedSidecars,
+        WarningKind::UnlistedChapters,
+        WarningKind::DuplicateHeadingNumbers,
+        WarningKind::CaseCollisions,
+        WarningKind::BrokenLinks,
+        WarningKind::NoGitRepository,
+    ];
+
+    /// The name used for the category in the `allow` list.
+    pub fn name(self) -> &'static str {
+        match self {
+            WarningKind::UnknownConfigKeys => "unknown-config-keys",
+            WarningKind::ChapterFileExtension => "chapter-file-extension",
+            WarningKind::NonUtf8Names => "non-utf8-names",
+            WarningKind::NonUtf8ChapterFiles => "non-utf8-chapter-files",
+            WarningKind::FileDirectoryCollisions => "file-directory-collisions",
+            WarningKind::SummaryDiffers => "summary-differs",
+            WarningKind::FailedEntries => "failed-entries",
+            WarningKind::EmptySummary => "empty-summary",
+            WarningKind::FixedChapters => "fixed-chapters",
+            WarningKind::MissingTranslations => "missing-translations",
+            WarningKind::MalformedSidecars => "malformed-sidecars",
+            WarningKind::UnlistedChapters => "unlisted-chapters",
+            WarningKind::DuplicateHeadingNumbers => "duplicate-heading-numbers",
+            WarningKind::CaseCollisions => "case-collisions",
+            WarningKind::BrokenLinks => "broken-links",
+            WarningKind::NoGitRepository => "no-git-repository",
+        }
+    }
+
+    /// The category with the name used in the `allow` list.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+/// A warning which was not emitted yet, e.g. because it was found while parsing the configuration.
+#[derive(Debug, Clone)]
+pub(crate) struct Warning {
+    pub(crate) kind: WarningKind,
+    pub(crate) message: String,
+}
+
+/// A warning or notable decision of a run, as written to `diagnostics_json`. See the README for
+/// the codes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// The name of the warning category (see [`WarningKind::name`]), or the name of the decision.
+    pub code: &'static str,
+    pub level: DiagnosticLevel,
+    /// The affected file or directory, relative to the src directory if it is inside of it.
+    pub path: Option<String>,
+    pub message: String,
+}
+
+/// Whether a [`Diagnostic`] is a warning or a notable decision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticLevel {
+    Warning,
+    Info,
+}
+
+/// The content of the `diagnostics_json` file.
+#[derive(Serialize)]
+struct DiagnosticsFile<'a> {
+    version: u32,
+    diagnostics: &'a [Diagnostic],
+}
+
+/// The warnings of a single run. Every warning is printed once as soon as it is emitted and
+/// remembered, so that `fail_on_warnings` can fail the run after the summary was generated.
+/// Together with notable decisions they are also collected as diagnostics, which are written to
+/// the `diagnostics_json` file once the run ends, even if it fails.
+pub(crate) struct Warnings {
+    allow: Vec<WarningKind>,
+    fail_on_warnings: bool,
+    emitted: Vec<Warning>,
+    diagnostics: Vec<Diagnostic>,
+    /// The file to write the diagnostics to, and the src directory their paths are relative to.
+    diagnostics_file: Option<(PathBuf, PathBuf)>,
+    /// The location of the file or directory whose decisions are recorded, followed by the other
+    /// locations standing for it like its chapter file. Empty unless explaining, see
+    /// [`Warnings::explain`].
+    explained: Vec<PathBuf>,
+}
+
+impl Warnings {
+    pub(crate) fn new(allow: Vec<WarningKind>, fail_on_warnings: bool) -> Self {
+        Self {
+            allow,
+            fail_on_warnings,
+            emitted: vec![],
+            diagnostics: vec![],
+            diagnostics_file: None,
+            explained: vec![],
+        }
+    }
+
+    /// Write the diagnostics to `path` once the run ends, with paths relative to `src_dir`.
+    #[cfg(feature = "preprocessor")]
+    pub(crate) fn write_diagnostics_to(&mut self, path: PathBuf, src_dir: PathBuf) {
+        self.diagnostics_file = Some((path, src_dir));
+    }
+
+    /// Record the decisions about the file or directory at `location` (relative to the src
+    /// directory), see [`Warnings::explain`].
+    #[cfg(feature = "preprocessor")]
+    pub(crate) fn explain_location(&mut self, location: PathBuf) {
+        self.explained = vec![location];
+    }
+
+    /// Record the decisions about `location` for the explained file or directory as well, e.g.
+    /// about the chapter file of an explained directory.
+    pub(crate) fn explain_as_well(&mut self, location: PathBuf) {
+        if !self.explained.contains(&location) {
+            self.explained.push(location);
+        }
+    }
+
+    /// The explained location followed by the ones standing for it, empty unless explaining.
+    pub(crate) fn explained(&self) -> &[PathBuf] {
+        &self.explained
+    }
+
+    /// Whether the decisions about `location` are recorded.
+    pub(crate) fn explains(&self, location: &Path) -> bool {
+        self.explained.iter().any(|explained| explained == location)
+    }
+
+    /// Record a decision about `location` as an "explanation" diagnostic of the explained file or
+    /// directory, if `location` is explained. The message is only formatted then.
+    pub(crate) fn explain(&mut self, location: &Path, message: impl FnOnce() -> String) {
+        if !self.explains(location) {
+            return;
+        }
+        self.diagnostics.push(Diagnostic {
+            code: "explanation",
+            level: DiagnosticLevel::Info,
+            path: Some(self.explained[0].display().to_string()),
+            message: message(),
+        });
+    }
+
+    /// Print the warning, unless its category is allowed or the same warning was already printed.
+    pub(crate) fn warn(&mut self, kind: WarningKind, message: impl Into<String>) {
+        self.warn_about(kind, None, message.into());
+    }
+
+    /// Like [`Warnings::warn`], for a warning about the file or directory at `path`.
+    pub(crate) fn warn_at(&mut self, kind: WarningKind, path: &Path, message: impl Into<String>) {
+        self.warn_about(kind, Some(path), message.into());
+    }
+
+    /// Record a notable decision for the diagnostics, e.g. a created chapter file.
+    pub(crate) fn record(&mut self, code: &'static str, path: &Path, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic {
+            code,
+            level: DiagnosticLevel::Info,
+            path: Some(path.display().to_string()),
+            message: message.into(),
+        });
+    }
+
+    fn warn_about(&mut self, kind: WarningKind, path: Option<&Path>, message: String) {
+        if self.allow.contains(&kind) {
+            debug!("Allowed warning ({}): {}", kind.name(), message);
+            return;
+        }
+        if self
+            .emitted
+            .iter()
+            .any(|warning| warning.kind == kind && warning.message == message)
+        {
+            return;
+        }
+
+        warn!("{}", message);
+        self.diagnostics.push(Diagnostic {
+            code: kind.name(),
+            level: DiagnosticLevel::Warning,
+            path: path.map(|path| path.display().to_string()),
+            message: message.clone(),
+        });
+        self.emitted.push(Warning { kind, message });
+    }
+
+    /// The diagnostics so far, with the paths inside of `src_dir` relative to it.
+    pub(crate) fn diagnostics(&self, src_dir: &Path) -> Vec<Diagnostic> {
+        let mut diagnostics = self.diagnostics.clone();
+        for diagnostic in &mut diagnostics {
+            if let Some(diagnostic_path) = &mut diagnostic.path {
+                if let Ok(relative_path) = Path::new(diagnostic_path).strip_prefix(src_dir) {
+                    *diagnostic_path = relative_path
+                        .components()
+                        .filter(|component| matches!(component, Component::Normal(_)))
+                        .map(|component| component.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                }
+            }
+        }
+        diagnostics
+    }
+
+    fn write_diagnostics(&self, path: &Path, src_dir: &Path) -> Result<(), Error> {
+        let content = serde_json::to_string_pretty(&DiagnosticsFile {
+            version: 1,
+            diagnostics: &self.diagnostics(src_dir),
+        })? + "\n";
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write the diagnostics to {}", path.display()))
+    }
+
+    /// Returns an error listing all warnings if `fail_on_warnings` is set and any were emitted.
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        if !self.fail_on_warnings || self.emitted.is_empty() {
+            return Ok(());
+        }
+
+        let warnings: Vec<_> = self
+            .emitted
+            .iter()
+            .map(|warning| format!("[{}] {}", warning.kind.name(), warning.message))
+            .collect();
+        Err(anyhow!(
+            "Failed because of {} warnings and `fail_on_warnings` is set. Fix them or allow their \
+             category with `allow = [\"<category>\"]`:\n{}",
+            warnings.len(),
+            warnings.join("\n")
+        ))
+    }
+}
+
+impl Drop for Warnings {
+    fn drop(&mut self) {
+        if let Some((path, src_dir)) = self.diagnostics_file.take() {
+            if let Err(error) = self.write_diagnostics(&path, &src_dir) {
+                warn!("{:#}", error);
+            }
+        }
+    }
+}
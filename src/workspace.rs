@@ -0,0 +1,207 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Error};
+use log::debug;
+use toml::value::Table;
+
+use crate::{ChapterFilter, EntryKind, GenerateSummary};
+
+/// Directories which are never searched for books, as they contain build output.
+const SKIPPED_DIRECTORIES: [&str; 2] = ["target", "build"];
+
+/// A book found by [`discover_books`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceBook {
+    /// The directory containing the book.toml.
+    pub root: PathBuf,
+    /// The directories of the books inside of the src directory of this book, relative to it.
+    /// They are left out of its summary, as they are books of their own.
+    pub nested_books: Vec<PathBuf>,
+}
+
+impl WorkspaceBook {
+    /// A generator for the summary of this book, which leaves out the nested books.
+    pub fn generator(&self) -> GenerateSummary {
+        let generator = GenerateSummary::new();
+        if self.nested_books.is_empty() {
+            return generator;
+        }
+        generator.with_filter(Box::new(NestedBooks(self.nested_books.clone())))
+    }
+}
+
+/// Leaves out the directories of nested books, see [`WorkspaceBook::nested_books`].
+struct NestedBooks(Vec<PathBuf>);
+
+impl ChapterFilter for NestedBooks {
+    fn include(&self, path: &Path, kind: EntryKind) -> bool {
+        kind == EntryKind::File || !self.0.iter().any(|book| book == path)
+    }
+}
+
+/// Find every book.toml in `root` and its subdirectories, for handling all books of a monorepo at
+/// once. The books are returned in the order of their paths, so that the result does not depend on
+/// the file system.
+/// Hidden directories and the build directories `target` and `build` are not searched, neither
+/// are the directories whose path relative to `root` matches one of the `exclude` globs. In them,
+/// `*` matches any part of a file name, `**` any number of directories and `?` a single character.
+/// A book inside of the src directory of another book is returned as its own book, and listed in the
+/// [`WorkspaceBook::nested_books`] of the other one.
+///
+/// # Example
+/// ```
+/// use std::path::Path;
+///
+/// use mdbook_generate_summary::discover_books;
+///
+/// let root = std::env::temp_dir().join(format!(
+///     "generate-summary-workspace-doctest-{}",
+///     std::process::id()
+/// ));
+/// for book in ["guide", "guide/src/api", "archive/old", "target/doc"] {
+///     std::fs::create_dir_all(root.join(book).join("src"))?;
+///     std::fs::write(root.join(book).join("book.toml"), "[book]\n")?;
+/// }
+///
+/// let books = discover_books(&root, &["archive/**".to_owned()])?;
+/// let roots: Vec<_> = books.iter().map(|book| book.root.strip_prefix(&root).unwrap()).collect();
+/// assert_eq!(roots, [Path::new("guide"), Path::new("guide/src/api")]);
+/// assert_eq!(books[0].nested_books, [Path::new("api")]);
+/// # std::fs::remove_dir_all(&root)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn discover_books(root: &Path, exclude: &[String]) -> Result<Vec<WorkspaceBook>, Error> {
+    let root = root
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve the workspace {}", root.display()))?;
+    let mut roots = vec![];
+    find_book_roots(&root, Path::new(""), exclude, &mut roots)?;
+
+    let mut books = vec![];
+    for book_root in &roots {
+        let src_dir = src_dir(book_root)?;
+        let nested_books = roots
+            .iter()
+            .filter(|other| *other != book_root)
+            .filter_map(|other| other.strip_prefix(&src_dir).ok())
+            .map(Path::to_path_buf)
+            .collect();
+        books.push(WorkspaceBook {
+            root: book_root.clone(),
+            nested_books,
+        });
+    }
+    Ok(books)
+}
+
+/// Collect the directories containing a book.toml in `dir`, which is `relative` to the root of the
+/// workspace, and its subdirectories.
+fn find_book_roots(
+    dir: &Path,
+    relative: &Path,
+    exclude: &[String],
+    roots: &mut Vec<PathBuf>,
+) -> Result<(), Error> {
+    if dir.join("book.toml").is_file() {
+        roots.push(dir.to_path_buf());
+    }
+
+    let read_error = || format!("Failed to read directory {}", dir.display());
+    let mut directories = vec![];
+    for entry in std::fs::read_dir(dir).with_context(read_error)? {
+        let entry = entry.with_context(read_error)?;
+        let file_type = entry.file_type().with_context(|| {
+            format!("Failed to get the file type of {}", entry.path().display())
+        })?;
+        if file_type.is_dir() {
+            directories.push(entry.file_name());
+        }
+    }
+    directories.sort();
+
+    for name in directories {
+        if is_skipped(&name) {
+            continue;
+        }
+        let relative = relative.join(&name);
+        let pattern_path = forward_slashes(&relative);
+        if let Some(pattern) = exclude
+            .iter()
+            .find(|pattern| glob_matches(pattern, &pattern_path))
+        {
+            debug!("Not searching {}, it matches `{}`", pattern_path, pattern);
+            continue;
+        }
+        find_book_roots(&dir.join(&name), &relative, exclude, roots)?;
+    }
+    Ok(())
+}
+
+/// Returns true if the directory is hidden or a build directory.
+fn is_skipped(name: &OsStr) -> bool {
+    let name = name.to_string_lossy();
+    name.starts_with('.') || SKIPPED_DIRECTORIES.contains(&name.as_ref())
+}
+
+/// The resolved src directory of the book in `root`, `book.src` of its book.toml.
+fn src_dir(root: &Path) -> Result<PathBuf, Error> {
+    let book_toml = root.join("book.toml");
+    let content = std::fs::read_to_string(&book_toml)
+        .with_context(|| format!("Failed to read {}", book_toml.display()))?;
+    let document: Table = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", book_toml.display()))?;
+    let src = document
+        .get("book")
+        .and_then(|book| book.get("src"))
+        .and_then(|src| src.as_str())
+        .unwrap_or("src");
+    let src_dir = root.join(src);
+    // A missing src directory is reported once the book is generated
+    Ok(src_dir.canonicalize().unwrap_or(src_dir))
+}
+
+/// The path with forward slashes, for matching it against the exclude globs.
+fn forward_slashes(path: &Path) -> String {
+    let components: Vec<_> = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+    components.join("/")
+}
+
+/// Returns true if the glob matches the whole `path`, see [`discover_books`].
+fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<_> = pattern.trim_matches('/').split('/').collect();
+    let path: Vec<_> = path.split('/').collect();
+    segments_match(&pattern, &path)
+}
+
+fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => {
+            (0..=path.len()).any(|skipped| segments_match(rest, &path[skipped..]))
+        }
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, path_rest)) => {
+                let segment: Vec<_> = segment.chars().collect();
+                let name: Vec<_> = name.chars().collect();
+                name_matches(&segment, &name) && segments_match(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Returns true if the part of a glob matches the file name.
+fn name_matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skipped| name_matches(rest, &name[skipped..])),
+        Some(('?', rest)) => !name.is_empty() && name_matches(rest, &name[1..]),
+        Some((byte, rest)) => name.first() == Some(byte) && name_matches(rest, &name[1..]),
+    }
+}
@@ -0,0 +1,54 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+
+use crate::config::Config;
+
+/// Checks every file the preprocessor writes against `assert_no_writes`. The options which write
+/// files are rejected up front with it, so a write reaching a guard which forbids it is a bug: it
+/// panics in the unit tests and fails the run otherwise.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WriteGuard {
+    forbid_writes: bool,
+}
+
+impl WriteGuard {
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
+            forbid_writes: config.assert_no_writes,
+        }
+    }
+
+    /// Returns an error if the file at `path` may not be written, see [`WriteGuard`].
+    pub(crate) fn check(self, path: &Path) -> Result<(), Error> {
+        if !self.forbid_writes {
+            return Ok(());
+        }
+        let message = format!(
+            "Tried to write {} although `assert_no_writes` is set",
+            path.display()
+        );
+        if cfg!(test) {
+            panic!("{}", message);
+        }
+        Err(anyhow!(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_writes_without_assert_no_writes() {
+        let guard = WriteGuard::new(&Config::default());
+        assert!(guard.check(Path::new("book/src/SUMMARY.md")).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "Tried to write book/src/SUMMARY.md although `assert_no_writes`")]
+    fn panics_on_forbidden_writes_in_the_tests() {
+        let config = Config::builder().assert_no_writes(true).build().unwrap();
+        let _ = WriteGuard::new(&config).check(Path::new("book/src/SUMMARY.md"));
+    }
+}